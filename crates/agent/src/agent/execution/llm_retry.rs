@@ -7,6 +7,7 @@ use crate::agent::utils::u32_from_usize;
 use crate::events::AgentEventKind;
 use futures_util::Stream;
 use log::{debug, info};
+use querymt::adapters::{new_idempotency_key, with_idempotency_key};
 use querymt::chat::StreamChunk;
 use querymt::error::LLMError;
 use std::future::Future;
@@ -35,6 +36,9 @@ where
 {
     let max_retries = config.execution_policy.rate_limit.max_retries;
     let mut attempt = 0;
+    // Shared by every attempt so providers with idempotency keys enabled can
+    // recognize a retry of a request they already executed.
+    let idempotency_key = new_idempotency_key();
     Span::current().record("rate_limited", false);
 
     loop {
@@ -44,7 +48,7 @@ where
             return Err(anyhow::anyhow!("Cancelled"));
         }
 
-        match call_fn().await {
+        match with_idempotency_key(idempotency_key.clone(), call_fn()).await {
             Ok(response) => {
                 Span::current().record("attempt", attempt);
                 return Ok(response);
@@ -203,6 +207,7 @@ where
 {
     let max_retries = config.execution_policy.rate_limit.max_retries;
     let mut attempt = 0;
+    let idempotency_key = new_idempotency_key();
 
     loop {
         attempt += 1;
@@ -211,7 +216,7 @@ where
             return Err(anyhow::anyhow!("Cancelled"));
         }
 
-        match create_stream().await {
+        match with_idempotency_key(idempotency_key.clone(), create_stream()).await {
             Ok(stream) => return Ok(stream),
 
             Err(e) => {
@@ -624,4 +629,116 @@ mod tests {
         // Should have tried max_retries times
         assert_eq!(call_count.load(Ordering::SeqCst), 3);
     }
+
+    // ── idempotency keys across retries ──────────────────────────────────────
+
+    /// Minimal HTTP provider whose responses always parse as "ok".
+    struct KeyedHttpProvider;
+
+    impl querymt::chat::http::HTTPChatProvider for KeyedHttpProvider {
+        fn chat_request(
+            &self,
+            _messages: &[querymt::chat::ChatMessage],
+            _tools: Option<&[querymt::chat::Tool]>,
+        ) -> Result<http::Request<Vec<u8>>, LLMError> {
+            Ok(http::Request::post("https://example.invalid/chat").body(Vec::new())?)
+        }
+
+        fn parse_chat(
+            &self,
+            _resp: http::Response<Vec<u8>>,
+        ) -> Result<Box<dyn ChatResponse>, LLMError> {
+            Ok(Box::new(crate::test_utils::MockChatResponse::text_only(
+                "ok",
+            )))
+        }
+    }
+
+    impl querymt::completion::http::HTTPCompletionProvider for KeyedHttpProvider {
+        fn complete_request(
+            &self,
+            _req: &querymt::completion::CompletionRequest,
+        ) -> Result<http::Request<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+
+        fn parse_complete(
+            &self,
+            _resp: http::Response<Vec<u8>>,
+        ) -> Result<querymt::completion::CompletionResponse, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    impl querymt::embedding::http::HTTPEmbeddingProvider for KeyedHttpProvider {
+        fn embed_request(&self, _inputs: &[String]) -> Result<http::Request<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+
+        fn parse_embed(&self, _resp: http::Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    impl querymt::HTTPLLMProvider for KeyedHttpProvider {}
+
+    /// Rate limits the first request and records every request's
+    /// idempotency key.
+    #[derive(Default)]
+    struct KeyRecordingTransport {
+        keys: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl querymt::transport::HttpTransport for KeyRecordingTransport {
+        async fn execute(
+            &self,
+            req: http::Request<Vec<u8>>,
+        ) -> Result<http::Response<Vec<u8>>, LLMError> {
+            let mut keys = self.keys.lock().unwrap();
+            keys.push(
+                req.headers()
+                    .get(querymt::adapters::IDEMPOTENCY_KEY_HEADER)
+                    .map(|v| v.to_str().unwrap().to_string()),
+            );
+            let status = if keys.len() == 1 { 429 } else { 200 };
+            Ok(http::Response::builder()
+                .status(status)
+                .header("retry-after", "0")
+                .body(Vec::new())?)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_llm_with_retry_reuses_idempotency_key() {
+        use querymt::chat::ChatProvider;
+
+        let (config, _temp) = make_config().await;
+        let token = CancellationToken::new();
+        let transport = Arc::new(KeyRecordingTransport::default());
+        let provider = querymt::adapters::LLMProviderFromHTTP::with_transport(
+            Box::new(KeyedHttpProvider),
+            transport.clone(),
+        )
+        .with_idempotency_keys(true);
+        let messages = [querymt::chat::ChatMessage::user().text("hi").build()];
+
+        for _ in 0..2 {
+            let result =
+                call_llm_with_retry(&config, "test-session", &token, || provider.chat(&messages))
+                    .await;
+            assert!(result.is_ok(), "got {:?}", result.err());
+        }
+
+        let keys = transport.keys.lock().unwrap().clone();
+        assert_eq!(
+            keys.len(),
+            4,
+            "each call is rate limited once, then retried"
+        );
+        assert!(keys[0].is_some());
+        assert_eq!(keys[0], keys[1], "a retry reuses its request's key");
+        assert_eq!(keys[2], keys[3]);
+        assert_ne!(keys[0], keys[2], "each logical request gets its own key");
+    }
 }
@@ -415,6 +415,18 @@ pub(super) async fn transition_call_llm(
                             );
                             thinking_signature = Some(signature);
                         }
+                        StreamChunk::Refusal(delta) => {
+                            // Shown like text, as a refusal without content
+                            // is in non-streaming responses.
+                            trace!(
+                                "stream chunk: session={} message_id={} type=refusal len={}",
+                                session_id,
+                                message_id,
+                                delta.len()
+                            );
+                            text.push_str(&delta);
+                            text_buffer.push_str(&delta);
+                        }
                         StreamChunk::ToolUseComplete { tool_call, .. } => {
                             // Flush before tool use so UI sees final text before tool starts
                             trace!(
@@ -426,6 +438,12 @@ pub(super) async fn transition_call_llm(
                                 stream_tool_calls.push(tool_call);
                             }
                         }
+                        StreamChunk::Warning(message) => {
+                            warn!(
+                                "stream warning: session={} message_id={} {}",
+                                session_id, message_id, message
+                            );
+                        }
                         StreamChunk::Usage(u) => {
                             trace!(
                                 "stream chunk: session={} message_id={} type=usage input={} output={} reasoning={}",
@@ -850,6 +868,12 @@ pub(super) async fn transition_after_llm(
             context: Some(new_context),
         }),
 
+        Some(FinishReason::Refusal) => Ok(ExecutionState::Stopped {
+            message: "Model refused the request".into(),
+            stop_type: StopType::ContentFilter,
+            context: Some(new_context),
+        }),
+
         Some(FinishReason::Error)
         | Some(FinishReason::Unknown)
         | Some(FinishReason::Other)
@@ -1041,6 +1065,7 @@ mod tests {
             role,
             content: vec![Content::text(content)],
             cache: None,
+            name: None,
         }
     }
 
@@ -53,12 +53,7 @@ mod mesh_provider_tests {
     async fn test_mesh_chat_provider_completion_not_supported() {
         let mesh = get_test_mesh().await;
         let provider = MeshChatProvider::new(mesh, "any-node", "anthropic", "claude-3");
-        let req = CompletionRequest {
-            prompt: "test".to_string(),
-            suffix: None,
-            max_tokens: None,
-            temperature: None,
-        };
+        let req = CompletionRequest::new("test");
         let result = provider.complete(&req).await;
         assert!(
             matches!(result, Err(LLMError::NotImplemented(_))),
@@ -72,6 +72,10 @@ use std::pin::Pin;
 /// [`current()`](ApiKeyResolver::current) for synchronous access in
 /// provider request builders.
 ///
+/// When the server rejects the token anyway (e.g. it was revoked before its
+/// recorded expiry), [`refresh()`](ApiKeyResolver::refresh) exchanges the
+/// stored refresh token unconditionally via [`refresh_tokens`].
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -127,4 +131,18 @@ impl ApiKeyResolver for OAuthKeyResolver {
     fn current(&self) -> String {
         self.cached_key.read().clone()
     }
+
+    fn refresh(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+        Box::pin(async {
+            let provider = get_oauth_provider(&self.provider_name, None)
+                .map_err(|e| LLMError::AuthError(format!("OAuth refresh failed: {}", e)))?;
+            let mut store = SecretStore::new()
+                .map_err(|e| LLMError::AuthError(format!("Keyring access failed: {}", e)))?;
+            let tokens = refresh_tokens(provider.as_ref(), &mut store)
+                .await
+                .map_err(|e| LLMError::AuthError(format!("OAuth refresh failed: {}", e)))?;
+            *self.cached_key.write() = tokens.access_token;
+            Ok(())
+        })
+    }
 }
@@ -125,6 +125,7 @@ impl DelegationSummarizer {
             role: ChatRole::User,
             content: vec![querymt::chat::Content::text(input)],
             cache: None,
+            name: None,
         }];
 
         let provider = self.provider.clone();
@@ -222,6 +223,12 @@ impl DelegationSummarizer {
                     conversation
                         .push_str(&format!("\n[User]: {}\n", Self::extract_text_content(msg)));
                 }
+                ChatRole::System => {
+                    conversation.push_str(&format!(
+                        "\n[System]: {}\n",
+                        Self::extract_text_content(msg)
+                    ));
+                }
                 ChatRole::Assistant => {
                     for part in &msg.parts {
                         match part {
@@ -144,6 +144,7 @@ impl ConversationContext {
             role: ChatRole::User,
             content: vec![Content::text(content)],
             cache: None,
+            name: None,
         };
 
         messages.push(injected_msg);
@@ -128,16 +128,19 @@ fn test_conversation_context_counts_user_messages() {
             role: ChatRole::User,
             content: vec![Content::text("one")],
             cache: None,
+            name: None,
         },
         ChatMessage {
             role: ChatRole::Assistant,
             content: vec![Content::text("two")],
             cache: None,
+            name: None,
         },
         ChatMessage {
             role: ChatRole::User,
             content: vec![Content::text("three")],
             cache: None,
+            name: None,
         },
     ];
     let stats = AgentStats {
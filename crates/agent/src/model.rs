@@ -250,6 +250,7 @@ impl AgentMessage {
             role: self.role.clone(),
             content: blocks,
             cache: None,
+            name: None,
         }
     }
 }
@@ -135,6 +135,7 @@ impl SessionCompaction {
             role: ChatRole::User,
             content: vec![querymt::chat::Content::text(COMPACTION_PROMPT)],
             cache: None,
+            name: None,
         });
 
         chat_messages
@@ -6,8 +6,9 @@ use querymt::plugin::LLMProviderFactory;
 use querymt::plugin::host::PluginRegistry;
 use querymt::provider_config::{
     ConfigOverrideMode, ProviderConfigBuilder,
-    ResolvedProviderConfig as SharedResolvedProviderConfig,
+    ResolvedProviderConfig as SharedResolvedProviderConfig, clamp_max_tokens,
 };
+use querymt::providers::ProvidersRegistry;
 use serde_json::Value;
 use std::sync::Arc;
 
@@ -194,6 +195,10 @@ pub(crate) async fn resolve_provider_config(
         }
     }
 
+    if let Some(models) = ProvidersRegistry::global() {
+        clamp_max_tokens(builder.value_mut(), provider_name, models);
+    }
+
     let SharedResolvedProviderConfig {
         #[cfg(feature = "oauth")]
         full_config,
@@ -123,6 +123,7 @@ impl SessionStore for SqliteStorage {
                         let role = match role_str.as_str() {
                             "User" => ChatRole::User,
                             "Assistant" => ChatRole::Assistant,
+                            "System" => ChatRole::System,
                             _ => ChatRole::User, // Default fallback
                         };
 
@@ -197,6 +198,7 @@ impl SessionStore for SqliteStorage {
             let role_str = match msg.role {
                 ChatRole::User => "User",
                 ChatRole::Assistant => "Assistant",
+                ChatRole::System => "System",
             };
 
             // Insert message with public_id and internal session_id/parent_message_id
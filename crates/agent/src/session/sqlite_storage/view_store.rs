@@ -63,6 +63,7 @@ fn parse_finish_reason(value: &str) -> Option<FinishReason> {
         "ContentFilter" | "content_filter" => Some(FinishReason::ContentFilter),
         "ToolCalls" | "tool_calls" => Some(FinishReason::ToolCalls),
         "Error" | "error" => Some(FinishReason::Error),
+        "Refusal" | "refusal" => Some(FinishReason::Refusal),
         "Other" | "other" => Some(FinishReason::Other),
         "Unknown" | "unknown" => Some(FinishReason::Unknown),
         _ => None,
@@ -137,6 +137,11 @@ pub async fn handle_any_response(
                                 StreamChunk::ThinkingSignature(_sig) => {
                                     // Signature is used for signed thinking replay, not terminal display.
                                 }
+                                StreamChunk::Refusal(t) => {
+                                    print!("{}", t);
+                                    io::stdout().flush().ok();
+                                    full_text.push_str(&t);
+                                }
                                 StreamChunk::ToolUseStart { index, id, name } => {
                                     log::debug!("Received tool use start: {} (idx {})", name, index);
                                     tool_calls_map.insert(index, (id, name, String::new()));
@@ -172,6 +177,9 @@ pub async fn handle_any_response(
                                         usage.output_tokens
                                     );
                                 }
+                                StreamChunk::Warning(message) => {
+                                    log::warn!("Stream warning: {}", message);
+                                }
                                 StreamChunk::Done { finish_reason } => {
                                     log::debug!("Stream done: finish_reason={:?}", finish_reason);
                                     println!();
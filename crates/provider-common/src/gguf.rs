@@ -0,0 +1,219 @@
+//! Reading model facts from a GGUF file header without loading the weights.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"GGUF";
+/// Longest key or architecture name accepted before the header is treated
+/// as corrupt.
+const MAX_STRING_LEN: u64 = 64 * 1024;
+
+const TYPE_U32: u32 = 4;
+const TYPE_I32: u32 = 5;
+const TYPE_STRING: u32 = 8;
+const TYPE_ARRAY: u32 = 9;
+const TYPE_U64: u32 = 10;
+const TYPE_I64: u32 = 11;
+
+/// Model facts read from a GGUF header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GgufHeader {
+    /// `general.architecture`, e.g. `llama` or `qwen2`.
+    pub architecture: Option<String>,
+    /// `<architecture>.context_length`: the context the model was trained with.
+    pub context_length: Option<u64>,
+}
+
+/// Read the [`GgufHeader`] of the GGUF file at `path`.
+///
+/// Only the key/value section is scanned, and it stops as soon as both
+/// values are known, so this is cheap even for multi-gigabyte models.
+pub fn read_gguf_header(path: impl AsRef<Path>) -> io::Result<GgufHeader> {
+    parse_header(&mut BufReader::new(File::open(path)?))
+}
+
+fn parse_header<R: Read + Seek>(r: &mut R) -> io::Result<GgufHeader> {
+    let mut magic = [0; 4];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a GGUF file".to_string()));
+    }
+    // Version 1 used 32-bit lengths and predates every model in use today.
+    let version = read_u32(r)?;
+    if version < 2 {
+        return Err(invalid(format!("unsupported GGUF version {version}")));
+    }
+    let _tensor_count = read_u64(r)?;
+    let kv_count = read_u64(r)?;
+
+    let mut header = GgufHeader::default();
+    let mut context_lengths = Vec::new();
+    for _ in 0..kv_count {
+        let key = read_string(r)?;
+        let value_type = read_u32(r)?;
+        if key == "general.architecture" && value_type == TYPE_STRING {
+            header.architecture = Some(read_string(r)?);
+        } else if let Some(arch) = key.strip_suffix(".context_length") {
+            if let Some(len) = read_uint(r, value_type)? {
+                context_lengths.push((arch.to_string(), len));
+            }
+        } else {
+            skip_value(r, value_type)?;
+        }
+
+        if let Some(arch) = &header.architecture
+            && let Some((_, len)) = context_lengths.iter().find(|(a, _)| a == arch)
+        {
+            header.context_length = Some(*len);
+            break;
+        }
+    }
+
+    Ok(header)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    let len = read_u64(r)?;
+    if len > MAX_STRING_LEN {
+        return Err(invalid(format!("GGUF string of {len} bytes")));
+    }
+    let mut buf = vec![0; len as usize];
+    r.read_exact(&mut buf)?;
+    String::from_utf8(buf).map_err(|e| invalid(e.to_string()))
+}
+
+/// Read an integer value, skipping (and returning `None` for) any other type.
+fn read_uint<R: Read + Seek>(r: &mut R, value_type: u32) -> io::Result<Option<u64>> {
+    match value_type {
+        TYPE_U32 => read_u32(r).map(|v| Some(v.into())),
+        TYPE_I32 => read_u32(r).map(|v| u64::try_from(v as i32).ok()),
+        TYPE_U64 => read_u64(r).map(Some),
+        TYPE_I64 => read_u64(r).map(|v| u64::try_from(v as i64).ok()),
+        _ => skip_value(r, value_type).map(|()| None),
+    }
+}
+
+/// Size in bytes of a fixed-size value type.
+fn fixed_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn skip_bytes<R: Seek>(r: &mut R, len: u64) -> io::Result<()> {
+    let len = i64::try_from(len).map_err(|_| invalid(format!("GGUF value of {len} bytes")))?;
+    r.seek_relative(len)
+}
+
+fn skip_value<R: Read + Seek>(r: &mut R, value_type: u32) -> io::Result<()> {
+    if let Some(size) = fixed_size(value_type) {
+        return skip_bytes(r, size);
+    }
+    match value_type {
+        TYPE_STRING => {
+            let len = read_u64(r)?;
+            skip_bytes(r, len)
+        }
+        TYPE_ARRAY => {
+            let elem_type = read_u32(r)?;
+            let count = read_u64(r)?;
+            if let Some(size) = fixed_size(elem_type) {
+                let len = count
+                    .checked_mul(size)
+                    .ok_or_else(|| invalid(format!("GGUF array of {count} elements")))?;
+                return skip_bytes(r, len);
+            }
+            for _ in 0..count {
+                skip_value(r, elem_type)?;
+            }
+            Ok(())
+        }
+        other => Err(invalid(format!("unknown GGUF value type {other}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut buf = (s.len() as u64).to_le_bytes().to_vec();
+        buf.extend(s.as_bytes());
+        buf
+    }
+
+    fn header(kvs: &[(&str, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        buf.extend(3u32.to_le_bytes());
+        buf.extend(0u64.to_le_bytes());
+        buf.extend((kvs.len() as u64).to_le_bytes());
+        for (key, value_type, value) in kvs {
+            buf.extend(string(key));
+            buf.extend(value_type.to_le_bytes());
+            buf.extend(value);
+        }
+        buf
+    }
+
+    #[test]
+    fn reads_context_length_of_the_architecture() {
+        let mut tokens = TYPE_STRING.to_le_bytes().to_vec();
+        tokens.extend(2u64.to_le_bytes());
+        tokens.extend(string("<s>"));
+        tokens.extend(string("</s>"));
+
+        let bytes = header(&[
+            ("general.name", TYPE_STRING, string("Qwen2.5 7B")),
+            ("tokenizer.ggml.tokens", TYPE_ARRAY, tokens),
+            (
+                "clip.vision.context_length",
+                TYPE_U32,
+                512u32.to_le_bytes().to_vec(),
+            ),
+            (
+                "qwen2.context_length",
+                TYPE_U32,
+                32_768u32.to_le_bytes().to_vec(),
+            ),
+            ("general.architecture", TYPE_STRING, string("qwen2")),
+            ("qwen2.block_count", TYPE_U32, 28u32.to_le_bytes().to_vec()),
+        ]);
+
+        let header = parse_header(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(
+            header,
+            GgufHeader {
+                architecture: Some("qwen2".to_string()),
+                context_length: Some(32_768),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_non_gguf_files() {
+        let err = parse_header(&mut Cursor::new(b"PK\x03\x04rest".to_vec())).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
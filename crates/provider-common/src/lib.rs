@@ -1,10 +1,21 @@
+#[cfg(feature = "hf")]
 use hf_hub::api::sync::ApiBuilder as SyncApiBuilder;
+#[cfg(feature = "hf")]
 use hf_hub::api::tokio::ApiBuilder as AsyncApiBuilder;
 use log::debug;
+#[cfg(feature = "hf")]
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+#[cfg(feature = "hf")]
+use std::time::Duration;
 use std::time::SystemTime;
 
+#[cfg(feature = "openai")]
+pub mod openai_messages;
+
+mod gguf;
+pub use gguf::{GgufHeader, read_gguf_header};
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HfModelRef {
     pub repo: String,
@@ -60,6 +71,15 @@ pub enum DownloadStatus {
 
 pub type ProgressCallback = Box<dyn Fn(DownloadProgress) + Send + Sync>;
 
+#[cfg(feature = "hf")]
+/// Options for fetching model files from the Hugging Face Hub.
+#[derive(Debug, Clone, Default)]
+pub struct HfDownloadOptions {
+    /// Give up on a file whose download hasn't finished after this long.
+    /// Applies to each shard of a split GGUF separately.
+    pub timeout: Option<Duration>,
+}
+
 impl std::fmt::Display for ModelRefError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -179,6 +199,7 @@ pub fn parse_gguf_metadata(filename: &str) -> GgufMetadata {
     GgufMetadata { family, quant }
 }
 
+#[cfg(feature = "hf")]
 pub fn list_cached_hf_gguf_models() -> Result<Vec<CachedGgufModel>, ModelRefError> {
     let home = dirs::home_dir()
         .ok_or_else(|| ModelRefError::Invalid("failed to resolve home directory".to_string()))?;
@@ -279,19 +300,215 @@ fn is_windows_abs_path(raw: &str) -> bool {
         && (bytes[2] == b'\\' || bytes[2] == b'/')
 }
 
+/// Whether an upper-cased filename segment is a quantization tag such as
+/// `Q4_K_M`, `IQ4_XS` or `F16`.
 fn is_quant_segment(seg: &str) -> bool {
-    seg.starts_with('Q') && seg.chars().skip(1).any(|c| c.is_ascii_digit())
+    match seg.strip_prefix("IQ").or_else(|| seg.strip_prefix('Q')) {
+        Some(rest) => rest.starts_with(|c: char| c.is_ascii_digit()),
+        None => matches!(seg, "F16" | "BF16" | "F32"),
+    }
 }
 
+/// Guess the filename for a `<repo>:<selector>` reference without
+/// contacting the Hub.
+///
+/// Filenames are used as-is. Quantization selectors are upper-cased and
+/// appended to the repo name minus its `-GGUF` suffix, so
+/// `Org/Model-GGUF:q4_k_m` becomes `Model-Q4_K_M.gguf`. Use
+/// [`reconcile_hf_model_ref`] to correct the guess against the repo's
+/// actual file listing.
 pub fn infer_gguf_filename(repo: &str, selector: &str) -> String {
-    if selector.ends_with(".gguf") {
+    if selector.to_ascii_lowercase().ends_with(".gguf") {
         return selector.to_string();
     }
     let repo_name = repo.rsplit('/').next().unwrap_or(repo);
-    let base = repo_name.strip_suffix("-GGUF").unwrap_or(repo_name);
-    format!("{base}-{selector}.gguf")
+    let base = match repo_name.len().checked_sub(5) {
+        Some(cut) if repo_name[cut..].eq_ignore_ascii_case("-gguf") => &repo_name[..cut],
+        _ => repo_name,
+    };
+    let upper = selector.to_ascii_uppercase();
+    let tag = if is_quant_segment(&upper) {
+        upper.as_str()
+    } else {
+        selector
+    };
+    format!("{base}-{tag}.gguf")
+}
+
+/// Quantizations tried, in order, when a repo is given without a selector.
+///
+/// `Q4_K_M` is the usual size/quality sweet spot; the rest are ordered by
+/// how closely they trade off the same way.
+const QUANT_PREFERENCES: &[&str] = &[
+    "Q4_K_M", "Q4_K_S", "Q5_K_M", "Q4_0", "IQ4_XS", "Q5_K_S", "Q6_K", "Q8_0", "Q3_K_M",
+];
+
+/// Parse the `-NNNNN-of-NNNNN` suffix of a sharded GGUF filename into
+/// `(shard, total)`, both 1-based.
+pub fn parse_gguf_shard(filename: &str) -> Option<(u32, u32)> {
+    let stem = filename.strip_suffix(".gguf")?;
+    let (rest, total) = stem.rsplit_once("-of-")?;
+    let (_, shard) = rest.rsplit_once('-')?;
+    let is_counter = |s: &str| s.len() == 5 && s.bytes().all(|b| b.is_ascii_digit());
+    if !is_counter(shard) || !is_counter(total) {
+        return None;
+    }
+    Some((shard.parse().ok()?, total.parse().ok()?))
+}
+
+/// Whether `filename` is tagged with `quant`, compared case-insensitively.
+///
+/// The tag must be delimited like `Model-Q4_K_M.gguf`, `model.Q4_K_M.gguf`
+/// or `Model-Q4_K_M-00001-of-00002.gguf`, so `Q4_K` doesn't match `Q4_K_M`
+/// and `Q4_XS` doesn't match `IQ4_XS`.
+fn has_quant_tag(filename: &str, quant: &str) -> bool {
+    let name = filename.to_ascii_uppercase();
+    let quant = quant.to_ascii_uppercase();
+    name.match_indices(&quant).any(|(start, _)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + quant.len()..].chars().next();
+        matches!(before, Some('-' | '.' | '_')) && matches!(after, Some('-' | '.'))
+    })
 }
 
+/// Pick the model file to load from a repo's file listing.
+///
+/// Only `.gguf` files are considered, excluding `mmproj` projectors and all
+/// but the first shard of split models. `prefer_quant` is tried first, then
+/// [`QUANT_PREFERENCES`]; if no quantization matches, the first candidate
+/// in name order is returned.
+pub fn select_gguf_file(files: &[String], prefer_quant: Option<&str>) -> Option<String> {
+    let candidates = gguf_candidates(files);
+    prefer_quant
+        .into_iter()
+        .chain(QUANT_PREFERENCES.iter().copied())
+        .find_map(|quant| candidates.iter().find(|f| has_quant_tag(f, quant)))
+        .or(candidates.first())
+        .map(|f| f.to_string())
+}
+
+/// Model files in a repo listing, sorted by name: `.gguf` files other than
+/// `mmproj` projectors and non-first shards of split models.
+fn gguf_candidates(files: &[String]) -> Vec<&String> {
+    let mut candidates: Vec<&String> = files
+        .iter()
+        .filter(|f| {
+            let name = f.rsplit('/').next().unwrap_or(f).to_ascii_lowercase();
+            name.ends_with(".gguf") && !name.starts_with("mmproj")
+        })
+        .filter(|f| parse_gguf_shard(f).is_none_or(|(shard, _)| shard == 1))
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// Find the file a `<repo>:<selector>` reference points to in a repo
+/// listing, comparing case-insensitively.
+///
+/// A selector ending in `.gguf` is matched against full paths and bare
+/// filenames; anything else is treated as a quantization tag (`q4_k_m`,
+/// `Q5_K_M`, `IQ4_XS`) and matched with the same rules as
+/// [`select_gguf_file`].
+pub fn match_gguf_selector(files: &[String], selector: &str) -> Option<String> {
+    if selector.to_ascii_lowercase().ends_with(".gguf") {
+        let wanted = selector.rsplit('/').next().unwrap_or(selector);
+        return files
+            .iter()
+            .find(|f| f.eq_ignore_ascii_case(selector))
+            .or_else(|| {
+                files.iter().find(|f| {
+                    f.rsplit('/')
+                        .next()
+                        .unwrap_or(f)
+                        .eq_ignore_ascii_case(wanted)
+                })
+            })
+            .cloned();
+    }
+    gguf_candidates(files)
+        .into_iter()
+        .find(|f| has_quant_tag(f, selector))
+        .cloned()
+}
+
+#[cfg(feature = "hf")]
+fn list_hf_repo_files(repo: &str) -> Result<Vec<String>, ModelRefError> {
+    let api = SyncApiBuilder::new()
+        .build()
+        .map_err(|e| ModelRefError::Download(e.to_string()))?;
+    let info = api
+        .model(repo.to_string())
+        .info()
+        .map_err(|e| ModelRefError::Download(e.to_string()))?;
+    Ok(info.siblings.into_iter().map(|s| s.rfilename).collect())
+}
+
+#[cfg(feature = "hf")]
+/// Correct a possibly guessed filename against the repo's file listing.
+///
+/// [`parse_model_ref`] has to template filenames for `<repo>:<quant>`
+/// references, which misses repos named like `model.Q4_K_M.gguf` or that
+/// keep quants in subdirectories. Unless the file is already cached, this
+/// looks it up by name and then by its quantization tag. The reference is
+/// returned unchanged when nothing matches or the Hub is unreachable.
+pub fn reconcile_hf_model_ref(model: &HfModelRef) -> HfModelRef {
+    let cache = hf_hub::Cache::from_env();
+    if cache.model(model.repo.clone()).get(&model.file).is_some() {
+        return model.clone();
+    }
+    let files = match list_hf_repo_files(&model.repo) {
+        Ok(files) => files,
+        Err(e) => {
+            debug!("reconcile_hf_model_ref: cannot list {}: {e}", model.repo);
+            return model.clone();
+        }
+    };
+    reconcile_with_files(model, &files)
+}
+
+#[cfg_attr(not(feature = "hf"), allow(dead_code))]
+fn reconcile_with_files(model: &HfModelRef, files: &[String]) -> HfModelRef {
+    if files.contains(&model.file) {
+        return model.clone();
+    }
+    let quant = parse_gguf_metadata(&model.file).quant;
+    let matched = match_gguf_selector(files, &model.file)
+        .or_else(|| (quant != "unknown").then(|| match_gguf_selector(files, &quant))?);
+    match matched {
+        Some(file) => {
+            debug!("reconcile_hf_model_ref: {} resolved to {file}", model.file);
+            HfModelRef {
+                repo: model.repo.clone(),
+                file,
+            }
+        }
+        None => model.clone(),
+    }
+}
+
+#[cfg(feature = "hf")]
+/// Resolve a bare `<owner>/<repo>` reference to a concrete GGUF file by
+/// querying the repo's file listing. See [`select_gguf_file`] for how the
+/// file is chosen.
+pub fn resolve_hf_repo(
+    repo: &str,
+    prefer_quant: Option<&str>,
+) -> Result<HfModelRef, ModelRefError> {
+    let files = list_hf_repo_files(repo)?;
+
+    let file = select_gguf_file(&files, prefer_quant).ok_or_else(|| {
+        ModelRefError::Invalid(format!(
+            "no .gguf model files found in Hugging Face repo {repo}"
+        ))
+    })?;
+    debug!("resolve_hf_repo: selected {file} from {repo}");
+    Ok(HfModelRef {
+        repo: repo.to_string(),
+        file,
+    })
+}
+
+#[cfg(feature = "hf")]
 pub async fn download_hf_gguf_with_progress(
     model: &HfModelRef,
     progress_cb: ProgressCallback,
@@ -321,6 +538,34 @@ pub async fn download_hf_gguf_with_progress(
         .build()
         .map_err(|e| ModelRefError::Download(e.to_string()))?;
 
+    let repo = api.model(model.repo.clone());
+    let cached = is_cached(model);
+    if !cached {
+        match api.metadata(&repo.url(&model.file)).await {
+            Ok(metadata) => {
+                if let Err(e) = ensure_free_space(
+                    hf_hub::Cache::default().path(),
+                    &model.file,
+                    metadata.size() as u64,
+                ) {
+                    progress_cb(DownloadProgress {
+                        bytes_downloaded: 0,
+                        bytes_total: Some(metadata.size() as u64),
+                        percent: None,
+                        speed_bps: None,
+                        eta_seconds: None,
+                        status: DownloadStatus::Failed(e.to_string()),
+                    });
+                    return Err(e);
+                }
+            }
+            Err(e) => debug!(
+                "download_hf_gguf_with_progress: no metadata for {}: {e}",
+                model.file
+            ),
+        }
+    }
+
     progress_cb(DownloadProgress {
         bytes_downloaded: 0,
         bytes_total: None,
@@ -330,17 +575,20 @@ pub async fn download_hf_gguf_with_progress(
         status: DownloadStatus::Downloading,
     });
 
-    let result = api.model(model.repo.clone()).get(&model.file).await;
+    let mut result = get_resuming(&repo, model).await;
+    if !cached && let Ok(path) = result {
+        progress_cb(DownloadProgress {
+            bytes_downloaded: 0,
+            bytes_total: None,
+            percent: Some(100.0),
+            speed_bps: None,
+            eta_seconds: Some(0),
+            status: DownloadStatus::Verifying,
+        });
+        result = verify_or_refetch_async(api, model, path).await;
+    }
     match result {
         Ok(path) => {
-            progress_cb(DownloadProgress {
-                bytes_downloaded: 0,
-                bytes_total: None,
-                percent: Some(100.0),
-                speed_bps: None,
-                eta_seconds: Some(0),
-                status: DownloadStatus::Verifying,
-            });
             progress_cb(DownloadProgress {
                 bytes_downloaded: 0,
                 bytes_total: None,
@@ -352,41 +600,323 @@ pub async fn download_hf_gguf_with_progress(
             Ok(path)
         }
         Err(e) => {
-            let msg = e.to_string();
             progress_cb(DownloadProgress {
                 bytes_downloaded: 0,
                 bytes_total: None,
                 percent: None,
                 speed_bps: None,
                 eta_seconds: None,
-                status: DownloadStatus::Failed(msg.clone()),
+                status: DownloadStatus::Failed(e.to_string()),
             });
-            Err(ModelRefError::Download(msg))
+            Err(e)
+        }
+    }
+}
+
+#[cfg(feature = "hf")]
+/// Attempts per file before a download fails. hf-hub keeps the partial file
+/// next to its blob, so every retry resumes where the last attempt stopped.
+const DOWNLOAD_ATTEMPTS: usize = 3;
+
+#[cfg(feature = "hf")]
+async fn get_resuming(
+    repo: &hf_hub::api::tokio::ApiRepo,
+    model: &HfModelRef,
+) -> Result<PathBuf, ModelRefError> {
+    let mut attempt = 1;
+    loop {
+        match repo.get(&model.file).await {
+            Ok(path) => return Ok(path),
+            Err(e) if attempt < DOWNLOAD_ATTEMPTS => {
+                log::warn!(
+                    "download of {}/{} failed (attempt {attempt}/{DOWNLOAD_ATTEMPTS}), resuming: {e}",
+                    model.repo,
+                    model.file
+                );
+                attempt += 1;
+            }
+            Err(e) => return Err(ModelRefError::Download(e.to_string())),
         }
     }
 }
 
-pub fn resolve_hf_model_sync(model: &HfModelRef) -> Result<PathBuf, ModelRefError> {
+#[cfg(feature = "hf")]
+/// [`verify_or_refetch`] on a blocking thread, refetching through `api`.
+async fn verify_or_refetch_async(
+    api: hf_hub::api::tokio::Api,
+    model: &HfModelRef,
+    path: PathBuf,
+) -> Result<PathBuf, ModelRefError> {
+    let handle = tokio::runtime::Handle::current();
+    let model = model.clone();
+    tokio::task::spawn_blocking(move || {
+        verify_or_refetch(path, || {
+            handle.block_on(get_resuming(&api.model(model.repo.clone()), &model))
+        })
+    })
+    .await
+    .map_err(|e| ModelRefError::Download(e.to_string()))?
+}
+
+#[cfg(feature = "hf")]
+/// Check a file fetched from the Hub against its SHA-256.
+///
+/// The Hub cache names LFS blobs after their SHA-256, so no extra request is
+/// needed; files kept in git (named by their SHA-1) aren't checked. A corrupt
+/// file is removed from the cache so the next fetch downloads it again.
+fn verify_download(path: &Path) -> Result<(), ModelRefError> {
+    let blob = std::fs::canonicalize(path)
+        .map_err(|e| ModelRefError::Download(format!("cannot resolve {}: {e}", path.display())))?;
+    let Some(expected) = blob
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+    else {
+        return Ok(());
+    };
+    let actual = sha256_file(&blob)
+        .map_err(|e| ModelRefError::Download(format!("cannot read {}: {e}", blob.display())))?;
+    if actual == expected {
+        return Ok(());
+    }
+    let _ = std::fs::remove_file(&blob);
+    if blob != path {
+        let _ = std::fs::remove_file(path);
+    }
+    Err(ModelRefError::Download(format!(
+        "checksum mismatch for {}: expected sha256 {expected}, got {actual}; removed the corrupt file",
+        path.display()
+    )))
+}
+
+#[cfg(feature = "hf")]
+/// Verify a freshly downloaded file, downloading it once more if it's corrupt.
+fn verify_or_refetch(
+    path: PathBuf,
+    refetch: impl FnOnce() -> Result<PathBuf, ModelRefError>,
+) -> Result<PathBuf, ModelRefError> {
+    match verify_download(&path) {
+        Ok(()) => Ok(path),
+        Err(e) => {
+            log::warn!("{e}; downloading it again");
+            let path = refetch()?;
+            verify_download(&path)?;
+            Ok(path)
+        }
+    }
+}
+
+#[cfg(feature = "hf")]
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    use sha2::{Digest, Sha256};
+
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Every shard filename of a split GGUF, in order, given any one of them.
+///
+/// Returns `None` for files without a `-NNNNN-of-NNNNN` suffix.
+pub fn gguf_shard_filenames(filename: &str) -> Option<Vec<String>> {
+    let (shard, total) = parse_gguf_shard(filename)?;
+    let suffix = format!("-{shard:05}-of-{total:05}.gguf");
+    let prefix = filename.strip_suffix(&suffix)?;
+    Some(
+        (1..=total)
+            .map(|n| format!("{prefix}-{n:05}-of-{total:05}.gguf"))
+            .collect(),
+    )
+}
+
+#[cfg_attr(not(feature = "hf"), allow(dead_code))]
+/// Fetch `model` with `fetch`, pulling in every sibling shard when it is
+/// part of a split GGUF.
+///
+/// llama.cpp loads the remaining shards from the first one's directory, so
+/// all of them must be present; the first shard's path is returned.
+fn resolve_with_shards(
+    model: &HfModelRef,
+    mut fetch: impl FnMut(&HfModelRef) -> Result<PathBuf, ModelRefError>,
+) -> Result<PathBuf, ModelRefError> {
+    let Some(shards) = gguf_shard_filenames(&model.file) else {
+        return fetch(model);
+    };
+
+    debug!(
+        "resolve_with_shards: fetching {} shards for {}/{}",
+        shards.len(),
+        model.repo,
+        model.file,
+    );
+    let mut first = None;
+    for file in shards {
+        let shard = HfModelRef {
+            repo: model.repo.clone(),
+            file,
+        };
+        let path = fetch(&shard)?;
+        if !path.is_file() {
+            return Err(ModelRefError::Download(format!(
+                "shard {} of {} is missing at {}",
+                shard.file,
+                model.repo,
+                path.display()
+            )));
+        }
+        first.get_or_insert(path);
+    }
+    first.ok_or_else(|| ModelRefError::Invalid(format!("no shards found for {}", model.file)))
+}
+
+#[cfg(feature = "hf")]
+pub fn resolve_hf_model_sync(
+    model: &HfModelRef,
+    options: &HfDownloadOptions,
+) -> Result<PathBuf, ModelRefError> {
     debug!(
         "resolve_hf_model_sync: single-stream ureq download for {}/{}",
         model.repo, model.file,
     );
+    // Retries resume from the partial file rather than starting over.
     let api = SyncApiBuilder::new()
         .with_progress(true)
+        .with_retries(DOWNLOAD_ATTEMPTS - 1)
         .build()
         .map_err(|e| ModelRefError::Download(e.to_string()))?;
-    api.model(model.repo.clone())
+    resolve_with_shards(model, |file| {
+        let cached = is_cached(file);
+        if !cached {
+            let repo = api.model(file.repo.clone());
+            match api.metadata(&repo.url(&file.file)) {
+                Ok(metadata) => ensure_free_space(
+                    hf_hub::Cache::default().path(),
+                    &file.file,
+                    metadata.size() as u64,
+                )?,
+                Err(e) => debug!("resolve_hf_model_sync: no metadata for {}: {e}", file.file),
+            }
+        }
+        let download = || {
+            let repo = api.model(file.repo.clone());
+            let Some(timeout) = options.timeout else {
+                return repo
+                    .get(&file.file)
+                    .map_err(|e| ModelRefError::Download(e.to_string()));
+            };
+            // ureq offers no overall deadline, so wait on the transfer from here.
+            // A transfer that times out is abandoned on its thread.
+            let (tx, rx) = std::sync::mpsc::channel();
+            let name = file.file.clone();
+            std::thread::spawn(move || {
+                let _ = tx.send(repo.get(&name));
+            });
+            match rx.recv_timeout(timeout) {
+                Ok(result) => result.map_err(|e| ModelRefError::Download(e.to_string())),
+                Err(_) => Err(download_timed_out(file, timeout)),
+            }
+        };
+        let path = download()?;
+        if cached {
+            return Ok(path);
+        }
+        verify_or_refetch(path, download)
+    })
+}
+
+#[cfg(feature = "hf")]
+fn download_timed_out(model: &HfModelRef, timeout: Duration) -> ModelRefError {
+    ModelRefError::Download(format!(
+        "download of {}/{} did not finish within {}s",
+        model.repo,
+        model.file,
+        timeout.as_secs()
+    ))
+}
+
+#[cfg(feature = "hf")]
+fn is_cached(model: &HfModelRef) -> bool {
+    hf_hub::Cache::default()
+        .model(model.repo.clone())
         .get(&model.file)
-        .map_err(|e| ModelRefError::Download(e.to_string()))
+        .is_some()
+}
+
+#[cfg(all(feature = "hf", unix))]
+/// Bytes available to unprivileged users on the filesystem holding `path`,
+/// or `None` where that can't be determined.
+#[allow(clippy::unnecessary_cast)]
+fn available_space(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    // The cache directory doesn't exist before the first download.
+    let dir = path.ancestors().find(|p| p.exists())?;
+    let c_path = CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    if unsafe { libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    Some((stat.f_bavail as u64).saturating_mul(stat.f_frsize as u64))
+}
+
+#[cfg(all(feature = "hf", not(unix)))]
+fn available_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(feature = "hf")]
+/// Fail before downloading `file` into `dir` when its `size` bytes won't
+/// fit. Passes when the free space can't be determined.
+fn ensure_free_space(dir: &Path, file: &str, size: u64) -> Result<(), ModelRefError> {
+    const GIB: f64 = (1u64 << 30) as f64;
+    match available_space(dir) {
+        Some(available) if available < size => Err(ModelRefError::Download(format!(
+            "not enough disk space for {file}: it needs {:.2} GiB but only {:.2} GiB is free in {}",
+            size as f64 / GIB,
+            available as f64 / GIB,
+            dir.display()
+        ))),
+        _ => Ok(()),
+    }
 }
 
+#[cfg(feature = "hf")]
 /// Number of parallel download streams used by the fast downloader.
 ///
 /// Each stream runs TLS decryption independently, so this directly trades
 /// CPU cores for download throughput.
 const FAST_DOWNLOAD_WORKER_THREADS: usize = 8;
 
-pub fn resolve_hf_model_fast(model: &HfModelRef) -> Result<PathBuf, ModelRefError> {
+#[cfg(feature = "hf")]
+pub fn resolve_hf_model_fast(
+    model: &HfModelRef,
+    options: &HfDownloadOptions,
+) -> Result<PathBuf, ModelRefError> {
+    resolve_with_shards(model, |file| fetch_hf_file_fast(file, options.timeout))
+}
+
+#[cfg(feature = "hf")]
+async fn download_with_timeout(
+    model: &HfModelRef,
+    timeout: Option<Duration>,
+) -> Result<PathBuf, ModelRefError> {
+    let download = download_hf_gguf_with_progress(model, Box::new(|_| {}));
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, download)
+            .await
+            .map_err(|_| download_timed_out(model, timeout))?,
+        None => download.await,
+    }
+}
+
+#[cfg(feature = "hf")]
+fn fetch_hf_file_fast(
+    model: &HfModelRef,
+    timeout: Option<Duration>,
+) -> Result<PathBuf, ModelRefError> {
     // Try the host's runtime first. This works when called from a regular
     // async binary, but fails when called from a cdylib plugin: each dylib
     // gets its own copy of thread-local storage, so the host's tokio runtime
@@ -402,9 +932,7 @@ pub fn resolve_hf_model_fast(model: &HfModelRef) -> Result<PathBuf, ModelRefErro
             );
             let model = model.clone();
             tokio::task::block_in_place(|| {
-                handle.block_on(async move {
-                    download_hf_gguf_with_progress(&model, Box::new(|_| {})).await
-                })
+                handle.block_on(async move { download_with_timeout(&model, timeout).await })
             })
         }
         Err(e) => {
@@ -422,14 +950,16 @@ pub fn resolve_hf_model_fast(model: &HfModelRef) -> Result<PathBuf, ModelRefErro
                 .build()
                 .map_err(|e| ModelRefError::Download(e.to_string()))?;
 
-            rt.block_on(async { download_hf_gguf_with_progress(model, Box::new(|_| {})).await })
+            rt.block_on(download_with_timeout(model, timeout))
         }
     }
 }
 
+#[cfg(feature = "hf")]
 /// Preferred mmproj filenames in priority order (best quality/size tradeoff first).
 const MMPROJ_PREFERENCES: &[&str] = &["mmproj-F16.gguf", "mmproj-BF16.gguf", "mmproj-F32.gguf"];
 
+#[cfg(feature = "hf")]
 /// Discover mmproj GGUF files in a Hugging Face repo by querying the repo's file listing.
 ///
 /// Queries the HF API for the repo's siblings and looks for filenames matching
@@ -479,17 +1009,23 @@ pub fn discover_mmproj_in_hf_repo(repo: &str) -> Result<Option<String>, ModelRef
     Ok(Some(mmproj_files[0].clone()))
 }
 
+#[cfg(feature = "hf")]
 /// Download (or return cached path) for an mmproj file from an HF repo.
 /// Uses the sync downloader; respects `fast_download` via the `fast` flag.
-pub fn resolve_hf_mmproj(repo: &str, filename: &str, fast: bool) -> Result<PathBuf, ModelRefError> {
+pub fn resolve_hf_mmproj(
+    repo: &str,
+    filename: &str,
+    fast: bool,
+    options: &HfDownloadOptions,
+) -> Result<PathBuf, ModelRefError> {
     let model_ref = HfModelRef {
         repo: repo.to_string(),
         file: filename.to_string(),
     };
     if fast {
-        resolve_hf_model_fast(&model_ref)
+        resolve_hf_model_fast(&model_ref, options)
     } else {
-        resolve_hf_model_sync(&model_ref)
+        resolve_hf_model_sync(&model_ref, options)
     }
 }
 
@@ -583,8 +1119,233 @@ mod tests {
         assert_eq!(unknown.quant, "unknown");
     }
 
+    #[test]
+    fn infer_gguf_filename_normalises_quant_selectors() {
+        for (selector, expected) in [
+            ("q4_k_m", "Model-Q4_K_M.gguf"),
+            ("Q4_K_M", "Model-Q4_K_M.gguf"),
+            ("iq4_xs", "Model-IQ4_XS.gguf"),
+            ("bf16", "Model-BF16.gguf"),
+            ("custom", "Model-custom.gguf"),
+        ] {
+            assert_eq!(infer_gguf_filename("Org/Model-GGUF", selector), expected);
+        }
+        assert_eq!(
+            infer_gguf_filename("org/model-gguf", "Q5_K_M"),
+            "model-Q5_K_M.gguf"
+        );
+    }
+
+    #[test]
+    fn match_gguf_selector_handles_common_naming_schemes() {
+        let bartowski = files(&[
+            "Model-Q4_K_M.gguf",
+            "Model-Q5_K_M.gguf",
+            "Model-IQ4_XS.gguf",
+        ]);
+        let thebloke = files(&["model.Q4_K_M.gguf", "model.Q5_K_M.gguf"]);
+        let unsloth = files(&[
+            "Q5_K_M/Model-Q5_K_M-00001-of-00002.gguf",
+            "Q5_K_M/Model-Q5_K_M-00002-of-00002.gguf",
+        ]);
+
+        for (listing, selector, expected) in [
+            (&bartowski, "q5_k_m", "Model-Q5_K_M.gguf"),
+            (&bartowski, "IQ4_XS", "Model-IQ4_XS.gguf"),
+            (&thebloke, "Q5_K_M", "model.Q5_K_M.gguf"),
+            (
+                &unsloth,
+                "q5_k_m",
+                "Q5_K_M/Model-Q5_K_M-00001-of-00002.gguf",
+            ),
+            (&bartowski, "model-q4_k_m.gguf", "Model-Q4_K_M.gguf"),
+        ] {
+            assert_eq!(
+                match_gguf_selector(listing, selector).as_deref(),
+                Some(expected),
+                "{selector}"
+            );
+        }
+        assert_eq!(match_gguf_selector(&bartowski, "Q8_0"), None);
+    }
+
+    #[test]
+    fn reconcile_replaces_templated_name_with_listed_file() {
+        let parsed = parse_model_ref("TheBloke/Foo-GGUF:q5_k_m").unwrap();
+        let ModelRef::Hf(model) = parsed else {
+            panic!("expected an hf ref, got {parsed:?}");
+        };
+        assert_eq!(model.file, "Foo-Q5_K_M.gguf");
+
+        let listing = files(&["foo.Q4_K_M.gguf", "foo.Q5_K_M.gguf"]);
+        assert_eq!(
+            reconcile_with_files(&model, &listing).file,
+            "foo.Q5_K_M.gguf"
+        );
+
+        let unknown = files(&["other.gguf"]);
+        assert_eq!(reconcile_with_files(&model, &unknown), model);
+    }
+
+    fn files(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn select_gguf_file_defaults_to_q4_k_m() {
+        let listing = files(&[
+            "README.md",
+            "Foo-7B-Q8_0.gguf",
+            "Foo-7B-Q4_K_S.gguf",
+            "Foo-7B-Q4_K_M.gguf",
+            "mmproj-Q4_K_M.gguf",
+        ]);
+        assert_eq!(
+            select_gguf_file(&listing, None).as_deref(),
+            Some("Foo-7B-Q4_K_M.gguf")
+        );
+    }
+
+    #[test]
+    fn select_gguf_file_honours_preferred_quant_case_insensitively() {
+        let listing = files(&[
+            "foo-7b.Q4_K_M.gguf",
+            "foo-7b.Q5_K_M.gguf",
+            "foo-7b.IQ4_XS.gguf",
+        ]);
+        assert_eq!(
+            select_gguf_file(&listing, Some("q5_k_m")).as_deref(),
+            Some("foo-7b.Q5_K_M.gguf")
+        );
+        assert_eq!(
+            select_gguf_file(&listing, Some("IQ4_XS")).as_deref(),
+            Some("foo-7b.IQ4_XS.gguf")
+        );
+        // An unavailable preference falls back to the default order.
+        assert_eq!(
+            select_gguf_file(&listing, Some("Q2_K")).as_deref(),
+            Some("foo-7b.Q4_K_M.gguf")
+        );
+    }
+
+    #[test]
+    fn select_gguf_file_picks_first_shard() {
+        let listing = files(&[
+            "Q4_K_M/Big-Q4_K_M-00002-of-00003.gguf",
+            "Q4_K_M/Big-Q4_K_M-00001-of-00003.gguf",
+            "Q4_K_M/Big-Q4_K_M-00003-of-00003.gguf",
+            "Q8_0/Big-Q8_0-00001-of-00005.gguf",
+        ]);
+        assert_eq!(
+            select_gguf_file(&listing, None).as_deref(),
+            Some("Q4_K_M/Big-Q4_K_M-00001-of-00003.gguf")
+        );
+    }
+
+    #[test]
+    fn select_gguf_file_falls_back_to_first_unrecognised_file() {
+        let listing = files(&["model-f16.gguf", "config.json"]);
+        assert_eq!(
+            select_gguf_file(&listing, None).as_deref(),
+            Some("model-f16.gguf")
+        );
+        assert_eq!(select_gguf_file(&files(&["config.json"]), None), None);
+    }
+
+    #[test]
+    fn parse_gguf_shard_reads_counters() {
+        assert_eq!(
+            parse_gguf_shard("Big-Q4_K_M-00002-of-00003.gguf"),
+            Some((2, 3))
+        );
+        assert_eq!(parse_gguf_shard("Big-Q4_K_M.gguf"), None);
+        assert_eq!(parse_gguf_shard("Big-2-of-3.gguf"), None);
+    }
+
+    #[test]
+    fn gguf_shard_filenames_lists_all_parts() {
+        assert_eq!(
+            gguf_shard_filenames("Q4_K_M/Big-Q4_K_M-00002-of-00003.gguf").unwrap(),
+            vec![
+                "Q4_K_M/Big-Q4_K_M-00001-of-00003.gguf",
+                "Q4_K_M/Big-Q4_K_M-00002-of-00003.gguf",
+                "Q4_K_M/Big-Q4_K_M-00003-of-00003.gguf",
+            ]
+        );
+        assert_eq!(gguf_shard_filenames("Big-Q4_K_M.gguf"), None);
+    }
+
+    fn shard_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("qmt-shards-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn resolve_with_shards_fetches_every_part_and_returns_first() {
+        let dir = shard_dir("all");
+        let model = HfModelRef {
+            repo: "org/Big-GGUF".to_string(),
+            file: "Big-Q4_K_M-00001-of-00003.gguf".to_string(),
+        };
+        let mut requested = Vec::new();
+
+        let path = resolve_with_shards(&model, |shard| {
+            assert_eq!(shard.repo, "org/Big-GGUF");
+            requested.push(shard.file.clone());
+            let path = dir.join(&shard.file);
+            std::fs::write(&path, b"GGUF").unwrap();
+            Ok(path)
+        })
+        .unwrap();
+
+        assert_eq!(
+            requested,
+            vec![
+                "Big-Q4_K_M-00001-of-00003.gguf",
+                "Big-Q4_K_M-00002-of-00003.gguf",
+                "Big-Q4_K_M-00003-of-00003.gguf",
+            ]
+        );
+        assert_eq!(path, dir.join("Big-Q4_K_M-00001-of-00003.gguf"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resolve_with_shards_rejects_missing_part() {
+        let dir = shard_dir("missing");
+        let model = HfModelRef {
+            repo: "org/Big-GGUF".to_string(),
+            file: "Big-Q4_K_M-00001-of-00002.gguf".to_string(),
+        };
+
+        let err = resolve_with_shards(&model, |shard| {
+            let path = dir.join(&shard.file);
+            if shard.file.contains("00001") {
+                std::fs::write(&path, b"GGUF").unwrap();
+            }
+            Ok(path)
+        })
+        .unwrap_err();
+
+        assert!(matches!(err, ModelRefError::Download(_)), "got {err:?}");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn resolve_with_shards_passes_single_files_through() {
+        let model = HfModelRef {
+            repo: "org/Small-GGUF".to_string(),
+            file: "Small-Q4_K_M.gguf".to_string(),
+        };
+        let path = resolve_with_shards(&model, |m| Ok(PathBuf::from(&m.file))).unwrap();
+        assert_eq!(path, PathBuf::from("Small-Q4_K_M.gguf"));
+    }
+
     /// Requires network access. Run with:
     /// `cargo test -p querymt-provider-common -- --ignored discover_mmproj`
+    #[cfg(feature = "hf")]
     #[test]
     #[ignore]
     fn discover_mmproj_qwen3vl() {
@@ -594,6 +1355,7 @@ mod tests {
         assert_eq!(file.as_deref(), Some("mmproj-F16.gguf"));
     }
 
+    #[cfg(feature = "hf")]
     #[test]
     #[ignore]
     fn discover_mmproj_text_only_repo() {
@@ -602,4 +1364,74 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
+
+    #[cfg(all(feature = "hf", unix))]
+    #[test]
+    fn free_space_preflight_rejects_files_that_do_not_fit() {
+        let dir = std::env::temp_dir().join("qmt-free-space-check/missing/subdir");
+        assert!(ensure_free_space(&dir, "tiny.gguf", 1).is_ok());
+
+        let err = ensure_free_space(&dir, "huge.gguf", u64::MAX).unwrap_err();
+        assert!(matches!(err, ModelRefError::Download(ref msg) if msg.contains("huge.gguf")));
+    }
+
+    #[cfg(all(feature = "hf", unix))]
+    #[test]
+    fn corrupt_download_is_detected_and_refetched() {
+        let dir = shard_dir("checksum");
+        let good = b"GGUF good weights";
+        let blob = dir.join("blobs").join(sha256_hex(good));
+        let pointer = dir.join("snapshots/abc/model.gguf");
+        std::fs::create_dir_all(blob.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(pointer.parent().unwrap()).unwrap();
+        std::fs::write(&blob, b"GGUF truncat").unwrap();
+        std::os::unix::fs::symlink(&blob, &pointer).unwrap();
+
+        let mut refetched = false;
+        let path = verify_or_refetch(pointer.clone(), || {
+            assert!(!blob.exists(), "corrupt blob should be removed");
+            refetched = true;
+            std::fs::write(&blob, good).unwrap();
+            std::os::unix::fs::symlink(&blob, &pointer).unwrap();
+            Ok(pointer.clone())
+        })
+        .unwrap();
+
+        assert!(refetched);
+        assert_eq!(path, pointer);
+        assert_eq!(std::fs::read(&path).unwrap(), good);
+    }
+
+    #[cfg(feature = "hf")]
+    #[test]
+    fn verify_download_fails_when_refetch_is_still_corrupt() {
+        let dir = shard_dir("checksum-corrupt");
+        let blob = dir.join(sha256_hex(b"expected"));
+        std::fs::write(&blob, b"other").unwrap();
+
+        let err = verify_or_refetch(blob.clone(), || {
+            std::fs::write(&blob, b"still other").unwrap();
+            Ok(blob.clone())
+        })
+        .unwrap_err();
+        assert!(
+            matches!(err, ModelRefError::Download(ref msg) if msg.contains("checksum mismatch"))
+        );
+        assert!(!blob.exists());
+    }
+
+    #[cfg(feature = "hf")]
+    #[test]
+    fn verify_download_skips_files_not_named_by_sha256() {
+        let dir = shard_dir("checksum-git");
+        let file = dir.join("config.json");
+        std::fs::write(&file, b"{}").unwrap();
+        assert!(verify_download(&file).is_ok());
+    }
+
+    #[cfg(feature = "hf")]
+    fn sha256_hex(data: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(data))
+    }
 }
@@ -0,0 +1,342 @@
+//! Mapping chat messages to OpenAI Chat Completions `messages` JSON.
+//!
+//! HTTP providers that speak the OpenAI format and local providers that feed
+//! OpenAI-shaped messages to chat templates both go through
+//! [`to_openai_messages`], so thinking, tool calls, tool results and images
+//! are translated the same way everywhere.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use querymt::chat::{ChatMessage, ChatRole, Content};
+use serde_json::{Map, Value, json};
+
+/// How images are represented in a message's `content`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ContentPartStyle {
+    /// Messages with images get an array of `text` and `image_url` parts,
+    /// with inline images sent as `data:` URLs. Other messages get a string.
+    #[default]
+    Parts,
+    /// `content` is always a string, with the marker in place of each inline
+    /// image, for prompt templates that expect media placeholders. Image URLs
+    /// are dropped.
+    Markers(String),
+}
+
+/// Options for [`to_openai_messages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenAIMapOpts {
+    /// Send thinking blocks as the message's `reasoning_content`.
+    pub reasoning_content: bool,
+    /// How images are represented.
+    pub content_parts: ContentPartStyle,
+    /// Include the tool's `name` on `role: "tool"` messages.
+    pub tool_result_names: bool,
+}
+
+impl Default for OpenAIMapOpts {
+    fn default() -> Self {
+        Self {
+            reasoning_content: true,
+            content_parts: ContentPartStyle::Parts,
+            tool_result_names: false,
+        }
+    }
+}
+
+/// Convert `messages` to OpenAI-style message objects, in order.
+///
+/// - Each `ToolResult` block becomes a `role: "tool"` message. Tool messages
+///   can't carry images, so images in tool results follow as `user` messages,
+///   one per image.
+/// - `ToolUse` blocks become `tool_calls`; `content` is `null` when the
+///   message has no text.
+/// - Text blocks are joined with newlines, and the participant `name` is kept.
+/// - Messages left without content are skipped.
+///
+/// System messages are mapped like any other message; hoisting or merging
+/// them is up to the caller.
+pub fn to_openai_messages(messages: &[ChatMessage], opts: OpenAIMapOpts) -> Vec<Value> {
+    let mut out = Vec::new();
+    for msg in messages {
+        push_message(msg, &opts, &mut out);
+    }
+    out
+}
+
+fn push_message(msg: &ChatMessage, opts: &OpenAIMapOpts, out: &mut Vec<Value>) {
+    let mut result_images = Vec::new();
+    for block in &msg.content {
+        let Content::ToolResult {
+            id, name, content, ..
+        } = block
+        else {
+            continue;
+        };
+        let text = content
+            .iter()
+            .filter_map(Content::as_text)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut tool = json!({
+            "role": "tool",
+            "tool_call_id": id,
+            "content": text,
+        });
+        if opts.tool_result_names {
+            tool["name"] = json!(name.as_deref().unwrap_or_default());
+        }
+        out.push(tool);
+        result_images.extend(
+            content
+                .iter()
+                .filter(|c| matches!(c, Content::Image { .. } | Content::ImageUrl { .. })),
+        );
+    }
+    for image in result_images {
+        if let Some(content) = render_content(&[image], &opts.content_parts) {
+            out.push(json!({ "role": "user", "content": content }));
+        }
+    }
+
+    let tool_calls: Vec<Value> = msg
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            Content::ToolUse {
+                id,
+                name,
+                arguments,
+            } => Some(json!({
+                "id": id,
+                "type": "function",
+                "function": { "name": name, "arguments": arguments.to_string() },
+            })),
+            _ => None,
+        })
+        .collect();
+
+    let thinking = if opts.reasoning_content {
+        msg.content
+            .iter()
+            .filter_map(|block| match block {
+                Content::Thinking { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        String::new()
+    };
+
+    let blocks: Vec<&Content> = msg.content.iter().filter(|b| !b.is_tool_result()).collect();
+    let content = render_content(&blocks, &opts.content_parts);
+    if content.is_none() && tool_calls.is_empty() && thinking.is_empty() {
+        return;
+    }
+
+    let role = match msg.role {
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+        ChatRole::System => "system",
+    };
+    let mut obj = Map::new();
+    obj.insert("role".into(), json!(role));
+    if let Some(name) = &msg.name {
+        obj.insert("name".into(), json!(name));
+    }
+    let content = match content {
+        Some(content) => content,
+        None if tool_calls.is_empty() => json!(""),
+        None => Value::Null,
+    };
+    obj.insert("content".into(), content);
+    if !tool_calls.is_empty() {
+        obj.insert("tool_calls".into(), Value::Array(tool_calls));
+    }
+    if !thinking.is_empty() {
+        obj.insert("reasoning_content".into(), json!(thinking));
+    }
+    out.push(Value::Object(obj));
+}
+
+/// The `content` value for the text and image `blocks`, or `None` if there
+/// is nothing to send.
+fn render_content(blocks: &[&Content], style: &ContentPartStyle) -> Option<Value> {
+    match style {
+        ContentPartStyle::Markers(marker) => {
+            // Markers go right before the text that follows their image, so
+            // N images always produce exactly N markers.
+            let mut parts: Vec<&str> = Vec::new();
+            let mut pending = 0;
+            for block in blocks {
+                match block {
+                    Content::Text { text } => {
+                        parts.extend(std::iter::repeat_n(marker.as_str(), pending));
+                        pending = 0;
+                        parts.push(text);
+                    }
+                    Content::Image { .. } => pending += 1,
+                    Content::ImageUrl { .. } => {
+                        log::warn!("Image URL skipped: only inline images can be marked");
+                    }
+                    _ => {}
+                }
+            }
+            parts.extend(std::iter::repeat_n(marker.as_str(), pending));
+            let text = parts.join("\n");
+            (!text.is_empty()).then(|| json!(text))
+        }
+        ContentPartStyle::Parts => {
+            let has_images = blocks
+                .iter()
+                .any(|b| matches!(b, Content::Image { .. } | Content::ImageUrl { .. }));
+            if !has_images {
+                let text = blocks
+                    .iter()
+                    .filter_map(|b| b.as_text())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                return (!text.is_empty()).then(|| json!(text));
+            }
+            let parts: Vec<Value> = blocks
+                .iter()
+                .filter_map(|block| match block {
+                    Content::Text { text } => Some(json!({ "type": "text", "text": text })),
+                    Content::ImageUrl { url } => {
+                        Some(json!({ "type": "image_url", "image_url": { "url": url } }))
+                    }
+                    Content::Image { mime_type, data } => Some(json!({
+                        "type": "image_url",
+                        "image_url": {
+                            "url": format!("data:{mime_type};base64,{}", BASE64.encode(data))
+                        }
+                    })),
+                    _ => None,
+                })
+                .collect();
+            Some(Value::Array(parts))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers(marker: &str) -> OpenAIMapOpts {
+        OpenAIMapOpts {
+            content_parts: ContentPartStyle::Markers(marker.to_string()),
+            ..OpenAIMapOpts::default()
+        }
+    }
+
+    #[test]
+    fn maps_text_thinking_and_name() {
+        let messages = vec![
+            ChatMessage::user().name("alice").text("Hello").build(),
+            ChatMessage::assistant()
+                .thinking("Greet back.")
+                .text("Hi!")
+                .build(),
+        ];
+        let out = to_openai_messages(&messages, OpenAIMapOpts::default());
+        assert_eq!(
+            out,
+            vec![
+                json!({ "role": "user", "name": "alice", "content": "Hello" }),
+                json!({ "role": "assistant", "content": "Hi!", "reasoning_content": "Greet back." }),
+            ]
+        );
+
+        let opts = OpenAIMapOpts {
+            reasoning_content: false,
+            ..OpenAIMapOpts::default()
+        };
+        let out = to_openai_messages(&messages[1..], opts);
+        assert!(out[0].get("reasoning_content").is_none());
+    }
+
+    #[test]
+    fn maps_tool_use_to_tool_calls() {
+        let messages = vec![
+            ChatMessage::assistant()
+                .tool_use("call_1", "get_weather", json!({ "city": "Paris" }))
+                .build(),
+        ];
+        let out = to_openai_messages(&messages, OpenAIMapOpts::default());
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0]["role"], "assistant");
+        assert!(out[0]["content"].is_null());
+        assert_eq!(out[0]["tool_calls"][0]["id"], "call_1");
+        assert_eq!(out[0]["tool_calls"][0]["type"], "function");
+        assert_eq!(out[0]["tool_calls"][0]["function"]["name"], "get_weather");
+        assert_eq!(
+            out[0]["tool_calls"][0]["function"]["arguments"],
+            r#"{"city":"Paris"}"#
+        );
+    }
+
+    #[test]
+    fn maps_tool_results_to_tool_messages() {
+        let messages = vec![
+            ChatMessage::user()
+                .tool_result(
+                    "call_1".into(),
+                    Some("get_weather".into()),
+                    false,
+                    vec![
+                        Content::text("22C"),
+                        Content::image("image/png", vec![1, 2, 3]),
+                    ],
+                )
+                .text("Anything else?")
+                .build(),
+        ];
+
+        let out = to_openai_messages(&messages, OpenAIMapOpts::default());
+        assert_eq!(out.len(), 3);
+        assert_eq!(
+            out[0],
+            json!({ "role": "tool", "tool_call_id": "call_1", "content": "22C" })
+        );
+        assert_eq!(out[1]["role"], "user");
+        assert_eq!(out[1]["content"][0]["type"], "image_url");
+        assert_eq!(
+            out[2],
+            json!({ "role": "user", "content": "Anything else?" })
+        );
+
+        let opts = OpenAIMapOpts {
+            tool_result_names: true,
+            ..markers("<M>")
+        };
+        let out = to_openai_messages(&messages, opts);
+        assert_eq!(out[0]["name"], "get_weather");
+        assert_eq!(out[1], json!({ "role": "user", "content": "<M>" }));
+    }
+
+    #[test]
+    fn maps_images_as_parts_or_markers() {
+        let messages = vec![
+            ChatMessage::user()
+                .image("image/png", vec![0x89, 0x50])
+                .text("What is this?")
+                .image_url("https://example.com/a.jpg")
+                .build(),
+        ];
+
+        let out = to_openai_messages(&messages, OpenAIMapOpts::default());
+        assert_eq!(
+            out[0]["content"],
+            json!([
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVA=" } },
+                { "type": "text", "text": "What is this?" },
+                { "type": "image_url", "image_url": { "url": "https://example.com/a.jpg" } },
+            ])
+        );
+
+        let out = to_openai_messages(&messages, markers("<M>"));
+        assert_eq!(out[0]["content"], "<M>\nWhat is this?");
+    }
+}
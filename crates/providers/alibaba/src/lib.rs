@@ -11,7 +11,7 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -23,7 +23,10 @@ use url::Url;
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Alibaba {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "Alibaba::default_base_url")]
+    #[serde(
+        default = "Alibaba::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: String,
     pub model: String,
@@ -44,6 +47,10 @@ pub struct Alibaba {
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
     pub thinking_budget: Option<u32>,
+    /// Sampling seed for best-effort reproducible output
+    pub seed: Option<u64>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAIProviderConfig for Alibaba {
@@ -111,6 +118,10 @@ impl OpenAIProviderConfig for Alibaba {
         self.json_schema.as_ref()
     }
 
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     fn extra_body(&self) -> Option<serde_json::Map<String, Value>> {
         if let Some(thinking_budget) = self.thinking_budget {
             let mut map = Map::new();
@@ -120,6 +131,10 @@ impl OpenAIProviderConfig for Alibaba {
         }
         None
     }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for Alibaba {
@@ -221,6 +236,19 @@ impl HTTPLLMProviderFactory for AlibabaFactory {
         "alibaba"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("ALIBABA_API_KEY".into())
     }
@@ -263,6 +291,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(AlibabaFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Alibaba, AlibabaFactory};
@@ -274,3 +311,22 @@ mod extism_exports {
         name   = "alibaba",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_request_includes_seed() {
+        let provider: Alibaba = serde_json::from_value(serde_json::json!({
+            "api_key": "test-key",
+            "model": "qwen-plus",
+            "seed": 42
+        }))
+        .unwrap();
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["seed"], 42);
+    }
+}
@@ -1,6 +1,10 @@
 use http::{Method, Request, Response, header::CONTENT_TYPE};
 use querymt::{
-    HTTPLLMProvider, error::LLMError, handle_http_error, plugin::HTTPLLMProviderFactory,
+    HTTPLLMProvider,
+    error::{LLMError, decode_json_response},
+    handle_http_error,
+    params::normalize_base_url,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::schema_for;
 use serde_json::Value;
@@ -21,14 +25,31 @@ impl HTTPLLMProviderFactory for AnthropicFactory {
         "anthropic"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: true,
+            embeddings: false,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("ANTHROPIC_API_KEY".into())
     }
 
+    fn default_model(&self) -> Option<&str> {
+        Some("claude-sonnet-4-5-20250929")
+    }
+
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => normalize_base_url(Url::parse(base_url_str)?),
             None => Anthropic::default_base_url(),
         };
 
@@ -65,7 +86,7 @@ impl HTTPLLMProviderFactory for AnthropicFactory {
     fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
         handle_http_error!(resp);
 
-        let resp_json: Value = serde_json::from_slice(resp.body())?;
+        let resp_json: Value = decode_json_response(resp.body(), "Anthropic models response")?;
         let arr = resp_json
             .get("data")
             .and_then(Value::as_array)
@@ -97,6 +118,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(AnthropicFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Anthropic, AnthropicFactory};
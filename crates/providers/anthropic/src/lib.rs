@@ -7,6 +7,10 @@ use std::collections::HashMap;
 /// Tool name prefix used for OAuth requests to avoid conflicts with server-side tools
 const TOOL_PREFIX: &str = "mcp_";
 
+/// Fallback `max_tokens` used when the config does not specify one.
+/// Anthropic requires the field on every request; 4096 is accepted by every Claude model.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
 /// OAuth system prompt
 const OAUTH_SYSTEM_PROMPT: &str = "You are Claude Code, Anthropic's official CLI for Claude.";
 
@@ -19,16 +23,18 @@ use querymt::{
     FunctionCall, HTTPLLMProvider, ToolCall, Usage,
     auth::ApiKeyResolver,
     chat::{
-        ChatMessage, ChatResponse, ChatRole, Content, FinishReason, ReasoningEffort, Tool,
-        ToolChoice,
+        ChatMessage, ChatResponse, ChatRole, Citation, Content, FinishReason, ReasoningEffort,
+        ServerToolUse, Tool, ToolChoice,
         http::{ChatStreamParser, HTTPChatProvider},
+        system_prompts,
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     handle_http_error,
+    params::merge_extra_body,
 };
-use schemars::JsonSchema;
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
@@ -94,12 +100,24 @@ pub fn detect_auth_type(api_key: &str, explicit_auth_type: Option<AuthType>) ->
     AuthType::ApiKey
 }
 
+pub fn url_schema(_gen: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "format": "uri"
+    })
+}
+
 /// Client for interacting with Anthropic's API.
 ///
 /// Provides methods for chat and completion requests using Anthropic's models.
 #[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Anthropic {
+    /// Optional: Override the API base URL (e.g. an internal LLM gateway or proxy).
+    /// Defaults to `https://api.anthropic.com/v1/`.
+    #[schemars(schema_with = "url_schema")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<Url>,
     pub api_key: String,
     /// Optional: Explicitly specify authentication type.
     /// If not provided, will auto-detect based on api_key format:
@@ -108,23 +126,104 @@ pub struct Anthropic {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub auth_type: Option<AuthType>,
     pub model: String,
-    pub max_tokens: u32,
+    /// Maximum tokens to generate. When omitted, a conservative default is sent
+    /// (raised above the thinking budget when reasoning is enabled).
+    pub max_tokens: Option<u32>,
     pub temperature: Option<f32>,
     pub timeout_seconds: Option<u64>,
     pub system: Option<AnthropicSystemPrompt>,
+    /// When true, the system prompt is always sent as an array of blocks with an
+    /// ephemeral `cache_control` breakpoint on the last block, so large stable
+    /// prompts are served from the prompt cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_system_prompt: Option<bool>,
     pub stream: Option<bool>,
     pub top_p: Option<f32>,
     pub top_k: Option<u32>,
+    /// Custom sequences that stop generation when emitted by the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
     pub tools: Option<Vec<Tool>>,
+    /// Beta features to enable, sent as a comma-separated `anthropic-beta`
+    /// header.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beta_features: Option<Vec<AnthropicBeta>>,
+    /// Server-side tools run by Anthropic itself, such as web search or code
+    /// execution, sent alongside `tools`. Their calls and results are
+    /// reported by [`ChatResponse::server_tool_uses`], not as tool calls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tools: Option<Vec<AnthropicServerTool>>,
     pub tool_choice: Option<ToolChoice>,
     pub reasoning_effort: Option<ReasoningEffort>,
     pub reasoning_budget_tokens: Option<u32>,
+    /// End-user identifier sent as `metadata.user_id`, for abuse monitoring and
+    /// per-user attribution in multi-tenant deployments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Accepted for parity with other providers, but ignored with a warning:
+    /// the Messages API has no sampling seed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Extra fields merged into the request body, for parameters this crate
+    /// doesn't model yet. Keys here override the computed ones; nested
+    /// objects are merged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_body: Option<serde_json::Map<String, Value>>,
     /// Optional resolver for dynamic credential refresh (e.g., OAuth tokens).
     #[serde(skip)]
     #[schemars(skip)]
     pub key_resolver: Option<Arc<dyn ApiKeyResolver>>,
 }
 
+/// A known `anthropic-beta` feature. Features without a variant yet are
+/// given by their header value, e.g. `"files-api-2025-04-14"`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnthropicBeta {
+    #[serde(alias = "prompt-caching-2024-07-31")]
+    PromptCaching,
+    /// 1M token context window.
+    #[serde(rename = "context_1m", alias = "context-1m-2025-08-07")]
+    Context1M,
+    /// 128k output tokens.
+    #[serde(alias = "output-128k-2025-02-19")]
+    ExtendedOutput,
+    #[serde(alias = "fine-grained-tool-streaming-2025-05-14")]
+    FineGrainedToolStreaming,
+    #[serde(alias = "interleaved-thinking-2025-05-14")]
+    InterleavedThinking,
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl AnthropicBeta {
+    /// The value sent in the `anthropic-beta` header.
+    pub fn header_value(&self) -> &str {
+        match self {
+            Self::PromptCaching => "prompt-caching-2024-07-31",
+            Self::Context1M => "context-1m-2025-08-07",
+            Self::ExtendedOutput => "output-128k-2025-02-19",
+            Self::FineGrainedToolStreaming => "fine-grained-tool-streaming-2025-05-14",
+            Self::InterleavedThinking => "interleaved-thinking-2025-05-14",
+            Self::Other(value) => value,
+        }
+    }
+}
+
+/// A server-side tool declaration, e.g.
+/// `{"type": "web_search_20250305", "name": "web_search", "max_uses": 5}`.
+///
+/// `type` selects the tool and its version; any other fields are passed
+/// through as the tool's options.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct AnthropicServerTool {
+    #[serde(rename = "type")]
+    pub tool_type: String,
+    pub name: String,
+    #[serde(flatten)]
+    pub options: serde_json::Map<String, Value>,
+}
+
 /// Per-block accumulator used while streaming tool-use content.
 ///
 /// Anthropic's SSE protocol emits tool arguments as a sequence of
@@ -153,6 +252,14 @@ struct AnthropicTool<'a> {
     schema: &'a serde_json::Value,
 }
 
+/// Entry of the request's `tools` array.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum AnthropicToolParam<'a> {
+    Function(AnthropicTool<'a>),
+    Server(&'a AnthropicServerTool),
+}
+
 /// Configuration for the thinking feature
 #[derive(Serialize, Debug)]
 struct ThinkingConfig {
@@ -180,11 +287,21 @@ struct AnthropicCompleteRequest<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     top_k: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    tools: Option<Vec<AnthropicTool<'a>>>,
+    stop_sequences: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<AnthropicToolParam<'a>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tool_choice: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     thinking: Option<ThinkingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<AnthropicMetadata<'a>>,
+}
+
+/// Request metadata; `user_id` attributes the request to an end user.
+#[derive(Serialize, Debug)]
+struct AnthropicMetadata<'a> {
+    user_id: &'a str,
 }
 
 /// Individual message in an Anthropic chat conversation.
@@ -404,6 +521,15 @@ pub struct TextBlockParam {
     pub citations: Option<Vec<TextCitationParam>>,
 }
 
+fn text_block(text: String) -> TextBlockParam {
+    TextBlockParam {
+        block_type: "text".to_string(),
+        text,
+        cache_control: None,
+        citations: None,
+    }
+}
+
 /// Anthropic system prompt: either a plain string or an array of TextBlockParam.
 ///
 /// Deserializes from three JSON shapes:
@@ -470,9 +596,17 @@ impl<'de> Deserialize<'de> for AnthropicSystemPrompt {
 /// Response from Anthropic's messages API endpoint.
 #[derive(Deserialize, Debug)]
 struct AnthropicCompleteResponse {
+    /// Message id (`msg_…`).
+    id: Option<String>,
     content: Vec<AnthropicContent>,
     stop_reason: String,
+    /// The custom stop sequence that ended generation (when `stop_reason` is `stop_sequence`)
+    #[serde(default)]
+    stop_sequence: Option<String>,
     usage: Option<Usage>,
+    /// `request-id` response header.
+    #[serde(skip)]
+    request_id: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -510,6 +644,10 @@ struct AnthropicStreamContentBlock {
     /// Initial text (for text blocks, usually empty)
     #[allow(dead_code)]
     text: Option<String>,
+    /// Initial thinking text (for thinking blocks, usually empty)
+    thinking: Option<String>,
+    /// Initial signature (for thinking blocks, usually empty)
+    signature: Option<String>,
 }
 
 /// Delta content within an Anthropic streaming response.
@@ -536,9 +674,39 @@ struct AnthropicContent {
     #[serde(rename = "type")]
     content_type: Option<String>,
     thinking: Option<String>,
+    /// Signature of a thinking block, required to replay it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
     name: Option<String>,
     input: Option<serde_json::Value>,
     id: Option<String>,
+    /// Id of the `server_tool_use` block a server tool result answers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_use_id: Option<String>,
+    /// Payload of a server tool result block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<serde_json::Value>,
+    /// Sources backing a text block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    citations: Option<Vec<AnthropicCitation>>,
+}
+
+/// Citation attached to a text block. Web search results carry `url` and
+/// `title`; document citations carry `document_title`.
+#[derive(Serialize, Deserialize, Debug)]
+struct AnthropicCitation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    document_title: Option<String>,
+}
+
+impl AnthropicContent {
+    fn is_text(&self) -> bool {
+        self.content_type.is_none() || self.content_type.as_deref() == Some("text")
+    }
 }
 
 impl std::fmt::Display for AnthropicCompleteResponse {
@@ -574,13 +742,8 @@ impl ChatResponse for AnthropicCompleteResponse {
         Some(
             self.content
                 .iter()
-                .filter_map(|c| {
-                    if c.content_type == Some("text".to_string()) || c.content_type.is_none() {
-                        c.text.clone()
-                    } else {
-                        None
-                    }
-                })
+                .filter(|c| c.is_text())
+                .filter_map(|c| c.text.clone())
                 .collect::<Vec<_>>()
                 .join("\n"),
         )
@@ -593,6 +756,13 @@ impl ChatResponse for AnthropicCompleteResponse {
             .and_then(|c| c.thinking.clone())
     }
 
+    fn thinking_signature(&self) -> Option<String> {
+        self.content
+            .iter()
+            .find(|c| c.content_type == Some("thinking".to_string()))
+            .and_then(|c| c.signature.clone())
+    }
+
     fn tool_calls(&self) -> Option<Vec<ToolCall>> {
         match self
             .content
@@ -626,27 +796,89 @@ impl ChatResponse for AnthropicCompleteResponse {
     }
 
     fn finish_reason(&self) -> Option<FinishReason> {
-        Some(match self.stop_reason.as_ref() {
-            "end_turn" => FinishReason::Stop,
-            "max_tokens" => FinishReason::Length,
-            "stop_sequence" => FinishReason::Stop,
-            "tool_use" => FinishReason::ToolCalls,
-            "refusal" | "pause_turn" => FinishReason::Other,
-            _ => FinishReason::Unknown,
-        })
+        Some(Anthropic::map_stop_reason(&self.stop_reason))
+    }
+
+    fn stop_sequence(&self) -> Option<String> {
+        self.stop_sequence.clone()
+    }
+
+    fn refusal(&self) -> Option<String> {
+        if self.stop_reason != "refusal" {
+            return None;
+        }
+        self.text().filter(|text| !text.is_empty())
+    }
+
+    fn response_id(&self) -> Option<String> {
+        self.id.clone().or_else(|| self.request_id.clone())
+    }
+
+    /// Each citation spans the text block it is attached to.
+    fn citations(&self) -> Option<Vec<Citation>> {
+        let mut citations = Vec::new();
+        let mut offset = 0;
+        for block in self.content.iter().filter(|c| c.is_text()) {
+            let Some(text) = &block.text else { continue };
+            for citation in block.citations.iter().flatten() {
+                citations.push(Citation {
+                    title: citation
+                        .title
+                        .clone()
+                        .or_else(|| citation.document_title.clone()),
+                    url: citation.url.clone(),
+                    start: Some(offset),
+                    end: Some(offset + text.len()),
+                });
+            }
+            // `text()` joins blocks with a newline.
+            offset += text.len() + 1;
+        }
+        (!citations.is_empty()).then_some(citations)
+    }
+
+    fn server_tool_uses(&self) -> Option<Vec<ServerToolUse>> {
+        let uses: Vec<ServerToolUse> = self
+            .content
+            .iter()
+            .filter(|c| c.content_type.as_deref() == Some("server_tool_use"))
+            .map(|c| {
+                let id = c.id.clone().unwrap_or_default();
+                // Results come as `web_search_tool_result`,
+                // `code_execution_tool_result`, ... blocks.
+                let result = self
+                    .content
+                    .iter()
+                    .find(|r| {
+                        r.tool_use_id.as_deref() == Some(id.as_str())
+                            && r.content_type
+                                .as_deref()
+                                .is_some_and(|t| t.ends_with("_tool_result"))
+                    })
+                    .and_then(|r| r.content.clone());
+                ServerToolUse {
+                    id,
+                    name: c.name.clone().unwrap_or_default(),
+                    input: c.input.clone().unwrap_or(Value::Null),
+                    result,
+                }
+            })
+            .collect();
+        (!uses.is_empty()).then_some(uses)
     }
 }
 
 impl Anthropic {
     /// Map a raw Anthropic `stop_reason` string to a typed `FinishReason`.
     ///
-    /// Reuses the same mapping logic as `AnthropicCompleteResponse::finish_reason()`.
+    /// Shared by `AnthropicCompleteResponse::finish_reason()` and the stream parser.
     pub(crate) fn map_stop_reason(stop_reason: &str) -> FinishReason {
         match stop_reason {
             "end_turn" | "stop_sequence" => FinishReason::Stop,
             "max_tokens" => FinishReason::Length,
             "tool_use" => FinishReason::ToolCalls,
-            "refusal" | "pause_turn" => FinishReason::Other,
+            "refusal" => FinishReason::Refusal,
+            "pause_turn" => FinishReason::Other,
             _ => FinishReason::Unknown,
         }
     }
@@ -655,6 +887,14 @@ impl Anthropic {
         Url::parse("https://api.anthropic.com/v1/").unwrap()
     }
 
+    /// Returns the configured base URL, falling back to the public Anthropic API.
+    fn base_url(&self) -> Url {
+        match &self.base_url {
+            Some(url) => querymt::params::normalize_base_url(url.clone()),
+            None => Anthropic::default_base_url(),
+        }
+    }
+
     /// Returns the current API key, using the resolver if available.
     fn resolved_key(&self) -> String {
         if let Some(ref resolver) = self.key_resolver {
@@ -716,6 +956,56 @@ impl Anthropic {
         }
     }
 
+    /// Appends the text of system-role messages to the system prompt,
+    /// converting a plain `Text` prompt to `Blocks` when both are present.
+    fn append_system_messages(
+        system: Option<AnthropicSystemPrompt>,
+        extra: Vec<String>,
+    ) -> Option<AnthropicSystemPrompt> {
+        if extra.is_empty() {
+            return system;
+        }
+        let mut blocks = match system {
+            None => return Some(AnthropicSystemPrompt::Text(extra.join("\n\n"))),
+            Some(AnthropicSystemPrompt::Text(text)) => vec![text_block(text)],
+            Some(AnthropicSystemPrompt::Blocks(blocks)) => blocks,
+        };
+        blocks.extend(extra.into_iter().map(text_block));
+        Some(AnthropicSystemPrompt::Blocks(blocks))
+    }
+
+    /// Marks the last system block as a cache breakpoint when
+    /// `cache_system_prompt` is enabled, converting a plain `Text` prompt to
+    /// `Blocks`. Blocks that already carry `cache_control` are left untouched.
+    fn apply_system_cache(
+        &self,
+        system: Option<AnthropicSystemPrompt>,
+    ) -> Option<AnthropicSystemPrompt> {
+        if !self.cache_system_prompt.unwrap_or(false) {
+            return system;
+        }
+
+        let mut blocks = match system? {
+            AnthropicSystemPrompt::Text(text) => vec![TextBlockParam {
+                block_type: "text".to_string(),
+                text,
+                cache_control: None,
+                citations: None,
+            }],
+            AnthropicSystemPrompt::Blocks(blocks) => blocks,
+        };
+        let has_breakpoint = blocks.iter().any(|b| b.cache_control.is_some());
+        if let Some(last) = blocks.last_mut()
+            && !has_breakpoint
+        {
+            last.cache_control = Some(CacheControlEphemeral {
+                control_type: "ephemeral".to_string(),
+                ttl: None,
+            });
+        }
+        Some(AnthropicSystemPrompt::Blocks(blocks))
+    }
+
     /// Prefixes a tool name with TOOL_PREFIX if using OAuth
     fn prefix_tool_name(&self, name: &str) -> String {
         if self.is_oauth() {
@@ -742,25 +1032,59 @@ impl Anthropic {
         }
     }
 
+    /// Returns the `max_tokens` value to send.
+    ///
+    /// Uses the configured value when present. Otherwise falls back to
+    /// `DEFAULT_MAX_TOKENS`, making sure it stays above the thinking budget
+    /// since Anthropic rejects `budget_tokens >= max_tokens`.
+    fn effective_max_tokens(&self, thinking_budget: Option<u32>) -> u32 {
+        if let Some(max_tokens) = self.max_tokens {
+            return max_tokens;
+        }
+        match thinking_budget {
+            Some(budget) if budget >= DEFAULT_MAX_TOKENS => {
+                budget.saturating_add(DEFAULT_MAX_TOKENS)
+            }
+            _ => DEFAULT_MAX_TOKENS,
+        }
+    }
+
     /// Strips the TOOL_PREFIX from a tool name if present (used for responses)
     fn strip_tool_prefix(name: &str) -> String {
         name.strip_prefix(TOOL_PREFIX).unwrap_or(name).to_string()
     }
 
+    /// The `anthropic-beta` header value: the betas OAuth requires plus the
+    /// configured `beta_features`, without duplicates.
+    fn beta_header(&self, auth_type: &AuthType) -> Option<String> {
+        let mut betas: Vec<&str> = match auth_type {
+            AuthType::OAuth => vec![
+                "oauth-2025-04-20",
+                AnthropicBeta::InterleavedThinking.header_value(),
+            ],
+            AuthType::ApiKey => Vec::new(),
+        };
+        for beta in self.beta_features.iter().flatten() {
+            if !betas.contains(&beta.header_value()) {
+                betas.push(beta.header_value());
+            }
+        }
+        (!betas.is_empty()).then(|| betas.join(","))
+    }
+
     /// Adds authentication headers to the request builder based on auth type
     fn add_auth_headers(&self, builder: http::request::Builder) -> http::request::Builder {
         let key = self.resolved_key();
         let auth_type = self.determine_auth_type();
-        let builder = match auth_type {
+        let mut builder = match auth_type {
             AuthType::OAuth => builder
                 .header(AUTHORIZATION, format!("Bearer {}", key))
-                .header(
-                    "anthropic-beta",
-                    "oauth-2025-04-20,interleaved-thinking-2025-05-14",
-                )
                 .header(USER_AGENT, "claude-cli/2.1.2 (external, cli)"),
             AuthType::ApiKey => builder.header("x-api-key", &key),
         };
+        if let Some(betas) = self.beta_header(&auth_type) {
+            builder = builder.header("anthropic-beta", betas);
+        }
         builder.header("anthropic-version", "2023-06-01")
     }
 }
@@ -774,11 +1098,17 @@ impl HTTPChatProvider for Anthropic {
         if self.resolved_key().is_empty() {
             return Err(LLMError::AuthError("Missing Anthropic API key".to_string()));
         }
+        if self.seed.is_some() {
+            log::warn!("Anthropic does not support `seed`; ignoring it");
+        }
 
         let anthropic_messages: Vec<AnthropicMessage> = messages
             .iter()
+            .filter(|m| !m.is_system())
             .map(|m| {
                 let mut content: Vec<MessageContent> = Vec::new();
+                // Replayed thinking must precede the turn's text and tool_use blocks.
+                let mut thinking_blocks = 0;
 
                 for block in &m.content {
                     match block {
@@ -794,14 +1124,21 @@ impl HTTPChatProvider for Anthropic {
                         }
                         Content::Thinking { text, signature } => {
                             // Anthropic requires signed thinking blocks when replaying
-                            // assistant history. Skip unsigned legacy blocks.
-                            if let Some(signature) = signature {
-                                content.push(MessageContent::Thinking {
-                                    content_type: "thinking",
-                                    thinking: text.clone(),
-                                    signature: signature.clone(),
-                                    cache_control: None,
-                                });
+                            // assistant history. Skip unsigned legacy blocks, and
+                            // thinking in non-assistant turns, which the API rejects.
+                            if let Some(signature) = signature
+                                && matches!(m.role, ChatRole::Assistant)
+                            {
+                                content.insert(
+                                    thinking_blocks,
+                                    MessageContent::Thinking {
+                                        content_type: "thinking",
+                                        thinking: text.clone(),
+                                        signature: signature.clone(),
+                                        cache_control: None,
+                                    },
+                                );
+                                thinking_blocks += 1;
                             }
                         }
                         Content::Image { mime_type, data } => {
@@ -914,8 +1251,9 @@ impl HTTPChatProvider for Anthropic {
 
                 AnthropicMessage {
                     role: match m.role {
-                        ChatRole::User => "user",
                         ChatRole::Assistant => "assistant",
+                        // System messages were hoisted into `system` above.
+                        ChatRole::User | ChatRole::System => "user",
                     },
                     content,
                 }
@@ -923,16 +1261,24 @@ impl HTTPChatProvider for Anthropic {
             .collect();
 
         let maybe_tool_slice: Option<&[Tool]> = tools.or(self.tools.as_deref());
-        let anthropic_tools = maybe_tool_slice.map(|slice| {
-            slice
-                .iter()
-                .map(|tool| AnthropicTool {
+        let anthropic_tools: Vec<AnthropicToolParam> = maybe_tool_slice
+            .unwrap_or_default()
+            .iter()
+            .map(|tool| {
+                AnthropicToolParam::Function(AnthropicTool {
                     name: self.prefix_tool_name(&tool.function.name),
                     description: &tool.function.description,
                     schema: &tool.function.parameters,
                 })
-                .collect::<Vec<_>>()
-        });
+            })
+            .chain(
+                self.server_tools
+                    .iter()
+                    .flatten()
+                    .map(AnthropicToolParam::Server),
+            )
+            .collect();
+        let anthropic_tools = (!anthropic_tools.is_empty()).then_some(anthropic_tools);
 
         let tool_choice = match self.tool_choice {
             Some(ToolChoice::Auto) => {
@@ -974,12 +1320,17 @@ impl HTTPChatProvider for Anthropic {
         });
 
         // Use sanitized system prompt for OAuth requests
-        let sanitized_system = self.sanitize_system_prompt();
+        let sanitized_system = self.apply_system_cache(Self::append_system_messages(
+            self.sanitize_system_prompt(),
+            system_prompts(messages),
+        ));
 
         let req_body = AnthropicCompleteRequest {
             messages: anthropic_messages,
             model: &self.model,
-            max_tokens: Some(self.max_tokens),
+            max_tokens: Some(
+                self.effective_max_tokens(thinking.as_ref().and_then(|t| t.budget_tokens)),
+            ),
             temperature: if self.reasoning_effort.is_some() {
                 // NOTE: Anthropic reasoning mode expects fixed temperature = 1.0.
                 Some(1.0)
@@ -990,13 +1341,25 @@ impl HTTPChatProvider for Anthropic {
             stream: self.stream,
             top_p: self.top_p,
             top_k: self.top_k,
+            stop_sequences: self.stop_sequences.as_deref(),
             tools: anthropic_tools,
             tool_choice: final_tool_choice,
             thinking,
+            metadata: self
+                .user_id
+                .as_deref()
+                .map(|user_id| AnthropicMetadata { user_id }),
         };
 
-        let json_req = serde_json::to_vec(&req_body)?;
-        let mut url = Anthropic::default_base_url().join("messages")?;
+        let json_req = match &self.extra_body {
+            Some(extra) => {
+                let mut body = serde_json::to_value(&req_body)?;
+                merge_extra_body(&mut body, extra);
+                serde_json::to_vec(&body)?
+            }
+            None => serde_json::to_vec(&req_body)?,
+        };
+        let mut url = self.base_url().join("messages")?;
 
         // Add beta query parameter for OAuth requests
         if self.is_oauth() {
@@ -1026,8 +1389,13 @@ impl HTTPChatProvider for Anthropic {
     fn parse_chat(&self, resp: Response<Vec<u8>>) -> Result<Box<dyn ChatResponse>, LLMError> {
         handle_http_error!(resp);
 
-        let mut json_resp: AnthropicCompleteResponse = serde_json::from_slice(resp.body())
-            .map_err(|e| LLMError::HttpError(format!("Failed to parse JSON: {}", e)))?;
+        let mut json_resp: AnthropicCompleteResponse =
+            decode_json_response(resp.body(), "Anthropic API response")?;
+        json_resp.request_id = resp
+            .headers()
+            .get("request-id")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
 
         // Strip tool prefix from tool names in response (for OAuth)
         if self.is_oauth() {
@@ -1112,8 +1480,15 @@ impl ChatStreamParser for AnthropicStreamParser {
                                     name,
                                 });
                             } else if block.block_type == "thinking" {
-                                self.thinking_state_buffer
-                                    .insert(index, AnthropicThinkingState::default());
+                                if let Some(thinking) = block.thinking.filter(|t| !t.is_empty()) {
+                                    chunks.push(querymt::chat::StreamChunk::Thinking(thinking));
+                                }
+                                self.thinking_state_buffer.insert(
+                                    index,
+                                    AnthropicThinkingState {
+                                        signature: block.signature.unwrap_or_default(),
+                                    },
+                                );
                             }
                         }
                     }
@@ -1128,14 +1503,15 @@ impl ChatStreamParser for AnthropicStreamParser {
                                     state.signature.push_str(&signature);
                                 }
                             } else if let Some(partial_json) = delta.partial_json {
+                                // Server tool input deltas have no client-side
+                                // tool use to feed.
                                 if let Some(state) = self.tool_state_buffer.get_mut(&index) {
                                     state.arguments_buffer.push_str(&partial_json);
+                                    chunks.push(querymt::chat::StreamChunk::ToolUseInputDelta {
+                                        index,
+                                        partial_json,
+                                    });
                                 }
-
-                                chunks.push(querymt::chat::StreamChunk::ToolUseInputDelta {
-                                    index,
-                                    partial_json,
-                                });
                             }
                         }
                     }
@@ -1236,20 +1612,28 @@ mod tests {
     /// Build a minimal `Anthropic` instance for use in unit tests.
     fn test_anthropic(api_key: &str) -> Anthropic {
         Anthropic {
+            base_url: None,
             api_key: api_key.to_string(),
             auth_type: None,
             model: "claude-3-7-sonnet-20250219".to_string(),
-            max_tokens: 100,
+            max_tokens: Some(100),
             temperature: Some(1.0),
             timeout_seconds: None,
             system: None,
+            cache_system_prompt: None,
             stream: None,
             top_p: None,
             top_k: None,
+            stop_sequences: None,
             tools: None,
+            beta_features: None,
+            server_tools: None,
             tool_choice: None,
             reasoning_effort: None,
             reasoning_budget_tokens: None,
+            user_id: None,
+            seed: None,
+            extra_body: None,
             key_resolver: None,
         }
     }
@@ -1322,6 +1706,357 @@ mod tests {
         assert_eq!(body["thinking"]["budget_tokens"], serde_json::json!(1024));
     }
 
+    #[test]
+    fn test_stop_sequences_serialized_in_request() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.stop_sequences = Some(vec!["###".to_string(), "END".to_string()]);
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["stop_sequences"], serde_json::json!(["###", "END"]));
+
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+        assert!(body.get("stop_sequences").is_none());
+    }
+
+    #[test]
+    fn test_parse_chat_stop_sequence_finish_reason() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let body = serde_json::json!({
+            "content": [{"type": "text", "text": "partial"}],
+            "stop_reason": "stop_sequence",
+            "stop_sequence": "###",
+            "usage": {"input_tokens": 3, "output_tokens": 1}
+        });
+        let resp = Response::builder()
+            .status(200)
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        assert_eq!(parsed.finish_reason(), Some(FinishReason::Stop));
+        assert_eq!(parsed.stop_sequence().as_deref(), Some("###"));
+        assert_eq!(parsed.text().as_deref(), Some("partial"));
+    }
+
+    #[test]
+    fn test_parse_chat_refusal() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let body = serde_json::json!({
+            "content": [{"type": "text", "text": "I can't help with that."}],
+            "stop_reason": "refusal",
+            "usage": {"input_tokens": 3, "output_tokens": 6}
+        });
+        let resp = Response::builder()
+            .status(200)
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        assert_eq!(parsed.finish_reason(), Some(FinishReason::Refusal));
+        assert_eq!(parsed.refusal().as_deref(), Some("I can't help with that."));
+    }
+
+    #[test]
+    fn test_parse_chat_response_id() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let body = serde_json::json!({
+            "id": "msg_01XFDUDYJgAACzvnptvVoYEL",
+            "content": [{"type": "text", "text": "hi"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 3, "output_tokens": 1}
+        });
+        let resp = Response::builder()
+            .status(200)
+            .header("request-id", "req_018EeWyXxfu5pfWkrYcMdjWG")
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        assert_eq!(
+            parsed.response_id().as_deref(),
+            Some("msg_01XFDUDYJgAACzvnptvVoYEL")
+        );
+        assert_eq!(parsed.system_fingerprint(), None);
+
+        let mut body = body;
+        body.as_object_mut().unwrap().remove("id");
+        let resp = Response::builder()
+            .status(200)
+            .header("request-id", "req_018EeWyXxfu5pfWkrYcMdjWG")
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        assert_eq!(
+            parsed.response_id().as_deref(),
+            Some("req_018EeWyXxfu5pfWkrYcMdjWG")
+        );
+    }
+
+    #[test]
+    fn test_web_search_server_tool_serializes_type() {
+        let cfg = serde_json::json!({
+            "api_key": "sk-ant-api03-test",
+            "model": "claude-3-7-sonnet-20250219",
+            "server_tools": [
+                {"type": "web_search_20250305", "name": "web_search", "max_uses": 5}
+            ]
+        });
+        let anthropic: Anthropic = serde_json::from_value(cfg).unwrap();
+
+        let messages = vec![ChatMessage::user().text("news?").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(
+            body["tools"],
+            serde_json::json!([
+                {"type": "web_search_20250305", "name": "web_search", "max_uses": 5}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_chat_server_tool_use_and_result() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let results = serde_json::json!([
+            {"type": "web_search_result", "url": "https://example.com", "title": "Example"}
+        ]);
+        let body = serde_json::json!({
+            "content": [
+                {"type": "server_tool_use", "id": "srvtoolu_01", "name": "web_search",
+                 "input": {"query": "rust"}},
+                {"type": "web_search_tool_result", "tool_use_id": "srvtoolu_01",
+                 "content": results},
+                {"type": "text", "text": "Rust is a language."}
+            ],
+            "stop_reason": "end_turn"
+        });
+        let resp = Response::builder()
+            .status(200)
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        assert_eq!(parsed.text().as_deref(), Some("Rust is a language."));
+        assert_eq!(parsed.tool_calls(), None);
+        assert_eq!(
+            parsed.server_tool_uses(),
+            Some(vec![ServerToolUse {
+                id: "srvtoolu_01".to_string(),
+                name: "web_search".to_string(),
+                input: serde_json::json!({"query": "rust"}),
+                result: Some(results),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_parse_chat_citations() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let body = serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Per the docs,"},
+                {"type": "text", "text": "Rust 1.0 shipped in 2015.", "citations": [{
+                    "type": "web_search_result_location",
+                    "url": "https://blog.rust-lang.org/2015/05/15/Rust-1.0.html",
+                    "title": "Announcing Rust 1.0",
+                    "encrypted_index": "Eo8BCioIAhgBIiQyYjQ0OWJmZi1lNm..",
+                    "cited_text": "We are very proud to announce the 1.0 release of Rust"
+                }]}
+            ],
+            "stop_reason": "end_turn"
+        });
+        let resp = Response::builder()
+            .status(200)
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let parsed = anthropic.parse_chat(resp).unwrap();
+        let text = parsed.text().unwrap();
+        let citations = parsed.citations().unwrap();
+        assert_eq!(
+            citations,
+            vec![Citation {
+                title: Some("Announcing Rust 1.0".to_string()),
+                url: Some("https://blog.rust-lang.org/2015/05/15/Rust-1.0.html".to_string()),
+                start: Some(14),
+                end: Some(39),
+            }]
+        );
+        assert_eq!(&text[14..39], "Rust 1.0 shipped in 2015.");
+    }
+
+    #[test]
+    fn test_parse_chat_400_keeps_error_body() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let resp = Response::builder()
+            .status(400)
+            .body(br#"{"type":"error","error":{"type":"invalid_request_error","message":"messages: at least one message is required"}}"#.to_vec())
+            .unwrap();
+
+        match anthropic.parse_chat(resp).unwrap_err() {
+            LLMError::InvalidRequest(message) => {
+                assert_eq!(message, "messages: at least one message is required")
+            }
+            other => panic!("expected InvalidRequest, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_chat_undecodable_body_is_kept() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let resp = Response::builder()
+            .status(200)
+            .body(b"<html>proxy error</html>".to_vec())
+            .unwrap();
+
+        match anthropic.parse_chat(resp).unwrap_err() {
+            LLMError::ResponseFormatError { raw_response, .. } => {
+                assert_eq!(raw_response, "<html>proxy error</html>")
+            }
+            other => panic!("expected ResponseFormatError, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_base_url_with_or_without_trailing_slash_resolves_messages() {
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        for base_url in ["https://gw.internal/v1", "https://gw.internal/v1/"] {
+            let mut anthropic = test_anthropic("sk-ant-api03-test");
+            anthropic.base_url = Some(Url::parse(base_url).unwrap());
+            let req = anthropic.chat_request(&messages, None).unwrap();
+            assert_eq!(
+                req.uri().to_string(),
+                "https://gw.internal/v1/messages",
+                "base_url {base_url}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_beta_features_join_into_one_header() {
+        let cfg = serde_json::json!({
+            "api_key": "sk-ant-api03-test",
+            "model": "claude-sonnet-4-5",
+            "beta_features": ["context_1m", "fine_grained_tool_streaming", "files-api-2025-04-14"]
+        });
+        let anthropic: Anthropic = serde_json::from_value(cfg).unwrap();
+        assert_eq!(
+            anthropic.beta_features.as_deref().unwrap()[2],
+            AnthropicBeta::Other("files-api-2025-04-14".to_string())
+        );
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let betas: Vec<_> = req.headers().get_all("anthropic-beta").iter().collect();
+        assert_eq!(
+            betas,
+            vec![
+                "context-1m-2025-08-07,fine-grained-tool-streaming-2025-05-14,files-api-2025-04-14"
+            ]
+        );
+
+        let req = test_anthropic("sk-ant-api03-test")
+            .chat_request(&messages, None)
+            .unwrap();
+        assert!(req.headers().get("anthropic-beta").is_none());
+    }
+
+    #[test]
+    fn test_oauth_betas_merge_with_beta_features() {
+        let mut anthropic = test_anthropic("sk-ant-oat01-abc123");
+        anthropic.beta_features = Some(vec![
+            AnthropicBeta::InterleavedThinking,
+            AnthropicBeta::PromptCaching,
+        ]);
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        assert_eq!(
+            req.headers()["anthropic-beta"],
+            "oauth-2025-04-20,interleaved-thinking-2025-05-14,prompt-caching-2024-07-31"
+        );
+    }
+
+    #[test]
+    fn test_max_tokens_defaults_when_omitted() {
+        let cfg = serde_json::json!({
+            "api_key": "sk-ant-api03-test",
+            "model": "claude-3-7-sonnet-20250219"
+        });
+        let anthropic: Anthropic =
+            serde_json::from_value(cfg).expect("max_tokens should be optional");
+        assert_eq!(anthropic.max_tokens, None);
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["max_tokens"], serde_json::json!(DEFAULT_MAX_TOKENS));
+    }
+
+    #[test]
+    fn test_max_tokens_default_exceeds_thinking_budget() {
+        let cfg = serde_json::json!({
+            "api_key": "sk-ant-api03-test",
+            "model": "claude-3-7-sonnet-20250219",
+            "reasoning_effort": "high"
+        });
+        let anthropic: Anthropic = serde_json::from_value(cfg).unwrap();
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: serde_json::Value = serde_json::from_slice(req.body()).unwrap();
+        let budget = body["thinking"]["budget_tokens"].as_u64().unwrap();
+        assert!(body["max_tokens"].as_u64().unwrap() > budget);
+    }
+
+    #[test]
+    fn test_max_tokens_default_saturates_for_huge_thinking_budget() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.max_tokens = None;
+        assert_eq!(anthropic.effective_max_tokens(Some(u32::MAX)), u32::MAX);
+    }
+
+    #[test]
+    fn test_factory_capabilities() {
+        let caps = create_http_factory().capabilities();
+        assert!(!caps.completion);
+        assert!(!caps.embeddings);
+        assert!(caps.streaming);
+        assert!(caps.pdf);
+        assert!(caps.thinking);
+    }
+
+    #[test]
+    fn test_chat_request_uses_default_base_url() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        assert_eq!(
+            req.uri().to_string(),
+            "https://api.anthropic.com/v1/messages"
+        );
+    }
+
+    #[test]
+    fn test_chat_request_base_url_override_keeps_path() {
+        let cfg = serde_json::json!({
+            "api_key": "sk-ant-api03-test",
+            "model": "claude-3-7-sonnet-20250219",
+            "max_tokens": 100,
+            "base_url": "https://gateway.internal/anthropic/v1"
+        });
+        let anthropic: Anthropic = serde_json::from_value(cfg).unwrap();
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        assert_eq!(
+            req.uri().to_string(),
+            "https://gateway.internal/anthropic/v1/messages"
+        );
+    }
+
     #[test]
     fn test_system_prompt_deserialize_string() {
         let json = serde_json::json!({
@@ -1431,6 +2166,210 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cached_multi_part_system_prompt_serializes_as_blocks() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.cache_system_prompt = Some(true);
+        anthropic.system = serde_json::from_value(serde_json::json!([
+            "You are a helpful assistant.",
+            "Here is the project handbook."
+        ]))
+        .unwrap();
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert_eq!(
+            body["system"],
+            serde_json::json!([
+                {"type": "text", "text": "You are a helpful assistant."},
+                {
+                    "type": "text",
+                    "text": "Here is the project handbook.",
+                    "cache_control": {"type": "ephemeral"}
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn test_cached_text_system_prompt_becomes_block() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.cache_system_prompt = Some(true);
+        anthropic.system = Some(AnthropicSystemPrompt::Text("Be brief.".to_string()));
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert_eq!(
+            body["system"],
+            serde_json::json!([
+                {"type": "text", "text": "Be brief.", "cache_control": {"type": "ephemeral"}}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_failed_tool_result_sets_is_error() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![
+            ChatMessage::user()
+                .tool_result(
+                    "toolu_1".to_string(),
+                    Some("bash".to_string()),
+                    true,
+                    vec![Content::text("command not found")],
+                )
+                .tool_result(
+                    "toolu_2".to_string(),
+                    Some("bash".to_string()),
+                    false,
+                    vec![Content::text("ok")],
+                )
+                .build(),
+        ];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let blocks = &body["messages"][0]["content"];
+
+        assert_eq!(blocks[0]["type"], "tool_result");
+        assert_eq!(blocks[0]["tool_use_id"], "toolu_1");
+        assert_eq!(blocks[0]["is_error"], true);
+        assert!(blocks[1].get("is_error").is_none());
+    }
+
+    #[test]
+    fn test_image_tool_result_serializes_image_block() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![
+            ChatMessage::user()
+                .tool_result(
+                    "toolu_1".to_string(),
+                    Some("screenshot".to_string()),
+                    false,
+                    vec![
+                        "captured".into(),
+                        Content::image("image/png", vec![1, 2, 3]),
+                    ],
+                )
+                .build(),
+        ];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let content = &body["messages"][0]["content"][0]["content"];
+
+        assert_eq!(content[0]["type"], "text");
+        assert_eq!(content[0]["text"], "captured");
+        assert_eq!(content[1]["type"], "image");
+        assert_eq!(content[1]["source"]["type"], "base64");
+        assert_eq!(content[1]["source"]["media_type"], "image/png");
+        assert_eq!(content[1]["source"]["data"], BASE64.encode([1, 2, 3]));
+    }
+
+    #[test]
+    fn test_interleaved_text_and_images_keep_order() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![
+            ChatMessage::user()
+                .text("Compare")
+                .image("image/png", vec![1])
+                .text("with")
+                .image("image/jpeg", vec![2])
+                .build(),
+        ];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let content = &body["messages"][0]["content"];
+
+        assert_eq!(content[0]["text"], "Compare");
+        assert_eq!(content[1]["source"]["media_type"], "image/png");
+        assert_eq!(content[2]["text"], "with");
+        assert_eq!(content[3]["source"]["media_type"], "image/jpeg");
+    }
+
+    #[test]
+    fn test_system_messages_move_to_system_field() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![
+            ChatMessage::user().text("hi").build(),
+            ChatMessage::system().text("Answer in French.").build(),
+        ];
+
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["system"], "Answer in French.");
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(body["messages"][0]["role"], "user");
+
+        anthropic.system = Some(AnthropicSystemPrompt::Text("Be brief.".to_string()));
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["system"][0]["text"], "Be brief.");
+        assert_eq!(body["system"][1]["text"], "Answer in French.");
+    }
+
+    #[test]
+    fn test_extra_body_merged_into_request() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.user_id = Some("tenant-42".to_string());
+        anthropic.extra_body = serde_json::from_value(serde_json::json!({
+            "service_tier": "standard_only",
+            "max_tokens": 64,
+            "metadata": { "trace": "abc" }
+        }))
+        .unwrap();
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert_eq!(body["service_tier"], "standard_only");
+        assert_eq!(body["max_tokens"], 64, "user keys override computed ones");
+        assert_eq!(body["metadata"]["user_id"], "tenant-42");
+        assert_eq!(body["metadata"]["trace"], "abc");
+    }
+
+    #[test]
+    fn test_user_id_sent_as_metadata() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.user_id = Some("tenant-42".to_string());
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert_eq!(
+            body["metadata"],
+            serde_json::json!({"user_id": "tenant-42"})
+        );
+    }
+
+    #[test]
+    fn test_seed_is_not_sent() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.seed = Some(42);
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert!(body.get("seed").is_none());
+    }
+
+    #[test]
+    fn test_system_prompt_uncached_by_default() {
+        let mut anthropic = test_anthropic("sk-ant-api03-test");
+        anthropic.system = Some(AnthropicSystemPrompt::Text("Be brief.".to_string()));
+
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        assert_eq!(body["system"], serde_json::json!("Be brief."));
+    }
+
     #[test]
     fn test_system_prompt_deserialize_blocks_with_citations() {
         let json = serde_json::json!({
@@ -1858,4 +2797,113 @@ mod tests {
         );
         // Parser state is per-stream and dropped with the parser instance.
     }
+
+    #[test]
+    fn test_streaming_thinking_block_emits_signature() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+
+        let lines = [
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":"","signature":""}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Let me think."}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"EqQBCgIYAhIM"}}"#,
+            r#"data: {"type":"content_block_stop","index":0}"#,
+        ];
+
+        let chunks = collect_chunks(&anthropic, &lines);
+
+        assert_eq!(chunks.len(), 2, "unexpected chunks: {chunks:?}");
+        assert!(
+            matches!(&chunks[0], querymt::chat::StreamChunk::Thinking(t) if t == "Let me think.")
+        );
+        assert!(matches!(
+            &chunks[1],
+            querymt::chat::StreamChunk::ThinkingSignature(s) if s == "EqQBCgIYAhIM"
+        ));
+    }
+
+    #[test]
+    fn test_thinking_signature_carried_into_chat_message() {
+        let resp: AnthropicCompleteResponse = serde_json::from_value(serde_json::json!({
+            "content": [
+                {"type": "thinking", "thinking": "Let me think.", "signature": "EqQBCgIYAhIM"},
+                {"type": "text", "text": "42"}
+            ],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 1, "output_tokens": 2}
+        }))
+        .unwrap();
+
+        let msg = ChatMessage::from(&resp as &dyn ChatResponse);
+        assert_eq!(
+            msg.content[0],
+            Content::Thinking {
+                text: "Let me think.".into(),
+                signature: Some("EqQBCgIYAhIM".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_assistant_thinking_is_replayed_before_text_and_tool_use() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+        let messages = vec![
+            ChatMessage::user().text("What is 6 * 7?").build(),
+            ChatMessage {
+                role: ChatRole::Assistant,
+                content: vec![
+                    Content::text("Let me check."),
+                    Content::Thinking {
+                        text: "6 * 7 = 42.".into(),
+                        signature: Some("EqQBCgIYAhIM".into()),
+                    },
+                    Content::ToolUse {
+                        id: "toolu_1".into(),
+                        name: "calc".into(),
+                        arguments: serde_json::json!({"expr": "6*7"}),
+                    },
+                ],
+                cache: None,
+                name: None,
+            },
+        ];
+
+        let req = anthropic.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let blocks = body["messages"][1]["content"].as_array().unwrap();
+        let types: Vec<&str> = blocks.iter().map(|b| b["type"].as_str().unwrap()).collect();
+        assert_eq!(types, vec!["thinking", "text", "tool_use"]);
+        assert_eq!(blocks[0]["thinking"], "6 * 7 = 42.");
+        assert_eq!(blocks[0]["signature"], "EqQBCgIYAhIM");
+    }
+
+    #[test]
+    fn test_text_only_response_has_no_tool_calls() {
+        let resp: AnthropicCompleteResponse = serde_json::from_value(serde_json::json!({
+            "content": [{"type": "text", "text": "42"}],
+            "stop_reason": "end_turn",
+            "usage": {"input_tokens": 1, "output_tokens": 2}
+        }))
+        .unwrap();
+        assert_eq!(resp.tool_calls(), None);
+    }
+
+    #[test]
+    fn test_streaming_thinking_and_text_deltas_stay_separate() {
+        let anthropic = test_anthropic("sk-ant-api03-test");
+
+        let lines = [
+            r#"data: {"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Reasoning."}}"#,
+            r#"data: {"type":"content_block_stop","index":0}"#,
+            r#"data: {"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"data: {"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Answer."}}"#,
+            r#"data: {"type":"content_block_stop","index":1}"#,
+        ];
+
+        let chunks = collect_chunks(&anthropic, &lines);
+
+        assert_eq!(chunks.len(), 2, "unexpected chunks: {chunks:?}");
+        assert!(matches!(&chunks[0], querymt::chat::StreamChunk::Thinking(t) if t == "Reasoning."));
+        assert!(matches!(&chunks[1], querymt::chat::StreamChunk::Text(t) if t == "Answer."));
+    }
 }
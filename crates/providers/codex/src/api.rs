@@ -11,9 +11,9 @@ use querymt::{
     FunctionCall, ToolCall, Usage,
     chat::{
         ChatMessage, ChatResponse, ChatRole, Content, FinishReason, ReasoningEffort, StreamChunk,
-        Tool, ToolChoice,
+        Tool, ToolChoice, system_prompts,
     },
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     handle_http_error,
 };
 use schemars::{Schema, SchemaGenerator, json_schema};
@@ -530,7 +530,15 @@ fn codex_chat_body_json<C: CodexProviderConfig>(
 ) -> Result<Vec<u8>, LLMError> {
     let instructions = resolve_instructions(cfg.model(), cfg.instructions())?;
     let mut inputs = Vec::with_capacity(messages.len() + 1);
-    if let Some(system) = cfg.system().filter(|text| !text.trim().is_empty()) {
+    let system = cfg
+        .system()
+        .map(str::to_string)
+        .into_iter()
+        .chain(system_prompts(messages))
+        .filter(|text| !text.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    if !system.is_empty() {
         let text = format!(
             "# AGENTS.md instructions for {directory}\n\n<INSTRUCTIONS>\n{system}\n</INSTRUCTIONS>",
             directory = DEFAULT_INSTRUCTIONS_DIRECTORY
@@ -542,7 +550,7 @@ fn codex_chat_body_json<C: CodexProviderConfig>(
             }],
         });
     }
-    for msg in messages {
+    for msg in messages.iter().filter(|m| !m.is_system()) {
         let is_user = matches!(msg.role, ChatRole::User);
 
         // ── Pass 1: collect regular content blocks into a single message item ──
@@ -769,14 +777,8 @@ pub fn codex_parse_chat_with_state(
         ));
     }
 
-    let json_resp: Result<CodexChatResponse, serde_json::Error> = serde_json::from_slice(body);
-    match json_resp {
-        Ok(response) => Ok(Box::new(response)),
-        Err(e) => Err(LLMError::ResponseFormatError {
-            message: format!("Failed to decode Codex API response: {}", e),
-            raw_response: raw.into_owned(),
-        }),
-    }
+    let response: CodexChatResponse = decode_json_response(body, "Codex API response")?;
+    Ok(Box::new(response))
 }
 
 pub fn codex_parse_stream_chunk_with_state(
@@ -1248,6 +1250,7 @@ pub fn codex_list_models_request_from_value(cfg: &Value) -> Result<Request<Vec<u
         .map(Url::parse)
         .transpose()
         .map_err(|e| LLMError::HttpError(e.to_string()))?
+        .map(querymt::params::normalize_base_url)
         .unwrap_or_else(|| Url::parse("https://chatgpt.com/backend-api/codex/").unwrap());
     let client_version = cfg
         .get("client_version")
@@ -1272,15 +1275,8 @@ pub fn codex_list_models_request_from_value(cfg: &Value) -> Result<Request<Vec<u
 
 pub fn codex_parse_list_models(response: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
     handle_http_error!(response);
-    let json_resp: Result<CodexModelsResponse, serde_json::Error> =
-        serde_json::from_slice(response.body());
-    match json_resp {
-        Ok(response) => Ok(response.models.into_iter().map(|m| m.slug).collect()),
-        Err(e) => Err(LLMError::ResponseFormatError {
-            message: format!("Failed to decode models response: {}", e),
-            raw_response: String::new(),
-        }),
-    }
+    let response: CodexModelsResponse = decode_json_response(response.body(), "models response")?;
+    Ok(response.models.into_iter().map(|m| m.slug).collect())
 }
 
 fn chatgpt_account_id(access_token: &str) -> Result<String, LLMError> {
@@ -1429,6 +1425,7 @@ mod tests {
                 content: inner,
             }],
             cache: None,
+            name: None,
         }
     }
 
@@ -1622,6 +1619,7 @@ mod tests {
                 },
             ],
             cache: None,
+            name: None,
         }];
 
         let body: Value = serde_json::from_slice(
@@ -1659,6 +1657,7 @@ mod tests {
                 url: "https://example.com/img.png".to_string(),
             }],
             cache: None,
+            name: None,
         }];
 
         let body: Value = serde_json::from_slice(
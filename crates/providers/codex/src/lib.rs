@@ -11,7 +11,7 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -28,7 +28,10 @@ pub struct Codex {
     /// OAuth access token for ChatGPT/Codex backend.
     pub api_key: String,
     #[schemars(schema_with = "api::url_schema")]
-    #[serde(default = "Codex::default_base_url")]
+    #[serde(
+        default = "Codex::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub model: String,
     pub max_tokens: Option<u32>,
@@ -239,6 +242,19 @@ impl HTTPLLMProviderFactory for CodexFactory {
         "codex"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: false,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         None
     }
@@ -279,6 +295,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(CodexFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Codex, CodexFactory};
@@ -45,22 +45,31 @@ use querymt::{
     FunctionCall, HTTPLLMProvider, ToolCall, Usage,
     auth::ApiKeyResolver,
     chat::{
-        ChatMessage, ChatResponse, ChatRole, Content, FinishReason, ReasoningEffort, StreamChunk,
-        StructuredOutputFormat, Tool, ToolChoice,
+        ChatMessage, ChatResponse, ChatRole, Citation, Content, FinishReason, ReasoningEffort,
+        StreamChunk, StructuredOutputFormat, Tool, ToolChoice,
         http::{ChatStreamParser, HTTPChatProvider},
+        non_empty_tool_calls, system_prompts,
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     handle_http_error,
-    plugin::HTTPLLMProviderFactory,
+    params::{merge_extra_body, normalize_base_url},
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
-use schemars::{JsonSchema, schema_for};
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::sync::Arc;
 use url::Url;
 
+fn url_schema(_gen: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "string",
+        "format": "uri"
+    })
+}
+
 /// Client for interacting with Google's Gemini API.
 ///
 /// This struct holds the configuration and state needed to make requests to the Gemini API.
@@ -68,6 +77,11 @@ use url::Url;
 #[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Google {
+    /// Override the API base URL (e.g. an internal gateway). Defaults to
+    /// `https://generativelanguage.googleapis.com/v1beta/models/`.
+    #[schemars(schema_with = "url_schema")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<Url>,
     /// API key for authentication with Google's API
     pub api_key: String,
     /// Model identifier (e.g. "gemini-1.5-flash")
@@ -98,6 +112,15 @@ pub struct Google {
     pub reasoning_effort: Option<ReasoningEffort>,
     pub thinking_budget: Option<u32>,
     pub cached_content: Option<String>,
+    /// Sampling seed, sent as `generationConfig.seed`, for best-effort
+    /// reproducible output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Extra fields merged into the request body, for parameters this crate
+    /// doesn't model yet. Keys here override the computed ones; nested
+    /// objects such as `generationConfig` are merged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_body: Option<serde_json::Map<String, Value>>,
 
     /// Optional resolver for dynamic credential refresh (e.g., OAuth tokens).
     #[serde(skip)]
@@ -204,14 +227,25 @@ impl<'a> GoogleContentPart<'a> {
         }
     }
 
-    fn function_response(name: String, content: Value) -> Self {
+    /// A tool result. Failed executions carry their output under `error`
+    /// rather than `content`, which is how Gemini tells them apart.
+    fn function_response(name: String, output: Value, is_error: bool) -> Self {
+        let (content, error) = if is_error {
+            (None, Some(output))
+        } else {
+            (Some(output), None)
+        };
         Self {
             text: None,
             inline_data: None,
             function_call: None,
             function_response: Some(GoogleFunctionResponse {
                 name: name.clone(),
-                response: GoogleFunctionResponseContent { name, content },
+                response: GoogleFunctionResponseContent {
+                    name,
+                    content,
+                    error,
+                },
             }),
             thought: None,
             thought_signature: None,
@@ -219,8 +253,9 @@ impl<'a> GoogleContentPart<'a> {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Debug)]
 struct GoogleInlineData {
+    #[serde(alias = "mimeType")]
     mime_type: String,
     data: String,
 }
@@ -248,6 +283,9 @@ struct GoogleGenerationConfig {
     response_schema: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none", rename = "thinkingConfig")]
     thinking_config: Option<GoogleThinkingConfig>,
+    /// Sampling seed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 /// Configuration parameters for text generation
@@ -264,10 +302,43 @@ struct GoogleThinkingConfig {
 /// Response from the chat completion API
 #[derive(Deserialize, Debug)]
 struct GoogleChatResponse {
-    /// Generated completion candidates
+    /// Generated completion candidates (absent when the prompt itself is blocked)
+    #[serde(default)]
     candidates: Vec<GoogleCandidate>,
     #[serde(rename = "usageMetadata")]
-    usage: Option<Usage>,
+    usage: Option<GoogleUsageMetadata>,
+}
+
+/// Raw `usageMetadata` from Gemini, before normalization.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct GoogleUsageMetadata {
+    /// Prompt tokens, including any served from cached content
+    #[serde(default)]
+    prompt_token_count: u32,
+    /// Response tokens, excluding thoughts
+    #[serde(default)]
+    candidates_token_count: u32,
+    /// Thinking tokens
+    #[serde(default)]
+    thoughts_token_count: u32,
+    /// Prompt tokens served from cached content
+    #[serde(default)]
+    cached_content_token_count: u32,
+}
+
+impl GoogleUsageMetadata {
+    fn into_usage(self) -> Usage {
+        Usage {
+            input_tokens: self
+                .prompt_token_count
+                .saturating_sub(self.cached_content_token_count),
+            output_tokens: self.candidates_token_count,
+            reasoning_tokens: self.thoughts_token_count,
+            cache_read: self.cached_content_token_count,
+            cache_write: 0,
+        }
+    }
 }
 
 impl std::fmt::Display for GoogleChatResponse {
@@ -295,16 +366,62 @@ impl std::fmt::Display for GoogleChatResponse {
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct GoogleCandidate {
-    /// Content of the candidate response
+    /// Content of the candidate response (absent when blocked by a safety filter)
+    #[serde(default)]
     content: GoogleResponseContent,
     /// Finish reason (only present in final streaming chunk or complete response)
     finish_reason: Option<String>,
     /// Index of this candidate
     index: usize,
+    /// Search grounding sources (when the `google_search` tool was used)
+    #[serde(default)]
+    grounding_metadata: Option<GoogleGroundingMetadata>,
+}
+
+/// Sources a grounded candidate drew on, and which parts of its text they
+/// support.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct GoogleGroundingMetadata {
+    #[serde(default)]
+    grounding_chunks: Vec<GoogleGroundingChunk>,
+    #[serde(default)]
+    grounding_supports: Vec<GoogleGroundingSupport>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GoogleGroundingChunk {
+    web: Option<GoogleWebSource>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GoogleWebSource {
+    uri: Option<String>,
+    title: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleGroundingSupport {
+    segment: Option<GoogleSegment>,
+    #[serde(default)]
+    grounding_chunk_indices: Vec<usize>,
+}
+
+/// Span of one part of the candidate, as byte offsets into that part's text.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GoogleSegment {
+    /// Omitted by the API when zero, as are the indices below.
+    #[serde(default)]
+    part_index: usize,
+    #[serde(default)]
+    start_index: usize,
+    end_index: Option<usize>,
 }
 
 /// Response content block
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Default, Serialize)]
 #[serde(rename_all = "camelCase")]
 struct GoogleResponseContent {
     /// Parts making up the content
@@ -318,16 +435,112 @@ struct GoogleResponseContent {
     function_calls: Option<Vec<GoogleFunctionCall>>,
 }
 
-impl ChatResponse for GoogleChatResponse {
-    fn text(&self) -> Option<String> {
-        self.candidates.first().map(|c| {
-            c.content
-                .parts
+impl GoogleCandidate {
+    /// Concatenated non-thought text of this candidate.
+    fn text(&self) -> String {
+        self.content
+            .parts
+            .iter()
+            .filter(|p| !p.thought)
+            .map(|p| p.text.clone().unwrap_or_default())
+            .collect()
+    }
+
+    /// Byte offset of part `part_index` within [`text`](Self::text), or
+    /// `None` when that part isn't part of the text.
+    fn text_offset(&self, part_index: usize) -> Option<usize> {
+        let parts = &self.content.parts;
+        if parts.get(part_index)?.thought {
+            return None;
+        }
+        Some(
+            parts[..part_index]
                 .iter()
                 .filter(|p| !p.thought)
-                .map(|p| p.text.clone().unwrap_or_default())
-                .collect()
-        })
+                .map(|p| p.text.as_deref().map_or(0, str::len))
+                .sum(),
+        )
+    }
+}
+
+impl ChatResponse for GoogleChatResponse {
+    fn text(&self) -> Option<String> {
+        self.candidates.first().map(GoogleCandidate::text)
+    }
+
+    fn images(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        let images: Vec<(String, Vec<u8>)> = self
+            .candidates
+            .first()?
+            .content
+            .parts
+            .iter()
+            .filter_map(|p| p.inline_data.as_ref())
+            .filter(|d| d.mime_type.starts_with("image/"))
+            .filter_map(|d| {
+                BASE64
+                    .decode(&d.data)
+                    .ok()
+                    .map(|bytes| (d.mime_type.clone(), bytes))
+            })
+            .collect();
+        if images.is_empty() {
+            None
+        } else {
+            Some(images)
+        }
+    }
+
+    /// One citation per grounding support and source. Sources no support
+    /// refers to are reported without a span.
+    fn citations(&self) -> Option<Vec<Citation>> {
+        let candidate = self.candidates.first()?;
+        let metadata = candidate.grounding_metadata.as_ref()?;
+        let source = |index: usize| {
+            let web = metadata.grounding_chunks.get(index)?.web.as_ref()?;
+            Some(Citation {
+                title: web.title.clone(),
+                url: web.uri.clone(),
+                ..Citation::default()
+            })
+        };
+
+        let mut cited = vec![false; metadata.grounding_chunks.len()];
+        let mut citations = Vec::new();
+        for support in &metadata.grounding_supports {
+            // Segment offsets are relative to their part; shift them into
+            // the combined text.
+            let span = support.segment.as_ref().and_then(|s| {
+                let offset = candidate.text_offset(s.part_index)?;
+                Some((offset + s.start_index, s.end_index.map(|end| offset + end)))
+            });
+            for &index in &support.grounding_chunk_indices {
+                let Some(citation) = source(index) else {
+                    continue;
+                };
+                cited[index] = true;
+                citations.push(Citation {
+                    start: span.map(|(start, _)| start),
+                    end: span.and_then(|(_, end)| end),
+                    ..citation
+                });
+            }
+        }
+        citations.extend(
+            (0..cited.len())
+                .filter(|&index| !cited[index])
+                .filter_map(source),
+        );
+        (!citations.is_empty()).then_some(citations)
+    }
+
+    fn candidates(&self) -> Option<Vec<String>> {
+        if self.candidates.is_empty() {
+            return None;
+        }
+        let mut candidates: Vec<&GoogleCandidate> = self.candidates.iter().collect();
+        candidates.sort_by_key(|c| c.index);
+        Some(candidates.into_iter().map(GoogleCandidate::text).collect())
     }
 
     fn thinking(&self) -> Option<String> {
@@ -348,7 +561,7 @@ impl ChatResponse for GoogleChatResponse {
     }
 
     fn tool_calls(&self) -> Option<Vec<ToolCall>> {
-        self.candidates.first().and_then(|c| {
+        non_empty_tool_calls(self.candidates.first().and_then(|c| {
             // First check for function calls at the part level (new API format)
             let part_function_calls: Vec<ToolCall> = c
                 .content
@@ -411,11 +624,11 @@ impl ChatResponse for GoogleChatResponse {
                     }]
                 })
             }
-        })
+        }))
     }
 
     fn usage(&self) -> Option<Usage> {
-        self.usage.clone()
+        self.usage.clone().map(GoogleUsageMetadata::into_usage)
     }
 
     fn finish_reason(&self) -> Option<FinishReason> {
@@ -423,28 +636,30 @@ impl ChatResponse for GoogleChatResponse {
             return Some(FinishReason::ToolCalls);
         }
 
-        match self
-            .candidates
-            .first()
-            .map(|c| c.finish_reason.clone())
-            .unwrap()
+        self.candidates
+            .first()?
+            .finish_reason
             .as_deref()
-        {
-            Some("STOP") => Some(FinishReason::Stop),
-            Some("MAX_TOKENS") => Some(FinishReason::Length),
-            Some(
-                "SAFETY"
-                | "IMAGE_SAFETY"
-                | "IMAGE_PROHIBITED_CONTENT"
-                | "BLOCKLIST"
-                | "SPII"
-                | "PROHIBITED_CONTENT",
-            ) => Some(FinishReason::ContentFilter),
-            Some("OTHER" | "IMAGE_OTHER") => Some(FinishReason::Other),
-            Some("MALFORMED_FUNCTION_CALL") => Some(FinishReason::Error),
-            Some("FINISH_REASON_UNSPECIFIED") => Some(FinishReason::Unknown),
-            _ => None,
-        }
+            .map(map_finish_reason)
+    }
+}
+
+/// Maps Gemini's `finishReason` onto querymt's [`FinishReason`].
+fn map_finish_reason(reason: &str) -> FinishReason {
+    match reason {
+        "STOP" => FinishReason::Stop,
+        "MAX_TOKENS" => FinishReason::Length,
+        "SAFETY"
+        | "RECITATION"
+        | "IMAGE_SAFETY"
+        | "IMAGE_PROHIBITED_CONTENT"
+        | "IMAGE_RECITATION"
+        | "BLOCKLIST"
+        | "SPII"
+        | "PROHIBITED_CONTENT" => FinishReason::ContentFilter,
+        "OTHER" | "IMAGE_OTHER" | "LANGUAGE" => FinishReason::Other,
+        "MALFORMED_FUNCTION_CALL" => FinishReason::Error,
+        _ => FinishReason::Unknown,
     }
 }
 
@@ -456,6 +671,9 @@ struct GoogleResponsePart {
     text: Option<String>,
     /// Function call contained in this part
     function_call: Option<GoogleFunctionCall>,
+    /// Inline binary data, e.g. a generated image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inline_data: Option<GoogleInlineData>,
     /// Whether this part is a thought part
     #[serde(default)]
     thought: bool,
@@ -626,7 +844,11 @@ struct GoogleFunctionResponseContent {
     /// Name of the function that was called
     name: String,
     /// Content of the function response
-    content: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<Value>,
+    /// Output of a failed function call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<Value>,
 }
 
 /// Request body for embedding content
@@ -641,10 +863,15 @@ struct GoogleEmbeddingContent<'a> {
     parts: Vec<GoogleContentPart<'a>>,
 }
 
-/// Response from the embedding API
+#[derive(Serialize)]
+struct GoogleBatchEmbeddingRequest<'a> {
+    requests: Vec<GoogleEmbeddingRequest<'a>>,
+}
+
+/// Response from the batch embedding API, one embedding per input
 #[derive(Deserialize)]
-struct GoogleEmbeddingResponse {
-    embedding: GoogleEmbedding,
+struct GoogleBatchEmbeddingResponse {
+    embeddings: Vec<GoogleEmbedding>,
 }
 
 #[derive(Deserialize)]
@@ -652,11 +879,22 @@ struct GoogleEmbedding {
     values: Vec<f32>,
 }
 
+/// Model used by `embed`.
+const EMBEDDING_MODEL: &str = "text-embedding-004";
+
 impl Google {
     fn default_base_url() -> Url {
         Url::parse("https://generativelanguage.googleapis.com/v1beta/models/").unwrap()
     }
 
+    /// Returns the configured base URL, falling back to the public Gemini API.
+    fn base_url(&self) -> Url {
+        match &self.base_url {
+            Some(url) => normalize_base_url(url.clone()),
+            None => Google::default_base_url(),
+        }
+    }
+
     /// Returns the current API key, using the resolver if available.
     fn resolved_key(&self) -> String {
         if let Some(ref resolver) = self.key_resolver {
@@ -733,14 +971,15 @@ impl HTTPChatProvider for Google {
             ));
         }
 
-        for msg in messages {
+        for msg in messages.iter().filter(|m| !m.is_system()) {
             let has_tool_result = msg.content.iter().any(|b| b.is_tool_result());
             let role = if has_tool_result {
                 "function"
             } else {
                 match msg.role {
-                    ChatRole::User => "user",
                     ChatRole::Assistant => "model",
+                    // System messages go to `system_instruction` below.
+                    ChatRole::User | ChatRole::System => "user",
                 }
             };
 
@@ -793,7 +1032,10 @@ impl HTTPChatProvider for Google {
                         ));
                     }
                     Content::ToolResult {
-                        id, name, content, ..
+                        id,
+                        name,
+                        is_error,
+                        content,
                     } => {
                         let text = content
                             .iter()
@@ -808,7 +1050,18 @@ impl HTTPChatProvider for Google {
                         parts.push(GoogleContentPart::function_response(
                             name.clone().unwrap_or_else(|| id.clone()),
                             payload,
+                            *is_error,
                         ));
+                        // functionResponse only carries JSON, so images a tool
+                        // returned follow it as inline data in the same turn.
+                        for c in content {
+                            if let Content::Image { mime_type, data } = c {
+                                parts.push(GoogleContentPart::inline_data(
+                                    mime_type.clone(),
+                                    BASE64.encode(data),
+                                ));
+                            }
+                        }
                     }
                     Content::Audio { .. } | Content::ResourceLink { .. } => {
                         // Unsupported in Google request format today.
@@ -819,9 +1072,16 @@ impl HTTPChatProvider for Google {
             chat_contents.push(GoogleChatContent { role, parts });
         }
 
-        // Add system message if present
-        let system_instruction = self.system.as_ref().map(|system| GoogleSystemInstruction {
-            parts: vec![GoogleContentPart::text(system)],
+        // Configured system prompt first, then any system messages
+        let message_system = system_prompts(messages);
+        let system_parts: Vec<GoogleContentPart> = self
+            .system
+            .iter()
+            .chain(&message_system)
+            .map(|text| GoogleContentPart::text(text))
+            .collect();
+        let system_instruction = (!system_parts.is_empty()).then(|| GoogleSystemInstruction {
+            parts: system_parts,
         });
 
         // Convert tools to Google's format if provided
@@ -863,6 +1123,7 @@ impl HTTPChatProvider for Google {
                 response_mime_type,
                 response_schema,
                 thinking_config,
+                seed: self.seed,
             })
         };
 
@@ -875,7 +1136,14 @@ impl HTTPChatProvider for Google {
             system_instruction,
         };
 
-        let json_body = serde_json::to_vec(&req_body)?;
+        let json_body = match &self.extra_body {
+            Some(extra) => {
+                let mut body = serde_json::to_value(&req_body)?;
+                merge_extra_body(&mut body, extra);
+                serde_json::to_vec(&body)?
+            }
+            None => serde_json::to_vec(&req_body)?,
+        };
 
         // Use streamGenerateContent endpoint when streaming is enabled
         let endpoint = if self.stream.unwrap_or(false) {
@@ -884,14 +1152,10 @@ impl HTTPChatProvider for Google {
             "generateContent"
         };
 
-        let path = format!(
-            "{}{}:{}",
-            Google::default_base_url().path(),
-            &self.model,
-            endpoint
-        );
-        let mut url = Google::default_base_url()
-            .join(&path)
+        // The `./` keeps `model:method` from being parsed as a URL scheme.
+        let mut url = self
+            .base_url()
+            .join(&format!("./{}:{}", self.model, endpoint))
             .map_err(|e| LLMError::HttpError(e.to_string()))?;
         url.set_query(Some(&format!("key={}", &resolved_key)));
 
@@ -915,19 +1179,9 @@ impl HTTPChatProvider for Google {
     fn parse_chat(&self, resp: Response<Vec<u8>>) -> Result<Box<dyn ChatResponse>, LLMError> {
         handle_http_error!(resp);
 
-        let json_resp: Result<GoogleChatResponse, serde_json::Error> =
-            serde_json::from_slice(resp.body());
-
-        match json_resp {
-            Ok(response) => Ok(Box::new(response)),
-            Err(e) => {
-                // Return a more descriptive error with the raw response
-                Err(LLMError::ResponseFormatError {
-                    message: format!("Failed to decode Google API response: {}", e),
-                    raw_response: String::from_utf8(resp.into_body())?,
-                })
-            }
-        }
+        let response: GoogleChatResponse =
+            decode_json_response(resp.body(), "Google API response")?;
+        Ok(Box::new(response))
     }
 
     fn supports_streaming(&self) -> bool {
@@ -948,7 +1202,10 @@ impl HTTPCompletionProvider for Google {
     fn parse_complete(&self, resp: Response<Vec<u8>>) -> Result<CompletionResponse, LLMError> {
         let chat_response = self.parse_chat(resp)?;
         if let Some(text) = chat_response.text() {
-            Ok(CompletionResponse { text })
+            Ok(CompletionResponse {
+                text,
+                usage: chat_response.usage(),
+            })
         } else {
             Err(LLMError::ProviderError(
                 "No answer returned by Google".to_string(),
@@ -963,46 +1220,45 @@ impl HTTPEmbeddingProvider for Google {
         if resolved_key.is_empty() {
             return Err(LLMError::AuthError("Missing Google API key".to_string()));
         }
-        let embedding_model = "text-embedding-004";
 
-        //let mut embeddings = Vec::new();
-
-        // Process each text separately as Gemini API accepts one text at a time
-        let mut json_body;
-        for text in inputs {
-            let req_body = GoogleEmbeddingRequest {
-                model: "models/text-embedding-004",
-                content: GoogleEmbeddingContent {
-                    parts: vec![GoogleContentPart::text(text)],
-                },
-            };
-            json_body = serde_json::to_vec(&req_body)?;
-        }
+        // `batchEmbedContents` embeds every input in one call; each request
+        // must repeat the model name.
+        let model = format!("models/{EMBEDDING_MODEL}");
+        let req_body = GoogleBatchEmbeddingRequest {
+            requests: inputs
+                .iter()
+                .map(|text| GoogleEmbeddingRequest {
+                    model: &model,
+                    content: GoogleEmbeddingContent {
+                        parts: vec![GoogleContentPart::text(text)],
+                    },
+                })
+                .collect(),
+        };
 
-        let mut url = Google::default_base_url()
-            .join(embedding_model)
-            .map_err(|e| LLMError::HttpError(e.to_string()))?
-            .join(":embedContent")
+        let mut url = self
+            .base_url()
+            .join(&format!("./{EMBEDDING_MODEL}:batchEmbedContents"))
             .map_err(|e| LLMError::HttpError(e.to_string()))?;
-        url.set_query(Some(&format!("key={}", &resolved_key)));
+        url.set_query(Some(&format!("key={resolved_key}")));
 
-        unimplemented!();
-        Err(LLMError::ProviderError("asd".to_string()))
-        /*
         Ok(Request::builder()
             .method(Method::POST)
             .uri(url.as_str())
             .header(CONTENT_TYPE, "application/json")
-            .body(json_body)?)
-            */
+            .body(serde_json::to_vec(&req_body)?)?)
     }
 
     fn parse_embed(&self, resp: Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
-        let embedding_resp: GoogleEmbeddingResponse = serde_json::from_slice(resp.body())?;
-        let _x = embedding_resp.embedding.values;
-        //Ok(embedding_resp.embedding.values)
-        todo!("finish google embedding");
-        Err(LLMError::ProviderError("asd".to_string()))
+        handle_http_error!(resp);
+
+        let embedding_resp: GoogleBatchEmbeddingResponse =
+            decode_json_response(resp.body(), "Google embedding response")?;
+        Ok(embedding_resp
+            .embeddings
+            .into_iter()
+            .map(|embedding| embedding.values)
+            .collect())
     }
 }
 
@@ -1020,114 +1276,68 @@ impl HTTPLLMProvider for Google {
     }
 }
 
+/// Parses `streamGenerateContent` output, which (without `alt=sse`) is a single
+/// JSON array whose elements arrive incrementally: `[{...}\n,\r\n{...}\n]`.
 #[derive(Default)]
 struct GoogleStreamParser {
-    buffer: String,
+    /// Raw bytes not yet consumed; kept as bytes so a UTF-8 sequence split
+    /// across network chunks is only decoded once complete.
+    buffer: Vec<u8>,
 }
 
 impl ChatStreamParser for GoogleStreamParser {
-    fn parse_chunk(&mut self, chunk: &[u8]) -> Result<Vec<querymt::chat::StreamChunk>, LLMError> {
-        let text =
-            std::str::from_utf8(chunk).map_err(|e| LLMError::GenericError(format!("{:#}", e)))?;
-
-        self.buffer.push_str(text);
-
-        let (extracted_chunks, bytes_consumed) = extract_complete_json_objects(&self.buffer)?;
-
-        if bytes_consumed > 0 {
-            self.buffer.drain(..bytes_consumed);
-        }
-
-        for chunk in &extracted_chunks {
-            if matches!(chunk, querymt::chat::StreamChunk::Done { .. }) {
-                self.buffer.clear();
-                break;
-            }
+    fn parse_chunk(&mut self, chunk: &[u8]) -> Result<Vec<StreamChunk>, LLMError> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut chunks = Vec::new();
+        while let Some(object) = next_json_object(&self.buffer) {
+            // Consume the object before decoding it, so a malformed one is
+            // reported once rather than on every following chunk.
+            let bytes: Vec<u8> = self.buffer.drain(..object.end).skip(object.start).collect();
+            let response: GoogleChatResponse = decode_json_response(&bytes, "Google stream chunk")?;
+            chunks.extend(extract_google_stream_chunks(response));
         }
 
-        Ok(extracted_chunks)
+        Ok(chunks)
     }
 }
 
-/// Extract complete JSON objects from a buffer containing Google's streaming array format
-/// Returns (extracted StreamChunks, number of bytes consumed from buffer)
-fn extract_complete_json_objects(
-    buffer: &str,
-) -> Result<(Vec<querymt::chat::StreamChunk>, usize), LLMError> {
-    let _result_chunks: Vec<querymt::chat::StreamChunk> = Vec::new();
-    let mut bytes_consumed = 0;
-
-    // Strip leading whitespace and array opening bracket
-    let trimmed = buffer.trim_start();
-    let working_text = if let Some(stripped) = trimmed.strip_prefix('[') {
-        bytes_consumed += buffer.len() - trimmed.len() + 1; // whitespace + '['
-        stripped
-    } else {
-        trimmed
-    };
-
-    // Strip leading comma and whitespace (between array elements)
-    let working_text = working_text.trim_start();
-    if let Some(stripped) = working_text.strip_prefix(',') {
-        bytes_consumed += 1;
-        let working_text = stripped.trim_start();
-        bytes_consumed +=
-            working_text.as_ptr() as usize - (buffer.as_ptr() as usize + bytes_consumed);
-
-        // Now try to parse JSON objects from the working text
-        return try_parse_json_objects(buffer, bytes_consumed, working_text);
-    }
-
-    try_parse_json_objects(buffer, bytes_consumed, working_text)
-}
-
-fn try_parse_json_objects(
-    original_buffer: &str,
-    initial_offset: usize,
-    text: &str,
-) -> Result<(Vec<querymt::chat::StreamChunk>, usize), LLMError> {
-    use serde_json::Deserializer;
-
-    let mut result_chunks = Vec::new();
-    let mut total_consumed = initial_offset;
-
-    // Try to parse JSON objects using StreamDeserializer
-    let mut deserializer = Deserializer::from_str(text).into_iter::<GoogleChatResponse>();
-
-    while let Some(result) = deserializer.next() {
-        match result {
-            Ok(response) => {
-                // Track how many bytes we consumed
-                let byte_offset = deserializer.byte_offset();
-                total_consumed = initial_offset + byte_offset;
-
-                // Extract StreamChunks from this response
-                let chunks = extract_google_stream_chunks(response);
-                result_chunks.extend(chunks);
-            }
-            Err(_e) => {
-                // Parse error - likely incomplete JSON
-                // Don't consume any more bytes - leave the rest in the buffer
-                break;
+/// Finds the first complete top-level JSON object in `buf`, skipping the
+/// array brackets, separators and whitespace around it. Returns `None` while
+/// the object is still incomplete.
+fn next_json_object(buf: &[u8]) -> Option<std::ops::Range<usize>> {
+    let start = buf.iter().position(|&b| b == b'{')?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, &b) in buf.iter().enumerate().skip(start) {
+        if in_string {
+            match b {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
             }
+            continue;
         }
-    }
-
-    // Check if there's a trailing ] (end of array)
-    if total_consumed < original_buffer.len() {
-        let remaining = &original_buffer[total_consumed..];
-        let trimmed_remaining = remaining.trim_start();
-        if trimmed_remaining.starts_with(']') {
-            // Consume the closing bracket and any whitespace before it
-            total_consumed += remaining.len() - trimmed_remaining.len() + 1;
+        match b {
+            b'"' => in_string = true,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(start..i + 1);
+                }
+            }
+            _ => {}
         }
     }
-
-    Ok((result_chunks, total_consumed))
+    None
 }
 
 /// Extract StreamChunks from a GoogleChatResponse
-fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::chat::StreamChunk> {
+fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<StreamChunk> {
     let mut chunks = Vec::new();
 
     if let Some(candidate) = response.candidates.first() {
@@ -1136,9 +1346,9 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
             if let Some(text) = &part.text {
                 if !text.is_empty() {
                     if part.thought {
-                        chunks.push(querymt::chat::StreamChunk::Thinking(text.clone()));
+                        chunks.push(StreamChunk::Thinking(text.clone()));
                     } else {
-                        chunks.push(querymt::chat::StreamChunk::Text(text.clone()));
+                        chunks.push(StreamChunk::Text(text.clone()));
                     }
                 }
             }
@@ -1151,13 +1361,13 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
                     format!("call_{}", function_call.name)
                 };
 
-                chunks.push(querymt::chat::StreamChunk::ToolUseStart {
+                chunks.push(StreamChunk::ToolUseStart {
                     index,
                     id: id.clone(),
                     name: function_call.name.clone(),
                 });
 
-                chunks.push(querymt::chat::StreamChunk::ToolUseComplete {
+                chunks.push(StreamChunk::ToolUseComplete {
                     index,
                     tool_call: querymt::ToolCall {
                         id,
@@ -1176,12 +1386,12 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
         if let Some(fc) = &candidate.content.function_call {
             let id = format!("call_{}", fc.name);
 
-            chunks.push(querymt::chat::StreamChunk::ToolUseStart {
+            chunks.push(StreamChunk::ToolUseStart {
                 index: 0,
                 id: id.clone(),
                 name: fc.name.clone(),
             });
-            chunks.push(querymt::chat::StreamChunk::ToolUseComplete {
+            chunks.push(StreamChunk::ToolUseComplete {
                 index: 0,
                 tool_call: querymt::ToolCall {
                     id,
@@ -1198,12 +1408,12 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
             for (index, fc) in fcs.iter().enumerate() {
                 let id = format!("call_{}", fc.name);
 
-                chunks.push(querymt::chat::StreamChunk::ToolUseStart {
+                chunks.push(StreamChunk::ToolUseStart {
                     index,
                     id: id.clone(),
                     name: fc.name.clone(),
                 });
-                chunks.push(querymt::chat::StreamChunk::ToolUseComplete {
+                chunks.push(StreamChunk::ToolUseComplete {
                     index,
                     tool_call: querymt::ToolCall {
                         id,
@@ -1221,7 +1431,7 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
         // capture Usage.  Google includes usage only in the final response
         // alongside finish_reason.
         if let Some(usage) = response.usage {
-            chunks.push(querymt::chat::StreamChunk::Usage(usage));
+            chunks.push(StreamChunk::Usage(usage.into_usage()));
         }
 
         // Check for finish reason (only in final chunk)
@@ -1234,21 +1444,9 @@ fn extract_google_stream_chunks(response: GoogleChatResponse) -> Vec<querymt::ch
             let finish_reason = if has_tool_calls {
                 FinishReason::ToolCalls
             } else {
-                match finish_reason.as_str() {
-                    "STOP" => FinishReason::Stop,
-                    "MAX_TOKENS" => FinishReason::Length,
-                    "SAFETY"
-                    | "IMAGE_SAFETY"
-                    | "IMAGE_PROHIBITED_CONTENT"
-                    | "BLOCKLIST"
-                    | "SPII"
-                    | "PROHIBITED_CONTENT" => FinishReason::ContentFilter,
-                    "OTHER" | "IMAGE_OTHER" => FinishReason::Other,
-                    "MALFORMED_FUNCTION_CALL" => FinishReason::Error,
-                    _ => FinishReason::Unknown,
-                }
+                map_finish_reason(finish_reason)
             };
-            chunks.push(querymt::chat::StreamChunk::Done { finish_reason });
+            chunks.push(StreamChunk::Done { finish_reason });
         }
     }
 
@@ -1261,14 +1459,31 @@ impl HTTPLLMProviderFactory for GoogleFactory {
         "google"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: true,
+            embeddings: true,
+            completion: true,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("GEMINI_API_KEY".into())
     }
 
+    fn default_model(&self) -> Option<&str> {
+        Some("gemini-2.5-pro")
+    }
+
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let mut base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => normalize_base_url(Url::parse(base_url_str)?),
             None => Google::default_base_url(),
         };
 
@@ -1287,7 +1502,7 @@ impl HTTPLLMProviderFactory for GoogleFactory {
     }
 
     fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
-        let resp_json: Value = serde_json::from_slice(resp.body())?;
+        let resp_json: Value = decode_json_response(resp.body(), "Google models response")?;
         let arr = resp_json
             .get("models")
             .and_then(Value::as_array)
@@ -1314,6 +1529,566 @@ impl HTTPLLMProviderFactory for GoogleFactory {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_google(extra: Value) -> Google {
+        let mut cfg = serde_json::json!({
+            "api_key": "test-key",
+            "model": "gemini-2.5-flash"
+        });
+        if let (Some(cfg), Some(extra)) = (cfg.as_object_mut(), extra.as_object()) {
+            cfg.extend(extra.clone());
+        }
+        serde_json::from_value(cfg).unwrap()
+    }
+
+    fn request_body(google: &Google) -> Value {
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = google.chat_request(&messages, None).unwrap();
+        serde_json::from_slice(req.body()).unwrap()
+    }
+
+    #[test]
+    fn chat_request_includes_seed_in_generation_config() {
+        let body = request_body(&test_google(serde_json::json!({ "seed": 42 })));
+        assert_eq!(body["generationConfig"]["seed"], 42);
+
+        let body = request_body(&test_google(serde_json::json!({})));
+        assert!(body["generationConfig"].get("seed").is_none());
+    }
+
+    #[test]
+    fn chat_request_includes_thinking_config_when_reasoning_enabled() {
+        let google = test_google(serde_json::json!({ "reasoning_effort": "low" }));
+        let body = request_body(&google);
+        let thinking = &body["generationConfig"]["thinkingConfig"];
+        assert_eq!(thinking["includeThoughts"], true);
+        assert_eq!(thinking["thinkingBudget"], 1_024);
+
+        let google = test_google(serde_json::json!({ "thinking_budget": 2_048 }));
+        let body = request_body(&google);
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"]["thinkingBudget"],
+            2_048
+        );
+    }
+
+    #[test]
+    fn embed_batches_inputs_and_parses_recorded_response() {
+        let google = test_google(serde_json::json!({}));
+        let req = google
+            .embed_request(&["first".to_string(), "second".to_string()])
+            .unwrap();
+        assert_eq!(
+            req.uri().to_string(),
+            "https://generativelanguage.googleapis.com/v1beta/models/text-embedding-004:batchEmbedContents?key=test-key"
+        );
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"requests": [
+                {"model": "models/text-embedding-004", "content": {"parts": [{"text": "first"}]}},
+                {"model": "models/text-embedding-004", "content": {"parts": [{"text": "second"}]}}
+            ]})
+        );
+
+        // Shape of a real `batchEmbedContents` response, truncated vectors.
+        let recorded = br#"{
+  "embeddings": [
+    {
+      "values": [0.0131685, -0.0087119, -0.0467827]
+    },
+    {
+      "values": [-0.0071777, 0.0012831, 0.0199811]
+    }
+  ]
+}
+"#;
+        let resp = Response::builder()
+            .status(200)
+            .body(recorded.to_vec())
+            .unwrap();
+        let embeddings = google.parse_embed(resp).unwrap();
+        assert_eq!(
+            embeddings,
+            vec![
+                vec![0.0131685, -0.0087119, -0.0467827],
+                vec![-0.0071777, 0.0012831, 0.0199811],
+            ]
+        );
+    }
+
+    #[test]
+    fn chat_request_omits_thinking_config_by_default() {
+        let body = request_body(&test_google(serde_json::json!({})));
+        assert!(body["generationConfig"].get("thinkingConfig").is_none());
+    }
+
+    #[test]
+    fn failed_tool_result_is_sent_as_error() {
+        let google = test_google(serde_json::json!({}));
+        let messages = vec![
+            ChatMessage::user()
+                .tool_result(
+                    "call_1".to_string(),
+                    Some("read_file".to_string()),
+                    true,
+                    vec![Content::text("no such file")],
+                )
+                .build(),
+            ChatMessage::user()
+                .tool_result(
+                    "call_2".to_string(),
+                    Some("read_file".to_string()),
+                    false,
+                    vec![Content::text("hello")],
+                )
+                .build(),
+        ];
+        let req = google.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        let failed = &body["contents"][0]["parts"][0]["functionResponse"]["response"];
+        assert_eq!(failed["error"], "no such file");
+        assert!(failed.get("content").is_none());
+
+        let ok = &body["contents"][1]["parts"][0]["functionResponse"]["response"];
+        assert_eq!(ok["content"], "hello");
+        assert!(ok.get("error").is_none());
+    }
+
+    #[test]
+    fn image_tool_result_follows_function_response() {
+        let google = test_google(serde_json::json!({}));
+        let messages = vec![
+            ChatMessage::user()
+                .tool_result(
+                    "call_1".to_string(),
+                    Some("screenshot".to_string()),
+                    false,
+                    vec![
+                        "captured".into(),
+                        Content::image("image/png", vec![1, 2, 3]),
+                    ],
+                )
+                .build(),
+        ];
+        let req = google.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let parts = &body["contents"][0]["parts"];
+
+        assert_eq!(
+            parts[0]["functionResponse"]["response"]["content"],
+            "captured"
+        );
+        assert_eq!(parts[1]["inlineData"]["mime_type"], "image/png");
+        assert_eq!(parts[1]["inlineData"]["data"], BASE64.encode([1, 2, 3]));
+    }
+
+    #[test]
+    fn interleaved_text_and_images_keep_order() {
+        let google = test_google(serde_json::json!({}));
+        let messages = vec![
+            ChatMessage::user()
+                .text("Compare")
+                .image("image/png", vec![1])
+                .text("with")
+                .image("image/jpeg", vec![2])
+                .build(),
+        ];
+        let req = google.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        let parts = &body["contents"][0]["parts"];
+
+        assert_eq!(parts[0]["text"], "Compare");
+        assert_eq!(parts[1]["inlineData"]["mime_type"], "image/png");
+        assert_eq!(parts[2]["text"], "with");
+        assert_eq!(parts[3]["inlineData"]["mime_type"], "image/jpeg");
+    }
+
+    #[test]
+    fn base_url_with_or_without_trailing_slash_resolves_generate_content() {
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        for base_url in ["https://gw.internal/v1", "https://gw.internal/v1/"] {
+            let google = test_google(serde_json::json!({ "base_url": base_url }));
+            let req = google.chat_request(&messages, None).unwrap();
+            assert_eq!(
+                req.uri().to_string(),
+                "https://gw.internal/v1/gemini-2.5-flash:generateContent?key=test-key",
+                "base_url {base_url}"
+            );
+        }
+
+        let req = test_google(serde_json::json!({}))
+            .chat_request(&messages, None)
+            .unwrap();
+        assert_eq!(
+            req.uri().to_string(),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.5-flash:generateContent?key=test-key"
+        );
+    }
+
+    #[test]
+    fn system_messages_join_system_instruction() {
+        let google = test_google(serde_json::json!({ "system": "Be brief." }));
+        let messages = vec![
+            ChatMessage::user().text("hi").build(),
+            ChatMessage::system().text("Answer in French.").build(),
+        ];
+        let req = google.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        let parts = &body["system_instruction"]["parts"];
+        assert_eq!(parts[0]["text"], "Be brief.");
+        assert_eq!(parts[1]["text"], "Answer in French.");
+        assert_eq!(body["contents"].as_array().unwrap().len(), 1);
+        assert_eq!(body["contents"][0]["role"], "user");
+    }
+
+    #[test]
+    fn extra_body_merges_into_request_and_generation_config() {
+        let google = test_google(serde_json::json!({
+            "max_tokens": 100,
+            "extra_body": {
+                "safetySettings": [],
+                "generationConfig": { "seed": 7 }
+            }
+        }));
+        let body = request_body(&google);
+
+        assert_eq!(body["safetySettings"], serde_json::json!([]));
+        assert_eq!(body["generationConfig"]["seed"], 7);
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 100);
+    }
+
+    #[test]
+    fn parse_usage_metadata() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "hi"}]},
+                "finishReason": "STOP",
+                "index": 0
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 100,
+                "candidatesTokenCount": 20,
+                "thoughtsTokenCount": 30,
+                "cachedContentTokenCount": 40,
+                "totalTokenCount": 150
+            }
+        }))
+        .unwrap();
+
+        let usage = resp.usage().expect("usage should be present");
+        assert_eq!(usage.input_tokens, 60);
+        assert_eq!(usage.output_tokens, 20);
+        assert_eq!(usage.reasoning_tokens, 30);
+        assert_eq!(usage.cache_read, 40);
+        assert_eq!(usage.cache_write, 0);
+    }
+
+    #[test]
+    fn parse_grounding_metadata_as_citations() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "Spain won Euro 2024."}]},
+                "finishReason": "STOP",
+                "index": 0,
+                "groundingMetadata": {
+                    "groundingChunks": [
+                        {"web": {"uri": "https://uefa.com/a", "title": "uefa.com"}},
+                        {"web": {"uri": "https://example.com/b", "title": "example.com"}}
+                    ],
+                    "groundingSupports": [{
+                        "segment": {"endIndex": 20, "text": "Spain won Euro 2024."},
+                        "groundingChunkIndices": [0]
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            resp.citations(),
+            Some(vec![
+                Citation {
+                    title: Some("uefa.com".to_string()),
+                    url: Some("https://uefa.com/a".to_string()),
+                    start: Some(0),
+                    end: Some(20),
+                },
+                Citation {
+                    title: Some("example.com".to_string()),
+                    url: Some("https://example.com/b".to_string()),
+                    start: None,
+                    end: None,
+                },
+            ])
+        );
+        assert_eq!(response_with_finish_reason("STOP").citations(), None);
+    }
+
+    #[test]
+    fn citation_spans_are_offset_by_their_part() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "Let me look that up.", "thought": true},
+                    {"text": "Spain won. "},
+                    {"text": "The final was in Berlin."}
+                ]},
+                "finishReason": "STOP",
+                "index": 0,
+                "groundingMetadata": {
+                    "groundingChunks": [
+                        {"web": {"uri": "https://uefa.com/a", "title": "uefa.com"}}
+                    ],
+                    "groundingSupports": [{
+                        "segment": {
+                            "partIndex": 2,
+                            "startIndex": 4,
+                            "endIndex": 9,
+                            "text": "final"
+                        },
+                        "groundingChunkIndices": [0]
+                    }]
+                }
+            }]
+        }))
+        .unwrap();
+
+        let text = resp.text().unwrap();
+        let citation = &resp.citations().unwrap()[0];
+        let (start, end) = (citation.start.unwrap(), citation.end.unwrap());
+        assert_eq!(&text[start..end], "final");
+    }
+
+    #[test]
+    fn stream_parser_reports_malformed_object_once() {
+        let mut parser = GoogleStreamParser::default();
+        let err = parser
+            .parse_chunk(b"[{\"candidates\": 1}\n")
+            .expect_err("candidates must be an array");
+        assert!(
+            matches!(err, LLMError::ResponseFormatError { .. }),
+            "got {err:?}"
+        );
+
+        let chunks = parser
+            .parse_chunk(
+                b",\r\n{\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"hi\"}]}, \"index\": 0}]}\n]",
+            )
+            .unwrap();
+        assert!(matches!(&chunks[..], [StreamChunk::Text(text)] if text == "hi"));
+    }
+
+    #[test]
+    fn stream_parser_chunks_json_array_across_boundaries() {
+        let body = concat!(
+            "[{\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"if (a) { \\\"ok\\\" }\"}], \"role\": \"model\"}, \"index\": 0}]}\n",
+            ",\r\n{\"candidates\": [{\"content\": {\"parts\": [{\"text\": \" café ]\"}], \"role\": \"model\"}, \"index\": 0}]}\n",
+            ",\r\n{\"candidates\": [{\"content\": {\"parts\": [{\"functionCall\": {\"name\": \"lookup\", \"args\": {\"q\": [1, {\"x\": \"}\"}]}}}], \"role\": \"model\"}, \"index\": 0, \"finishReason\": \"STOP\"}]}\n",
+            "]"
+        )
+        .as_bytes();
+
+        // Split at every byte offset, including inside escapes and the
+        // multi-byte `é`, and check the result never depends on the split.
+        for split in 0..=body.len() {
+            let mut parser = GoogleStreamParser::default();
+            let mut chunks = parser.parse_chunk(&body[..split]).unwrap();
+            chunks.extend(parser.parse_chunk(&body[split..]).unwrap());
+
+            let text: String = chunks
+                .iter()
+                .filter_map(|c| match c {
+                    StreamChunk::Text(t) => Some(t.as_str()),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(text, "if (a) { \"ok\" } café ]", "split at {split}");
+
+            let tool_call = chunks
+                .iter()
+                .find_map(|c| match c {
+                    StreamChunk::ToolUseComplete { tool_call, .. } => Some(tool_call),
+                    _ => None,
+                })
+                .expect("tool call should be emitted");
+            assert_eq!(tool_call.function.name, "lookup");
+            assert_eq!(
+                serde_json::from_str::<Value>(&tool_call.function.arguments).unwrap(),
+                serde_json::json!({"q": [1, {"x": "}"}]})
+            );
+            assert!(matches!(chunks.last(), Some(StreamChunk::Done { .. })));
+        }
+    }
+
+    #[test]
+    fn stream_parser_holds_incomplete_object() {
+        let mut parser = GoogleStreamParser::default();
+        let chunks = parser
+            .parse_chunk(b"[{\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"hel")
+            .unwrap();
+        assert!(chunks.is_empty());
+
+        let chunks = parser.parse_chunk(b"lo\"}]}, \"index\": 0}]}").unwrap();
+        assert!(matches!(chunks.as_slice(), [StreamChunk::Text(t)] if t == "hello"));
+    }
+
+    fn response_with_finish_reason(reason: &str) -> GoogleChatResponse {
+        serde_json::from_value(serde_json::json!({
+            "candidates": [{ "finishReason": reason, "index": 0 }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn finish_reason_maps_gemini_values() {
+        assert_eq!(
+            response_with_finish_reason("MAX_TOKENS").finish_reason(),
+            Some(FinishReason::Length)
+        );
+        assert_eq!(
+            response_with_finish_reason("SAFETY").finish_reason(),
+            Some(FinishReason::ContentFilter)
+        );
+        assert_eq!(
+            response_with_finish_reason("RECITATION").finish_reason(),
+            Some(FinishReason::ContentFilter)
+        );
+        assert_eq!(
+            response_with_finish_reason("STOP").finish_reason(),
+            Some(FinishReason::Stop)
+        );
+    }
+
+    #[test]
+    fn finish_reason_is_none_without_candidates() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "promptFeedback": { "blockReason": "SAFETY" }
+        }))
+        .unwrap();
+        assert_eq!(resp.finish_reason(), None);
+    }
+
+    #[test]
+    fn tool_calls_are_none_without_function_calls() {
+        assert_eq!(response_with_finish_reason("STOP").tool_calls(), None);
+
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "hi"}], "functionCalls": []},
+                "finishReason": "STOP",
+                "index": 0
+            }]
+        }))
+        .unwrap();
+        assert_eq!(resp.tool_calls(), None);
+    }
+
+    #[test]
+    fn parse_thought_parts_as_thinking() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "pondering", "thought": true},
+                    {"text": "answer"}
+                ]},
+                "finishReason": "STOP",
+                "index": 0
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(resp.thinking().as_deref(), Some("pondering"));
+        assert_eq!(resp.text().as_deref(), Some("answer"));
+    }
+
+    #[test]
+    fn parse_chat_400_keeps_error_body() {
+        let google = test_google(Value::Null);
+        let resp = http::Response::builder()
+            .status(400)
+            .body(
+                br#"{"error":{"code":400,"message":"API key not valid.","status":"INVALID_ARGUMENT"}}"#
+                    .to_vec(),
+            )
+            .unwrap();
+
+        match google.parse_chat(resp).unwrap_err() {
+            LLMError::InvalidRequest(message) => assert_eq!(message, "API key not valid."),
+            other => panic!("expected InvalidRequest, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn candidates_returns_every_candidate_text() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [
+                {
+                    "content": {"parts": [{"text": "second"}]},
+                    "finishReason": "STOP",
+                    "index": 1
+                },
+                {
+                    "content": {"parts": [
+                        {"text": "hmm", "thought": true},
+                        {"text": "first"}
+                    ]},
+                    "finishReason": "STOP",
+                    "index": 0
+                }
+            ]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            resp.candidates(),
+            Some(vec!["first".to_string(), "second".to_string()])
+        );
+    }
+
+    #[test]
+    fn images_decodes_inline_data_parts() {
+        let png = vec![0x89, b'P', b'N', b'G'];
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [
+                    {"text": "Here is your image"},
+                    {"inlineData": {"mimeType": "image/png", "data": BASE64.encode(&png)}}
+                ]},
+                "finishReason": "STOP",
+                "index": 0
+            }]
+        }))
+        .unwrap();
+
+        assert_eq!(resp.text().as_deref(), Some("Here is your image"));
+        assert_eq!(resp.images(), Some(vec![("image/png".to_string(), png)]));
+    }
+
+    #[test]
+    fn images_is_none_for_text_only_response() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({
+            "candidates": [{
+                "content": {"parts": [{"text": "no pictures"}]},
+                "index": 0
+            }]
+        }))
+        .unwrap();
+        assert_eq!(resp.images(), None);
+    }
+
+    #[test]
+    fn candidates_is_none_without_candidates() {
+        let resp: GoogleChatResponse = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(resp.candidates(), None);
+    }
+}
+
 /// Creates a Google HTTP factory for direct static registration.
 pub fn create_http_factory() -> Arc<dyn HTTPLLMProviderFactory> {
     Arc::new(GoogleFactory)
@@ -1325,6 +2100,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(GoogleFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Google, GoogleFactory};
@@ -6,7 +6,7 @@ pub use config::IzwiConfig;
 use provider::{CachedRuntime, IzwiProvider};
 use querymt::LLMProvider;
 use querymt::error::LLMError;
-use querymt::plugin::{Fut, LLMProviderFactory};
+use querymt::plugin::{Fut, LLMProviderFactory, ProviderCapabilities};
 use schemars::schema_for;
 
 /// Create a provider directly from a config struct (useful for testing and embedding).
@@ -29,6 +29,11 @@ impl LLMProviderFactory for IzwiFactory {
         "izwi"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        // Audio-only provider: none of the chat/embedding features apply.
+        ProviderCapabilities::default()
+    }
+
     fn config_schema(&self) -> String {
         let schema = schema_for!(IzwiConfig);
         serde_json::to_string(&schema).expect("IzwiConfig schema should always serialize")
@@ -58,6 +63,12 @@ pub extern "C" fn plugin_factory() -> *mut dyn LLMProviderFactory {
     })) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
 /// Initialize logging from the host process.
 ///
 /// This function is called by the host after loading the plugin via dlopen.
@@ -4,8 +4,8 @@ use http::{
 };
 use kimi_auth::kimi_cli_oauth_config;
 use qmt_openai::api::{
-    OpenAIProviderConfig, OpenAIToolUseState, openai_chat_request, openai_parse_chat,
-    parse_openai_sse_chunk, url_schema,
+    OpenAIProviderConfig, OpenAIStreamState, openai_chat_request, openai_embed_request,
+    openai_parse_chat, openai_parse_embed, parse_openai_sse_chunk, url_schema,
 };
 use querymt::{
     HTTPLLMProvider,
@@ -17,12 +17,11 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashMap;
 use std::sync::Arc;
 use url::Url;
 
@@ -30,7 +29,10 @@ use url::Url;
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct KimiCode {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "KimiCode::default_base_url")]
+    #[serde(
+        default = "KimiCode::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: String,
     pub model: String,
@@ -48,6 +50,14 @@ pub struct KimiCode {
     pub frequency_penalty: Option<f32>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Overrides the `x-msh-device-name` header from the OAuth profile.
+    pub device_name: Option<String>,
+    /// Overrides the `x-msh-device-model` header from the OAuth profile.
+    pub device_model: Option<String>,
+    /// Overrides the `x-msh-os-version` header from the OAuth profile.
+    pub os_version: Option<String>,
+    /// Overrides the `x-msh-platform` header from the OAuth profile.
+    pub platform: Option<String>,
     /// Optional resolver for dynamic credential refresh (e.g., OAuth tokens).
     #[serde(skip)]
     #[schemars(skip)]
@@ -55,6 +65,8 @@ pub struct KimiCode {
     #[serde(skip)]
     #[schemars(skip)]
     pub kimi_profile: Option<kimi_auth::OAuthConfig>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAIProviderConfig for KimiCode {
@@ -139,6 +151,10 @@ impl OpenAIProviderConfig for KimiCode {
 
         None
     }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for KimiCode {
@@ -155,7 +171,7 @@ impl HTTPChatProvider for KimiCode {
         resolved.api_key = self.resolved_api_key();
         let profile = self.profile();
         let mut request = openai_chat_request(&resolved, messages, tools)?;
-        KimiCode::apply_kimi_agent_headers(&mut request, &profile)?;
+        KimiCode::apply_kimi_agent_headers(&mut request, &profile, Some(self))?;
         Ok(request)
     }
 
@@ -169,7 +185,7 @@ impl HTTPChatProvider for KimiCode {
         resolved.stream = Some(true);
         let profile = self.profile();
         let mut request = openai_chat_request(&resolved, messages, tools)?;
-        KimiCode::apply_kimi_agent_headers(&mut request, &profile)?;
+        KimiCode::apply_kimi_agent_headers(&mut request, &profile, Some(self))?;
         Ok(request)
     }
 
@@ -184,7 +200,7 @@ impl HTTPChatProvider for KimiCode {
 
 #[derive(Default)]
 struct KimiCodeStreamParser {
-    tool_states: HashMap<usize, OpenAIToolUseState>,
+    state: OpenAIStreamState,
 }
 
 impl ChatStreamParser for KimiCodeStreamParser {
@@ -192,30 +208,39 @@ impl ChatStreamParser for KimiCodeStreamParser {
         log::trace!(
             "kimi-code SSE chunk ({} bytes): {:?}",
             chunk.len(),
-            String::from_utf8_lossy(chunk)
+            querymt::redact::redact_text(&String::from_utf8_lossy(chunk))
         );
         let normalized = KimiCode::normalize_sse_data_prefix(chunk);
-        parse_openai_sse_chunk(&normalized, &mut self.tool_states)
+        parse_openai_sse_chunk(&normalized, &mut self.state)
     }
 }
 
 impl HTTPEmbeddingProvider for KimiCode {
-    fn embed_request(&self, _inputs: &[String]) -> Result<Request<Vec<u8>>, LLMError> {
-        unimplemented!("feature is missing!")
+    fn embed_request(&self, inputs: &[String]) -> Result<Request<Vec<u8>>, LLMError> {
+        let mut resolved = self.clone();
+        resolved.api_key = self.resolved_api_key();
+        let profile = self.profile();
+        let mut request = openai_embed_request(&resolved, inputs)?;
+        KimiCode::apply_kimi_agent_headers(&mut request, &profile, Some(self))?;
+        Ok(request)
     }
 
-    fn parse_embed(&self, _resp: Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
-        unimplemented!("feature is missing!")
+    fn parse_embed(&self, resp: Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
+        openai_parse_embed(self, resp)
     }
 }
 
 impl HTTPCompletionProvider for KimiCode {
     fn complete_request(&self, _req: &CompletionRequest) -> Result<Request<Vec<u8>>, LLMError> {
-        unimplemented!("feature is missing!")
+        Err(LLMError::NotImplemented(
+            "kimi-code does not support completions".into(),
+        ))
     }
 
     fn parse_complete(&self, _resp: Response<Vec<u8>>) -> Result<CompletionResponse, LLMError> {
-        unimplemented!("feature is missing!")
+        Err(LLMError::NotImplemented(
+            "kimi-code does not support completions".into(),
+        ))
     }
 }
 
@@ -252,9 +277,13 @@ impl KimiCode {
             .unwrap_or_else(kimi_cli_oauth_config)
     }
 
+    /// Sets the Kimi CLI identity headers on `request`.
+    ///
+    /// Device fields set on `config` take precedence over the OAuth `profile`.
     fn apply_kimi_agent_headers(
         request: &mut Request<Vec<u8>>,
         profile: &kimi_auth::OAuthConfig,
+        config: Option<&KimiCode>,
     ) -> Result<(), LLMError> {
         let mut set_header = |name: &'static str, value: &str| -> Result<(), LLMError> {
             let value = http::header::HeaderValue::from_str(value).map_err(|e| {
@@ -268,12 +297,26 @@ impl KimiCode {
         let user_agent =
             std::env::var("KIMI_USER_AGENT").unwrap_or_else(|_| format!("KimiCLI/{msh_version}"));
 
+        let platform = config.and_then(|c| c.platform.as_deref());
+        let device_name = config.and_then(|c| c.device_name.as_deref());
+        let device_model = config.and_then(|c| c.device_model.as_deref());
+        let os_version = config.and_then(|c| c.os_version.as_deref());
+
         set_header("user-agent", &user_agent)?;
-        set_header("x-msh-platform", &profile.platform)?;
+        set_header("x-msh-platform", platform.unwrap_or(&profile.platform))?;
         set_header("x-msh-version", msh_version)?;
-        set_header("x-msh-device-name", &profile.device_name)?;
-        set_header("x-msh-device-model", &profile.device_model)?;
-        set_header("x-msh-os-version", &profile.os_version)?;
+        set_header(
+            "x-msh-device-name",
+            device_name.unwrap_or(&profile.device_name),
+        )?;
+        set_header(
+            "x-msh-device-model",
+            device_model.unwrap_or(&profile.device_model),
+        )?;
+        set_header(
+            "x-msh-os-version",
+            os_version.unwrap_or(&profile.os_version),
+        )?;
         set_header("x-msh-device-id", &profile.device_id)?;
         Ok(())
     }
@@ -305,6 +348,19 @@ impl HTTPLLMProviderFactory for KimiCodeFactory {
         "kimi-code"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         None
     }
@@ -312,7 +368,7 @@ impl HTTPLLMProviderFactory for KimiCodeFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => KimiCode::default_base_url(),
         };
         let models_url = base_url.join("models")?;
@@ -333,7 +389,7 @@ impl HTTPLLMProviderFactory for KimiCodeFactory {
 
         let mut request = builder.body(Vec::new())?;
         let profile = kimi_cli_oauth_config();
-        KimiCode::apply_kimi_agent_headers(&mut request, &profile)?;
+        KimiCode::apply_kimi_agent_headers(&mut request, &profile, None)?;
         Ok(request)
     }
 
@@ -404,6 +460,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn chat_request_device_overrides_win_over_profile() {
+        let provider: KimiCode = serde_json::from_value(serde_json::json!({
+            "api_key": "test-token",
+            "model": "kimi-latest",
+            "device_name": "ci-runner",
+            "device_model": "container",
+            "os_version": "6.1.0",
+            "platform": "linux"
+        }))
+        .unwrap();
+        let profile = provider.profile();
+
+        let messages = vec![ChatMessage::user().text("hello").build()];
+        let request = provider.chat_request(&messages, None).unwrap();
+        let headers = request.headers();
+
+        assert_eq!(headers["x-msh-device-name"], "ci-runner");
+        assert_eq!(headers["x-msh-device-model"], "container");
+        assert_eq!(headers["x-msh-os-version"], "6.1.0");
+        assert_eq!(headers["x-msh-platform"], "linux");
+        // Fields without an override still come from the profile.
+        assert_eq!(headers["x-msh-device-id"], &*profile.device_id);
+    }
+
+    #[test]
+    fn embed_request_targets_embeddings_endpoint() {
+        use querymt::embedding::http::HTTPEmbeddingProvider;
+
+        let provider = test_provider();
+        let request = provider.embed_request(&["hello".to_string()]).unwrap();
+
+        assert_eq!(
+            request.uri().to_string(),
+            "https://api.kimi.com/coding/v1/embeddings"
+        );
+        assert!(request.headers().contains_key("x-msh-device-id"));
+        let body: Value = serde_json::from_slice(request.body()).unwrap();
+        assert_eq!(body["input"], serde_json::json!(["hello"]));
+    }
+
     #[test]
     fn chat_request_injects_reasoning_content_for_assistant_tool_calls() {
         let provider = test_provider();
@@ -663,6 +760,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn aborted_stream_does_not_leak_tool_state_into_next_stream() {
+        let provider = test_provider();
+
+        // First stream dies mid tool call: no finish_reason, no [DONE].
+        let mut aborted = provider
+            .chat_stream_parser()
+            .expect("stream parser should initialize");
+        let start = b"data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_old\",\"type\":\"function\",\"function\":{\"name\":\"get_weather\",\"arguments\":\"{\\\"city\\\":\"}}]}}]}\n\n";
+        aborted.parse_chunk(start).unwrap();
+        drop(aborted);
+
+        // Each new stream gets a fresh parser, so no buffered arguments carry over.
+        let mut parser = provider
+            .chat_stream_parser()
+            .expect("stream parser should initialize");
+        let chunk1 = b"data: {\"choices\":[{\"index\":0,\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_new\",\"type\":\"function\",\"function\":{\"name\":\"get_time\",\"arguments\":\"{}\"}}]}}]}\n\n";
+        parser.parse_chunk(chunk1).unwrap();
+        let chunk2 = b"data: {\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"tool_calls\"}]}\n\n";
+        let events = parser.parse_chunk(chunk2).unwrap();
+
+        let tool_call = events
+            .iter()
+            .find_map(|e| match e {
+                querymt::chat::StreamChunk::ToolUseComplete { tool_call, .. } => Some(tool_call),
+                _ => None,
+            })
+            .expect("expected ToolUseComplete");
+        assert_eq!(tool_call.id, "call_new");
+        assert_eq!(tool_call.function.name, "get_time");
+        assert_eq!(tool_call.function.arguments, "{}");
+    }
+
     #[test]
     fn parse_chat_stream_chunk_handles_done_sentinel() {
         let provider = test_provider();
@@ -755,6 +885,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(KimiCodeFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{KimiCode, KimiCodeFactory};
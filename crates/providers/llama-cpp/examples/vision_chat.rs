@@ -97,6 +97,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         presence_penalty: None,
         frequency_penalty: None,
         penalty_last_n: None,
+        mirostat_tau: None,
+        mirostat_eta: None,
         system: vec![],
         n_batch: None,
         n_threads: None,
@@ -107,6 +109,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         add_bos: None,
         log: None,
         fast_download: None,
+        download_timeout_seconds: None,
+        max_concurrent_requests: None,
         enable_thinking: None,
         flash_attention: None,
         kv_cache_type_k: None,
@@ -116,6 +120,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         n_ubatch: None,
         text_only: None,
         json_schema: None,
+        fim_tokens: None,
+        grammar: None,
+        lora_adapters: None,
+        timeout_seconds: None,
+        kv_cache_reuse: None,
+        repair_tool_json: None,
+        tool_fallback: None,
+        pooling: None,
+        normalize: None,
     };
 
     println!("Loading model: {}", args.model);
@@ -135,6 +148,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             Content::text(&args.prompt),
         ],
         cache: None,
+        name: None,
     }];
 
     println!("Prompt: {}\n", args.prompt);
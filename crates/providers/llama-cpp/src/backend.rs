@@ -6,6 +6,9 @@ use std::sync::{Mutex, OnceLock};
 /// Get the global llama backend instance.
 ///
 /// The backend is initialized once and shared across all provider instances.
+/// Requests hold the guard only while creating their context, so one
+/// request's generation doesn't block others; how many requests run on a
+/// model at once is up to its [`RequestQueue`](crate::queue::RequestQueue).
 pub(crate) fn llama_backend() -> Result<std::sync::MutexGuard<'static, LlamaBackend>, LLMError> {
     static BACKEND: OnceLock<Result<Mutex<LlamaBackend>, String>> = OnceLock::new();
     let backend = BACKEND
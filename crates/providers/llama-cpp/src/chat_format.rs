@@ -174,6 +174,31 @@ fn extract_reasoning_blocks(
     (thinking, output)
 }
 
+/// Count tagged tool-call blocks in `text` that no format parser could
+/// understand. Such blocks are dropped from the parsed tool calls.
+pub(crate) fn count_malformed_tool_calls(
+    text: &str,
+    reasoning_format: ReasoningFormat,
+    starts_in_thinking: bool,
+) -> usize {
+    let (_, text) = extract_reasoning_blocks(text, reasoning_format, starts_in_thinking);
+    let qwen = extract_tag_bodies(&text, "<tool_call>", "</tool_call>")
+        .into_iter()
+        .filter(|body| {
+            parse_json_tool_call(body.trim()).is_none() && parse_function_tool_call(body).is_none()
+        })
+        .count();
+    let gemma = extract_tag_bodies(&text, "<start_function_call>", "<end_function_call>")
+        .into_iter()
+        .filter(|body| parse_gemma_call_body(body).is_none())
+        .count();
+    let gemma4 = extract_tag_bodies(&text, "<|tool_call>", "<tool_call|>")
+        .into_iter()
+        .filter(|body| parse_gemma4_call(body).is_none())
+        .count();
+    qwen + gemma + gemma4
+}
+
 fn extract_tool_calls(text: &str) -> Option<Vec<ToolCall>> {
     let mut calls = Vec::new();
     calls.extend(extract_qwen_json_tool_calls(text));
@@ -455,7 +480,7 @@ fn extract_qwen_json_tool_calls(text: &str) -> Vec<ToolCall> {
         .collect()
 }
 
-fn parse_json_tool_call(raw: &str) -> Option<ToolCall> {
+pub(crate) fn parse_json_tool_call(raw: &str) -> Option<ToolCall> {
     let value: Value = serde_json::from_str(raw).ok()?;
     let name = value.get("name")?.as_str()?.to_string();
     let arguments = value
@@ -587,24 +612,26 @@ fn parse_gemma_call_body(body: &str) -> Option<ToolCall> {
 fn extract_gemma4_tool_calls(text: &str) -> Vec<ToolCall> {
     extract_tag_bodies(text, "<|tool_call>", "<tool_call|>")
         .into_iter()
-        .filter_map(|body| {
-            let rest = body.trim().strip_prefix("call:")?;
-            let open = rest.find('{')?;
-            let close = rest.rfind('}')?;
-            let name = rest[..open].trim().to_string();
-            let args = parse_gemma4_object(&rest[open..=close])?;
-            Some(ToolCall {
-                id: stable_tool_call_id(&name, body),
-                call_type: "function".to_string(),
-                function: FunctionCall {
-                    name,
-                    arguments: args.to_string(),
-                },
-            })
-        })
+        .filter_map(parse_gemma4_call)
         .collect()
 }
 
+fn parse_gemma4_call(body: &str) -> Option<ToolCall> {
+    let rest = body.trim().strip_prefix("call:")?;
+    let open = rest.find('{')?;
+    let close = rest.rfind('}')?;
+    let name = rest[..open].trim().to_string();
+    let args = parse_gemma4_object(&rest[open..=close])?;
+    Some(ToolCall {
+        id: stable_tool_call_id(&name, body),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name,
+            arguments: args.to_string(),
+        },
+    })
+}
+
 fn parse_function_tool_call(body: &str) -> Option<ToolCall> {
     let function_start = body.find("<function=")?;
     let name_start = function_start + "<function=".len();
@@ -931,6 +958,27 @@ mod tests {
         assert_eq!(parsed.tool_calls.unwrap()[0].function.name, "glob");
     }
 
+    #[test]
+    fn counts_malformed_tool_calls() {
+        let text = "<tool_call>{\"name\":\"glob\",\"arguments\":{}}</tool_call>\
+                    <tool_call>{\"name\":\"read\",\"argu</tool_call>";
+        let parsed = parse_assistant_format(text);
+        assert_eq!(parsed.tool_calls.unwrap().len(), 1);
+        assert_eq!(
+            count_malformed_tool_calls(text, ReasoningFormat::ThinkTags, false),
+            1
+        );
+    }
+
+    #[test]
+    fn ignores_tool_call_tags_inside_thinking() {
+        let text = "<think>maybe <tool_call>not yet</tool_call></think>Done";
+        assert_eq!(
+            count_malformed_tool_calls(text, ReasoningFormat::ThinkTags, false),
+            0
+        );
+    }
+
     #[test]
     fn parses_gemma_channel_reasoning() {
         let parsed = parse_assistant_format_with_state(
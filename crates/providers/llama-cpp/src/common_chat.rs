@@ -82,6 +82,19 @@ pub(crate) struct GrammarTrigger {
 }
 
 impl ChatTemplateResult {
+    /// A prompt used as-is, without a chat template, grammar or extra stops,
+    /// e.g. for raw text completion.
+    pub(crate) fn raw(prompt: String) -> Self {
+        Self {
+            prompt,
+            grammar: None,
+            preserved_tokens: Vec::new(),
+            additional_stops: Vec::new(),
+            starts_in_thinking: false,
+            reasoning_format: ReasoningFormat::ThinkTags,
+        }
+    }
+
     pub(crate) fn streaming_state(&self) -> ChatStreamingState {
         ChatStreamingState::new(self.reasoning_format, self.starts_in_thinking)
     }
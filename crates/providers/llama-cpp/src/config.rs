@@ -1,6 +1,8 @@
 use querymt::chat::StructuredOutputFormat;
+use querymt_provider_common::HfDownloadOptions;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 /// Default maximum tokens to generate when not specified.
 pub(crate) const DEFAULT_MAX_TOKENS: u32 = 256;
@@ -38,6 +40,14 @@ pub struct LlamaCppConfig {
     /// -1 = full context, 0 = disabled. Defaults to 64 when any penalty is set
     /// but this is not explicitly configured.
     pub penalty_last_n: Option<i32>,
+    /// Mirostat v2 target entropy (tau). Setting this or `mirostat_eta` enables
+    /// mirostat v2, which replaces `top_k`, `top_p` and `min_p` (they are
+    /// ignored while mirostat is active). Defaults to 5.0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirostat_tau: Option<f32>,
+    /// Mirostat v2 learning rate (eta). Defaults to 0.1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mirostat_eta: Option<f32>,
     /// System prompt to prepend to chat requests.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub system: Vec<String>,
@@ -54,7 +64,8 @@ pub struct LlamaCppConfig {
     /// When a multimodal projection (mmproj) is active and this value is not
     /// set, the provider automatically uses `n_batch` as `n_ubatch` so that
     /// any image that fits in the logical batch also fits in a single ubatch.
-    /// Set this explicitly only when you need fine-grained control.
+    /// Set this explicitly only when you need fine-grained control; it must
+    /// not exceed `n_batch`.
     pub n_ubatch: Option<u32>,
     /// Threads for evaluation.
     pub n_threads: Option<i32>,
@@ -64,7 +75,13 @@ pub struct LlamaCppConfig {
     pub n_gpu_layers: Option<u32>,
     /// RNG seed for sampling.
     pub seed: Option<u32>,
-    /// Explicit chat template name (defaults to model's template).
+    /// Chat template override (defaults to the model's embedded template).
+    ///
+    /// Either the name of a template embedded in the GGUF, or a full Jinja
+    /// template given inline (any value containing `{{` or `{%`), which is
+    /// useful when the embedded template is missing or wrong. Use
+    /// [`LlamaCppFactory::resolved_chat_template`](crate::LlamaCppFactory::resolved_chat_template)
+    /// to check which template is actually used.
     pub chat_template: Option<String>,
     /// Disable llama.cpp chat template usage and fall back to a simple prompt format.
     pub use_chat_template: Option<bool>,
@@ -77,6 +94,18 @@ pub struct LlamaCppConfig {
     /// heavily utilize CPU cores during download. Only recommended for cloud
     /// instances with high CPU and bandwidth.
     pub fast_download: Option<bool>,
+    /// Abort a Hugging Face Hub download that hasn't finished after this many
+    /// seconds. Applies to each downloaded file. Unlimited by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_timeout_seconds: Option<u64>,
+    /// How many requests may generate on the loaded model at once; later
+    /// requests wait in arrival order. Defaults to 1, since requests on one
+    /// GPU contend for the same compute and each needs its own KV cache.
+    /// Taken from the config that loads the model and fixed while it stays
+    /// cached: providers created from it later share that limit, and a
+    /// different value only logs a warning until the model is evicted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_requests: Option<u32>,
     /// Enable thinking/reasoning output from the model.
     /// When true, the template is rendered with thinking support and
     /// `<think>` blocks are parsed into separate reasoning_content.
@@ -140,6 +169,99 @@ pub struct LlamaCppConfig {
     /// The schema is forwarded to the chat template engine via
     /// `OpenAIChatTemplateParams::json_schema`.
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Raw GBNF grammar (with a `root` rule) that constrains output of plain
+    /// chat and completion turns, e.g. to force a DSL. Tool-calling and
+    /// `json_schema` turns use their own template-derived grammar instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grammar: Option<String>,
+    /// Fill-in-the-middle special tokens used for completions with a suffix.
+    ///
+    /// When unset, the provider detects the common token sets (Qwen-Coder /
+    /// StarCoder `<|fim_prefix|>`, CodeLlama `<PRE>`, DeepSeek-Coder
+    /// `<｜fim▁begin｜>`) from the model vocabulary. Set this for models that
+    /// use other spellings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fim_tokens: Option<FimTokens>,
+    /// LoRA adapters applied on top of the base model, without merging weights.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lora_adapters: Option<Vec<LoraAdapter>>,
+    /// Wall-clock limit, in seconds, on token generation for a single
+    /// request. Generation stops with a timeout error once it is exceeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u64>,
+    /// Keep the KV cache of each chat turn so the next turn only decodes the
+    /// tokens after the prefix it shares with the previous prompt and reply.
+    /// Each provider instance keeps snapshots of its four most recently used
+    /// conversations. A snapshot is roughly the KV cache size of the
+    /// conversation so far and lives in host memory.
+    /// Turns with tools or structured output are only reused when `n_ctx`
+    /// is set, since their context is otherwise sized per prompt.
+    /// Defaults to `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kv_cache_reuse: Option<bool>,
+    /// Repair slightly malformed JSON in streamed tool-call arguments
+    /// (trailing commas, single quotes, unterminated strings). Defaults to
+    /// `true`; set to `false` to receive arguments exactly as generated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repair_tool_json: Option<bool>,
+    /// When the chat template fails to render with tools, describe the tools
+    /// in a system message instead and parse tool calls from JSON objects in
+    /// the output. Defaults to `false`, which returns the template error.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_fallback: Option<bool>,
+    /// How token embeddings are pooled into one vector per input for
+    /// embeddings. Defaults to the model's own pooling type.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pooling: Option<EmbeddingPooling>,
+    /// Scale embeddings to unit length (L2 norm). Defaults to `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize: Option<bool>,
+}
+
+impl LlamaCppConfig {
+    /// Concurrent requests allowed on the model this config loads.
+    pub(crate) fn request_limit(&self) -> usize {
+        self.max_concurrent_requests.unwrap_or(1) as usize
+    }
+
+    /// Options for downloading the model, mmproj and LoRA adapters.
+    pub(crate) fn download_options(&self) -> HfDownloadOptions {
+        HfDownloadOptions {
+            timeout: self.download_timeout_seconds.map(Duration::from_secs),
+        }
+    }
+}
+
+/// A LoRA adapter to apply to the base model.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub struct LoraAdapter {
+    /// Adapter GGUF reference. Supports the same formats as `model`.
+    pub path: String,
+    /// Strength of the adapter; 1.0 applies it fully.
+    #[serde(default = "LoraAdapter::default_scale")]
+    pub scale: f32,
+}
+
+impl LoraAdapter {
+    fn default_scale() -> f32 {
+        1.0
+    }
+}
+
+/// Special tokens that frame a fill-in-the-middle prompt.
+///
+/// The prompt is assembled as `prefix <code before> suffix <code after> middle`,
+/// and the model generates the missing code after the `middle` token.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub struct FimTokens {
+    /// Token that precedes the code before the cursor.
+    pub prefix: String,
+    /// Token that precedes the code after the cursor.
+    pub suffix: String,
+    /// Token after which the model generates the infill.
+    pub middle: String,
 }
 
 #[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
@@ -161,3 +283,117 @@ pub enum FlashAttentionPolicy {
     /// Explicitly disable flash attention.
     Disabled,
 }
+
+/// Pooling strategy for embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingPooling {
+    /// Average of all token embeddings.
+    Mean,
+    /// Embedding of the first (CLS) token.
+    Cls,
+    /// Embedding of the last token.
+    Last,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mmproj_path_is_optional() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "Qwen/Qwen2.5-VL-7B-Instruct-GGUF:Q4_K_M",
+            "mmproj_path": "hf:Qwen/Qwen2.5-VL-7B-Instruct-GGUF:mmproj-F16.gguf",
+        }))
+        .unwrap();
+        assert_eq!(
+            cfg.mmproj_path.as_deref(),
+            Some("hf:Qwen/Qwen2.5-VL-7B-Instruct-GGUF:mmproj-F16.gguf")
+        );
+
+        let cfg: LlamaCppConfig =
+            serde_json::from_value(serde_json::json!({ "model": "/models/local.gguf" })).unwrap();
+        assert_eq!(cfg.mmproj_path, None);
+    }
+
+    #[test]
+    fn parses_n_ubatch() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "/models/local.gguf",
+            "n_batch": 1024,
+            "n_ubatch": 256,
+        }))
+        .unwrap();
+        assert_eq!(cfg.n_batch, Some(1024));
+        assert_eq!(cfg.n_ubatch, Some(256));
+        crate::context::validate_batch_sizes(&cfg).unwrap();
+    }
+
+    #[test]
+    fn rejects_n_ubatch_larger_than_n_batch() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "/models/local.gguf",
+            "n_batch": 256,
+            "n_ubatch": 512,
+        }))
+        .unwrap();
+        let err = crate::context::validate_batch_sizes(&cfg).unwrap_err();
+        assert!(
+            matches!(err, querymt::error::LLMError::InvalidRequest(ref m) if m.contains("n_ubatch (512)")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn parses_flash_attention_and_kv_cache_types() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "/models/local.gguf",
+            "flash_attention": "enabled",
+            "kv_cache_type_k": "q8_0",
+            "kv_cache_type_v": "Q4_0",
+        }))
+        .unwrap();
+        assert!(matches!(
+            cfg.flash_attention,
+            Some(FlashAttentionPolicy::Enabled)
+        ));
+        assert!(
+            crate::memory::parse_kv_cache_type(cfg.kv_cache_type_k.as_deref().unwrap()).is_ok()
+        );
+        assert!(
+            crate::memory::parse_kv_cache_type(cfg.kv_cache_type_v.as_deref().unwrap()).is_ok()
+        );
+
+        let err = crate::memory::parse_kv_cache_type("q3_k").unwrap_err();
+        assert!(
+            matches!(err, querymt::error::LLMError::InvalidRequest(ref m) if m.contains("q3_k")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn parses_embedding_options() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "/models/embed.gguf",
+            "pooling": "cls",
+            "normalize": true,
+        }))
+        .unwrap();
+        assert_eq!(cfg.pooling, Some(EmbeddingPooling::Cls));
+        assert_eq!(cfg.normalize, Some(true));
+    }
+
+    #[test]
+    fn download_timeout_maps_to_download_options() {
+        let cfg: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+            "model": "/models/model.gguf",
+            "download_timeout_seconds": 90,
+        }))
+        .unwrap();
+        assert_eq!(
+            cfg.download_options().timeout,
+            Some(std::time::Duration::from_secs(90))
+        );
+    }
+}
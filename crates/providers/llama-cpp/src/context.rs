@@ -1,14 +1,15 @@
 use crate::config::{
     FlashAttentionPolicy, LLAMA_FLASH_ATTN_TYPE_AUTO, LLAMA_FLASH_ATTN_TYPE_DISABLED,
-    LLAMA_FLASH_ATTN_TYPE_ENABLED, LlamaCppConfig,
+    LLAMA_FLASH_ATTN_TYPE_ENABLED, LlamaCppConfig, LoraAdapter,
 };
 use crate::memory::{
     MemoryEstimate, kv_cache_bytes_per_element, parse_kv_cache_type, query_gpu_memory,
 };
+use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::context::params::LlamaContextParams;
-use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::model::{LlamaLoraAdapter, LlamaModel};
 use querymt::error::LLMError;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 /// The maximum batch size to use when the user has not configured `n_batch`.
 ///
@@ -20,6 +21,63 @@ use std::sync::Arc;
 /// `n_batch`.
 pub(crate) const DEFAULT_N_BATCH_CAP: u32 = 4096;
 
+/// The configured LoRA adapters of a provider, loaded once when it is built
+/// and attached to every context it creates.
+pub(crate) struct LoraAdapters {
+    /// The `lora_adapters` config they were loaded from.
+    config: Vec<LoraAdapter>,
+    /// Each loaded adapter with the scale to apply it at. `lora_adapter_set`
+    /// takes the adapter mutably, so attaching is serialized.
+    loaded: Mutex<Vec<(LlamaLoraAdapter, f32)>>,
+}
+
+// SAFETY: once loaded, an adapter is an immutable set of tensors owned by the
+// model, and attaching it only records it in the context, so contexts on any
+// thread may share it. The mutex serializes the mutable access.
+unsafe impl Send for LoraAdapters {}
+unsafe impl Sync for LoraAdapters {}
+
+impl LoraAdapters {
+    /// Load the adapters configured in `cfg` for `model`. Paths are resolved
+    /// to local files when the provider is constructed.
+    pub(crate) fn load(model: &LlamaModel, cfg: &LlamaCppConfig) -> Result<Self, LLMError> {
+        let config = cfg.lora_adapters.clone().unwrap_or_default();
+        let loaded = config
+            .iter()
+            .map(|adapter| {
+                let loaded = model.lora_adapter_init(&adapter.path).map_err(|e| {
+                    LLMError::ProviderError(format!(
+                        "Failed to load LoRA adapter '{}': {}. \
+                         Check that it was trained for this base model.",
+                        adapter.path, e
+                    ))
+                })?;
+                Ok((loaded, adapter.scale))
+            })
+            .collect::<Result<Vec<_>, LLMError>>()?;
+        Ok(Self {
+            config,
+            loaded: Mutex::new(loaded),
+        })
+    }
+
+    /// Whether these adapters were loaded from the `lora_adapters` of `cfg`.
+    pub(crate) fn matches(&self, cfg: &LlamaCppConfig) -> bool {
+        self.config == cfg.lora_adapters.as_deref().unwrap_or_default()
+    }
+
+    /// Attach the adapters to a freshly created context.
+    pub(crate) fn apply(&self, ctx: &LlamaContext<'_>) -> Result<(), LLMError> {
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        for (adapter, scale) in loaded.iter_mut() {
+            ctx.lora_adapter_set(adapter, *scale).map_err(|e| {
+                LLMError::ProviderError(format!("Failed to apply LoRA adapter: {e}"))
+            })?;
+        }
+        Ok(())
+    }
+}
+
 /// Resolve the effective `n_batch` value.
 ///
 /// Returns:
@@ -65,6 +123,28 @@ pub(crate) fn resolve_n_ubatch(cfg: &LlamaCppConfig, n_batch: u32, has_multimoda
     }
 }
 
+/// Check that the configured batch sizes are usable.
+///
+/// llama.cpp splits each logical batch (`n_batch`) into physical batches of
+/// `n_ubatch` tokens, so `n_ubatch` must be non-zero and no larger than an
+/// explicitly configured `n_batch`.
+pub(crate) fn validate_batch_sizes(cfg: &LlamaCppConfig) -> Result<(), LLMError> {
+    if cfg.n_batch == Some(0) {
+        return Err(LLMError::InvalidRequest(
+            "n_batch must be greater than 0".into(),
+        ));
+    }
+    match (cfg.n_ubatch, cfg.n_batch) {
+        (Some(0), _) => Err(LLMError::InvalidRequest(
+            "n_ubatch must be greater than 0".into(),
+        )),
+        (Some(n_ubatch), Some(n_batch)) if n_ubatch > n_batch => Err(LLMError::InvalidRequest(
+            format!("n_ubatch ({n_ubatch}) must not exceed n_batch ({n_batch})"),
+        )),
+        _ => Ok(()),
+    }
+}
+
 /// Apply flash attention and KV cache quantization settings to context params.
 ///
 /// This is called from all context creation sites to ensure consistent behavior.
@@ -0,0 +1,128 @@
+use crate::backend::llama_backend;
+use crate::config::{EmbeddingPooling, LlamaCppConfig};
+use crate::context::{LoraAdapters, apply_context_params};
+use llama_cpp_2::context::params::{LlamaContextParams, LlamaPoolingType};
+use llama_cpp_2::llama_batch::LlamaBatch;
+use llama_cpp_2::model::{AddBos, LlamaModel};
+use querymt::error::LLMError;
+use std::num::NonZeroU32;
+use std::sync::Arc;
+
+/// Embed each of `inputs` with a dedicated embedding context, returning one
+/// pooled vector per input.
+///
+/// Every input is decoded as a single batch, since encoder models attend
+/// non-causally and need all tokens in one physical batch, so an input may
+/// not exceed the context size.
+pub(crate) fn embed(
+    model: &Arc<LlamaModel>,
+    cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
+    inputs: &[String],
+) -> Result<Vec<Vec<f32>>, LLMError> {
+    let backend = llama_backend()?;
+
+    let mut ctx_params = LlamaContextParams::default().with_embeddings(true);
+    if let Some(n_ctx) = cfg.n_ctx {
+        let n_ctx = NonZeroU32::new(n_ctx)
+            .ok_or_else(|| LLMError::InvalidRequest("n_ctx must be greater than zero".into()))?;
+        ctx_params = ctx_params.with_n_ctx(Some(n_ctx));
+    }
+    if let Some(pooling) = cfg.pooling {
+        ctx_params = ctx_params.with_pooling_type(match pooling {
+            EmbeddingPooling::Mean => LlamaPoolingType::Mean,
+            EmbeddingPooling::Cls => LlamaPoolingType::Cls,
+            EmbeddingPooling::Last => LlamaPoolingType::Last,
+        });
+    }
+    if let Some(n_threads) = cfg.n_threads {
+        ctx_params = ctx_params.with_n_threads(n_threads);
+    }
+    if let Some(n_threads_batch) = cfg.n_threads_batch {
+        ctx_params = ctx_params.with_n_threads_batch(n_threads_batch);
+    }
+    // Size both batches to the whole context so any input that fits the
+    // context is decoded in one go.
+    let n_ctx = ctx_params
+        .n_ctx()
+        .map(NonZeroU32::get)
+        .unwrap_or_else(|| model.n_ctx_train());
+    ctx_params = ctx_params.with_n_batch(n_ctx).with_n_ubatch(n_ctx);
+    ctx_params = apply_context_params(cfg, ctx_params)?;
+
+    let mut ctx = model
+        .new_context(&*backend, ctx_params)
+        .map_err(|e| LLMError::ProviderError(format!("Failed to create context: {}", e)))?;
+    drop(backend);
+    lora.apply(&ctx)?;
+
+    let n_ctx = ctx.n_ctx() as usize;
+    let add_bos = if cfg.add_bos.unwrap_or(true) {
+        AddBos::Always
+    } else {
+        AddBos::Never
+    };
+    let normalize = cfg.normalize.unwrap_or(false);
+
+    let mut embeddings = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let tokens = model
+            .str_to_token(input, add_bos)
+            .map_err(|e| LLMError::ProviderError(e.to_string()))?;
+        if tokens.is_empty() {
+            return Err(LLMError::InvalidRequest(
+                "Embedding input tokenization resulted in an empty sequence".into(),
+            ));
+        }
+        if tokens.len() > n_ctx {
+            return Err(LLMError::InvalidRequest(format!(
+                "Embedding input ({} tokens) exceeds context window ({})",
+                tokens.len(),
+                n_ctx
+            )));
+        }
+
+        ctx.clear_kv_cache();
+        let mut batch = LlamaBatch::new(tokens.len(), 1);
+        batch
+            .add_sequence(&tokens, 0, true)
+            .map_err(|e| LLMError::ProviderError(e.to_string()))?;
+        ctx.decode(&mut batch).map_err(|e| {
+            LLMError::ProviderError(format!("Failed to decode embedding input: {}", e))
+        })?;
+
+        let embedding = ctx
+            .embeddings_seq_ith(0)
+            .map_err(|e| LLMError::ProviderError(format!("Failed to read embedding: {}", e)))?;
+        let mut embedding = embedding.to_vec();
+        if normalize {
+            l2_normalize(&mut embedding);
+        }
+        embeddings.push(embedding);
+    }
+    Ok(embeddings)
+}
+
+/// Scale `v` to unit length; zero vectors are left unchanged.
+fn l2_normalize(v: &mut [f32]) {
+    let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        v.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::l2_normalize;
+
+    #[test]
+    fn l2_normalize_scales_to_unit_length() {
+        let mut v = vec![3.0, 4.0];
+        l2_normalize(&mut v);
+        assert_eq!(v, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        l2_normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+}
@@ -0,0 +1,83 @@
+//! Fill-in-the-middle (FIM) prompt assembly for code completion.
+
+use crate::config::{FimTokens, LlamaCppConfig};
+use llama_cpp_2::model::{AddBos, LlamaModel};
+
+/// FIM token sets used by common code models, tried in order.
+const KNOWN_FIM_TOKENS: &[(&str, &str, &str)] = &[
+    // Qwen2.5-Coder, StarCoder, CodeGemma-style
+    ("<|fim_prefix|>", "<|fim_suffix|>", "<|fim_middle|>"),
+    // StarCoder1 / SantaCoder
+    ("<fim_prefix>", "<fim_suffix>", "<fim_middle>"),
+    // CodeLlama
+    ("<PRE>", "<SUF>", "<MID>"),
+    // DeepSeek-Coder
+    ("<｜fim▁begin｜>", "<｜fim▁hole｜>", "<｜fim▁end｜>"),
+];
+
+/// Resolve the FIM tokens for `model`: the configured set if any, otherwise
+/// the first known set whose markers are all single tokens in the vocabulary.
+pub(crate) fn resolve_fim_tokens(model: &LlamaModel, cfg: &LlamaCppConfig) -> Option<FimTokens> {
+    if let Some(tokens) = &cfg.fim_tokens {
+        return Some(tokens.clone());
+    }
+    detect_fim_tokens(|text| {
+        model
+            .str_to_token(text, AddBos::Never)
+            .is_ok_and(|tokens| tokens.len() == 1)
+    })
+}
+
+fn detect_fim_tokens(is_single_token: impl Fn(&str) -> bool) -> Option<FimTokens> {
+    KNOWN_FIM_TOKENS
+        .iter()
+        .find(|(prefix, suffix, middle)| {
+            is_single_token(prefix) && is_single_token(suffix) && is_single_token(middle)
+        })
+        .map(|(prefix, suffix, middle)| FimTokens {
+            prefix: prefix.to_string(),
+            suffix: suffix.to_string(),
+            middle: middle.to_string(),
+        })
+}
+
+/// Assemble a prefix-suffix-middle prompt; the model generates the infill.
+pub(crate) fn build_fim_prompt(tokens: &FimTokens, prefix: &str, suffix: &str) -> String {
+    format!(
+        "{}{}{}{}{}",
+        tokens.prefix, prefix, tokens.suffix, suffix, tokens.middle
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_first_known_token_set_in_vocab() {
+        let vocab = ["<PRE>", "<SUF>", "<MID>"];
+        let tokens = detect_fim_tokens(|t| vocab.contains(&t)).unwrap();
+        assert_eq!(tokens.prefix, "<PRE>");
+        assert_eq!(tokens.suffix, "<SUF>");
+        assert_eq!(tokens.middle, "<MID>");
+    }
+
+    #[test]
+    fn partial_token_set_is_not_detected() {
+        let vocab = ["<|fim_prefix|>", "<|fim_suffix|>"];
+        assert_eq!(detect_fim_tokens(|t| vocab.contains(&t)), None);
+    }
+
+    #[test]
+    fn builds_prefix_suffix_middle_prompt() {
+        let tokens = FimTokens {
+            prefix: "<|fim_prefix|>".to_string(),
+            suffix: "<|fim_suffix|>".to_string(),
+            middle: "<|fim_middle|>".to_string(),
+        };
+        assert_eq!(
+            build_fim_prompt(&tokens, "def add(a, b):\n    ", "\n\nprint(add(1, 2))"),
+            "<|fim_prefix|>def add(a, b):\n    <|fim_suffix|>\n\nprint(add(1, 2))<|fim_middle|>"
+        );
+    }
+}
@@ -3,24 +3,27 @@ use crate::chat_format::ParsedDelta;
 use crate::common_chat::ChatTemplateResult;
 use crate::config::LlamaCppConfig;
 use crate::context::{
-    DEFAULT_N_BATCH_CAP, apply_context_params, estimate_context_memory, resolve_n_batch,
-    resolve_n_ubatch,
+    DEFAULT_N_BATCH_CAP, LoraAdapters, apply_context_params, estimate_context_memory,
+    resolve_n_batch, resolve_n_ubatch,
 };
 use crate::messages;
 use crate::multimodal::MultimodalContext;
+use crate::prefill::{PrefillCache, PrefillSnapshot};
 use crate::response::GeneratedText;
-use crate::tools::sampler::{SamplingParams, build_fallback_sampler, build_standard_sampler};
+use crate::tools::sampler::{SamplingParams, build_fallback_sampler, build_generation_sampler};
 use futures::channel::mpsc;
+use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::context::params::LlamaContextParams;
 use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel};
 use llama_cpp_2::mtmd::{MtmdBitmap, MtmdInputChunkType, MtmdInputText};
 use querymt::Usage;
 use querymt::chat::ChatMessage;
-use querymt::error::LLMError;
+use querymt::error::{LLMError, TransportErrorKind, transport_error};
 use std::collections::HashSet;
 use std::num::NonZeroU32;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Build a prompt from chat messages using optional chat template.
 pub(crate) fn build_prompt_with(
@@ -55,7 +58,7 @@ pub(crate) fn build_prompt_with(
         );
     }
 
-    if let Ok(template) = model.chat_template(cfg.chat_template.as_deref()) {
+    if let Ok((template, _)) = crate::template::resolve_chat_template(model, cfg) {
         if let Ok(prompt) = model.apply_chat_template(&template, &chat_messages, true) {
             return Ok((prompt, true));
         }
@@ -115,6 +118,30 @@ pub(crate) fn decode_token_piece(
     }
 }
 
+/// Deadline derived from [`LlamaCppConfig::timeout_seconds`], checked once
+/// per generated token.
+pub(crate) struct GenerationDeadline(Option<Instant>);
+
+impl GenerationDeadline {
+    pub(crate) fn start(cfg: &LlamaCppConfig) -> Self {
+        Self(
+            cfg.timeout_seconds
+                .filter(|secs| *secs > 0)
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+        )
+    }
+
+    pub(crate) fn check(&self) -> Result<(), LLMError> {
+        match self.0 {
+            Some(deadline) if Instant::now() >= deadline => Err(transport_error(
+                TransportErrorKind::Timeout,
+                "generation exceeded timeout_seconds",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
 fn preserved_token_set(
     model: &Arc<LlamaModel>,
     result: Option<&ChatTemplateResult>,
@@ -132,26 +159,20 @@ fn preserved_token_set(
     preserved
 }
 
-pub(crate) fn generate(
-    model: &Arc<LlamaModel>,
+/// Create a context for generating from `model`, sized by `cfg`, with `lora`
+/// attached. `multimodal` sizes the micro-batch for image chunks.
+///
+/// Generation and [`prefill`] share this so that a prefill snapshot is
+/// restored into a context configured exactly like the one it came from.
+/// Returns the context and the `n_batch` it was configured with.
+fn new_generation_context<'a>(
+    model: &'a Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
-    prompt: &str,
-    max_tokens: u32,
-    temperature: Option<f32>,
-    mm_ctx: Option<&MultimodalContext>,
-    bitmaps: &[MtmdBitmap],
-) -> Result<GeneratedText, LLMError> {
+    lora: &LoraAdapters,
+    multimodal: bool,
+) -> Result<(LlamaContext<'a>, u32), LLMError> {
     let backend = llama_backend()?;
 
-    // Validate: if bitmaps provided, must have mm_ctx
-    if !bitmaps.is_empty() && mm_ctx.is_none() {
-        return Err(LLMError::InvalidRequest(
-            "Images provided but model does not support multimodal input. \
-             Configure mmproj_path or use a vision-capable model."
-                .into(),
-        ));
-    }
-
     let mut ctx_params = LlamaContextParams::default();
     let effective_n_ctx;
     let effective_n_batch;
@@ -159,7 +180,7 @@ pub(crate) fn generate(
         let n_ctx = NonZeroU32::new(n_ctx)
             .ok_or_else(|| LLMError::InvalidRequest("n_ctx must be greater than zero".into()))?;
         let n_batch = resolve_n_batch(cfg, n_ctx.get());
-        let n_ubatch = resolve_n_ubatch(cfg, n_batch, mm_ctx.is_some());
+        let n_ubatch = resolve_n_ubatch(cfg, n_batch, multimodal);
         ctx_params = ctx_params.with_n_ctx(Some(n_ctx));
         ctx_params = ctx_params.with_n_batch(n_batch);
         ctx_params = ctx_params.with_n_ubatch(n_ubatch);
@@ -177,7 +198,7 @@ pub(crate) fn generate(
     }
     ctx_params = apply_context_params(cfg, ctx_params)?;
 
-    let mut ctx = model.new_context(&*backend, ctx_params).map_err(|e| {
+    let ctx = model.new_context(&*backend, ctx_params).map_err(|e| {
         let n = if effective_n_ctx > 0 {
             effective_n_ctx
         } else {
@@ -191,11 +212,72 @@ pub(crate) fn generate(
             est.summary()
         ))
     })?;
+    drop(backend);
+    lora.apply(&ctx)?;
+    Ok((ctx, effective_n_batch))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate(
+    model: &Arc<LlamaModel>,
+    cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
+    prompt: &str,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    mm_ctx: Option<&MultimodalContext>,
+    bitmaps: &[MtmdBitmap],
+) -> Result<GeneratedText, LLMError> {
+    generate_with_prefill(
+        model,
+        cfg,
+        lora,
+        prompt,
+        max_tokens,
+        temperature,
+        mm_ctx,
+        bitmaps,
+        None,
+        &[],
+    )
+}
+
+/// Like [`generate`], but text-only prompts restore the longest prefix they
+/// share with the snapshot in `prefill` instead of decoding it again, and
+/// generation ends at the first of the `stop` sequences, which is cut from
+/// the output.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn generate_with_prefill(
+    model: &Arc<LlamaModel>,
+    cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
+    prompt: &str,
+    max_tokens: u32,
+    temperature: Option<f32>,
+    mm_ctx: Option<&MultimodalContext>,
+    bitmaps: &[MtmdBitmap],
+    prefill: Option<&PrefillCache>,
+    stop: &[String],
+) -> Result<GeneratedText, LLMError> {
+    // Validate: if bitmaps provided, must have mm_ctx
+    if !bitmaps.is_empty() && mm_ctx.is_none() {
+        return Err(LLMError::InvalidRequest(
+            "Images provided but model does not support multimodal input. \
+             Configure mmproj_path or use a vision-capable model."
+                .into(),
+        ));
+    }
+
+    let (mut ctx, effective_n_batch) = new_generation_context(model, cfg, lora, mm_ctx.is_some())?;
 
     let n_ctx_total = ctx.n_ctx() as i32;
     let n_batch = resolve_n_batch(cfg, n_ctx_total as u32);
 
     // UNIFIED TOKENIZATION AND EVALUATION
+    let mut reused_tokens = 0;
+    // Tokens in the KV cache, tracked for text-only prompts so the state can
+    // be kept for the next turn.
+    let mut decoded_tokens = Vec::new();
     let (n_past, input_tokens) = if let Some(mm_ctx) = mm_ctx.filter(|_| !bitmaps.is_empty()) {
         // Multimodal path: use MTMD tokenization
         let input_text = MtmdInputText {
@@ -311,11 +393,16 @@ pub(crate) fn generate(
             )));
         }
 
+        // Restore the cached prefix, if any, and decode only the rest.
+        if let Some(cache) = prefill {
+            reused_tokens = PrefillSnapshot::restore_from(cache, &mut ctx, &tokens)?;
+        }
+
         // Decode prompt in chunks (standard batched decode)
         let mut batch = LlamaBatch::new(n_batch as usize, 1);
         let last_index = tokens.len().saturating_sub(1);
 
-        for chunk_start in (0..tokens.len()).step_by(n_batch as usize) {
+        for chunk_start in (reused_tokens..tokens.len()).step_by(n_batch as usize) {
             batch.clear();
             let chunk_end = (chunk_start + n_batch as usize).min(tokens.len());
             for i in chunk_start..chunk_end {
@@ -335,14 +422,17 @@ pub(crate) fn generate(
             })?;
         }
 
-        (tokens.len() as i32, input_tokens)
+        let n_past = tokens.len() as i32;
+        decoded_tokens = tokens;
+        (n_past, input_tokens)
     };
 
     // UNIFIED GENERATION PHASE (identical for both paths)
 
     let params = SamplingParams::from_config(cfg, temperature);
-    let mut sampler = build_standard_sampler(&params);
-    let allow_fallback = !params.is_explicit();
+    let mut sampler = build_generation_sampler(model, cfg, &params)?;
+    // The fallback sampler would drop a configured grammar, so never use it then.
+    let allow_fallback = !params.is_explicit() && cfg.grammar.is_none();
     let mut fallback_used = false;
 
     let mut n_cur = n_past;
@@ -352,7 +442,9 @@ pub(crate) fn generate(
     let mut output = String::new();
     let mut decoder = encoding_rs::UTF_8.new_decoder();
     let preserved = preserved_token_set(model, None);
+    let deadline = GenerationDeadline::start(cfg);
     while n_cur < n_len_total {
+        deadline.check()?;
         let token = sampler.sample(&ctx, batch.n_tokens() - 1);
         if model.is_eog_token(token) {
             if output_tokens == 0 && allow_fallback && !fallback_used {
@@ -364,9 +456,17 @@ pub(crate) fn generate(
         }
 
         let chunk = decode_token_piece(model, &mut decoder, &preserved, token)?;
+        let searched = output.len();
         output.push_str(&chunk);
+        if let Some(end) = find_stop(&output, searched, stop) {
+            output.truncate(end);
+            break;
+        }
 
         batch.clear();
+        if !decoded_tokens.is_empty() {
+            decoded_tokens.push(token);
+        }
         batch
             .add(token, n_cur, &[0], true)
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
@@ -377,18 +477,178 @@ pub(crate) fn generate(
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
     }
 
+    if cfg.kv_cache_reuse.unwrap_or(false)
+        && let Some(cache) = prefill
+        && !decoded_tokens.is_empty()
+    {
+        PrefillSnapshot::store(cache, &ctx, decoded_tokens);
+    }
+
     Ok(GeneratedText {
         text: output,
         usage: Usage {
             input_tokens: input_tokens as u32,
             output_tokens,
-            cache_read: 0,
+            cache_read: reused_tokens as u32,
             cache_write: 0,
             reasoning_tokens: 0,
         },
     })
 }
 
+/// Byte offset of the earliest `stop` sequence in `output` that ends after
+/// `searched`, the length of `output` already checked.
+fn find_stop(output: &str, searched: usize, stop: &[String]) -> Option<usize> {
+    stop.iter()
+        .filter(|stop| !stop.is_empty())
+        .filter_map(|stop| {
+            // A match may start in text checked earlier but end in new text.
+            let mut from = searched.saturating_sub(stop.len() - 1);
+            while !output.is_char_boundary(from) {
+                from -= 1;
+            }
+            output[from..].find(stop.as_str()).map(|pos| from + pos)
+        })
+        .min()
+}
+
+/// Applies `stop` sequences to streamed content, holding back any tail that
+/// could still grow into one so a stop sequence is never partly emitted.
+struct StopFilter<'a> {
+    stop: &'a [String],
+    /// Content since the last thinking block.
+    text: String,
+    /// Length of `text` already emitted.
+    sent: usize,
+    stopped: bool,
+}
+
+impl<'a> StopFilter<'a> {
+    fn new(stop: &'a [String]) -> Self {
+        Self {
+            stop,
+            text: String::new(),
+            sent: 0,
+            stopped: false,
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Append the chunks `delta` releases to `out`, returning whether a stop
+    /// sequence was reached.
+    fn apply(&mut self, delta: ParsedDelta, out: &mut Vec<querymt::chat::StreamChunk>) -> bool {
+        match delta {
+            ParsedDelta::Thinking(thinking) => {
+                // A stop sequence can't span a thinking block.
+                self.flush(out);
+                out.push(querymt::chat::StreamChunk::Thinking(thinking));
+            }
+            ParsedDelta::Content(content) => {
+                let searched = self.text.len();
+                self.text.push_str(&content);
+                let end = match find_stop(&self.text, searched, self.stop) {
+                    Some(end) => {
+                        self.stopped = true;
+                        end
+                    }
+                    None => self.text.len() - self.partial_stop_len(),
+                };
+                if end > self.sent {
+                    out.push(querymt::chat::StreamChunk::Text(
+                        self.text[self.sent..end].to_string(),
+                    ));
+                    self.sent = end;
+                }
+            }
+        }
+        self.stopped
+    }
+
+    /// Release the held-back content; no more is coming before thinking or
+    /// the end of the stream.
+    fn flush(&mut self, out: &mut Vec<querymt::chat::StreamChunk>) {
+        if self.sent < self.text.len() {
+            out.push(querymt::chat::StreamChunk::Text(
+                self.text[self.sent..].to_string(),
+            ));
+        }
+        self.text.clear();
+        self.sent = 0;
+    }
+
+    /// Length of the longest tail of `text` that starts a stop sequence.
+    fn partial_stop_len(&self) -> usize {
+        self.stop
+            .iter()
+            .filter_map(|stop| {
+                (1..stop.len())
+                    .rev()
+                    .find(|&len| stop.is_char_boundary(len) && self.text.ends_with(&stop[..len]))
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// Decode `prompt` without sampling and snapshot the resulting context state,
+/// so [`generate_with_prefill`] can skip decoding it again.
+pub(crate) fn prefill(
+    model: &Arc<LlamaModel>,
+    cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
+    prompt: &str,
+) -> Result<PrefillSnapshot, LLMError> {
+    // Text-only, like the generations that restore the snapshot.
+    let (mut ctx, _) = new_generation_context(model, cfg, lora, false)?;
+
+    let n_ctx_total = ctx.n_ctx() as usize;
+    let n_batch = resolve_n_batch(cfg, n_ctx_total as u32) as usize;
+
+    let add_bos = cfg.add_bos.unwrap_or(true);
+    let tokens = model
+        .str_to_token(
+            prompt,
+            if add_bos {
+                AddBos::Always
+            } else {
+                AddBos::Never
+            },
+        )
+        .map_err(|e| LLMError::ProviderError(e.to_string()))?;
+    if tokens.is_empty() {
+        return Err(LLMError::InvalidRequest(
+            "Prompt tokenization resulted in an empty sequence".into(),
+        ));
+    }
+    if tokens.len() > n_ctx_total {
+        return Err(LLMError::InvalidRequest(format!(
+            "Prompt ({}) exceeds context window ({})",
+            tokens.len(),
+            n_ctx_total
+        )));
+    }
+
+    let mut batch = LlamaBatch::new(n_batch, 1);
+    let last_index = tokens.len() - 1;
+    for chunk_start in (0..tokens.len()).step_by(n_batch) {
+        batch.clear();
+        let chunk_end = (chunk_start + n_batch).min(tokens.len());
+        for i in chunk_start..chunk_end {
+            batch
+                .add(tokens[i], i as i32, &[0], i == last_index)
+                .map_err(|e| LLMError::ProviderError(e.to_string()))?;
+        }
+        ctx.decode(&mut batch).map_err(|e| {
+            LLMError::ProviderError(format!("Failed to decode prompt batch: {}", e))
+        })?;
+    }
+
+    Ok(PrefillSnapshot::capture(&ctx, tokens))
+}
+
 /// Generate text with streaming, routing thinking tokens to `StreamChunk::Thinking`.
 ///
 /// Uses a Rust-side incremental parser so `<think>` protocol text is emitted as
@@ -399,18 +659,26 @@ pub(crate) fn generate(
 /// evaluation so that image data is encoded into the KV-cache before generation begins.
 /// The prompt in `result` must already contain the media marker tokens at the correct
 /// positions (injected by `messages_to_json` → `apply_template_for_thinking`).
+///
+/// Text-only prompts restore their longest cached prefix from `prefill`, as in
+/// [`generate_with_prefill`].
+///
+/// Generation ends at the first `stop` sequence in the content, which is not
+/// emitted. Text that could be the start of one is held back until it can't.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_streaming_with_thinking(
     model: &Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
     result: &ChatTemplateResult,
     max_tokens: u32,
     temperature: Option<f32>,
     tx: &mpsc::UnboundedSender<Result<querymt::chat::StreamChunk, LLMError>>,
     mm_ctx: Option<&MultimodalContext>,
     bitmaps: &[MtmdBitmap],
+    prefill: Option<&PrefillCache>,
+    stop: &[String],
 ) -> Result<Usage, LLMError> {
-    let backend = llama_backend()?;
-
     // Validate: bitmaps require a multimodal context.
     if !bitmaps.is_empty() && mm_ctx.is_none() {
         return Err(LLMError::InvalidRequest(
@@ -420,51 +688,17 @@ pub(crate) fn generate_streaming_with_thinking(
         ));
     }
 
-    let mut ctx_params = LlamaContextParams::default();
-    let effective_n_ctx;
-    let effective_n_batch;
-    if let Some(n_ctx) = cfg.n_ctx {
-        let n_ctx = NonZeroU32::new(n_ctx)
-            .ok_or_else(|| LLMError::InvalidRequest("n_ctx must be greater than zero".into()))?;
-        let n_batch = resolve_n_batch(cfg, n_ctx.get());
-        let n_ubatch = resolve_n_ubatch(cfg, n_batch, mm_ctx.is_some());
-        ctx_params = ctx_params.with_n_ctx(Some(n_ctx));
-        ctx_params = ctx_params.with_n_batch(n_batch);
-        ctx_params = ctx_params.with_n_ubatch(n_ubatch);
-        effective_n_ctx = n_ctx.get();
-        effective_n_batch = n_batch;
-    } else {
-        effective_n_ctx = 0; // will use llama.cpp default
-        effective_n_batch = DEFAULT_N_BATCH_CAP;
-    }
-    if let Some(n_threads) = cfg.n_threads {
-        ctx_params = ctx_params.with_n_threads(n_threads);
-    }
-    if let Some(n_threads_batch) = cfg.n_threads_batch {
-        ctx_params = ctx_params.with_n_threads_batch(n_threads_batch);
-    }
-    ctx_params = apply_context_params(cfg, ctx_params)?;
-
-    let mut ctx = model.new_context(&*backend, ctx_params).map_err(|e| {
-        let n = if effective_n_ctx > 0 {
-            effective_n_ctx
-        } else {
-            512
-        };
-        let est = estimate_context_memory(model, cfg, n);
-        LLMError::ProviderError(format!(
-            "Failed to create context: {}. {}\n\
-                     Try reducing n_ctx or using KV cache quantization.",
-            e,
-            est.summary()
-        ))
-    })?;
+    let (mut ctx, effective_n_batch) = new_generation_context(model, cfg, lora, mm_ctx.is_some())?;
 
     let n_ctx_total = ctx.n_ctx() as i32;
     let n_batch = resolve_n_batch(cfg, n_ctx_total as u32) as usize;
     let mut batch = LlamaBatch::new(n_batch, 1);
 
     // TOKENIZATION AND EVALUATION — dual path: multimodal vs text-only
+    let mut reused_tokens = 0;
+    // Tokens in the KV cache, tracked for text-only prompts so the state can
+    // be kept for the next turn.
+    let mut decoded_tokens = Vec::new();
     let (n_past, input_tokens) = if let Some(mm_ctx) = mm_ctx.filter(|_| !bitmaps.is_empty()) {
         // Multimodal path: use MTMD tokenization so image embeddings are encoded.
         let input_text = MtmdInputText {
@@ -567,9 +801,18 @@ pub(crate) fn generate_streaming_with_thinking(
             )));
         }
 
+        // Restore the cached prefix, if any, and decode only the rest.
+        if let Some(cache) = prefill {
+            reused_tokens = PrefillSnapshot::restore_from(cache, &mut ctx, &tokens)?;
+        }
+
         // Decode prompt in chunks of n_batch.
         let last_index = tokens.len().saturating_sub(1);
-        for chunk_start in (0..tokens.len()).step_by(n_batch) {
+        for chunk_start in (reused_tokens..tokens.len()).step_by(n_batch) {
+            // Long prompts take a while to decode; stop if the stream was dropped.
+            if tx.is_closed() {
+                return Err(LLMError::Cancelled);
+            }
             batch.clear();
             let chunk_end = (chunk_start + n_batch).min(tokens.len());
             for i in chunk_start..chunk_end {
@@ -589,14 +832,19 @@ pub(crate) fn generate_streaming_with_thinking(
             })?;
         }
 
-        (tokens.len() as i32, tokens.len())
+        let n_past = tokens.len() as i32;
+        let input_tokens = tokens.len();
+        decoded_tokens = tokens;
+        (n_past, input_tokens)
     };
 
     let mut stream_state = result.streaming_state();
+    let mut stop_filter = StopFilter::new(stop);
 
     let params = SamplingParams::from_config(cfg, temperature);
-    let mut sampler = build_standard_sampler(&params);
-    let allow_fallback = !params.is_explicit();
+    let mut sampler = build_generation_sampler(model, cfg, &params)?;
+    // The fallback sampler would drop a configured grammar, so never use it then.
+    let allow_fallback = !params.is_explicit() && cfg.grammar.is_none();
     let mut fallback_used = false;
 
     let mut n_cur = n_past;
@@ -605,7 +853,13 @@ pub(crate) fn generate_streaming_with_thinking(
     let mut decoder = encoding_rs::UTF_8.new_decoder();
     let preserved = preserved_token_set(model, Some(result));
 
+    let deadline = GenerationDeadline::start(cfg);
     while n_cur < n_len_total {
+        deadline.check()?;
+        // The stream was dropped, e.g. because the caller timed out.
+        if tx.is_closed() {
+            return Err(LLMError::Cancelled);
+        }
         let token = sampler.sample(&ctx, batch.n_tokens() - 1);
         if model.is_eog_token(token) {
             if output_tokens == 0 && allow_fallback && !fallback_used {
@@ -618,11 +872,15 @@ pub(crate) fn generate_streaming_with_thinking(
 
         let chunk = decode_token_piece(model, &mut decoder, &preserved, token)?;
 
+        let mut stream_chunks = Vec::new();
+        let mut stopped = false;
         for delta in stream_state.update(&chunk, true) {
-            let stream_chunk = match delta {
-                ParsedDelta::Content(content) => querymt::chat::StreamChunk::Text(content),
-                ParsedDelta::Thinking(thinking) => querymt::chat::StreamChunk::Thinking(thinking),
-            };
+            stopped = stop_filter.apply(delta, &mut stream_chunks);
+            if stopped {
+                break;
+            }
+        }
+        for stream_chunk in stream_chunks {
             if tx.unbounded_send(Ok(stream_chunk)).is_err() {
                 return Ok(Usage {
                     input_tokens: input_tokens as u32,
@@ -633,8 +891,14 @@ pub(crate) fn generate_streaming_with_thinking(
                 });
             }
         }
+        if stopped {
+            break;
+        }
 
         batch.clear();
+        if !decoded_tokens.is_empty() {
+            decoded_tokens.push(token);
+        }
         batch
             .add(token, n_cur, &[0], true)
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
@@ -645,11 +909,23 @@ pub(crate) fn generate_streaming_with_thinking(
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
     }
 
-    for delta in stream_state.finish() {
-        let stream_chunk = match delta {
-            ParsedDelta::Content(content) => querymt::chat::StreamChunk::Text(content),
-            ParsedDelta::Thinking(thinking) => querymt::chat::StreamChunk::Thinking(thinking),
-        };
+    if cfg.kv_cache_reuse.unwrap_or(false)
+        && let Some(cache) = prefill
+        && !decoded_tokens.is_empty()
+    {
+        PrefillSnapshot::store(cache, &ctx, decoded_tokens);
+    }
+
+    let mut stream_chunks = Vec::new();
+    if !stop_filter.stopped() {
+        for delta in stream_state.finish() {
+            if stop_filter.apply(delta, &mut stream_chunks) {
+                break;
+            }
+        }
+        stop_filter.flush(&mut stream_chunks);
+    }
+    for stream_chunk in stream_chunks {
         if tx.unbounded_send(Ok(stream_chunk)).is_err() {
             break;
         }
@@ -658,8 +934,72 @@ pub(crate) fn generate_streaming_with_thinking(
     Ok(Usage {
         input_tokens: input_tokens as u32,
         output_tokens,
-        cache_read: 0,
+        cache_read: reused_tokens as u32,
         cache_write: 0,
         reasoning_tokens: 0,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{StopFilter, find_stop};
+    use crate::chat_format::ParsedDelta;
+    use querymt::chat::StreamChunk;
+
+    #[test]
+    fn find_stop_matches_sequences_spanning_chunks() {
+        let stop = vec!["\n\n".to_string(), "###".to_string()];
+        assert_eq!(find_stop("fn main() {}", 0, &stop), None);
+        // "\n" was already checked; the second "\n" completes the stop.
+        assert_eq!(find_stop("fn main() {}\n\n", 13, &stop), Some(12));
+        assert_eq!(find_stop("a ### b\n\n", 2, &stop), Some(2));
+        // Backing up for a partial match must not split `é`.
+        assert_eq!(find_stop("aé###", 3, &stop), Some(3));
+        assert_eq!(find_stop("abc", 0, &[String::new()]), None);
+    }
+
+    fn texts(chunks: &[StreamChunk]) -> Vec<&str> {
+        chunks
+            .iter()
+            .map(|chunk| match chunk {
+                StreamChunk::Text(text) => text.as_str(),
+                StreamChunk::Thinking(_) => "<thinking>",
+                _ => "<other>",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stop_filter_holds_back_partial_stop_sequences() {
+        let stop = vec!["###".to_string()];
+        let mut filter = StopFilter::new(&stop);
+        let mut out = Vec::new();
+        assert!(!filter.apply(ParsedDelta::Content("a #".into()), &mut out));
+        assert!(!filter.apply(ParsedDelta::Content("# b".into()), &mut out));
+        // "## b" can't grow into "###", the trailing "##" still might.
+        assert!(!filter.apply(ParsedDelta::Content(" ##".into()), &mut out));
+        assert_eq!(texts(&out), vec!["a ", "## b", " "]);
+        assert!(filter.apply(ParsedDelta::Content("# tail".into()), &mut out));
+        assert!(filter.stopped());
+        assert_eq!(texts(&out), vec!["a ", "## b", " "]);
+    }
+
+    #[test]
+    fn stop_filter_flushes_held_text_at_the_end_and_before_thinking() {
+        let stop = vec!["\n\n".to_string()];
+        let mut filter = StopFilter::new(&stop);
+        let mut out = Vec::new();
+        filter.apply(ParsedDelta::Content("a\n".into()), &mut out);
+        filter.apply(ParsedDelta::Thinking("hmm".into()), &mut out);
+        // The earlier "\n" doesn't combine with text after the thinking.
+        filter.apply(ParsedDelta::Content("\nb\n".into()), &mut out);
+        filter.flush(&mut out);
+        assert_eq!(texts(&out), vec!["a", "\n", "<thinking>", "\nb", "\n"]);
+        assert!(!filter.stopped());
+
+        let mut unfiltered = StopFilter::new(&[]);
+        let mut out = Vec::new();
+        unfiltered.apply(ParsedDelta::Content("a\n".into()), &mut out);
+        assert_eq!(texts(&out), vec!["a\n"]);
+    }
+}
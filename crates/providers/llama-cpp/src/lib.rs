@@ -3,17 +3,22 @@ mod chat_format;
 mod common_chat;
 mod config;
 mod context;
+mod embedding;
+mod fim;
 mod generation;
 mod memory;
 mod messages;
 mod multimodal;
+mod prefill;
 mod provider;
+mod queue;
 mod response;
 mod template;
 mod tools;
 
-pub use config::LlamaCppConfig;
-use provider::LlamaCppProvider;
+pub use config::{EmbeddingPooling, FimTokens, LlamaCppConfig, LoraAdapter};
+pub use provider::LlamaCppProvider;
+pub use template::{ChatTemplateSource, ResolvedChatTemplate};
 
 /// Create a provider directly from a config struct (useful for testing and embedding).
 pub fn create_provider(
@@ -25,7 +30,8 @@ pub fn create_provider(
 use provider::CachedModel;
 use querymt::LLMProvider;
 use querymt::error::LLMError;
-use querymt::plugin::{Fut, LLMProviderFactory};
+use querymt::plugin::{Fut, LLMProviderFactory, ModelMetadata, ProviderCapabilities};
+use querymt_provider_common::{ModelRef, parse_model_ref, read_gguf_header};
 use schemars::schema_for;
 
 /// Create a factory that can be statically registered in a `PluginRegistry`.
@@ -33,12 +39,11 @@ use schemars::schema_for;
 /// This is the preferred entry-point for embedding targets that link provider
 /// crates directly instead of loading them as runtime plugins.
 pub fn create_factory() -> std::sync::Arc<dyn LLMProviderFactory> {
-    std::sync::Arc::new(LlamaCppFactory {
-        model_cache: std::sync::Mutex::new(None),
-    })
+    std::sync::Arc::new(LlamaCppFactory::new())
 }
 
-struct LlamaCppFactory {
+/// Factory for [`LlamaCppProvider`]s that share a loaded model.
+pub struct LlamaCppFactory {
     /// Single-slot model cache. Stores the most recently loaded model
     /// (`Arc<LlamaModel>` + `Arc<MultimodalContext>`) keyed on hardware
     /// params (model path, n_gpu_layers).
@@ -49,11 +54,58 @@ struct LlamaCppFactory {
     model_cache: std::sync::Mutex<Option<CachedModel>>,
 }
 
+impl Default for LlamaCppFactory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LlamaCppFactory {
+    /// A factory with an empty model cache.
+    pub fn new() -> Self {
+        Self {
+            model_cache: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Whether `model` (a config `model` reference or a resolved GGUF path)
+    /// is loaded in the cache.
+    pub fn is_cached(&self, model: &str) -> bool {
+        let guard = self.model_cache.lock().unwrap_or_else(|e| e.into_inner());
+        guard.as_ref().is_some_and(|cached| cached.matches(model))
+    }
+
+    /// Report which chat template requests for `cfg` use, loading the model
+    /// through the shared cache (or reusing it when already loaded).
+    ///
+    /// Useful for debugging "wrong chat format" issues, e.g. with freshly
+    /// quantized models whose embedded template is missing or wrong.
+    pub fn resolved_chat_template(
+        &self,
+        cfg: LlamaCppConfig,
+    ) -> Result<ResolvedChatTemplate, LLMError> {
+        LlamaCppProvider::new_with_cache(cfg, &self.model_cache)?.resolved_chat_template()
+    }
+}
+
 impl LLMProviderFactory for LlamaCppFactory {
     fn name(&self) -> &str {
         "llama_cpp"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: true,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn config_schema(&self) -> String {
         let schema = schema_for!(LlamaCppConfig);
         serde_json::to_string(&schema).expect("LlamaCppConfig schema should always serialize")
@@ -78,9 +130,69 @@ impl LLMProviderFactory for LlamaCppFactory {
         })
     }
 
+    /// Loads the model (through the shared cache, so a later `from_config`
+    /// reuses it) to confirm the model file exists and is loadable.
+    fn health_check<'a>(&'a self, cfg: &str) -> Fut<'a, Result<(), LLMError>> {
+        let cfg = cfg.to_string();
+        Box::pin(async move {
+            let cfg: LlamaCppConfig = serde_json::from_str(&cfg)?;
+            LlamaCppProvider::new_with_cache(cfg, &self.model_cache).map(|_| ())
+        })
+    }
+
     fn supports_custom_models(&self) -> bool {
         true
     }
+
+    /// Reported by the model itself: the cached model if `model` is loaded,
+    /// otherwise the header of a local GGUF file. Hugging Face references
+    /// that aren't loaded yield `None` rather than triggering a download.
+    fn model_metadata(&self, model: &str) -> Option<ModelMetadata> {
+        let supports_tools = self.capabilities().tools;
+        {
+            let guard = self.model_cache.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(cached) = guard.as_ref().filter(|cached| cached.matches(model)) {
+                return Some(ModelMetadata {
+                    max_context: Some(cached.model.n_ctx_train().into()),
+                    max_output: None,
+                    supports_vision: cached.multimodal.is_some(),
+                    supports_tools,
+                });
+            }
+        }
+
+        let Ok(ModelRef::LocalPath(path)) = parse_model_ref(model) else {
+            return None;
+        };
+        match read_gguf_header(&path) {
+            Ok(header) => Some(ModelMetadata {
+                max_context: header.context_length,
+                max_output: None,
+                // The projector is configured separately from the model file.
+                supports_vision: false,
+                supports_tools,
+            }),
+            Err(e) => {
+                log::debug!("Failed to read GGUF header of {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Drops `model` (a config `model` reference or a resolved GGUF path)
+    /// from the cache so the next provider reloads it.
+    ///
+    /// The weights, and the VRAM they occupy, are freed once every provider
+    /// created from them has been dropped as well.
+    fn evict(&self, model: &str) -> bool {
+        let mut guard = self.model_cache.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(cached) = guard.take_if(|cached| cached.matches(model)) else {
+            return false;
+        };
+        drop(guard);
+        cached.release();
+        true
+    }
 }
 
 #[cfg(feature = "native")]
@@ -91,9 +203,13 @@ impl LLMProviderFactory for LlamaCppFactory {
 // the same vtable layout. This pattern is used throughout the plugin system.
 #[allow(improper_ctypes_definitions)]
 pub extern "C" fn plugin_factory() -> *mut dyn LLMProviderFactory {
-    Box::into_raw(Box::new(LlamaCppFactory {
-        model_cache: std::sync::Mutex::new(None),
-    })) as *mut _
+    Box::into_raw(Box::new(LlamaCppFactory::new())) as *mut _
+}
+
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
 }
 
 /// Initialize logging from the host process.
@@ -118,3 +234,84 @@ pub unsafe extern "C" fn plugin_init_logging(
         querymt::plugin::plugin_log::init_from_host(callback, max_level);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LlamaCppFactory;
+    use querymt::plugin::LLMProviderFactory;
+
+    #[test]
+    fn capabilities_report_embeddings() {
+        let factory = LlamaCppFactory::new();
+        let caps = factory.capabilities();
+        assert!(caps.embeddings);
+        assert!(caps.streaming);
+        assert!(caps.completion);
+    }
+
+    #[test]
+    fn model_metadata_reports_trained_context_length() {
+        fn string(s: &str) -> Vec<u8> {
+            let mut buf = (s.len() as u64).to_le_bytes().to_vec();
+            buf.extend(s.as_bytes());
+            buf
+        }
+
+        // GGUF v3 header: no tensors, two key/value pairs.
+        let mut gguf = b"GGUF".to_vec();
+        gguf.extend(3u32.to_le_bytes());
+        gguf.extend(0u64.to_le_bytes());
+        gguf.extend(2u64.to_le_bytes());
+        gguf.extend(string("general.architecture"));
+        gguf.extend(8u32.to_le_bytes());
+        gguf.extend(string("llama"));
+        gguf.extend(string("llama.context_length"));
+        gguf.extend(4u32.to_le_bytes());
+        gguf.extend(131_072u32.to_le_bytes());
+
+        let path = std::env::temp_dir().join(format!("qmt-llama-meta-{}.gguf", std::process::id()));
+        std::fs::write(&path, gguf).unwrap();
+        let metadata = LlamaCppFactory::new().model_metadata(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let metadata = metadata.expect("metadata should be read from the GGUF header");
+        assert_eq!(metadata.max_context, Some(131_072));
+        assert!(metadata.supports_tools);
+    }
+
+    #[test]
+    fn evict_without_cached_model_is_a_no_op() {
+        let factory = LlamaCppFactory::new();
+        assert!(!factory.is_cached("/models/model.gguf"));
+        assert!(!factory.evict("/models/model.gguf"));
+    }
+
+    /// Needs a real model; skipped unless `TEST_MODEL` is set.
+    #[test]
+    fn evict_drops_cached_model_and_next_load_recreates_it() {
+        let Ok(model) = std::env::var("TEST_MODEL") else {
+            return;
+        };
+        let factory = LlamaCppFactory::new();
+        let cfg =
+            serde_json::json!({ "model": model, "n_ctx": 512, "n_gpu_layers": 0 }).to_string();
+
+        let provider = factory.from_config(&cfg).unwrap();
+        let first = {
+            let guard = factory.model_cache.lock().unwrap();
+            std::sync::Arc::downgrade(&guard.as_ref().unwrap().model)
+        };
+        assert!(factory.is_cached(&model));
+        drop(provider);
+
+        assert!(factory.evict(&model));
+        assert!(!factory.is_cached(&model));
+        assert!(factory.model_cache.lock().unwrap().is_none());
+        assert!(first.upgrade().is_none(), "evicted weights should be freed");
+
+        // The old weights are gone, so the cache must load the model anew.
+        let _provider = factory.from_config(&cfg).unwrap();
+        assert!(factory.is_cached(&model));
+        assert!(first.upgrade().is_none());
+    }
+}
@@ -5,9 +5,11 @@
 //! or simple text format (for raw prompt building).
 
 use crate::config::LlamaCppConfig;
-use querymt::chat::{ChatMessage, ChatRole, Content};
+use querymt::chat::{ChatMessage, Content, system_prompts};
 use querymt::error::LLMError;
-use serde_json::Value;
+use querymt_provider_common::openai_messages::{
+    ContentPartStyle, OpenAIMapOpts, to_openai_messages,
+};
 
 /// Convert ChatMessages to JSON array for template application.
 /// This is the unified path for both tool-aware and basic conversations.
@@ -28,160 +30,39 @@ pub(crate) fn messages_to_json(
     media_marker: Option<&str>,
 ) -> Result<(String, usize), LLMError> {
     let mut json_messages = Vec::new();
-    let mut media_count = 0;
-    let marker = media_marker.unwrap_or("");
 
-    // Add system message if configured
-    if !cfg.system.is_empty() {
-        let system = cfg.system.join("\n\n");
+    // Configured system prompt and system messages become one leading
+    // system message.
+    let system = system_text(cfg, messages);
+    if !system.is_empty() {
         json_messages.push(serde_json::json!({
             "role": "system",
             "content": system
         }));
     }
 
-    for msg in messages {
-        let role = match msg.role {
-            ChatRole::User => "user",
-            ChatRole::Assistant => "assistant",
-        };
-
-        let thinking = msg
+    let opts = OpenAIMapOpts {
+        reasoning_content: true,
+        content_parts: ContentPartStyle::Markers(media_marker.unwrap_or("").to_string()),
+        tool_result_names: true,
+    };
+    let mut media_count = 0;
+    for msg in messages.iter().filter(|m| !m.is_system()) {
+        json_messages.extend(to_openai_messages(std::slice::from_ref(msg), opts.clone()));
+        // Count only Content::Image (not ImageUrl, which gets no marker).
+        // This must exactly match what extract_media() collects.
+        media_count += msg
             .content
             .iter()
-            .filter_map(|b| match b {
-                Content::Thinking { text, .. } => Some(text.as_str()),
-                _ => None,
+            .map(|block| match block {
+                Content::Image { .. } => 1,
+                Content::ToolResult { content, .. } => content
+                    .iter()
+                    .filter(|c| matches!(c, Content::Image { .. }))
+                    .count(),
+                _ => 0,
             })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        let mut tool_calls_array: Vec<Value> = Vec::new();
-        // Count only Content::Image (not ImageUrl — unsupported for now).
-        // This must exactly match what extract_media() collects.
-        let mut image_count: usize = 0;
-
-        // Build text by interleaving markers at exact image positions.
-        // This ensures N images produce exactly N markers in the text.
-        let mut text_parts: Vec<String> = Vec::new();
-        let mut pending_markers: usize = 0;
-
-        for block in &msg.content {
-            match block {
-                Content::Text { text } => {
-                    // Flush any pending image markers before the next text segment.
-                    for _ in 0..pending_markers {
-                        text_parts.push(marker.to_string());
-                    }
-                    pending_markers = 0;
-                    text_parts.push(text.clone());
-                }
-                Content::Image { .. } => {
-                    image_count += 1;
-                    pending_markers += 1;
-                }
-                Content::ImageUrl { .. } => {
-                    // ImageUrl is not supported by extract_media — skip marker
-                    // to avoid count mismatch. Log a warning.
-                    log::warn!("ImageUrl in message skipped (not supported for multimodal)");
-                }
-                Content::ToolUse {
-                    id,
-                    name,
-                    arguments,
-                } => {
-                    tool_calls_array.push(serde_json::json!({
-                        "id": id,
-                        "type": "function",
-                        "function": {
-                            "name": name,
-                            "arguments": serde_json::to_string(arguments).unwrap_or_default()
-                        }
-                    }));
-                }
-                Content::ToolResult {
-                    id, name, content, ..
-                } => {
-                    let output_text = content
-                        .iter()
-                        .filter_map(|c| c.as_text())
-                        .collect::<Vec<_>>()
-                        .join("\n");
-                    json_messages.push(serde_json::json!({
-                        "role": "tool",
-                        "tool_call_id": id,
-                        "name": name.clone().unwrap_or_default(),
-                        "content": output_text
-                    }));
-
-                    // Count only Content::Image inside tool results (matching extract_media).
-                    let nested_images = content
-                        .iter()
-                        .filter(|c| matches!(c, Content::Image { .. }))
-                        .count();
-                    for _ in 0..nested_images {
-                        json_messages.push(serde_json::json!({
-                            "role": "user",
-                            "content": marker,
-                        }));
-                        media_count += 1;
-                    }
-
-                    // Warn about skipped ImageUrl in tool results.
-                    let skipped_urls = content
-                        .iter()
-                        .filter(|c| matches!(c, Content::ImageUrl { .. }))
-                        .count();
-                    if skipped_urls > 0 {
-                        log::warn!(
-                            "Skipped {} ImageUrl block(s) inside ToolResult (not supported for multimodal)",
-                            skipped_urls
-                        );
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        // Flush any trailing image markers (images at the end of a message).
-        for _ in 0..pending_markers {
-            text_parts.push(marker.to_string());
-        }
-
-        media_count += image_count;
-
-        if !tool_calls_array.is_empty() {
-            let text = text_parts.join("\n");
-            let content = if text.is_empty() {
-                Value::Null
-            } else {
-                Value::String(text)
-            };
-
-            let mut json_msg = serde_json::json!({
-                "role": "assistant",
-                "content": content,
-                "tool_calls": tool_calls_array
-            });
-            if !thinking.is_empty() {
-                json_msg["reasoning_content"] = serde_json::json!(thinking);
-            }
-            json_messages.push(json_msg);
-            continue;
-        }
-
-        let text = text_parts.join("\n");
-
-        if !text.is_empty() || !thinking.is_empty() {
-            let mut json_msg = serde_json::json!({
-                "role": role,
-                "content": text
-            });
-            if !thinking.is_empty() {
-                json_msg["reasoning_content"] = serde_json::json!(thinking);
-            }
-            json_messages.push(json_msg);
-        }
+            .sum::<usize>();
     }
 
     let json = serde_json::to_string(&json_messages).map_err(|e| {
@@ -222,9 +103,10 @@ pub(crate) fn messages_to_text(
     // Normalize tool messages to text for basic prompt building.
     let normalized = normalize_messages_to_text(messages);
 
-    let mut prompt = String::new();
-    if !cfg.system.is_empty() {
-        prompt.push_str(&cfg.system.join("\n\n"));
+    let normalized: Vec<&ChatMessage> = normalized.iter().filter(|m| !m.is_system()).collect();
+
+    let mut prompt = system_text(cfg, messages);
+    if !prompt.is_empty() {
         prompt.push_str("\n\n");
     }
     for (idx, msg) in normalized.iter().enumerate() {
@@ -236,9 +118,20 @@ pub(crate) fn messages_to_text(
     Ok(prompt)
 }
 
+/// The configured system prompt followed by any system messages, joined by
+/// blank lines.
+fn system_text(cfg: &LlamaCppConfig, messages: &[ChatMessage]) -> String {
+    cfg.system
+        .iter()
+        .cloned()
+        .chain(system_prompts(messages))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
 /// Normalize messages for providers that don't support structured tool messages.
 /// ToolUse/ToolResult blocks are rendered into text blocks.
-fn normalize_messages_to_text(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+pub(crate) fn normalize_messages_to_text(messages: &[ChatMessage]) -> Vec<ChatMessage> {
     messages
         .iter()
         .map(|msg| {
@@ -274,6 +167,7 @@ fn normalize_messages_to_text(messages: &[ChatMessage]) -> Vec<ChatMessage> {
                 role: msg.role.clone(),
                 content: out_blocks,
                 cache: msg.cache.clone(),
+                name: msg.name.clone(),
             }
         })
         .collect()
@@ -282,6 +176,8 @@ fn normalize_messages_to_text(messages: &[ChatMessage]) -> Vec<ChatMessage> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use querymt::chat::ChatRole;
+    use serde_json::Value;
 
     fn test_config() -> LlamaCppConfig {
         LlamaCppConfig {
@@ -295,6 +191,8 @@ mod tests {
             presence_penalty: None,
             frequency_penalty: None,
             penalty_last_n: None,
+            mirostat_tau: None,
+            mirostat_eta: None,
             system: vec![],
             n_ctx: None,
             n_batch: None,
@@ -307,6 +205,8 @@ mod tests {
             add_bos: None,
             log: None,
             fast_download: None,
+            download_timeout_seconds: None,
+            max_concurrent_requests: None,
             enable_thinking: None,
             flash_attention: None,
             kv_cache_type_k: None,
@@ -318,6 +218,15 @@ mod tests {
             n_ubatch: None,
             text_only: None,
             json_schema: None,
+            fim_tokens: None,
+            grammar: None,
+            lora_adapters: None,
+            timeout_seconds: None,
+            kv_cache_reuse: None,
+            repair_tool_json: None,
+            tool_fallback: None,
+            pooling: None,
+            normalize: None,
         }
     }
 
@@ -326,6 +235,7 @@ mod tests {
             role: ChatRole::User,
             content: blocks,
             cache: None,
+            name: None,
         }
     }
 
@@ -334,6 +244,7 @@ mod tests {
             role: ChatRole::Assistant,
             content: blocks,
             cache: None,
+            name: None,
         }
     }
 
@@ -373,6 +284,25 @@ mod tests {
         assert_eq!(parsed[1]["role"], "user");
     }
 
+    #[test]
+    fn system_messages_merge_into_leading_system_message() {
+        let mut cfg = test_config();
+        cfg.system = vec!["Be brief.".to_string()];
+
+        let messages = vec![
+            user_msg(vec![Content::text("Hello")]),
+            ChatMessage::system().text("Answer in French.").build(),
+        ];
+
+        let (result, _) = messages_to_json(&cfg, &messages, None).unwrap();
+        let parsed: Vec<Value> = serde_json::from_str(&result).unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0]["role"], "system");
+        assert_eq!(parsed[0]["content"], "Be brief.\n\nAnswer in French.");
+        assert_eq!(parsed[1]["role"], "user");
+    }
+
     #[test]
     fn thinking_block_emitted() {
         let cfg = test_config();
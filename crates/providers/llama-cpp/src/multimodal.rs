@@ -14,7 +14,8 @@ use llama_cpp_2::mtmd::{MtmdBitmap, MtmdContext, MtmdContextParams, mtmd_default
 use querymt::chat::{ChatMessage, Content};
 use querymt::error::LLMError;
 use querymt_provider_common::{
-    ModelRef, ModelRefError, parse_model_ref, resolve_hf_model_fast, resolve_hf_model_sync,
+    HfDownloadOptions, ModelRef, ModelRefError, parse_model_ref, resolve_hf_model_fast,
+    resolve_hf_model_sync,
 };
 use std::ffi::CString;
 use std::path::{Path, PathBuf};
@@ -79,10 +80,11 @@ impl MultimodalContext {
         model_hf_repo: Option<&str>,
     ) -> Result<Option<MtmdContext>, LLMError> {
         let fast = cfg.fast_download.unwrap_or(false);
+        let options = cfg.download_options();
 
         // 1. Explicit mmproj_path takes precedence
         if let Some(ref path_str) = cfg.mmproj_path {
-            let path = Self::resolve_mmproj_path(path_str, fast)?;
+            let path = Self::resolve_mmproj_path(path_str, fast, &options)?;
             let ctx = Self::init_mtmd_from_path(&path, model, cfg)?;
             log::info!(
                 "Multimodal projection loaded: vision={}, audio={}",
@@ -102,8 +104,13 @@ impl MultimodalContext {
                         mmproj_filename
                     );
                     log::info!("Downloading {} from {}...", mmproj_filename, repo);
-                    match querymt_provider_common::resolve_hf_mmproj(repo, &mmproj_filename, fast)
-                        .map_err(Self::map_model_ref_error)
+                    match querymt_provider_common::resolve_hf_mmproj(
+                        repo,
+                        &mmproj_filename,
+                        fast,
+                        &options,
+                    )
+                    .map_err(Self::map_model_ref_error)
                     {
                         Ok(path) => {
                             let ctx = Self::init_mtmd_from_path(&path, model, cfg)?;
@@ -189,7 +196,11 @@ impl MultimodalContext {
     }
 
     /// Resolve an explicit mmproj_path value (local path or hf: ref) to a local PathBuf.
-    fn resolve_mmproj_path(raw: &str, fast: bool) -> Result<PathBuf, LLMError> {
+    fn resolve_mmproj_path(
+        raw: &str,
+        fast: bool,
+        options: &HfDownloadOptions,
+    ) -> Result<PathBuf, LLMError> {
         let model_ref = parse_model_ref(raw).map_err(Self::map_model_ref_error)?;
         match model_ref {
             ModelRef::LocalPath(path) => {
@@ -203,9 +214,9 @@ impl MultimodalContext {
             }
             ModelRef::Hf(hf_ref) => {
                 if fast {
-                    resolve_hf_model_fast(&hf_ref).map_err(Self::map_model_ref_error)
+                    resolve_hf_model_fast(&hf_ref, options).map_err(Self::map_model_ref_error)
                 } else {
-                    resolve_hf_model_sync(&hf_ref).map_err(Self::map_model_ref_error)
+                    resolve_hf_model_sync(&hf_ref, options).map_err(Self::map_model_ref_error)
                 }
             }
             ModelRef::HfRepo(repo) => Err(LLMError::InvalidRequest(format!(
@@ -328,6 +339,7 @@ mod tests {
             role: ChatRole::User,
             content: blocks,
             cache: None,
+            name: None,
         }
     }
 
@@ -471,6 +483,8 @@ mod tests {
             presence_penalty: None,
             frequency_penalty: None,
             penalty_last_n: None,
+            mirostat_tau: None,
+            mirostat_eta: None,
             n_ctx: None,
             n_batch: None,
             n_threads: None,
@@ -482,6 +496,8 @@ mod tests {
             add_bos: None,
             log: None,
             fast_download: None,
+            download_timeout_seconds: None,
+            max_concurrent_requests: None,
             enable_thinking: None,
             flash_attention: None,
             kv_cache_type_k: None,
@@ -493,6 +509,15 @@ mod tests {
             n_ubatch: None,
             text_only: None,
             json_schema: None,
+            fim_tokens: None,
+            grammar: None,
+            lora_adapters: None,
+            timeout_seconds: None,
+            kv_cache_reuse: None,
+            repair_tool_json: None,
+            tool_fallback: None,
+            pooling: None,
+            normalize: None,
         };
 
         // Case: multiple top-level images + tool result with nested images
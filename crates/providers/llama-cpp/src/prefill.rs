@@ -0,0 +1,218 @@
+//! Prompt prefill reuse for [`LlamaCppProvider::warmup`] and
+//! [`LlamaCppConfig::kv_cache_reuse`].
+//!
+//! A warmup (or, with `kv_cache_reuse`, every chat turn) keeps a snapshot of
+//! the context state after decoding (essentially the KV cache for those
+//! tokens). A later generation restores the snapshot sharing the longest
+//! prefix with its prompt and only decodes the remainder. Snapshots of a few
+//! recent conversations are kept, so interleaved sessions on one provider
+//! don't evict each other on every turn.
+//!
+//! [`LlamaCppConfig::kv_cache_reuse`]: crate::LlamaCppConfig::kv_cache_reuse
+//! [`LlamaCppProvider::warmup`]: crate::LlamaCppProvider::warmup
+
+use llama_cpp_2::context::LlamaContext;
+use llama_cpp_2::token::LlamaToken;
+use querymt::error::LLMError;
+use std::sync::Mutex;
+
+/// Context state captured after decoding `tokens` on sequence 0.
+pub(crate) struct PrefillSnapshot {
+    tokens: Vec<LlamaToken>,
+    state: Vec<u8>,
+}
+
+/// Number of snapshots a [`PrefillCache`] keeps before evicting the least
+/// recently used one.
+const MAX_SNAPSHOTS: usize = 4;
+
+/// The most recent snapshots of a provider, least recently used first.
+#[derive(Default)]
+pub(crate) struct PrefillCache {
+    snapshots: Mutex<Vec<PrefillSnapshot>>,
+}
+
+impl PrefillCache {
+    /// Add `snapshot`, replacing the snapshots it extends (earlier turns of
+    /// the same conversation) and evicting the least recently used one when
+    /// full.
+    pub(crate) fn insert(&self, snapshot: PrefillSnapshot) {
+        let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        insert_snapshot(&mut snapshots, snapshot);
+    }
+}
+
+fn insert_snapshot(snapshots: &mut Vec<PrefillSnapshot>, snapshot: PrefillSnapshot) {
+    snapshots.retain(|cached| !snapshot.tokens.starts_with(&cached.tokens));
+    if snapshots.len() >= MAX_SNAPSHOTS {
+        snapshots.remove(0);
+    }
+    snapshots.push(snapshot);
+}
+
+/// Index of the snapshot sharing the longest prefix with `prompt`, and the
+/// number of tokens it can restore.
+fn best_snapshot(snapshots: &[PrefillSnapshot], prompt: &[LlamaToken]) -> Option<(usize, usize)> {
+    snapshots
+        .iter()
+        .map(|snapshot| snapshot.reusable_prefix(prompt))
+        .enumerate()
+        .filter(|&(_, reused)| reused > 0)
+        .max_by_key(|&(_, reused)| reused)
+}
+
+impl PrefillSnapshot {
+    /// Copy the state of `ctx`, which has just decoded `tokens`.
+    pub(crate) fn capture(ctx: &LlamaContext<'_>, tokens: Vec<LlamaToken>) -> Self {
+        let mut state = vec![0u8; ctx.get_state_size()];
+        // SAFETY: `state` is sized by `get_state_size` for this very context.
+        let written = unsafe { ctx.copy_state_data(state.as_mut_ptr()) };
+        state.truncate(written);
+        Self { tokens, state }
+    }
+
+    /// Number of prompt tokens covered by the snapshot.
+    pub(crate) fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Size of the stored state in bytes.
+    pub(crate) fn size_bytes(&self) -> usize {
+        self.state.len()
+    }
+
+    /// Number of leading `prompt` tokens that can be restored instead of
+    /// decoded. At least one prompt token is always left to decode, since
+    /// sampling needs the logits of the final prompt token.
+    pub(crate) fn reusable_prefix(&self, prompt: &[LlamaToken]) -> usize {
+        let shared = self
+            .tokens
+            .iter()
+            .zip(prompt)
+            .take_while(|(cached, token)| cached == token)
+            .count();
+        shared.min(prompt.len().saturating_sub(1))
+    }
+
+    /// Load the snapshot into `ctx`, keeping only its first `n_tokens`
+    /// positions in the KV cache. Returns `false`, with the KV cache
+    /// cleared, if llama.cpp could not load the state.
+    ///
+    /// `ctx` must be freshly created from the same model and config the
+    /// snapshot was captured with.
+    pub(crate) fn restore(
+        &self,
+        ctx: &mut LlamaContext<'_>,
+        n_tokens: usize,
+    ) -> Result<bool, LLMError> {
+        // SAFETY: the state was produced by `copy_state_data` on a context
+        // with the same model and parameters.
+        if unsafe { ctx.set_state_data(&self.state) } == 0 {
+            log::warn!("Failed to restore cached context state; decoding the full prompt");
+            ctx.clear_kv_cache();
+            return Ok(false);
+        }
+        ctx.clear_kv_cache_seq(Some(0), Some(n_tokens as u32), None)
+            .map_err(|e| {
+                LLMError::ProviderError(format!("Failed to trim restored KV cache: {e}"))
+            })?;
+        Ok(true)
+    }
+
+    /// Restore the longest prefix of `prompt` cached in `cache` into `ctx`
+    /// and return how many tokens were restored.
+    pub(crate) fn restore_from(
+        cache: &PrefillCache,
+        ctx: &mut LlamaContext<'_>,
+        prompt: &[LlamaToken],
+    ) -> Result<usize, LLMError> {
+        let mut snapshots = cache.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        let Some((index, reused)) = best_snapshot(&snapshots, prompt) else {
+            return Ok(0);
+        };
+        // Move it to the back as the most recently used.
+        let snapshot = snapshots.remove(index);
+        let restored = snapshot.restore(ctx, reused);
+        snapshots.push(snapshot);
+        if !restored? {
+            return Ok(0);
+        }
+        log::debug!(
+            "Reusing {} of {} prompt tokens from cached context state",
+            reused,
+            prompt.len()
+        );
+        Ok(reused)
+    }
+
+    /// Add the state of `ctx` to `cache`.
+    pub(crate) fn store(cache: &PrefillCache, ctx: &LlamaContext<'_>, tokens: Vec<LlamaToken>) {
+        cache.insert(Self::capture(ctx, tokens));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tokens: &[i32]) -> PrefillSnapshot {
+        PrefillSnapshot {
+            tokens: tokens.iter().copied().map(LlamaToken::new).collect(),
+            state: Vec::new(),
+        }
+    }
+
+    fn prompt(tokens: &[i32]) -> Vec<LlamaToken> {
+        tokens.iter().copied().map(LlamaToken::new).collect()
+    }
+
+    #[test]
+    fn reuses_shared_prefix() {
+        let snap = snapshot(&[1, 2, 3, 4]);
+        assert_eq!(snap.reusable_prefix(&prompt(&[1, 2, 3, 4, 5, 6])), 4);
+        assert_eq!(snap.reusable_prefix(&prompt(&[1, 2, 9, 4, 5])), 2);
+        assert_eq!(snap.reusable_prefix(&prompt(&[7, 8])), 0);
+    }
+
+    #[test]
+    fn always_leaves_last_prompt_token_to_decode() {
+        let snap = snapshot(&[1, 2, 3]);
+        assert_eq!(snap.reusable_prefix(&prompt(&[1, 2, 3])), 2);
+        assert_eq!(snap.reusable_prefix(&prompt(&[1, 2])), 1);
+        assert_eq!(snap.reusable_prefix(&prompt(&[])), 0);
+    }
+
+    fn cached(snapshots: &[PrefillSnapshot]) -> Vec<Vec<i32>> {
+        snapshots
+            .iter()
+            .map(|s| s.tokens.iter().map(|t| t.0).collect())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_one_snapshot_per_conversation() {
+        let mut snapshots = Vec::new();
+        insert_snapshot(&mut snapshots, snapshot(&[1, 2, 3]));
+        insert_snapshot(&mut snapshots, snapshot(&[7, 8]));
+        // The next turn of the first conversation replaces its earlier turn.
+        insert_snapshot(&mut snapshots, snapshot(&[1, 2, 3, 4, 5]));
+        assert_eq!(cached(&snapshots), vec![vec![7, 8], vec![1, 2, 3, 4, 5]]);
+
+        assert_eq!(
+            best_snapshot(&snapshots, &prompt(&[1, 2, 3, 4, 5, 6])),
+            Some((1, 5))
+        );
+        assert_eq!(best_snapshot(&snapshots, &prompt(&[7, 8, 9])), Some((0, 2)));
+        assert_eq!(best_snapshot(&snapshots, &prompt(&[9])), None);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_snapshot() {
+        let mut snapshots = Vec::new();
+        for first in 1..=MAX_SNAPSHOTS as i32 + 1 {
+            insert_snapshot(&mut snapshots, snapshot(&[first, 0]));
+        }
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS);
+        assert_eq!(cached(&snapshots)[0], vec![2, 0]);
+    }
+}
@@ -1,34 +1,96 @@
 use crate::backend::{install_abort_callback, llama_backend};
+use crate::common_chat::ChatTemplateResult;
 use crate::config::{DEFAULT_MAX_TOKENS, LlamaCppConfig, LlamaCppLogMode};
-use crate::context::estimate_context_memory;
+use crate::context::{LoraAdapters, estimate_context_memory, validate_batch_sizes};
+use crate::embedding::embed;
+use crate::fim::{build_fim_prompt, resolve_fim_tokens};
 use crate::generation::{
     build_prompt, build_prompt_with, build_raw_prompt, generate, generate_streaming_with_thinking,
+    generate_with_prefill, prefill,
 };
-use crate::memory::MemoryEstimate;
+use crate::memory::{MemoryEstimate, parse_kv_cache_type};
 use crate::multimodal::MultimodalContext;
+use crate::prefill::PrefillCache;
+use crate::queue::RequestQueue;
 use crate::response::LlamaCppChatResponse;
+use crate::template::{
+    ResolvedChatTemplate, resolve_chat_template, resolve_chat_template_or_chatml,
+};
+use crate::tools::fallback::{chat_with_fallback, response_chunks};
+use crate::tools::sampler::build_config_grammar;
 use crate::tools::{
     apply_template_for_thinking, apply_template_with_tools, generate_streaming_with_tools,
     generate_with_tools, parse_tool_response,
 };
 use async_trait::async_trait;
-use futures::Stream;
 use futures::channel::mpsc;
+use futures::{Stream, StreamExt};
 use llama_cpp_2::model::LlamaModel;
 use llama_cpp_2::model::params::LlamaModelParams;
 use llama_cpp_2::{LogOptions, send_logs_to_tracing};
 use querymt::LLMProvider;
-use querymt::chat::{ChatMessage, ChatProvider, ChatResponse, FinishReason, Tool};
+use querymt::chat::{ChatMessage, ChatProvider, ChatResponse, FinishReason, StreamChunk, Tool};
 use querymt::completion::{CompletionProvider, CompletionRequest, CompletionResponse};
 use querymt::embedding::EmbeddingProvider;
 use querymt::error::LLMError;
 use querymt_provider_common::{
-    ModelRef, ModelRefError, parse_model_ref, resolve_hf_model_fast, resolve_hf_model_sync,
+    HfDownloadOptions, ModelRef, ModelRefError, parse_model_ref, reconcile_hf_model_ref,
+    resolve_hf_model_fast, resolve_hf_model_sync, resolve_hf_repo,
 };
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::thread;
 
+/// Chunks streamed from a generation thread.
+///
+/// Dropping the stream, e.g. when the caller gives up on a slow response,
+/// closes the channel, which stops the generation loop at its next prompt batch
+/// or token. The thread is joined on the blocking pool so the drop never stalls
+/// an async worker; outside a runtime it is detached.
+struct GenerationStream {
+    rx: mpsc::UnboundedReceiver<Result<StreamChunk, LLMError>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl GenerationStream {
+    fn new(
+        rx: mpsc::UnboundedReceiver<Result<StreamChunk, LLMError>>,
+        worker: thread::JoinHandle<()>,
+    ) -> Self {
+        Self {
+            rx,
+            worker: Some(worker),
+        }
+    }
+}
+
+impl Stream for GenerationStream {
+    type Item = Result<StreamChunk, LLMError>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<Self::Item>> {
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for GenerationStream {
+    fn drop(&mut self) {
+        self.rx.close();
+        let Some(worker) = self.worker.take() else {
+            return;
+        };
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn_blocking(move || {
+                if worker.join().is_err() {
+                    log::error!("llama.cpp generation thread panicked");
+                }
+            });
+        }
+    }
+}
+
 /// Cache key for model loading — only params that affect `LlamaModel::load_from_file`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ModelCacheKey {
@@ -41,33 +103,90 @@ pub(crate) struct ModelCacheKey {
 /// A cached model + multimodal context, shared across provider instances.
 pub(crate) struct CachedModel {
     pub key: ModelCacheKey,
+    /// The `model` reference from the config that loaded it.
+    pub model_ref: String,
     pub model: Arc<LlamaModel>,
     pub multimodal: Option<Arc<MultimodalContext>>,
+    /// LoRA adapters loaded for `model` by the config that loaded it.
+    pub lora: Arc<LoraAdapters>,
+    pub requests: Arc<RequestQueue>,
+}
+
+impl CachedModel {
+    /// Whether `model` names this entry, either by the config reference or
+    /// by the resolved GGUF path.
+    pub fn matches(&self, model: &str) -> bool {
+        self.model_ref == model || self.key.model_path == model
+    }
+
+    /// Drop the cache's references, logging whether that freed the weights.
+    pub fn release(self) {
+        let Self {
+            key,
+            model,
+            multimodal,
+            ..
+        } = self;
+        let size_gb = model.size() as f64 / (1024.0 * 1024.0 * 1024.0);
+        drop(multimodal);
+        match Arc::into_inner(model) {
+            Some(model) => {
+                drop(model);
+                log::info!(
+                    "LlamaCpp model unloaded: {} ({:.1}GB freed)",
+                    key.model_path,
+                    size_gb
+                );
+            }
+            None => log::info!(
+                "LlamaCpp model evicted from cache: {} ({:.1}GB freed once its providers are dropped)",
+                key.model_path,
+                size_gb
+            ),
+        }
+    }
 }
 
 /// The main llama.cpp provider.
-pub(crate) struct LlamaCppProvider {
+pub struct LlamaCppProvider {
     pub(crate) model: Arc<LlamaModel>,
     pub(crate) cfg: LlamaCppConfig,
     pub(crate) multimodal: Option<Arc<MultimodalContext>>,
+    /// The configured LoRA adapters, loaded once and attached to each context.
+    lora: Arc<LoraAdapters>,
+    /// Snapshots left by [`warmup`](Self::warmup) and, with
+    /// `kv_cache_reuse`, by recent chat turns. Shared with streaming threads.
+    prefill: Arc<PrefillCache>,
+    /// Admission of requests to `model`, shared by every provider using it.
+    requests: Arc<RequestQueue>,
 }
 
 impl LlamaCppProvider {
     /// Resolve a model path, potentially downloading from Hugging Face Hub.
-    fn resolve_model_path(raw: &str, fast: bool) -> Result<PathBuf, LLMError> {
+    fn resolve_model_path(
+        raw: &str,
+        fast: bool,
+        options: &HfDownloadOptions,
+    ) -> Result<PathBuf, LLMError> {
         let model_ref = parse_model_ref(raw).map_err(Self::map_model_ref_error)?;
         match model_ref {
             ModelRef::LocalPath(path) => Ok(path),
             ModelRef::Hf(model) => {
+                let model = reconcile_hf_model_ref(&model);
                 if fast {
-                    resolve_hf_model_fast(&model).map_err(Self::map_model_ref_error)
+                    resolve_hf_model_fast(&model, options).map_err(Self::map_model_ref_error)
                 } else {
-                    resolve_hf_model_sync(&model).map_err(Self::map_model_ref_error)
+                    resolve_hf_model_sync(&model, options).map_err(Self::map_model_ref_error)
+                }
+            }
+            ModelRef::HfRepo(repo) => {
+                let model = resolve_hf_repo(&repo, None).map_err(Self::map_model_ref_error)?;
+                if fast {
+                    resolve_hf_model_fast(&model, options).map_err(Self::map_model_ref_error)
+                } else {
+                    resolve_hf_model_sync(&model, options).map_err(Self::map_model_ref_error)
                 }
             }
-            ModelRef::HfRepo(repo) => Err(LLMError::InvalidRequest(format!(
-                "llama_cpp model must include a selector for Hugging Face repos: {repo}:<selector>"
-            ))),
         }
     }
 
@@ -78,7 +197,8 @@ impl LlamaCppProvider {
         }
     }
 
-    pub(crate) fn new(cfg: LlamaCppConfig) -> Result<Self, LLMError> {
+    /// Load the model for `cfg` and build a provider around it.
+    pub fn new(cfg: LlamaCppConfig) -> Result<Self, LLMError> {
         // Install the ggml abort callback before any llama.cpp operations.
         // This ensures that if Metal/CUDA triggers a fatal error, the user sees
         // a meaningful error message instead of just a raw stack trace.
@@ -91,7 +211,12 @@ impl LlamaCppProvider {
             LlamaCppLogMode::Tracing => send_logs_to_tracing(LogOptions::default()),
             LlamaCppLogMode::Off => backend.void_logs(),
         }
-        let model_path = Self::resolve_model_path(&cfg.model, cfg.fast_download.unwrap_or(false))?;
+        let model_path = Self::resolve_model_path(
+            &cfg.model,
+            cfg.fast_download.unwrap_or(false),
+            &cfg.download_options(),
+        )?;
+        let cfg = Self::resolve_lora_paths(cfg)?;
         let model_path = Path::new(&model_path);
         if !model_path.exists() {
             return Err(LLMError::InvalidRequest(format!(
@@ -107,6 +232,7 @@ impl LlamaCppProvider {
 
         let model = LlamaModel::load_from_file(&*backend, model_path, &params)
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
+        let lora = Arc::new(LoraAdapters::load(&model, &cfg)?);
 
         // Extract the HF repo name (if the model came from HF) so multimodal
         // context can auto-discover the matching mmproj file from the same repo.
@@ -130,12 +256,18 @@ impl LlamaCppProvider {
             log::debug!("Multimodal support not available for this model");
         }
 
+        let requests = Arc::new(RequestQueue::new(cfg.request_limit()));
         let provider = Self {
             model: Arc::new(model),
             cfg,
             multimodal,
+            lora,
+            prefill: Arc::default(),
+            requests,
         };
 
+        provider.validate_config()?;
+
         // Advisory memory warning at startup — never fails, just informs.
         Self::log_memory_advisory(&provider);
 
@@ -162,7 +294,12 @@ impl LlamaCppProvider {
             LlamaCppLogMode::Off => backend.void_logs(),
         }
 
-        let model_path = Self::resolve_model_path(&cfg.model, cfg.fast_download.unwrap_or(false))?;
+        let model_path = Self::resolve_model_path(
+            &cfg.model,
+            cfg.fast_download.unwrap_or(false),
+            &cfg.download_options(),
+        )?;
+        let cfg = Self::resolve_lora_paths(cfg)?;
         let model_path_str = model_path.to_string_lossy().to_string();
         let key = ModelCacheKey {
             model_path: model_path_str,
@@ -175,11 +312,28 @@ impl LlamaCppProvider {
             if cached.key == key {
                 // Cache hit — reuse model, attach new config
                 log::debug!("LlamaCpp model cache hit: {}", key.model_path);
+                let lora = if cached.lora.matches(&cfg) {
+                    Arc::clone(&cached.lora)
+                } else {
+                    Arc::new(LoraAdapters::load(&cached.model, &cfg)?)
+                };
+                if cfg.request_limit() != cached.requests.limit() {
+                    log::warn!(
+                        "LlamaCpp max_concurrent_requests = {} ignored: {} is already loaded with a limit of {}; evict it to change the limit",
+                        cfg.request_limit(),
+                        key.model_path,
+                        cached.requests.limit()
+                    );
+                }
                 let provider = Self {
                     model: Arc::clone(&cached.model),
                     cfg,
                     multimodal: cached.multimodal.as_ref().map(Arc::clone),
+                    lora,
+                    prefill: Arc::default(),
+                    requests: Arc::clone(&cached.requests),
                 };
+                provider.validate_config()?;
                 return Ok(provider);
             }
             // Cache miss — different model, evict old one
@@ -213,6 +367,7 @@ impl LlamaCppProvider {
             LlamaModel::load_from_file(&backend, model_path, &params)
                 .map_err(|e| LLMError::ProviderError(e.to_string()))?,
         );
+        let lora = Arc::new(LoraAdapters::load(&model, &cfg)?);
 
         let model_hf_repo = match parse_model_ref(&cfg.model) {
             Ok(ModelRef::Hf(hf_ref)) => Some(hf_ref.repo),
@@ -234,24 +389,151 @@ impl LlamaCppProvider {
         }
 
         // Store in cache
+        let requests = Arc::new(RequestQueue::new(cfg.request_limit()));
         let mut guard = cache.lock().unwrap_or_else(|e| e.into_inner());
         *guard = Some(CachedModel {
             key,
+            model_ref: cfg.model.clone(),
             model: Arc::clone(&model),
             multimodal: multimodal.as_ref().map(Arc::clone),
+            lora: Arc::clone(&lora),
+            requests: Arc::clone(&requests),
         });
 
         let provider = Self {
             model,
             cfg,
             multimodal,
+            lora,
+            prefill: Arc::default(),
+            requests,
         };
+        provider.validate_config()?;
 
         Self::log_memory_advisory(&provider);
 
         Ok(provider)
     }
 
+    /// Decode the prompt for `messages` ahead of time, so the next `chat`
+    /// call whose prompt starts with the same tokens skips that prefill and
+    /// produces its first token sooner.
+    ///
+    /// Warmups are kept alongside the snapshots of recent chat turns, the
+    /// least recently used being evicted first. Each holds a copy of the
+    /// context state for the warmed tokens, roughly the KV cache size for
+    /// that many tokens (see `estimate_context_memory`), in host memory until
+    /// it is evicted or the provider is dropped. Prompts with images, tools
+    /// or structured output are not warmed.
+    ///
+    /// The warmup waits for a request slot like any other request.
+    pub async fn warmup(&self, messages: &[ChatMessage]) -> Result<(), LLMError> {
+        if !crate::multimodal::extract_media(messages).is_empty() {
+            return Err(LLMError::InvalidRequest(
+                "warmup does not support media content".into(),
+            ));
+        }
+        let (prompt, _) = build_prompt(&self.model, &self.cfg, messages, None)?;
+        let _slot = self.requests.acquire().await?;
+        let snapshot = prefill(&self.model, &self.cfg, &self.lora, &prompt)?;
+        log::debug!(
+            "Warmed up {} prompt tokens ({} bytes of context state)",
+            snapshot.len(),
+            snapshot.size_bytes()
+        );
+        self.prefill.insert(snapshot);
+        Ok(())
+    }
+
+    /// Report which chat template this provider's requests resolve to.
+    ///
+    /// A configured `chat_template` that can't be resolved is an error, as it
+    /// is for requests; ChatML is only reported for models without a template
+    /// of their own.
+    pub fn resolved_chat_template(&self) -> Result<ResolvedChatTemplate, LLMError> {
+        let (template, source) = if self.cfg.chat_template.is_some() {
+            resolve_chat_template(&self.model, &self.cfg)?
+        } else {
+            resolve_chat_template_or_chatml(&self.model, &self.cfg)?
+        };
+        let template = template
+            .to_string()
+            .map_err(|e| LLMError::ProviderError(format!("Invalid chat template: {e}")))?;
+        Ok(ResolvedChatTemplate { source, template })
+    }
+
+    /// The prompt for a completion request: the prompt itself, or a
+    /// fill-in-the-middle prompt when the request has a suffix.
+    fn completion_prompt(&self, req: &CompletionRequest) -> Result<String, LLMError> {
+        let Some(suffix) = &req.suffix else {
+            return Ok(req.prompt.clone());
+        };
+        let tokens = resolve_fim_tokens(&self.model, &self.cfg).ok_or_else(|| {
+            LLMError::NotImplemented(
+                "Suffix completion requires a model with fill-in-the-middle tokens \
+                 (or `fim_tokens` in the config)"
+                    .into(),
+            )
+        })?;
+        Ok(build_fim_prompt(&tokens, &req.prompt, suffix))
+    }
+
+    /// The config with the request's sampling overrides applied.
+    fn completion_config(&self, req: &CompletionRequest) -> LlamaCppConfig {
+        let mut cfg = self.cfg.clone();
+        cfg.top_p = req.top_p.or(cfg.top_p);
+        cfg.presence_penalty = req.presence_penalty.or(cfg.presence_penalty);
+        cfg.frequency_penalty = req.frequency_penalty.or(cfg.frequency_penalty);
+        cfg
+    }
+
+    fn completion_max_tokens(&self, req: &CompletionRequest) -> u32 {
+        req.max_tokens
+            .or(self.cfg.max_tokens)
+            .unwrap_or(DEFAULT_MAX_TOKENS)
+    }
+
+    /// Reject a grammar llama.cpp cannot parse, or LoRA adapters that do not
+    /// fit the base model, up front rather than on the first request.
+    fn validate_config(&self) -> Result<(), LLMError> {
+        validate_batch_sizes(&self.cfg)?;
+        if self.cfg.max_concurrent_requests == Some(0) {
+            return Err(LLMError::InvalidRequest(
+                "max_concurrent_requests must be greater than zero".into(),
+            ));
+        }
+        // Reject unknown KV cache types at load time rather than on the
+        // first request.
+        for cache_type in [&self.cfg.kv_cache_type_k, &self.cfg.kv_cache_type_v]
+            .into_iter()
+            .flatten()
+        {
+            parse_kv_cache_type(cache_type)?;
+        }
+        if let Some(grammar) = &self.cfg.grammar {
+            build_config_grammar(&self.model, grammar)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve LoRA adapter references to local files, downloading from
+    /// Hugging Face Hub if needed.
+    fn resolve_lora_paths(mut cfg: LlamaCppConfig) -> Result<LlamaCppConfig, LLMError> {
+        let fast = cfg.fast_download.unwrap_or(false);
+        let options = cfg.download_options();
+        for adapter in cfg.lora_adapters.iter_mut().flatten() {
+            let path = Self::resolve_model_path(&adapter.path, fast, &options)?;
+            if !path.exists() {
+                return Err(LLMError::InvalidRequest(format!(
+                    "LoRA adapter path does not exist: {}",
+                    path.display()
+                )));
+            }
+            adapter.path = path.to_string_lossy().to_string();
+        }
+        Ok(cfg)
+    }
+
     /// Log advisory memory warnings at startup.
     fn log_memory_advisory(provider: &Self) {
         if let Some(n_ctx) = provider.cfg.n_ctx {
@@ -331,25 +613,45 @@ impl ChatProvider for LlamaCppProvider {
             self.multimodal.as_deref()
         };
         let media_marker = active_multimodal.map(|m| m.marker());
+        let _slot = self.requests.acquire().await?;
 
         // If tools are provided and not empty, use tool-aware generation
         if let Some(tools) = tools {
             if !tools.is_empty() {
-                let template_result = apply_template_with_tools(
+                let template_result = match apply_template_with_tools(
                     &self.model,
                     &self.cfg,
                     messages,
                     tools,
                     media_marker,
-                )?;
+                ) {
+                    Ok(result) => result,
+                    Err(err) if self.cfg.tool_fallback.unwrap_or(false) => {
+                        log::warn!("Chat template rejected tools, using prompt fallback: {err}");
+                        return Ok(Box::new(chat_with_fallback(
+                            &self.model,
+                            &self.cfg,
+                            &self.lora,
+                            messages,
+                            tools,
+                            max_tokens,
+                            active_multimodal,
+                            &bitmaps,
+                            Some(&*self.prefill),
+                        )?));
+                    }
+                    Err(err) => return Err(err),
+                };
                 let generated = generate_with_tools(
                     &self.model,
                     &self.cfg,
+                    &self.lora,
                     &template_result,
                     max_tokens,
                     None,
                     active_multimodal,
                     &bitmaps,
+                    Some(&*self.prefill),
                 )?;
                 let (content, thinking, tool_calls, finish_reason) =
                     parse_tool_response(&template_result, &generated.text)?;
@@ -372,11 +674,13 @@ impl ChatProvider for LlamaCppProvider {
             let generated = generate_with_tools(
                 &self.model,
                 &self.cfg,
+                &self.lora,
                 &template_result,
                 max_tokens,
                 None,
                 active_multimodal,
                 &bitmaps,
+                Some(&*self.prefill),
             )?;
             let (content, thinking, _tool_calls, finish_reason) =
                 parse_tool_response(&template_result, &generated.text)?;
@@ -394,14 +698,17 @@ impl ChatProvider for LlamaCppProvider {
             build_prompt(&self.model, &self.cfg, messages, media_marker)?;
 
         // Call unified generate() with optional multimodal params
-        let mut generated = generate(
+        let mut generated = generate_with_prefill(
             &self.model,
             &self.cfg,
+            &self.lora,
             &prompt,
             max_tokens,
             None,
             active_multimodal,
             &bitmaps,
+            Some(&*self.prefill),
+            &[],
         )?;
         // Fallback handling (existing logic)
         if generated.text.trim().is_empty() {
@@ -411,6 +718,7 @@ impl ChatProvider for LlamaCppProvider {
                 generated = generate(
                     &self.model,
                     &self.cfg,
+                    &self.lora,
                     &fallback_prompt,
                     max_tokens,
                     None,
@@ -424,6 +732,7 @@ impl ChatProvider for LlamaCppProvider {
             generated = generate(
                 &self.model,
                 &self.cfg,
+                &self.lora,
                 &raw_prompt,
                 max_tokens,
                 None,
@@ -495,25 +804,53 @@ impl ChatProvider for LlamaCppProvider {
                     messages,
                     tools,
                     media_marker,
-                )?;
+                );
                 let cfg = self.cfg.clone();
                 let model = Arc::clone(&self.model);
+                let lora = Arc::clone(&self.lora);
                 let multimodal = if bitmaps.is_empty() {
                     None
                 } else {
                     self.multimodal.clone()
                 };
+                let template_result = match template_result {
+                    Ok(result) => result,
+                    Err(err) if cfg.tool_fallback.unwrap_or(false) => {
+                        log::warn!("Chat template rejected tools, using prompt fallback: {err}");
+                        // Generated in one piece, like `chat`, then replayed as chunks.
+                        let _slot = self.requests.acquire().await?;
+                        let response = chat_with_fallback(
+                            &model,
+                            &cfg,
+                            &lora,
+                            messages,
+                            tools,
+                            max_tokens,
+                            multimodal.as_deref(),
+                            &bitmaps,
+                            Some(&*self.prefill),
+                        )?;
+                        let chunks = response_chunks(response).into_iter().map(Ok);
+                        return Ok(Box::pin(futures::stream::iter(chunks)));
+                    }
+                    Err(err) => return Err(err),
+                };
 
-                thread::spawn(move || {
+                let prefill = Arc::clone(&self.prefill);
+                let slot = self.requests.acquire().await?;
+                let worker = thread::spawn(move || {
+                    let _slot = slot;
                     match generate_streaming_with_tools(
                         &model,
                         &cfg,
+                        &lora,
                         &template_result,
                         max_tokens,
                         None,
                         &tx,
                         multimodal.as_deref(),
                         &bitmaps,
+                        Some(&*prefill),
                     ) {
                         Ok((usage, has_tool_calls)) => {
                             let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Usage(usage)));
@@ -531,7 +868,7 @@ impl ChatProvider for LlamaCppProvider {
                     }
                 });
 
-                return Ok(Box::pin(rx));
+                return Ok(Box::pin(GenerationStream::new(rx, worker)));
             }
         }
 
@@ -543,22 +880,29 @@ impl ChatProvider for LlamaCppProvider {
             apply_template_for_thinking(&self.model, &self.cfg, messages, media_marker)?;
         let cfg = self.cfg.clone();
         let model = Arc::clone(&self.model);
+        let lora = Arc::clone(&self.lora);
+        let slot = self.requests.acquire().await?;
         let multimodal = if bitmaps.is_empty() {
             None
         } else {
             self.multimodal.clone()
         };
 
-        thread::spawn(move || {
+        let prefill = Arc::clone(&self.prefill);
+        let worker = thread::spawn(move || {
+            let _slot = slot;
             match generate_streaming_with_thinking(
                 &model,
                 &cfg,
+                &lora,
                 &thinking_template,
                 max_tokens,
                 None,
                 &tx,
                 multimodal.as_deref(),
                 &bitmaps,
+                Some(&*prefill),
+                &[],
             ) {
                 Ok(usage) => {
                     let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Usage(usage)));
@@ -572,45 +916,89 @@ impl ChatProvider for LlamaCppProvider {
             }
         });
 
-        Ok(Box::pin(rx))
+        Ok(Box::pin(GenerationStream::new(rx, worker)))
     }
 }
 
 #[async_trait]
 impl CompletionProvider for LlamaCppProvider {
     async fn complete(&self, req: &CompletionRequest) -> Result<CompletionResponse, LLMError> {
-        if req.suffix.is_some() {
-            return Err(LLMError::NotImplemented(
-                "Suffix completion is not supported by llama.cpp provider".into(),
-            ));
-        }
-
-        let max_tokens = req
-            .max_tokens
-            .or(self.cfg.max_tokens)
-            .unwrap_or(DEFAULT_MAX_TOKENS);
+        let prompt = self.completion_prompt(req)?;
+        let max_tokens = self.completion_max_tokens(req);
         // Completions are text-only, no multimodal support
-        let generated = generate(
+        let cfg = self.completion_config(req);
+        let _slot = self.requests.acquire().await?;
+        let generated = generate_with_prefill(
             &self.model,
-            &self.cfg,
-            &req.prompt,
+            &cfg,
+            &self.lora,
+            &prompt,
             max_tokens,
             req.temperature,
             None,
             &[],
+            None,
+            req.stop.as_deref().unwrap_or_default(),
         )?;
         Ok(CompletionResponse {
             text: generated.text,
+            usage: Some(generated.usage),
         })
     }
+
+    async fn complete_stream(
+        &self,
+        req: &CompletionRequest,
+    ) -> Result<
+        std::pin::Pin<Box<dyn Stream<Item = Result<querymt::chat::StreamChunk, LLMError>> + Send>>,
+        LLMError,
+    > {
+        let prompt = ChatTemplateResult::raw(self.completion_prompt(req)?);
+        let max_tokens = self.completion_max_tokens(req);
+        let temperature = req.temperature;
+        let stop = req.stop.clone().unwrap_or_default();
+        let cfg = self.completion_config(req);
+        let model = Arc::clone(&self.model);
+        let lora = Arc::clone(&self.lora);
+        let slot = self.requests.acquire().await?;
+        let (tx, rx) = mpsc::unbounded();
+
+        let worker = thread::spawn(move || {
+            let _slot = slot;
+            match generate_streaming_with_thinking(
+                &model,
+                &cfg,
+                &lora,
+                &prompt,
+                max_tokens,
+                temperature,
+                &tx,
+                None,
+                &[],
+                None,
+                &stop,
+            ) {
+                Ok(usage) => {
+                    let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Usage(usage)));
+                    let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Done {
+                        finish_reason: FinishReason::Stop,
+                    }));
+                }
+                Err(err) => {
+                    let _ = tx.unbounded_send(Err(err));
+                }
+            }
+        });
+
+        Ok(Box::pin(GenerationStream::new(rx, worker)))
+    }
 }
 
 #[async_trait]
 impl EmbeddingProvider for LlamaCppProvider {
-    async fn embed(&self, _input: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError> {
-        Err(LLMError::NotImplemented(
-            "Embeddings are not supported by llama.cpp provider".into(),
-        ))
+    async fn embed(&self, input: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError> {
+        let _slot = self.requests.acquire().await?;
+        embed(&self.model, &self.cfg, &self.lora, &input)
     }
 }
 
@@ -0,0 +1,123 @@
+use querymt::error::LLMError;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// First-come, first-served admission of requests to one loaded model.
+///
+/// Requests on a single GPU contend for the same compute, and each running
+/// request holds its own context and KV cache, so by default they run one at
+/// a time (`max_concurrent_requests = 1`). Waiting requests are admitted in
+/// arrival order (tokio's semaphore is fair), so a short request queued
+/// behind a long generation runs next instead of racing every later arrival
+/// for the slot. Waiting doesn't block a runtime thread.
+pub(crate) struct RequestQueue {
+    slots: Arc<Semaphore>,
+    limit: usize,
+}
+
+impl RequestQueue {
+    /// A queue admitting up to `limit` concurrent requests (at least one).
+    pub(crate) fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            slots: Arc::new(Semaphore::new(limit)),
+            limit,
+        }
+    }
+
+    /// The number of requests admitted at once.
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Wait until every earlier request has been admitted and a slot is
+    /// free. The slot is released when the returned guard is dropped.
+    pub(crate) async fn acquire(&self) -> Result<RequestSlot, LLMError> {
+        if self.slots.available_permits() == 0 {
+            log::debug!("LlamaCpp request queued behind running requests");
+        }
+        Arc::clone(&self.slots)
+            .acquire_owned()
+            .await
+            .map(|permit| RequestSlot { _permit: permit })
+            .map_err(|e| LLMError::ProviderError(format!("Request queue closed: {e}")))
+    }
+}
+
+/// A running request's slot in a [`RequestQueue`].
+pub(crate) struct RequestSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RequestQueue;
+    use std::sync::mpsc;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn single_slot_admits_in_arrival_order() {
+        let queue = Arc::new(RequestQueue::new(1));
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let running = futures::executor::block_on(queue.acquire()).unwrap();
+        let mut handles = Vec::new();
+        for i in 0..3 {
+            let (waiter, order) = (Arc::clone(&queue), Arc::clone(&order));
+            let (queued, is_queued) = mpsc::channel();
+            handles.push(thread::spawn(move || {
+                futures::executor::block_on(async {
+                    let acquire = waiter.acquire();
+                    futures::pin_mut!(acquire);
+                    // Poll once to join the queue before the next arrives.
+                    let slot = match futures::poll!(acquire.as_mut()) {
+                        std::task::Poll::Ready(slot) => slot,
+                        std::task::Poll::Pending => {
+                            queued.send(()).unwrap();
+                            acquire.await
+                        }
+                    };
+                    let _slot = slot.unwrap();
+                    order.lock().unwrap().push(i);
+                })
+            }));
+            is_queued
+                .recv_timeout(Duration::from_secs(5))
+                .expect("request should wait for the running one");
+        }
+        assert!(order.lock().unwrap().is_empty());
+
+        drop(running);
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn limit_allows_concurrent_requests() {
+        let queue = Arc::new(RequestQueue::new(2));
+        let (tx, rx) = mpsc::channel();
+        let first = futures::executor::block_on(queue.acquire()).unwrap();
+        let second = {
+            let queue = Arc::clone(&queue);
+            thread::spawn(move || {
+                let _slot = futures::executor::block_on(queue.acquire()).unwrap();
+                tx.send(()).unwrap();
+            })
+        };
+        rx.recv_timeout(Duration::from_secs(5))
+            .expect("second request should run alongside the first");
+        second.join().unwrap();
+        drop(first);
+        assert_eq!(queue.slots.available_permits(), 2);
+    }
+
+    #[test]
+    fn limit_is_at_least_one() {
+        assert_eq!(RequestQueue::new(0).limit(), 1);
+        assert_eq!(RequestQueue::new(3).limit(), 3);
+    }
+}
@@ -28,7 +28,7 @@ impl ChatResponse for LlamaCppChatResponse {
     }
 
     fn tool_calls(&self) -> Option<Vec<querymt::ToolCall>> {
-        self.tool_calls.clone()
+        querymt::chat::non_empty_tool_calls(self.tool_calls.clone())
     }
 
     fn usage(&self) -> Option<Usage> {
@@ -2,7 +2,7 @@ use crate::chat_format::ToolFormat;
 use crate::common_chat::{ChatTemplateResult, ReasoningFormat, prompt_starts_in_thinking};
 use crate::config::LlamaCppConfig;
 use crate::messages;
-use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::model::{LlamaChatTemplate, LlamaModel};
 use minijinja::{Environment, Value};
 use querymt::chat::{ChatMessage, Tool};
 use querymt::error::LLMError;
@@ -29,6 +29,79 @@ fn strftime_now(format: String) -> String {
     chrono::Local::now().format(&format).to_string()
 }
 
+/// Where the chat template used for a request came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatTemplateSource {
+    /// A full Jinja template given inline in `chat_template`.
+    Inline,
+    /// A named template embedded in the GGUF, selected via `chat_template`.
+    Named(String),
+    /// The model's default embedded template.
+    ModelDefault,
+    /// The built-in ChatML template, used when the model has no usable template.
+    ChatMlFallback,
+}
+
+/// The chat template a provider resolved, for debugging chat-format issues.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedChatTemplate {
+    pub source: ChatTemplateSource,
+    /// The template text (or built-in template name for `ChatMlFallback`).
+    pub template: String,
+}
+
+/// Returns true when a `chat_template` value is template source rather than a
+/// template name.
+pub(crate) fn is_inline_template(value: &str) -> bool {
+    value.contains("{{") || value.contains("{%")
+}
+
+fn inline_chat_template(template: &str) -> Result<LlamaChatTemplate, LLMError> {
+    LlamaChatTemplate::new(template)
+        .map_err(|e| LLMError::InvalidRequest(format!("Invalid inline chat template: {e}")))
+}
+
+/// Resolve the configured chat template: inline source is used verbatim, any
+/// other value names a template embedded in the GGUF, and `None` selects the
+/// model default.
+pub(crate) fn resolve_chat_template(
+    model: &LlamaModel,
+    cfg: &LlamaCppConfig,
+) -> Result<(LlamaChatTemplate, ChatTemplateSource), LLMError> {
+    match cfg.chat_template.as_deref() {
+        Some(inline) if is_inline_template(inline) => {
+            Ok((inline_chat_template(inline)?, ChatTemplateSource::Inline))
+        }
+        Some(name) => model
+            .chat_template(Some(name))
+            .map(|t| (t, ChatTemplateSource::Named(name.to_string())))
+            .map_err(|e| {
+                LLMError::ProviderError(format!("Failed to get chat template '{name}': {e}"))
+            }),
+        None => model
+            .chat_template(None)
+            .map(|t| (t, ChatTemplateSource::ModelDefault))
+            .map_err(|e| LLMError::ProviderError(format!("Failed to get chat template: {e}"))),
+    }
+}
+
+/// Resolve the chat template, falling back to ChatML when the model has none.
+pub(crate) fn resolve_chat_template_or_chatml(
+    model: &LlamaModel,
+    cfg: &LlamaCppConfig,
+) -> Result<(LlamaChatTemplate, ChatTemplateSource), LLMError> {
+    match resolve_chat_template(model, cfg) {
+        Ok(resolved) => Ok(resolved),
+        Err(e) => {
+            log::debug!("{e}; falling back to chatml");
+            let chatml = LlamaChatTemplate::new("chatml").map_err(|e| {
+                LLMError::ProviderError(format!("Failed to get chat template: {e}"))
+            })?;
+            Ok((chatml, ChatTemplateSource::ChatMlFallback))
+        }
+    }
+}
+
 pub(crate) fn apply_template_for_thinking(
     model: &Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
@@ -103,13 +176,18 @@ fn render_template(
         enable_thinking,
         cfg.enable_thinking
     );
-    let add_generation_prompt = messages.last().map_or(true, |msg| {
-        msg.role == querymt::chat::ChatRole::User
-            || msg
-                .content
-                .iter()
-                .any(|block| matches!(block, querymt::chat::Content::ToolResult { .. }))
-    });
+    // System messages are hoisted out of the conversation, so they don't
+    // decide whose turn it is.
+    let add_generation_prompt = messages
+        .iter()
+        .rfind(|m| !m.is_system())
+        .map_or(true, |msg| {
+            msg.role == querymt::chat::ChatRole::User
+                || msg
+                    .content
+                    .iter()
+                    .any(|block| matches!(block, querymt::chat::Content::ToolResult { .. }))
+        });
 
     let prompt = tmpl
         .render(minijinja::context! {
@@ -168,7 +246,13 @@ fn select_template(
             with_tools,
             configured.len()
         );
-        return Ok(configured.to_string());
+        if is_inline_template(configured) {
+            return Ok(configured.to_string());
+        }
+        let (template, _) = resolve_chat_template(model, cfg)?;
+        return template
+            .to_string()
+            .map_err(|e| LLMError::ProviderError(format!("Invalid chat template: {e}")));
     }
 
     if with_tools {
@@ -255,3 +339,23 @@ fn known_preserved_tokens() -> Vec<String> {
 fn known_stop_sequences() -> Vec<String> {
     Vec::new()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_inline_templates() {
+        assert!(is_inline_template("{{ messages[0].content }}"));
+        assert!(is_inline_template("{% for m in messages %}{% endfor %}"));
+        assert!(!is_inline_template("chatml"));
+        assert!(!is_inline_template("tool_use"));
+    }
+
+    #[test]
+    fn inline_template_is_used_verbatim() {
+        let source = "{% for m in messages %}<|{{ m.role }}|>{{ m.content }}{% endfor %}";
+        let template = inline_chat_template(source).unwrap();
+        assert_eq!(template.to_string().unwrap(), source);
+    }
+}
@@ -0,0 +1,275 @@
+//! Prompt-based tool calling for chat templates that reject tools.
+//!
+//! With [`LlamaCppConfig::tool_fallback`] enabled, a template that fails to
+//! render with tools is rendered without them instead: the tool definitions
+//! are described in a system message, tool calls and results in the history
+//! are flattened to text, and tool calls are recovered from JSON objects in
+//! the model's output.
+
+use crate::chat_format::parse_json_tool_call;
+use crate::config::LlamaCppConfig;
+use crate::context::LoraAdapters;
+use crate::messages::normalize_messages_to_text;
+use crate::multimodal::MultimodalContext;
+use crate::prefill::PrefillCache;
+use crate::response::LlamaCppChatResponse;
+use crate::tools::{apply_template_for_thinking, generate_with_tools, parse_tool_response};
+use llama_cpp_2::model::LlamaModel;
+use llama_cpp_2::mtmd::MtmdBitmap;
+use querymt::ToolCall;
+use querymt::chat::{ChatMessage, Content, FinishReason, StreamChunk, Tool};
+use querymt::error::LLMError;
+use std::sync::Arc;
+
+/// `messages` with the tool definitions added to the system message and
+/// tool blocks rendered as text.
+///
+/// Many templates only accept a single, leading system message, so the
+/// definitions are appended to an existing one rather than prepended.
+pub(crate) fn fallback_messages(
+    messages: &[ChatMessage],
+    tools: &[Tool],
+) -> Result<Vec<ChatMessage>, LLMError> {
+    let definitions = serde_json::to_string_pretty(tools)
+        .map_err(|e| LLMError::ProviderError(format!("Failed to serialize tools: {e}")))?;
+    let instructions = format!(
+        "You can call the following tools:\n\n{definitions}\n\n\
+         To call a tool, reply with a JSON object of the form \
+         {{\"name\": \"<tool name>\", \"arguments\": {{...}}}} and nothing else. \
+         Use one JSON object per call. If no tool is needed, answer normally."
+    );
+
+    let mut out = normalize_messages_to_text(messages);
+    match out.first_mut() {
+        Some(system) if system.is_system() => {
+            system
+                .content
+                .push(Content::text(format!("\n\n{instructions}")));
+        }
+        _ => out.insert(0, ChatMessage::system().text(instructions).build()),
+    }
+    Ok(out)
+}
+
+/// Pull calls to `tools` out of `text`, given as JSON objects with a `name`
+/// and `arguments`, optionally in Markdown code fences.
+///
+/// Returns the remaining text and the calls found.
+pub(crate) fn extract_json_tool_calls(text: &str, tools: &[Tool]) -> (String, Vec<ToolCall>) {
+    let mut calls = Vec::new();
+    let mut content = String::new();
+    let mut rest = 0;
+    for (start, end) in top_level_objects(text) {
+        let Some(call) = parse_json_tool_call(&text[start..end]) else {
+            continue;
+        };
+        if !tools.iter().any(|t| t.function.name == call.function.name) {
+            continue;
+        }
+        content.push_str(&text[rest..start]);
+        rest = end;
+        calls.push(call);
+    }
+    content.push_str(&text[rest..]);
+
+    let content = content
+        .lines()
+        .filter(|line| !matches!(line.trim(), "```" | "```json"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    (content.trim().to_string(), calls)
+}
+
+/// Byte ranges of the balanced, top-level `{...}` spans in `text`.
+fn top_level_objects(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' if depth > 0 => in_string = true,
+            '{' => {
+                if depth == 0 {
+                    start = i;
+                }
+                depth += 1;
+            }
+            '}' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    spans.push((start, i + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// Run a tool turn without the template's tool support.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn chat_with_fallback(
+    model: &Arc<LlamaModel>,
+    cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
+    messages: &[ChatMessage],
+    tools: &[Tool],
+    max_tokens: u32,
+    mm_ctx: Option<&MultimodalContext>,
+    bitmaps: &[MtmdBitmap],
+    prefill: Option<&PrefillCache>,
+) -> Result<LlamaCppChatResponse, LLMError> {
+    let messages = fallback_messages(messages, tools)?;
+    let media_marker = mm_ctx.map(|m| m.marker());
+    let template_result = apply_template_for_thinking(model, cfg, &messages, media_marker)?;
+    let generated = generate_with_tools(
+        model,
+        cfg,
+        lora,
+        &template_result,
+        max_tokens,
+        None,
+        mm_ctx,
+        bitmaps,
+        prefill,
+    )?;
+    let (content, thinking, tool_calls, _) =
+        parse_tool_response(&template_result, &generated.text)?;
+
+    // Models sometimes use their native tool-call tags anyway.
+    let (text, tool_calls) = match tool_calls {
+        Some(calls) => (content, calls),
+        None => extract_json_tool_calls(&content, tools),
+    };
+    let finish_reason = if tool_calls.is_empty() {
+        FinishReason::Stop
+    } else {
+        FinishReason::ToolCalls
+    };
+    Ok(LlamaCppChatResponse {
+        text,
+        thinking,
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        finish_reason,
+        usage: generated.usage,
+    })
+}
+
+/// The stream chunks equivalent to a complete `response`.
+pub(crate) fn response_chunks(response: LlamaCppChatResponse) -> Vec<StreamChunk> {
+    let mut chunks = Vec::new();
+    if let Some(thinking) = response.thinking {
+        chunks.push(StreamChunk::Thinking(thinking));
+    }
+    if !response.text.is_empty() {
+        chunks.push(StreamChunk::Text(response.text));
+    }
+    for (index, tool_call) in response.tool_calls.into_iter().flatten().enumerate() {
+        chunks.push(StreamChunk::ToolUseComplete { index, tool_call });
+    }
+    chunks.push(StreamChunk::Usage(response.usage));
+    chunks.push(StreamChunk::Done {
+        finish_reason: response.finish_reason,
+    });
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use querymt::chat::FunctionTool;
+
+    fn weather_tool() -> Tool {
+        Tool {
+            tool_type: "function".to_string(),
+            function: FunctionTool {
+                name: "get_weather".to_string(),
+                description: "Current weather for a city".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"]
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn fallback_messages_describe_tools_and_flatten_history() {
+        let messages = vec![
+            ChatMessage::user().text("Weather in Paris?").build(),
+            ChatMessage::assistant()
+                .tool_use(
+                    "call_1",
+                    "get_weather",
+                    serde_json::json!({ "city": "Paris" }),
+                )
+                .build(),
+        ];
+        let out = fallback_messages(&messages, &[weather_tool()]).unwrap();
+
+        assert_eq!(out.len(), 3);
+        assert!(out[0].is_system());
+        assert!(out[0].text().contains("\"get_weather\""));
+        assert!(
+            out[2]
+                .content
+                .iter()
+                .all(|b| matches!(b, Content::Text { .. }))
+        );
+        assert!(out[2].text().contains("[ToolUse: get_weather"));
+    }
+
+    #[test]
+    fn fallback_messages_extend_an_existing_system_message() {
+        let messages = vec![
+            ChatMessage::system().text("You are terse.").build(),
+            ChatMessage::user().text("Weather in Paris?").build(),
+        ];
+        let out = fallback_messages(&messages, &[weather_tool()]).unwrap();
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out.iter().filter(|m| m.is_system()).count(), 1);
+        let system = out[0].text();
+        assert!(system.starts_with("You are terse.\n\n"), "{system}");
+        assert!(system.contains("\"get_weather\""), "{system}");
+    }
+
+    #[test]
+    fn extracts_fenced_json_tool_call() {
+        let text = "Let me check.\n```json\n{\"name\": \"get_weather\", \"arguments\": {\"city\": \"Paris\"}}\n```";
+        let (content, calls) = extract_json_tool_calls(text, &[weather_tool()]);
+
+        assert_eq!(content, "Let me check.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.name, "get_weather");
+        let args: serde_json::Value = serde_json::from_str(&calls[0].function.arguments).unwrap();
+        assert_eq!(args["city"], "Paris");
+    }
+
+    #[test]
+    fn ignores_json_that_is_not_a_known_tool_call() {
+        let text = r#"The payload is {"name": "rm_rf", "arguments": {}} and {"city": "Paris"}."#;
+        let (content, calls) = extract_json_tool_calls(text, &[weather_tool()]);
+
+        assert!(calls.is_empty());
+        assert_eq!(content, text);
+    }
+
+    #[test]
+    fn finds_objects_with_braces_in_strings() {
+        let text = r#"x {"a": "}{", "b": {"c": 1}} y"#;
+        assert_eq!(top_level_objects(text), vec![(2, 28)]);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::chat_format::parse_assistant_format_with_state;
 use crate::common_chat::ChatTemplateResult;
 use crate::config::LlamaCppConfig;
+use crate::context::LoraAdapters;
+use crate::generation::GenerationDeadline;
 use crate::multimodal::MultimodalContext;
+use crate::prefill::{PrefillCache, PrefillSnapshot};
 use crate::response::GeneratedText;
 use crate::tools::prefill::prefill_for_tool_generation;
 use crate::tools::sampler::{SamplingParams, build_tool_sampler};
@@ -14,17 +17,31 @@ use std::collections::HashSet;
 use std::sync::Arc;
 
 /// Generate text with grammar-constrained sampling for tool calls.
+///
+/// `prefill` is consulted for a cached prompt prefix and, with
+/// `kv_cache_reuse` enabled, updated with this turn's context state.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_with_tools(
     model: &Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
     result: &ChatTemplateResult,
     max_tokens: u32,
     temperature: Option<f32>,
     mm_ctx: Option<&MultimodalContext>,
     bitmaps: &[MtmdBitmap],
+    prefill: Option<&PrefillCache>,
 ) -> Result<GeneratedText, LLMError> {
-    let mut state =
-        prefill_for_tool_generation(model, cfg, &result.prompt, max_tokens, mm_ctx, bitmaps)?;
+    let mut state = prefill_for_tool_generation(
+        model,
+        cfg,
+        lora,
+        &result.prompt,
+        max_tokens,
+        mm_ctx,
+        bitmaps,
+        prefill,
+    )?;
 
     log::debug!(
         "Generating with tools: input_tokens={}, max_tokens={}, has_multimodal={}",
@@ -73,7 +90,9 @@ pub(crate) fn generate_with_tools(
         max_tokens
     );
 
+    let deadline = GenerationDeadline::start(cfg);
     while state.n_cur < state.n_len_total {
+        deadline.check()?;
         let token = sampler.sample(&state.ctx, batch.n_tokens() - 1);
         if model.is_eog_token(token) {
             eog_hit = true;
@@ -111,6 +130,9 @@ pub(crate) fn generate_with_tools(
         }
 
         batch.clear();
+        if !state.tokens.is_empty() {
+            state.tokens.push(token);
+        }
         batch
             .add(token, state.n_cur, &[0], true)
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
@@ -123,6 +145,14 @@ pub(crate) fn generate_with_tools(
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
     }
 
+    if cfg.kv_cache_reuse.unwrap_or(false)
+        && cfg.n_ctx.is_some()
+        && let Some(cache) = prefill
+        && !state.tokens.is_empty()
+    {
+        PrefillSnapshot::store(cache, &state.ctx, std::mem::take(&mut state.tokens));
+    }
+
     // Trim matched stop sequences
     for stop in &result.additional_stops {
         if !stop.is_empty() && output.ends_with(stop) {
@@ -154,7 +184,7 @@ pub(crate) fn generate_with_tools(
         usage: Usage {
             input_tokens: state.input_tokens,
             output_tokens,
-            cache_read: 0,
+            cache_read: state.reused_tokens,
             cache_write: 0,
             reasoning_tokens: 0,
         },
@@ -177,7 +207,7 @@ pub(crate) fn parse_tool_response(
     LLMError,
 > {
     log::debug!("Parsing tool response: text_len={}", text.len());
-    log::debug!("Raw generated text: {}", text);
+    log::debug!("Raw generated text: {}", querymt::redact::redact_text(text));
 
     extract_parsed_response(text, result.reasoning_format, result.starts_in_thinking)
 }
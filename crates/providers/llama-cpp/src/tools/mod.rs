@@ -1,3 +1,4 @@
+pub(crate) mod fallback;
 pub(crate) mod generation;
 pub(crate) mod prefill;
 pub(crate) mod sampler;
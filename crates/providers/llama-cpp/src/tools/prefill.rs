@@ -1,14 +1,16 @@
 use crate::backend::llama_backend;
 use crate::config::LlamaCppConfig;
 use crate::context::{
-    apply_context_params, estimate_context_memory, resolve_n_batch, resolve_n_ubatch,
+    LoraAdapters, apply_context_params, estimate_context_memory, resolve_n_batch, resolve_n_ubatch,
 };
 use crate::multimodal::MultimodalContext;
+use crate::prefill::{PrefillCache, PrefillSnapshot};
 use llama_cpp_2::context::LlamaContext;
 use llama_cpp_2::context::params::LlamaContextParams;
 use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::{AddBos, LlamaModel};
 use llama_cpp_2::mtmd::{MtmdBitmap, MtmdInputChunkType, MtmdInputText};
+use llama_cpp_2::token::LlamaToken;
 use querymt::error::LLMError;
 use std::num::NonZeroU32;
 use std::sync::Arc;
@@ -16,6 +18,10 @@ use std::sync::Arc;
 pub(crate) struct ToolPrefillState<'a> {
     pub(crate) ctx: LlamaContext<'a>,
     pub(crate) input_tokens: u32,
+    /// Prompt tokens restored from a cached context state.
+    pub(crate) reused_tokens: u32,
+    /// Tokens in the KV cache, for text-only prompts.
+    pub(crate) tokens: Vec<LlamaToken>,
     pub(crate) n_cur: i32,
     pub(crate) n_len_total: i32,
     pub(crate) n_batch: usize,
@@ -25,13 +31,18 @@ pub(crate) struct ToolPrefillState<'a> {
 ///
 /// This helper centralizes prompt prefill so both sync and streaming tool paths
 /// share identical context sizing and multimodal behavior.
+///
+/// Text-only prompts restore their longest prefix cached in `cache`. That
+/// requires a fixed `n_ctx`, since the context is otherwise sized per prompt.
 pub(crate) fn prefill_for_tool_generation<'a>(
     model: &'a Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
     prompt: &str,
     max_tokens: u32,
     mm_ctx: Option<&MultimodalContext>,
     bitmaps: &[MtmdBitmap],
+    cache: Option<&PrefillCache>,
 ) -> Result<ToolPrefillState<'a>, LLMError> {
     if !bitmaps.is_empty() && mm_ctx.is_none() {
         return Err(LLMError::InvalidRequest(
@@ -89,6 +100,8 @@ pub(crate) fn prefill_for_tool_generation<'a>(
                 est.summary()
             ))
         })?;
+        drop(backend);
+        lora.apply(&ctx)?;
 
         let n_ctx_total = ctx.n_ctx() as i32;
         let n_len_total = input_tokens as i32 + max_tokens as i32;
@@ -122,6 +135,8 @@ pub(crate) fn prefill_for_tool_generation<'a>(
         return Ok(ToolPrefillState {
             ctx,
             input_tokens,
+            reused_tokens: 0,
+            tokens: Vec::new(),
             n_cur: n_past,
             n_len_total,
             n_batch: n_batch as usize,
@@ -180,6 +195,8 @@ pub(crate) fn prefill_for_tool_generation<'a>(
             est.summary()
         ))
     })?;
+    drop(backend);
+    lora.apply(&ctx)?;
 
     let n_ctx_total = ctx.n_ctx() as i32;
     let n_len_total = tokens.len() as i32 + max_tokens as i32;
@@ -189,9 +206,16 @@ pub(crate) fn prefill_for_tool_generation<'a>(
         )));
     }
 
+    let reused_tokens = match cache {
+        Some(cache) if cfg.n_ctx.is_some() => {
+            PrefillSnapshot::restore_from(cache, &mut ctx, &tokens)?
+        }
+        _ => 0,
+    };
+
     let mut batch = LlamaBatch::new(n_batch as usize, 1);
     let last_index = tokens.len().saturating_sub(1);
-    for chunk_start in (0..tokens.len()).step_by(n_batch as usize) {
+    for chunk_start in (reused_tokens..tokens.len()).step_by(n_batch as usize) {
         batch.clear();
         let chunk_end = (chunk_start + n_batch as usize).min(tokens.len());
         for i in chunk_start..chunk_end {
@@ -216,7 +240,9 @@ pub(crate) fn prefill_for_tool_generation<'a>(
     Ok(ToolPrefillState {
         ctx,
         input_tokens,
+        reused_tokens: reused_tokens as u32,
         n_cur: tokens.len() as i32,
+        tokens,
         n_len_total,
         n_batch: n_batch as usize,
     })
@@ -21,6 +21,8 @@ pub(crate) struct SamplingParams {
     pub presence_penalty: Option<f32>,
     pub frequency_penalty: Option<f32>,
     pub penalty_last_n: Option<i32>,
+    pub mirostat_tau: Option<f32>,
+    pub mirostat_eta: Option<f32>,
     pub seed: u32,
 }
 
@@ -37,6 +39,8 @@ impl SamplingParams {
             presence_penalty: cfg.presence_penalty,
             frequency_penalty: cfg.frequency_penalty,
             penalty_last_n: cfg.penalty_last_n,
+            mirostat_tau: cfg.mirostat_tau,
+            mirostat_eta: cfg.mirostat_eta,
             seed: cfg.seed.unwrap_or(1234),
         }
     }
@@ -54,6 +58,12 @@ impl SamplingParams {
             || self.repeat_penalty.is_some()
             || self.presence_penalty.is_some()
             || self.frequency_penalty.is_some()
+            || self.uses_mirostat()
+    }
+
+    /// Returns true when mirostat v2 sampling is enabled.
+    pub(crate) fn uses_mirostat(&self) -> bool {
+        self.mirostat_tau.is_some() || self.mirostat_eta.is_some()
     }
 }
 
@@ -166,42 +176,139 @@ fn regex_escape(value: &str) -> String {
     escaped
 }
 
-/// Build a standard sampler without grammar constraints.
-pub(crate) fn build_standard_sampler(params: &SamplingParams) -> LlamaSampler {
-    let mut samplers = Vec::new();
+/// One stage of the standard sampler chain, in application order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum SamplerStage {
+    Penalties {
+        last_n: i32,
+        repeat: f32,
+        frequency: f32,
+        presence: f32,
+    },
+    TopK(i32),
+    TopP(f32),
+    MinP(f32),
+    Temp(f32),
+    MirostatV2 {
+        seed: u32,
+        tau: f32,
+        eta: f32,
+    },
+    Dist(u32),
+    Greedy,
+}
+
+impl SamplerStage {
+    fn build(self) -> LlamaSampler {
+        match self {
+            SamplerStage::Penalties {
+                last_n,
+                repeat,
+                frequency,
+                presence,
+            } => LlamaSampler::penalties(last_n, repeat, frequency, presence),
+            SamplerStage::TopK(k) => LlamaSampler::top_k(k),
+            SamplerStage::TopP(p) => LlamaSampler::top_p(p, 1),
+            SamplerStage::MinP(p) => LlamaSampler::min_p(p, 1),
+            SamplerStage::Temp(t) => LlamaSampler::temp(t),
+            SamplerStage::MirostatV2 { seed, tau, eta } => {
+                LlamaSampler::mirostat_v2(seed, tau, eta)
+            }
+            SamplerStage::Dist(seed) => LlamaSampler::dist(seed),
+            SamplerStage::Greedy => LlamaSampler::greedy(),
+        }
+    }
+}
+
+/// Resolve the standard sampler chain for `params`.
+///
+/// Order follows llama.cpp: penalties, then truncation (top-k, top-p, min-p),
+/// then temperature and the final token selector. Mirostat v2 is itself a
+/// selector that adapts truncation on the fly, so the truncation samplers are
+/// skipped when it is enabled.
+pub(crate) fn sampler_stages(params: &SamplingParams) -> Vec<SamplerStage> {
+    let mut stages = Vec::new();
 
     // Penalties first — they modify logits before temperature/top-p sampling.
     if params.repeat_penalty.is_some()
         || params.presence_penalty.is_some()
         || params.frequency_penalty.is_some()
     {
-        samplers.push(LlamaSampler::penalties(
-            params.penalty_last_n.unwrap_or(64),
-            params.repeat_penalty.unwrap_or(1.0),
-            params.frequency_penalty.unwrap_or(0.0),
-            params.presence_penalty.unwrap_or(0.0),
-        ));
+        stages.push(SamplerStage::Penalties {
+            last_n: params.penalty_last_n.unwrap_or(64),
+            repeat: params.repeat_penalty.unwrap_or(1.0),
+            frequency: params.frequency_penalty.unwrap_or(0.0),
+            presence: params.presence_penalty.unwrap_or(0.0),
+        });
+    }
+
+    if params.uses_mirostat() {
+        if params.top_k.is_some() || params.top_p.is_some() || params.min_p.is_some() {
+            log::warn!("mirostat is enabled; ignoring top_k, top_p and min_p");
+        }
+        if let Some(t) = params.temperature.filter(|t| *t > 0.0) {
+            stages.push(SamplerStage::Temp(t));
+        }
+        stages.push(SamplerStage::MirostatV2 {
+            seed: params.seed,
+            tau: params.mirostat_tau.unwrap_or(5.0),
+            eta: params.mirostat_eta.unwrap_or(0.1),
+        });
+        return stages;
     }
 
     if let Some(top_k) = params.top_k {
-        samplers.push(LlamaSampler::top_k(top_k as i32));
+        stages.push(SamplerStage::TopK(top_k as i32));
     }
     if let Some(top_p) = params.top_p {
-        samplers.push(LlamaSampler::top_p(top_p, 1));
+        stages.push(SamplerStage::TopP(top_p));
     }
     if let Some(min_p) = params.min_p {
-        samplers.push(LlamaSampler::min_p(min_p, 1));
+        stages.push(SamplerStage::MinP(min_p));
     }
 
     match params.temperature {
         Some(t) if t > 0.0 => {
-            samplers.push(LlamaSampler::temp(t));
-            samplers.push(LlamaSampler::dist(params.seed));
+            stages.push(SamplerStage::Temp(t));
+            stages.push(SamplerStage::Dist(params.seed));
         }
-        _ => samplers.push(LlamaSampler::greedy()),
+        // min-p only makes sense with stochastic selection; greedy would always
+        // pick the top token and make the filter a no-op.
+        None if params.min_p.is_some() => stages.push(SamplerStage::Dist(params.seed)),
+        _ => stages.push(SamplerStage::Greedy),
     }
 
-    LlamaSampler::chain_simple(samplers)
+    stages
+}
+
+/// Build a standard sampler without grammar constraints.
+pub(crate) fn build_standard_sampler(params: &SamplingParams) -> LlamaSampler {
+    LlamaSampler::chain_simple(sampler_stages(params).into_iter().map(SamplerStage::build))
+}
+
+/// Build the sampler for a configured GBNF grammar with a `root` rule.
+pub(crate) fn build_config_grammar(
+    model: &LlamaModel,
+    grammar: &str,
+) -> Result<LlamaSampler, LLMError> {
+    LlamaSampler::grammar(model, grammar, "root")
+        .map_err(|e| LLMError::InvalidRequest(format!("Invalid GBNF grammar: {e}")))
+}
+
+/// Build the sampler for plain chat and completion turns: the configured
+/// grammar, if any, followed by the standard chain.
+pub(crate) fn build_generation_sampler(
+    model: &LlamaModel,
+    cfg: &LlamaCppConfig,
+    params: &SamplingParams,
+) -> Result<LlamaSampler, LLMError> {
+    match &cfg.grammar {
+        Some(grammar) => Ok(LlamaSampler::chain_simple([
+            build_config_grammar(model, grammar)?,
+            build_standard_sampler(params),
+        ])),
+        None => Ok(build_standard_sampler(params)),
+    }
 }
 
 /// Conservative fallback used only when a model immediately emits EOG with the
@@ -214,3 +321,83 @@ pub(crate) fn build_fallback_sampler(seed: u32) -> LlamaSampler {
         LlamaSampler::dist(seed),
     ])
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(extra: serde_json::Value) -> LlamaCppConfig {
+        let mut value = serde_json::json!({ "model": "model.gguf" });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn repeat_penalty_adds_penalty_stage_first() {
+        let cfg = config(serde_json::json!({ "repeat_penalty": 1.1, "top_k": 40 }));
+        let stages = sampler_stages(&SamplingParams::from_config(&cfg, None));
+        assert_eq!(
+            stages,
+            vec![
+                SamplerStage::Penalties {
+                    last_n: 64,
+                    repeat: 1.1,
+                    frequency: 0.0,
+                    presence: 0.0,
+                },
+                SamplerStage::TopK(40),
+                SamplerStage::Greedy,
+            ]
+        );
+    }
+
+    #[test]
+    fn mirostat_replaces_truncation_samplers() {
+        let cfg = config(serde_json::json!({
+            "mirostat_tau": 4.0,
+            "top_p": 0.9,
+            "temperature": 0.7
+        }));
+        let params = SamplingParams::from_config(&cfg, None);
+        assert!(params.is_explicit());
+        assert_eq!(
+            sampler_stages(&params),
+            vec![
+                SamplerStage::Temp(0.7),
+                SamplerStage::MirostatV2 {
+                    seed: 1234,
+                    tau: 4.0,
+                    eta: 0.1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn min_p_alone_samples_from_distribution() {
+        let cfg = config(serde_json::json!({ "min_p": 0.05 }));
+        let stages = sampler_stages(&SamplingParams::from_config(&cfg, None));
+        assert_eq!(
+            stages,
+            vec![SamplerStage::MinP(0.05), SamplerStage::Dist(1234)]
+        );
+    }
+
+    #[test]
+    fn min_p_with_zero_temperature_stays_greedy() {
+        let cfg = config(serde_json::json!({ "min_p": 0.05, "temperature": 0.0 }));
+        let stages = sampler_stages(&SamplingParams::from_config(&cfg, None));
+        assert_eq!(stages, vec![SamplerStage::MinP(0.05), SamplerStage::Greedy]);
+    }
+
+    #[test]
+    fn no_options_samples_greedily() {
+        let cfg = config(serde_json::json!({}));
+        let params = SamplingParams::from_config(&cfg, None);
+        assert!(!params.is_explicit());
+        assert_eq!(sampler_stages(&params), vec![SamplerStage::Greedy]);
+    }
+}
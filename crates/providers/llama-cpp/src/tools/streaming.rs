@@ -1,7 +1,10 @@
-use crate::chat_format::ParsedDelta;
+use crate::chat_format::{ParsedDelta, count_malformed_tool_calls};
 use crate::common_chat::ChatTemplateResult;
 use crate::config::LlamaCppConfig;
+use crate::context::LoraAdapters;
+use crate::generation::GenerationDeadline;
 use crate::multimodal::MultimodalContext;
+use crate::prefill::{PrefillCache, PrefillSnapshot};
 use crate::tools::generation::parse_tool_response;
 use crate::tools::prefill::prefill_for_tool_generation;
 use crate::tools::sampler::{SamplingParams, build_tool_sampler};
@@ -10,24 +13,37 @@ use llama_cpp_2::llama_batch::LlamaBatch;
 use llama_cpp_2::model::{AddBos, LlamaModel};
 use llama_cpp_2::mtmd::MtmdBitmap;
 use querymt::Usage;
+use querymt::chat::json_repair::repair_tool_arguments;
 use querymt::error::LLMError;
 use std::collections::HashSet;
 use std::sync::Arc;
 
 /// Generate text with streaming and grammar-constrained sampling for tool calls.
 /// Returns (Usage, has_tool_calls) where has_tool_calls indicates if tool calls were made.
+/// `prefill` is used as in [`generate_with_tools`](crate::tools::generate_with_tools).
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_streaming_with_tools(
     model: &Arc<LlamaModel>,
     cfg: &LlamaCppConfig,
+    lora: &LoraAdapters,
     result: &ChatTemplateResult,
     max_tokens: u32,
     temperature: Option<f32>,
     tx: &mpsc::UnboundedSender<Result<querymt::chat::StreamChunk, LLMError>>,
     mm_ctx: Option<&MultimodalContext>,
     bitmaps: &[MtmdBitmap],
+    prefill: Option<&PrefillCache>,
 ) -> Result<(Usage, bool), LLMError> {
-    let mut state =
-        prefill_for_tool_generation(model, cfg, &result.prompt, max_tokens, mm_ctx, bitmaps)?;
+    let mut state = prefill_for_tool_generation(
+        model,
+        cfg,
+        lora,
+        &result.prompt,
+        max_tokens,
+        mm_ctx,
+        bitmaps,
+        prefill,
+    )?;
 
     log::debug!(
         "Streaming generation with tools: input_tokens={}, max_tokens={}, has_multimodal={}",
@@ -67,7 +83,13 @@ pub(crate) fn generate_streaming_with_tools(
     let mut generated_text = String::new();
     let mut decoder = encoding_rs::UTF_8.new_decoder();
 
+    let deadline = GenerationDeadline::start(cfg);
     while state.n_cur < state.n_len_total {
+        deadline.check()?;
+        // The stream was dropped, e.g. because the caller timed out.
+        if tx.is_closed() {
+            return Err(LLMError::Cancelled);
+        }
         let token = sampler.sample(&state.ctx, batch.n_tokens() - 1);
         if model.is_eog_token(token) {
             break;
@@ -115,6 +137,9 @@ pub(crate) fn generate_streaming_with_tools(
         }
 
         batch.clear();
+        if !state.tokens.is_empty() {
+            state.tokens.push(token);
+        }
         batch
             .add(token, state.n_cur, &[0], true)
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
@@ -127,6 +152,14 @@ pub(crate) fn generate_streaming_with_tools(
             .map_err(|e| LLMError::ProviderError(e.to_string()))?;
     }
 
+    if cfg.kv_cache_reuse.unwrap_or(false)
+        && cfg.n_ctx.is_some()
+        && let Some(cache) = prefill
+        && !state.tokens.is_empty()
+    {
+        PrefillSnapshot::store(cache, &state.ctx, std::mem::take(&mut state.tokens));
+    }
+
     for stop in &result.additional_stops {
         if !stop.is_empty() && generated_text.ends_with(stop) {
             let new_len = generated_text.len().saturating_sub(stop.len());
@@ -146,9 +179,36 @@ pub(crate) fn generate_streaming_with_tools(
         }
     }
 
-    let (content, _, tool_calls, _) = parse_tool_response(result, &generated_text)?;
+    // A failed parse should not throw away a completed generation: report it
+    // and fall back to the raw text.
+    let (content, tool_calls) = match parse_tool_response(result, &generated_text) {
+        Ok((content, _, tool_calls, _)) => (content, tool_calls),
+        Err(e) => {
+            log::warn!("Failed to parse tool response, returning raw text: {}", e);
+            let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Warning(format!(
+                "failed to parse tool calls: {e}"
+            ))));
+            (generated_text.trim().to_string(), None)
+        }
+    };
+
+    let malformed = count_malformed_tool_calls(
+        &generated_text,
+        result.reasoning_format,
+        result.starts_in_thinking,
+    );
+    if malformed > 0 {
+        log::warn!("Dropped {} malformed tool call(s)", malformed);
+        let _ = tx.unbounded_send(Ok(querymt::chat::StreamChunk::Warning(format!(
+            "dropped {malformed} malformed tool call(s)"
+        ))));
+    }
+
     let has_tool_calls = if let Some(calls) = tool_calls {
-        for (index, call) in calls.into_iter().enumerate() {
+        for (index, mut call) in calls.into_iter().enumerate() {
+            if cfg.repair_tool_json.unwrap_or(true) {
+                repair_tool_arguments(&mut call);
+            }
             if tx
                 .unbounded_send(Ok(querymt::chat::StreamChunk::ToolUseComplete {
                     index,
@@ -171,7 +231,7 @@ pub(crate) fn generate_streaming_with_tools(
         Usage {
             input_tokens: state.input_tokens,
             output_tokens,
-            cache_read: 0,
+            cache_read: state.reused_tokens,
             cache_write: 0,
             reasoning_tokens: 0,
         },
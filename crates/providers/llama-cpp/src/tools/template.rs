@@ -2,7 +2,8 @@ use crate::common_chat::ChatTemplateResult;
 use crate::config::LlamaCppConfig;
 use crate::messages;
 use llama_cpp_2::common_chat::{CommonChatParams, CommonReasoningFormat};
-use llama_cpp_2::model::{LlamaChatTemplate, LlamaModel};
+use crate::template::resolve_chat_template_or_chatml;
+use llama_cpp_2::model::LlamaModel;
 use querymt::chat::{ChatMessage, Tool};
 use querymt::error::LLMError;
 use std::sync::Arc;
@@ -93,10 +94,7 @@ pub(crate) fn apply_template_for_thinking(
         .and_then(|v| serde_json::to_string(v).ok());
     let has_schema = json_schema_str.is_some();
 
-    let template = model
-        .chat_template(cfg.chat_template.as_deref())
-        .or_else(|_| LlamaChatTemplate::new("chatml"))
-        .map_err(|e| LLMError::ProviderError(format!("Failed to get chat template: {}", e)))?;
+    let (template, _) = resolve_chat_template_or_chatml(model, cfg)?;
 
     let mut params = CommonChatParams::new(&messages_json);
     params.json_schema = json_schema_str.as_deref();
@@ -136,7 +134,10 @@ pub(crate) fn apply_template_with_tools(
         messages.len(),
         tools.len()
     );
-    log::debug!("Messages JSON: {}", messages_json);
+    log::debug!(
+        "Messages JSON: {}",
+        querymt::redact::redact_text(&messages_json)
+    );
     log::debug!("Tools JSON: {}", tools_json);
 
     let json_schema_str = cfg
@@ -146,10 +147,7 @@ pub(crate) fn apply_template_with_tools(
         .and_then(|v| serde_json::to_string(v).ok());
     let has_schema = json_schema_str.is_some();
 
-    let template = model
-        .chat_template(cfg.chat_template.as_deref())
-        .or_else(|_| LlamaChatTemplate::new("chatml"))
-        .map_err(|e| LLMError::ProviderError(format!("Failed to get chat template: {}", e)))?;
+    let (template, _) = resolve_chat_template_or_chatml(model, cfg)?;
 
     let mut params = CommonChatParams::new(&messages_json);
     params.tools_json = Some(&tools_json);
@@ -0,0 +1,223 @@
+//! Integration tests for the completion API.
+//!
+//! These tests require an actual model and are skipped unless `TEST_MODEL`
+//! is set (local path or hf:<repo>:<file> or <repo>:<quant>).
+//!
+//! ```bash
+//! TEST_MODEL="unsloth/Qwen3-0.6B-GGUF:Q4_K_M" \
+//! cargo test --package qmt-llama-cpp --test completion_test -- --nocapture
+//! ```
+
+use qmt_llama_cpp::{LlamaCppConfig, create_provider};
+use querymt::completion::CompletionRequest;
+use serde_json::json;
+use std::env;
+
+const SKIP_MSG: &str = "Skipping — set TEST_MODEL to run";
+
+#[tokio::test]
+async fn test_complete_reports_usage() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "max_tokens": 16
+    }))
+    .expect("Failed to build config");
+    let provider = create_provider(cfg).expect("Failed to create provider");
+
+    let response = provider
+        .complete(&CompletionRequest::builder("The capital of France is").build())
+        .await
+        .expect("Completion failed");
+    let usage = response.usage.expect("Completion should report usage");
+
+    assert!(usage.input_tokens > 0, "Should have input tokens");
+    assert!(usage.output_tokens > 0, "Should have output tokens");
+}
+
+#[tokio::test]
+async fn test_stop_sequence_halts_generation() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "temperature": 0.0
+    }))
+    .expect("Failed to build config");
+    let provider = create_provider(cfg).expect("Failed to create provider");
+
+    let response = provider
+        .complete(
+            &CompletionRequest::builder("1, 2, 3, 4,")
+                .max_tokens(64)
+                .stop("7")
+                .build(),
+        )
+        .await
+        .expect("Completion failed");
+    let usage = response.usage.expect("Completion should report usage");
+
+    assert!(
+        !response.text.contains('7'),
+        "Stop sequence should be cut from the output, got {:?}",
+        response.text
+    );
+    assert!(
+        usage.output_tokens < 64,
+        "Generation should stop before max_tokens, got {} tokens",
+        usage.output_tokens
+    );
+}
+
+#[tokio::test]
+async fn test_stop_sequence_halts_streamed_generation() {
+    use futures::StreamExt;
+    use querymt::chat::StreamChunk;
+
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "temperature": 0.0
+    }))
+    .expect("Failed to build config");
+    let provider = create_provider(cfg).expect("Failed to create provider");
+
+    let req = CompletionRequest::builder("1, 2, 3, 4,")
+        .max_tokens(64)
+        .stop("7")
+        .build();
+    let streamed: Vec<_> = provider
+        .complete_stream(&req)
+        .await
+        .expect("Stream failed to start")
+        .collect()
+        .await;
+    let mut text = String::new();
+    let mut output_tokens = None;
+    for chunk in streamed {
+        match chunk.expect("Stream chunk failed") {
+            StreamChunk::Text(delta) => text.push_str(&delta),
+            StreamChunk::Usage(usage) => output_tokens = Some(usage.output_tokens),
+            _ => {}
+        }
+    }
+    let response = provider.complete(&req).await.expect("Completion failed");
+
+    assert_eq!(
+        text, response.text,
+        "Streaming should stop where complete does"
+    );
+    assert!(output_tokens.expect("Stream should report usage") < 64);
+}
+
+#[tokio::test]
+async fn test_complete_stream_yields_text_then_done() {
+    use futures::StreamExt;
+    use querymt::chat::StreamChunk;
+
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "max_tokens": 16
+    }))
+    .expect("Failed to build config");
+    let provider = create_provider(cfg).expect("Failed to create provider");
+
+    let mut stream = provider
+        .complete_stream(&CompletionRequest::builder("The capital of France is").build())
+        .await
+        .expect("Streaming completion failed");
+
+    let mut text = String::new();
+    let mut usage = None;
+    let mut done = false;
+    while let Some(chunk) = stream.next().await {
+        match chunk.expect("Stream error") {
+            StreamChunk::Text(delta) => text.push_str(&delta),
+            StreamChunk::Usage(u) => usage = Some(u),
+            StreamChunk::Done { .. } => done = true,
+            _ => {}
+        }
+    }
+
+    assert!(done, "Stream should end with Done");
+    assert!(!text.is_empty(), "Should stream some text");
+    let usage = usage.expect("Stream should report usage");
+    assert!(usage.output_tokens > 0, "Should have output tokens");
+}
+
+#[tokio::test]
+async fn test_grammar_constrains_output() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "max_tokens": 8,
+        "grammar": "root ::= \"yes\" | \"no\""
+    }))
+    .expect("Failed to build config");
+    let provider = create_provider(cfg).expect("Failed to create provider");
+
+    let response = provider
+        .complete(&CompletionRequest::builder("Is the sky blue? Answer:").build())
+        .await
+        .expect("Completion failed");
+
+    assert!(
+        response.text == "yes" || response.text == "no",
+        "Output should match the grammar, got {:?}",
+        response.text
+    );
+}
+
+#[test]
+fn test_invalid_grammar_is_rejected() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "grammar": "root ::= ("
+    }))
+    .expect("Failed to build config");
+
+    let err = create_provider(cfg)
+        .err()
+        .expect("Invalid grammar should fail");
+    assert!(
+        matches!(err, querymt::error::LLMError::InvalidRequest(_)),
+        "got {err:?}"
+    );
+}
@@ -0,0 +1,139 @@
+//! Integration tests for concurrent requests on one model.
+//!
+//! These tests require an actual model and are skipped unless `TEST_MODEL`
+//! is set (local path or hf:<repo>:<file> or <repo>:<quant>).
+//!
+//! ```bash
+//! TEST_MODEL="unsloth/Qwen3-0.6B-GGUF:Q4_K_M" \
+//! cargo test --package qmt-llama-cpp --test concurrency_test -- --nocapture
+//! ```
+
+use futures::StreamExt;
+use qmt_llama_cpp::{LlamaCppConfig, LlamaCppFactory, LlamaCppProvider};
+use querymt::completion::{CompletionProvider, CompletionRequest};
+use querymt::plugin::LLMProviderFactory;
+use serde_json::json;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SKIP_MSG: &str = "Skipping — set TEST_MODEL to run";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_concurrent_completions_both_finish() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 512,
+        "n_gpu_layers": 0,
+        "max_tokens": 16,
+        "temperature": 0.0
+    }))
+    .expect("Failed to build config");
+    let provider = Arc::new(LlamaCppProvider::new(cfg).expect("Failed to create provider"));
+
+    let complete = |prompt: &'static str| {
+        let provider = Arc::clone(&provider);
+        tokio::spawn(async move {
+            provider
+                .complete(&CompletionRequest::builder(prompt).build())
+                .await
+        })
+    };
+    let first = complete("The capital of France is");
+    let second = complete("The largest planet is");
+
+    let (first, second) = tokio::time::timeout(Duration::from_secs(300), async {
+        (first.await, second.await)
+    })
+    .await
+    .expect("Concurrent completions deadlocked");
+    let first = first.expect("Task panicked").expect("Completion failed");
+    let second = second.expect("Task panicked").expect("Completion failed");
+    assert!(!first.text.is_empty());
+    assert!(!second.text.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_providers_sharing_a_cached_model_share_its_queue() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let factory = LlamaCppFactory::new();
+    let cfg = json!({
+        "model": model,
+        "n_ctx": 512,
+        "n_gpu_layers": 0,
+        "max_tokens": 16,
+        "temperature": 0.0
+    })
+    .to_string();
+    let first: Arc<dyn querymt::LLMProvider> = factory
+        .from_config(&cfg)
+        .expect("Failed to create provider")
+        .into();
+    let second: Arc<dyn querymt::LLMProvider> = factory
+        .from_config(&cfg)
+        .expect("Failed to create provider")
+        .into();
+
+    let complete = |provider: Arc<dyn querymt::LLMProvider>, prompt: &'static str| {
+        tokio::spawn(async move {
+            provider
+                .complete(&CompletionRequest::builder(prompt).build())
+                .await
+        })
+    };
+    let first = complete(first, "The capital of France is");
+    let second = complete(second, "The largest planet is");
+
+    let (first, second) = tokio::time::timeout(Duration::from_secs(300), async {
+        (first.await, second.await)
+    })
+    .await
+    .expect("Completions on a shared model deadlocked");
+    let first = first.expect("Task panicked").expect("Completion failed");
+    let second = second.expect("Task panicked").expect("Completion failed");
+    assert!(!first.text.is_empty());
+    assert!(!second.text.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dropped_stream_releases_its_slot() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 512,
+        "n_gpu_layers": 0,
+        "max_tokens": 256,
+        "temperature": 0.0
+    }))
+    .expect("Failed to build config");
+    let provider = LlamaCppProvider::new(cfg).expect("Failed to create provider");
+
+    let mut stream = provider
+        .complete_stream(&CompletionRequest::builder("Once upon a time").build())
+        .await
+        .expect("Stream failed to start");
+    let _ = stream.next().await;
+    drop(stream);
+
+    let response = tokio::time::timeout(
+        Duration::from_secs(300),
+        provider.complete(&CompletionRequest::builder("The capital of France is").build()),
+    )
+    .await
+    .expect("Completion after a dropped stream deadlocked")
+    .expect("Completion failed");
+    assert!(!response.text.is_empty());
+}
@@ -0,0 +1,52 @@
+//! Integration tests for llama.cpp embeddings.
+//!
+//! These tests require an embedding model and are skipped unless
+//! `TEST_EMBEDDING_MODEL` is set (local path or hf:<repo>:<file> or
+//! <repo>:<quant>).
+//!
+//! ```bash
+//! TEST_EMBEDDING_MODEL="nomic-ai/nomic-embed-text-v1.5-GGUF:Q4_K_M" \
+//! cargo test --package qmt-llama-cpp --test embedding_test -- --nocapture
+//! ```
+
+use qmt_llama_cpp::{LlamaCppConfig, LlamaCppProvider};
+use querymt::embedding::EmbeddingProvider;
+use serde_json::json;
+use std::env;
+
+const SKIP_MSG: &str = "Skipping — set TEST_EMBEDDING_MODEL to run";
+
+#[tokio::test]
+async fn test_embed_returns_one_vector_per_input() {
+    let Ok(model) = env::var("TEST_EMBEDDING_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 512,
+        "n_gpu_layers": 0,
+        "pooling": "mean",
+        "normalize": true
+    }))
+    .expect("Failed to build config");
+    let provider = LlamaCppProvider::new(cfg).expect("Failed to create provider");
+
+    let embeddings = provider
+        .embed(vec![
+            "The cat sat on the mat.".to_string(),
+            "Rust is a systems programming language.".to_string(),
+        ])
+        .await
+        .expect("Embedding failed");
+
+    assert_eq!(embeddings.len(), 2);
+    assert!(!embeddings[0].is_empty());
+    assert_eq!(embeddings[0].len(), embeddings[1].len());
+    for embedding in &embeddings {
+        let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-3, "norm = {norm}");
+    }
+    assert_ne!(embeddings[0], embeddings[1]);
+}
@@ -2,7 +2,7 @@
 ///
 /// These tests verify that the modular structure compiles and the public API
 /// remains compatible.
-use qmt_llama_cpp::LlamaCppConfig;
+use qmt_llama_cpp::{LlamaCppConfig, LoraAdapter};
 use schemars::schema_for;
 
 #[test]
@@ -26,6 +26,8 @@ fn test_config_serialization() {
         presence_penalty: None,
         frequency_penalty: None,
         penalty_last_n: None,
+        mirostat_tau: None,
+        mirostat_eta: None,
         system: vec!["System prompt".to_string()],
         n_ctx: Some(2048),
         n_batch: Some(512),
@@ -38,6 +40,8 @@ fn test_config_serialization() {
         add_bos: Some(true),
         log: None,
         fast_download: Some(false),
+        download_timeout_seconds: None,
+        max_concurrent_requests: None,
         enable_thinking: Some(true),
         flash_attention: None,
         kv_cache_type_k: Some("q4_0".to_string()),
@@ -49,6 +53,15 @@ fn test_config_serialization() {
         n_ubatch: Some(4096),
         text_only: None,
         json_schema: None,
+        fim_tokens: None,
+        grammar: None,
+        lora_adapters: None,
+        timeout_seconds: None,
+        kv_cache_reuse: None,
+        repair_tool_json: None,
+        tool_fallback: None,
+        pooling: None,
+        normalize: None,
     };
 
     let json = serde_json::to_string(&config).expect("Failed to serialize config");
@@ -80,3 +93,30 @@ fn test_module_structure() {
 
     let _: Option<LlamaCppConfig> = None;
 }
+
+#[test]
+fn test_lora_adapter_config_parsing() {
+    let config: LlamaCppConfig = serde_json::from_value(serde_json::json!({
+        "model": "/path/to/model.gguf",
+        "lora_adapters": [
+            { "path": "/path/to/adapter.gguf", "scale": 0.5 },
+            { "path": "hf:org/repo:adapter-f16.gguf" }
+        ]
+    }))
+    .expect("Failed to parse config with LoRA adapters");
+
+    let adapters = config.lora_adapters.expect("adapters should be set");
+    assert_eq!(
+        adapters,
+        vec![
+            LoraAdapter {
+                path: "/path/to/adapter.gguf".to_string(),
+                scale: 0.5,
+            },
+            LoraAdapter {
+                path: "hf:org/repo:adapter-f16.gguf".to_string(),
+                scale: 1.0,
+            },
+        ]
+    );
+}
@@ -0,0 +1,48 @@
+//! Integration tests for the factory's shared model cache.
+//!
+//! These tests require an actual model and are skipped unless `TEST_MODEL`
+//! is set (local path or hf:<repo>:<file> or <repo>:<quant>).
+//!
+//! ```bash
+//! TEST_MODEL="unsloth/Qwen3-0.6B-GGUF:Q4_K_M" \
+//! cargo test --package qmt-llama-cpp --test model_cache_test -- --nocapture
+//! ```
+
+use qmt_llama_cpp::LlamaCppFactory;
+use querymt::plugin::LLMProviderFactory;
+use serde_json::json;
+use std::env;
+
+const SKIP_MSG: &str = "Skipping — set TEST_MODEL to run";
+
+#[test]
+fn test_evict_forces_reload() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let factory = LlamaCppFactory::new();
+    let cfg = json!({
+        "model": model,
+        "n_ctx": 512,
+        "n_gpu_layers": 0
+    })
+    .to_string();
+
+    let provider = factory
+        .from_config(&cfg)
+        .expect("Failed to create provider");
+    assert!(factory.is_cached(&model));
+    drop(provider);
+
+    assert!(factory.evict(&model));
+    assert!(!factory.is_cached(&model));
+    assert!(!factory.evict(&model), "Nothing left to evict");
+
+    let _provider = factory.from_config(&cfg).expect("Failed to reload model");
+    assert!(
+        factory.is_cached(&model),
+        "Reload should repopulate the cache"
+    );
+}
@@ -52,6 +52,8 @@ fn make_provider(model: String, mmproj_path: Option<String>) -> Box<dyn querymt:
         presence_penalty: None,
         frequency_penalty: None,
         penalty_last_n: None,
+        mirostat_tau: None,
+        mirostat_eta: None,
         system: vec![],
         n_batch: None,
         n_threads: None,
@@ -62,6 +64,8 @@ fn make_provider(model: String, mmproj_path: Option<String>) -> Box<dyn querymt:
         add_bos: None,
         log: None,
         fast_download: None,
+        download_timeout_seconds: None,
+        max_concurrent_requests: None,
         enable_thinking: None,
         flash_attention: None,
         kv_cache_type_k: None,
@@ -72,6 +76,15 @@ fn make_provider(model: String, mmproj_path: Option<String>) -> Box<dyn querymt:
         n_ubatch: None,
         text_only: None,
         json_schema: None,
+        fim_tokens: None,
+        grammar: None,
+        lora_adapters: None,
+        timeout_seconds: None,
+        kv_cache_reuse: None,
+        repair_tool_json: None,
+        tool_fallback: None,
+        pooling: None,
+        normalize: None,
     };
     create_provider(cfg).expect("Failed to create provider")
 }
@@ -299,6 +312,8 @@ fn test_config_with_multimodal_fields() {
         presence_penalty: None,
         frequency_penalty: None,
         penalty_last_n: None,
+        mirostat_tau: None,
+        mirostat_eta: None,
         system: vec![],
         n_batch: None,
         n_threads: None,
@@ -309,6 +324,8 @@ fn test_config_with_multimodal_fields() {
         add_bos: None,
         log: None,
         fast_download: None,
+        download_timeout_seconds: None,
+        max_concurrent_requests: None,
         enable_thinking: None,
         flash_attention: None,
         kv_cache_type_k: None,
@@ -316,6 +333,15 @@ fn test_config_with_multimodal_fields() {
         n_ubatch: None,
         text_only: None,
         json_schema: None,
+        fim_tokens: None,
+        grammar: None,
+        lora_adapters: None,
+        timeout_seconds: None,
+        kv_cache_reuse: None,
+        repair_tool_json: None,
+        tool_fallback: None,
+        pooling: None,
+        normalize: None,
     };
 
     let json = serde_json::to_string(&config).expect("serialize");
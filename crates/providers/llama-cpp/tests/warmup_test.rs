@@ -0,0 +1,103 @@
+//! Integration tests for prompt warmup and KV cache reuse across turns.
+//!
+//! These tests require an actual model and are skipped unless `TEST_MODEL`
+//! is set (local path or hf:<repo>:<file> or <repo>:<quant>).
+//!
+//! ```bash
+//! TEST_MODEL="unsloth/Qwen3-0.6B-GGUF:Q4_K_M" \
+//! cargo test --package qmt-llama-cpp --test warmup_test -- --nocapture
+//! ```
+
+use qmt_llama_cpp::{LlamaCppConfig, LlamaCppProvider};
+use querymt::chat::{ChatMessage, ChatProvider};
+use serde_json::json;
+use std::env;
+
+const SKIP_MSG: &str = "Skipping — set TEST_MODEL to run";
+
+#[tokio::test]
+async fn test_warmup_does_not_change_output() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg: LlamaCppConfig = serde_json::from_value(json!({
+        "model": model,
+        "n_ctx": 2048,
+        "n_gpu_layers": 0,
+        "max_tokens": 24,
+        "temperature": 0.0,
+        "seed": 42
+    }))
+    .expect("Failed to build config");
+    let provider = LlamaCppProvider::new(cfg).expect("Failed to create provider");
+
+    let history = vec![
+        ChatMessage::user()
+            .text("List three primary colors.")
+            .build(),
+    ];
+    let mut messages = history.clone();
+    messages.push(
+        ChatMessage::assistant()
+            .text("Red, yellow and blue.")
+            .build(),
+    );
+    messages.push(ChatMessage::user().text("Now list three planets.").build());
+
+    let cold = provider.chat(&messages).await.expect("Chat failed");
+
+    provider.warmup(&history).await.expect("Warmup failed");
+    let warm = provider.chat(&messages).await.expect("Chat failed");
+
+    assert_eq!(cold.text(), warm.text());
+    let usage = warm.usage().expect("Chat should report usage");
+    assert!(usage.cache_read > 0, "Warm chat should reuse the prefill");
+}
+
+#[tokio::test]
+async fn test_kv_cache_reuse_shortens_second_turn_prefill() {
+    let Ok(model) = env::var("TEST_MODEL") else {
+        println!("{}", SKIP_MSG);
+        return;
+    };
+
+    let cfg = |reuse: bool| -> LlamaCppConfig {
+        serde_json::from_value(json!({
+            "model": model,
+            "n_ctx": 2048,
+            "n_gpu_layers": 0,
+            "max_tokens": 24,
+            "temperature": 0.0,
+            "seed": 42,
+            "kv_cache_reuse": reuse
+        }))
+        .expect("Failed to build config")
+    };
+
+    let mut messages = vec![ChatMessage::user().text("Name a primary color.").build()];
+
+    let session = LlamaCppProvider::new(cfg(true)).expect("Failed to create provider");
+    let first = session.chat(&messages).await.expect("Chat failed");
+    messages.push(
+        ChatMessage::assistant()
+            .text(first.text().unwrap_or_default())
+            .build(),
+    );
+    messages.push(ChatMessage::user().text("Name another one.").build());
+
+    let warm = session.chat(&messages).await.expect("Chat failed");
+    let warm_usage = warm.usage().expect("Chat should report usage");
+
+    let cold_provider = LlamaCppProvider::new(cfg(false)).expect("Failed to create provider");
+    let cold = cold_provider.chat(&messages).await.expect("Chat failed");
+    let cold_usage = cold.usage().expect("Chat should report usage");
+
+    assert_eq!(warm_usage.input_tokens, cold_usage.input_tokens);
+    assert_eq!(cold_usage.cache_read, 0);
+    assert!(
+        warm_usage.input_tokens - warm_usage.cache_read < cold_usage.input_tokens,
+        "Second turn should decode fewer prompt tokens than a cold call"
+    );
+}
@@ -13,9 +13,9 @@ use querymt::{
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     handle_http_error,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -27,7 +27,10 @@ use url::Url;
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Mistral {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "Mistral::default_base_url")]
+    #[serde(
+        default = "Mistral::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: String,
     pub model: String,
@@ -47,6 +50,10 @@ pub struct Mistral {
     pub reasoning_effort: Option<querymt::chat::ReasoningEffort>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Sampling seed for best-effort reproducible output
+    pub seed: Option<u64>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -145,6 +152,18 @@ impl OpenAIProviderConfig for Mistral {
     fn json_schema(&self) -> Option<&StructuredOutputFormat> {
         self.json_schema.as_ref()
     }
+
+    /// Mistral names the sampling seed `random_seed`.
+    fn extra_body(&self) -> Option<serde_json::Map<String, Value>> {
+        let seed = self.seed?;
+        let mut map = serde_json::Map::new();
+        map.insert("random_seed".into(), seed.into());
+        Some(map)
+    }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for Mistral {
@@ -203,15 +222,12 @@ impl HTTPCompletionProvider for Mistral {
     fn parse_complete(&self, resp: Response<Vec<u8>>) -> Result<CompletionResponse, LLMError> {
         handle_http_error!(resp);
 
-        let json_resp: Result<MistralCompletionResponse, serde_json::Error> =
-            serde_json::from_slice(resp.body());
-
-        match json_resp {
-            Ok(completion_response) => Ok(CompletionResponse {
-                text: completion_response.choices[0].message.content.clone(), // FIXME
-            }),
-            Err(e) => Err(LLMError::JsonError(e)),
-        }
+        let completion_response: MistralCompletionResponse =
+            decode_json_response(resp.body(), "completion response")?;
+        Ok(CompletionResponse {
+            text: completion_response.choices[0].message.content.clone(), // FIXME
+            usage: None,
+        })
     }
 }
 
@@ -234,6 +250,19 @@ impl HTTPLLMProviderFactory for MistralFactory {
         "mistral"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: true,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("MISTRAL_API_KEY".into())
     }
@@ -241,7 +270,7 @@ impl HTTPLLMProviderFactory for MistralFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => Mistral::default_base_url(),
         };
         openai_list_models_request(&base_url, &cfg)
@@ -274,6 +303,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(MistralFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Mistral, MistralFactory};
@@ -285,3 +323,23 @@ mod extism_exports {
         name   = "mistral",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_request_includes_seed() {
+        let provider: Mistral = serde_json::from_value(serde_json::json!({
+            "api_key": "test-key",
+            "model": "mistral-small-latest",
+            "seed": 42
+        }))
+        .unwrap();
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["random_seed"], 42);
+        assert!(body.get("seed").is_none());
+    }
+}
@@ -11,7 +11,7 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -23,7 +23,10 @@ use url::Url;
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct MoonshotAI {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "MoonshotAI::default_base_url")]
+    #[serde(
+        default = "MoonshotAI::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: String,
     pub model: String,
@@ -41,6 +44,8 @@ pub struct MoonshotAI {
     pub frequency_penalty: Option<f32>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAIProviderConfig for MoonshotAI {
@@ -125,6 +130,10 @@ impl OpenAIProviderConfig for MoonshotAI {
 
         None
     }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for MoonshotAI {
@@ -180,6 +189,19 @@ impl HTTPLLMProviderFactory for MoonshotAIFactory {
         "moonshotai"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: false,
+            completion: false,
+            structured_output: true,
+            thinking: false,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("MOONSHOT_API_KEY".into())
     }
@@ -187,7 +209,7 @@ impl HTTPLLMProviderFactory for MoonshotAIFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => MoonshotAI::default_base_url(),
         };
         openai_list_models_request(&base_url, &cfg)
@@ -220,6 +242,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(MoonshotAIFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{MoonshotAI, MoonshotAIFactory};
@@ -71,7 +71,7 @@ impl ChatResponse for MistralChatResponse {
         self.usage.clone()
     }
     fn tool_calls(&self) -> Option<Vec<querymt::ToolCall>> {
-        self.tool_calls.clone()
+        querymt::chat::non_empty_tool_calls(self.tool_calls.clone())
     }
     fn thinking(&self) -> Option<String> {
         None
@@ -1,5 +1,5 @@
 use querymt::error::LLMError;
-use querymt::plugin::LLMProviderFactory;
+use querymt::plugin::{LLMProviderFactory, ProviderCapabilities};
 use schemars::schema_for;
 use serde_json::Value;
 
@@ -26,6 +26,19 @@ impl LLMProviderFactory for MistralRSFactory {
         "mistralrs"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: false,
+            thinking: false,
+        }
+    }
+
     fn config_schema(&self) -> String {
         let schema = schema_for!(MistralRSConfig);
         serde_json::to_string(&schema).expect("OpenRouter JSON Schema should always serialize")
@@ -66,3 +79,9 @@ pub extern "C" fn plugin_factory() -> *mut dyn LLMProviderFactory {
         model_cache: std::sync::Mutex::new(None),
     })) as *mut _
 }
+
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
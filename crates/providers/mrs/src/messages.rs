@@ -9,6 +9,7 @@ fn map_chat_role(role: &ChatRole) -> TextMessageRole {
     match role {
         ChatRole::User => TextMessageRole::User,
         ChatRole::Assistant => TextMessageRole::Assistant,
+        ChatRole::System => TextMessageRole::System,
     }
 }
 
@@ -70,12 +70,7 @@ async fn embedding_provider_requires_embedding_model() {
 #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
 async fn completion_provider_is_currently_unimplemented() {
     let provider = get_provider();
-    let dummy_req = CompletionRequest {
-        prompt: "test".into(),
-        max_tokens: None,
-        temperature: None,
-        suffix: None,
-    };
+    let dummy_req = CompletionRequest::new("test");
     let err = provider.complete(&dummy_req).await.unwrap_err();
     assert!(matches!(err, LLMError::NotImplemented(_)));
 }
@@ -8,13 +8,13 @@ use querymt::{
     FunctionCall, HTTPLLMProvider, ToolCall, Usage,
     chat::{
         ChatMessage, ChatResponse, ChatRole, Content, FinishReason, ReasoningEffort,
-        StructuredOutputFormat, Tool, http::HTTPChatProvider,
+        StructuredOutputFormat, Tool, http::HTTPChatProvider, system_prompts,
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     get_env_var, handle_http_error,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -37,7 +37,10 @@ pub fn url_schema(_gen: &mut SchemaGenerator) -> Schema {
 pub struct Ollama {
     // ===== Core Configuration =====
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "Ollama::default_base_url")]
+    #[serde(
+        default = "Ollama::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: Option<String>,
     pub model: String,
@@ -51,6 +54,10 @@ pub struct Ollama {
     pub system: Option<String>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Constrain output to valid JSON without a schema (`"format": "json"`).
+    /// Ignored when `json_schema` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_mode: Option<bool>,
     /// Available tools for function calling
     pub tools: Option<Vec<Tool>>,
 
@@ -131,6 +138,7 @@ struct OllamaChatRequest {
     stream: bool,
     think: bool,
     options: Option<OllamaOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     format: Option<OllamaResponseFormat>,
     #[serde(skip_serializing_if = "Option::is_none")]
     tools: Option<Vec<Tool>>,
@@ -286,7 +294,7 @@ impl ChatResponse for OllamaResponse {
 
     fn tool_calls(&self) -> Option<Vec<ToolCall>> {
         let msg = self.message.as_ref()?;
-        let calls = msg.tool_calls.as_ref()?;
+        let calls = msg.tool_calls.as_ref().filter(|calls| !calls.is_empty())?;
         Some(
             calls
                 .iter()
@@ -366,20 +374,15 @@ struct OllamaEmbeddingResponse {
     embeddings: Vec<Vec<f32>>,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
-#[serde(untagged)]
-enum OllamaResponseType {
-    #[serde(rename = "json")]
+/// Ollama's `format` field: either `"json"` or a full JSON schema object.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OllamaResponseFormat {
     Json,
+    #[serde(untagged)]
     StructuredOutput(Value),
 }
 
-#[derive(Deserialize, Debug, Serialize)]
-struct OllamaResponseFormat {
-    #[serde(flatten)]
-    format: OllamaResponseType,
-}
-
 #[derive(Deserialize, Debug)]
 struct OllamaToolCall {
     function: OllamaFunctionToolCall,
@@ -397,7 +400,7 @@ struct OllamaFunctionToolCall {
 impl Ollama {
     fn default_base_url() -> Url {
         let base_url = get_env_var!("OLLAMA_HOST").unwrap_or("http://localhost:11434".to_string());
-        Url::parse(&base_url).unwrap()
+        querymt::params::normalize_base_url(Url::parse(&base_url).unwrap())
     }
 
     /// Add `Authorization: Bearer` header if `api_key` is set.
@@ -446,10 +449,11 @@ impl HTTPChatProvider for Ollama {
     ) -> Result<Request<Vec<u8>>, LLMError> {
         let mut chat_messages: Vec<OllamaChatMessage> = vec![];
 
-        for msg in messages {
+        for msg in messages.iter().filter(|m| !m.is_system()) {
             let role = match msg.role {
-                ChatRole::User => "user",
                 ChatRole::Assistant => "assistant",
+                // System messages are hoisted to the top below.
+                ChatRole::User | ChatRole::System => "user",
             }
             .to_string();
 
@@ -511,7 +515,7 @@ impl HTTPChatProvider for Ollama {
                         role,
                         content: text,
                         images: None,
-                        name: None,
+                        name: msg.name.clone(),
                     });
                 }
                 continue;
@@ -526,30 +530,32 @@ impl HTTPChatProvider for Ollama {
                     } else {
                         Some(inline_images)
                     },
-                    name: None,
+                    name: msg.name.clone(),
                 });
             }
         }
 
-        if let Some(system) = &self.system {
-            chat_messages.insert(
-                0,
-                OllamaChatMessage {
-                    role: "system".to_string(),
-                    content: system.clone(),
-                    images: None,
-                    name: None,
-                },
-            );
-        }
-
-        // Ollama doesn't require the "name" field in the schema, so we just use the schema itself
-        let format = if let Some(schema) = &self.json_schema {
-            schema.schema.as_ref().map(|schema| OllamaResponseFormat {
-                format: OllamaResponseType::StructuredOutput(schema.clone()),
-            })
-        } else {
-            None
+        let system = self.system.iter().cloned().chain(system_prompts(messages));
+        chat_messages.splice(
+            0..0,
+            system.map(|content| OllamaChatMessage {
+                role: "system".to_string(),
+                content,
+                images: None,
+                name: None,
+            }),
+        );
+
+        // Ollama doesn't require the "name" field in the schema, so we just use the schema
+        // itself. A schema-less `json_schema` or `json_mode` asks for free-form JSON.
+        let format = match &self.json_schema {
+            Some(StructuredOutputFormat {
+                schema: Some(schema),
+                ..
+            }) => Some(OllamaResponseFormat::StructuredOutput(schema.clone())),
+            Some(_) => Some(OllamaResponseFormat::Json),
+            None if self.json_mode.unwrap_or(false) => Some(OllamaResponseFormat::Json),
+            None => None,
         };
 
         let req_body = OllamaChatRequest {
@@ -575,7 +581,7 @@ impl HTTPChatProvider for Ollama {
     fn parse_chat(&self, resp: Response<Vec<u8>>) -> Result<Box<dyn ChatResponse>, LLMError> {
         handle_http_error!(resp);
 
-        let json_resp: OllamaResponse = serde_json::from_slice(resp.body())?;
+        let json_resp: OllamaResponse = decode_json_response(resp.body(), "Ollama chat response")?;
         Ok(Box::new(json_resp))
     }
 }
@@ -603,11 +609,16 @@ impl HTTPCompletionProvider for Ollama {
     }
 
     fn parse_complete(&self, resp: Response<Vec<u8>>) -> Result<CompletionResponse, LLMError> {
-        let ollama_response: OllamaResponse = serde_json::from_slice(resp.body())?;
+        handle_http_error!(resp);
 
+        let ollama_response: OllamaResponse =
+            decode_json_response(resp.body(), "Ollama completion response")?;
+
+        let usage = ollama_response.usage();
         if let Some(prompt_response) = ollama_response.response {
             Ok(CompletionResponse {
                 text: prompt_response,
+                usage,
             })
         } else {
             Err(LLMError::ProviderError(
@@ -636,7 +647,10 @@ impl HTTPEmbeddingProvider for Ollama {
     }
 
     fn parse_embed(&self, resp: Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
-        let json_resp: OllamaEmbeddingResponse = serde_json::from_slice(resp.body())?;
+        handle_http_error!(resp);
+
+        let json_resp: OllamaEmbeddingResponse =
+            decode_json_response(resp.body(), "Ollama embedding response")?;
         Ok(json_resp.embeddings)
     }
 }
@@ -653,6 +667,19 @@ impl HTTPLLMProviderFactory for OllamaFactory {
         "ollama"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: true,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("OLLAMA_API_KEY".into())
     }
@@ -663,18 +690,17 @@ impl HTTPLLMProviderFactory for OllamaFactory {
 
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
-        let base = cfg
-            .get("base_url")
-            .and_then(Value::as_str)
-            .map(String::from)
-            .unwrap_or_else(|| Ollama::default_base_url().as_str().to_string());
+        let base = match cfg.get("base_url").and_then(Value::as_str) {
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
+            None => Ollama::default_base_url(),
+        };
         let api_key = cfg.get("api_key").and_then(Value::as_str);
 
-        let url: String = format!("{}/api/tags", base);
+        let url = base.join("api/tags")?;
         let mut builder = Request::builder()
             .method(Method::GET)
             .header(CONTENT_TYPE, "application/json")
-            .uri(url);
+            .uri(url.as_str());
         if let Some(key) = api_key {
             if !key.is_empty() {
                 builder = builder.header(AUTHORIZATION, format!("Bearer {}", key));
@@ -684,7 +710,7 @@ impl HTTPLLMProviderFactory for OllamaFactory {
     }
 
     fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
-        let resp_json: Value = serde_json::from_slice(resp.body())?;
+        let resp_json: Value = decode_json_response(resp.body(), "Ollama models response")?;
 
         let arr = resp_json
             .get("models")
@@ -722,6 +748,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(OllamaFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Ollama, OllamaFactory};
@@ -741,6 +776,16 @@ mod tests {
     use querymt::completion::http::HTTPCompletionProvider;
     use querymt::embedding::http::HTTPEmbeddingProvider;
 
+    #[test]
+    fn empty_tool_calls_are_none() {
+        let resp: OllamaResponse = serde_json::from_value(serde_json::json!({
+            "message": { "role": "assistant", "content": "hi", "tool_calls": [] },
+            "done": true
+        }))
+        .unwrap();
+        assert_eq!(resp.tool_calls(), None);
+    }
+
     #[test]
     fn factory_api_key_name_returns_ollama_api_key() {
         let factory = OllamaFactory;
@@ -757,6 +802,7 @@ mod tests {
             reasoning_effort: None,
             system: None,
             json_schema: None,
+            json_mode: None,
             tools: None,
             max_tokens: None,
             temperature: None,
@@ -795,6 +841,67 @@ mod tests {
         assert_eq!(auth.to_str().unwrap(), "Bearer test-key-123");
     }
 
+    fn chat_body(ollama: &Ollama) -> Value {
+        let req = ollama
+            .chat_request(&[], None)
+            .expect("chat_request should succeed");
+        serde_json::from_slice(req.body()).unwrap()
+    }
+
+    #[test]
+    fn chat_request_format_carries_json_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "answer": { "type": "string" } },
+            "required": ["answer"]
+        });
+        let mut ollama = test_ollama(None);
+        ollama.json_schema = Some(StructuredOutputFormat {
+            name: "answer".to_string(),
+            description: None,
+            schema: Some(schema.clone()),
+            strict: None,
+        });
+
+        assert_eq!(chat_body(&ollama)["format"], schema);
+    }
+
+    #[test]
+    fn chat_request_format_json_mode() {
+        let mut ollama = test_ollama(None);
+        ollama.json_mode = Some(true);
+
+        assert_eq!(chat_body(&ollama)["format"], "json");
+    }
+
+    #[test]
+    fn chat_request_hoists_system_messages() {
+        let mut ollama = test_ollama(None);
+        ollama.system = Some("Be brief.".to_string());
+        let messages = vec![
+            ChatMessage::user().text("hi").build(),
+            ChatMessage::system().text("Answer in French.").build(),
+        ];
+        let req = ollama.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        let roles: Vec<&str> = body["messages"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, ["system", "system", "user"]);
+        assert_eq!(body["messages"][0]["content"], "Be brief.");
+        assert_eq!(body["messages"][1]["content"], "Answer in French.");
+    }
+
+    #[test]
+    fn chat_request_omits_format_by_default() {
+        let body = chat_body(&test_ollama(None));
+        assert!(body.get("format").is_none());
+    }
+
     #[test]
     fn chat_request_omits_auth_when_no_api_key() {
         let ollama = test_ollama(None);
@@ -808,12 +915,7 @@ mod tests {
     fn complete_request_includes_bearer_when_api_key_set() {
         let ollama = test_ollama(Some("key-abc"));
         let req = ollama
-            .complete_request(&CompletionRequest {
-                prompt: "hello".to_string(),
-                suffix: None,
-                max_tokens: None,
-                temperature: None,
-            })
+            .complete_request(&CompletionRequest::new("hello"))
             .expect("complete_request should succeed");
         let auth = req
             .headers()
@@ -826,12 +928,7 @@ mod tests {
     fn complete_request_omits_auth_when_no_api_key() {
         let ollama = test_ollama(None);
         let req = ollama
-            .complete_request(&CompletionRequest {
-                prompt: "hello".to_string(),
-                suffix: None,
-                max_tokens: None,
-                temperature: None,
-            })
+            .complete_request(&CompletionRequest::new("hello"))
             .expect("complete_request should succeed");
         assert!(req.headers().get("authorization").is_none());
     }
@@ -1,4 +1,3 @@
-use either::*;
 use http::{
     Method, Request, Response,
     header::{AUTHORIZATION, CONTENT_TYPE},
@@ -6,18 +5,18 @@ use http::{
 use querymt::{
     FunctionCall, ToolCall, Usage,
     chat::{
-        ChatMessage, ChatResponse, ChatRole, Content, FinishReason, ReasoningEffort, StreamChunk,
-        StructuredOutputFormat, Tool, ToolChoice,
+        ChatMessage, ChatResponse, FinishReason, ReasoningEffort, StreamChunk,
+        StructuredOutputFormat, Tool, ToolChoice, non_empty_tool_calls,
     },
-    error::LLMError,
+    error::{LLMError, decode_json_response, response_format_error},
     handle_http_error,
     stt::{SttRequest, SttResponse},
     tts::{TtsRequest, TtsResponse},
 };
+use querymt_provider_common::openai_messages::{OpenAIMapOpts, to_openai_messages};
 use schemars::{Schema, SchemaGenerator, json_schema};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::borrow::Cow;
 use std::collections::HashMap;
 use url::Url;
 
@@ -78,58 +77,6 @@ pub fn url_schema(_gen: &mut SchemaGenerator) -> Schema {
     })
 }
 
-/// Individual message in an OpenAI chat conversation.
-#[derive(Serialize, Debug)]
-struct OpenAIChatMessage<'a> {
-    #[allow(dead_code)]
-    role: Cow<'a, str>,
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        with = "either::serde_untagged_optional"
-    )]
-    content: Option<Either<Vec<MessageContent<'a>>, Cow<'a, str>>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tool_calls: Option<Vec<OpenAIFunctionCall<'a>>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    tool_call_id: Option<Cow<'a, str>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    reasoning_content: Option<Cow<'a, str>>,
-}
-
-#[derive(Serialize, Debug)]
-struct OpenAIFunctionPayload<'a> {
-    name: Cow<'a, str>,
-    arguments: Cow<'a, str>,
-}
-
-#[derive(Serialize, Debug)]
-struct OpenAIFunctionCall<'a> {
-    id: Cow<'a, str>,
-    #[serde(rename = "type")]
-    content_type: Cow<'a, str>,
-    function: OpenAIFunctionPayload<'a>,
-}
-
-#[derive(Serialize, Debug)]
-struct MessageContent<'a> {
-    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
-    message_type: Option<Cow<'a, str>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    text: Option<Cow<'a, str>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    image_url: Option<ImageUrlContent<'a>>,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "tool_call_id")]
-    tool_call_id: Option<Cow<'a, str>>,
-    #[serde(skip_serializing_if = "Option::is_none", rename = "content")]
-    tool_output: Option<Cow<'a, str>>,
-}
-
-/// Individual image message in an OpenAI chat conversation.
-#[derive(Serialize, Debug)]
-struct ImageUrlContent<'a> {
-    url: Cow<'a, str>,
-}
-
 #[derive(Serialize)]
 struct OpenAIEmbeddingRequest {
     model: String,
@@ -144,7 +91,7 @@ struct OpenAIEmbeddingRequest {
 #[derive(Serialize, Debug)]
 struct OpenAIChatRequest<'a> {
     model: &'a str,
-    messages: Vec<OpenAIChatMessage<'a>>,
+    messages: Vec<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -162,6 +109,14 @@ struct OpenAIChatRequest<'a> {
     reasoning_effort: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     response_format: Option<OpenAIResponseFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    logit_bias: Option<&'a HashMap<String, f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
     #[serde(flatten, skip_serializing_if = "Option::is_none")]
     extra_body: Option<Map<String, Value>>,
 }
@@ -246,8 +201,15 @@ impl OpenAIRawUsage {
 /// Response from OpenAI's chat API endpoint.
 #[derive(Deserialize, Debug)]
 struct OpenAIChatResponse {
+    id: Option<String>,
     choices: Vec<OpenAIChatChoice>,
     usage: Option<OpenAIRawUsage>,
+    system_fingerprint: Option<String>,
+    /// `x-request-id` response header, for bodies without an `id`.
+    #[serde(skip)]
+    request_id: Option<String>,
+    #[serde(skip)]
+    raw: Option<Value>,
 }
 
 /// Individual choice within an OpenAI chat API response.
@@ -266,10 +228,14 @@ struct OpenAIChatMsg {
     #[serde(default, alias = "reasoning", alias = "reasoning_content")]
     thinking: Option<String>,
     tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default)]
+    refusal: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
 struct OpenAIEmbeddingData {
+    #[serde(default)]
+    index: usize,
     embedding: Vec<f32>,
 }
 #[derive(Deserialize, Debug)]
@@ -337,10 +303,24 @@ impl ChatResponse for OpenAIChatResponse {
         self.choices.first().and_then(|c| c.message.content.clone())
     }
 
+    fn candidates(&self) -> Option<Vec<String>> {
+        if self.choices.is_empty() {
+            return None;
+        }
+        Some(
+            self.choices
+                .iter()
+                .map(|c| c.message.content.clone().unwrap_or_default())
+                .collect(),
+        )
+    }
+
     fn tool_calls(&self) -> Option<Vec<ToolCall>> {
-        self.choices
-            .first()
-            .and_then(|c| c.message.tool_calls.clone())
+        non_empty_tool_calls(
+            self.choices
+                .first()
+                .and_then(|c| c.message.tool_calls.clone()),
+        )
     }
 
     fn thinking(&self) -> Option<String> {
@@ -353,10 +333,28 @@ impl ChatResponse for OpenAIChatResponse {
         self.usage.clone().map(|u| u.into_usage())
     }
 
+    fn refusal(&self) -> Option<String> {
+        self.choices.first().and_then(|c| c.message.refusal.clone())
+    }
+
+    fn raw(&self) -> Option<&Value> {
+        self.raw.as_ref()
+    }
+
+    fn response_id(&self) -> Option<String> {
+        self.id.clone().or_else(|| self.request_id.clone())
+    }
+
+    fn system_fingerprint(&self) -> Option<String> {
+        self.system_fingerprint.clone()
+    }
+
     fn finish_reason(&self) -> Option<FinishReason> {
         self.choices
             .first()
             .map(|c| match c.finish_reason.as_str() {
+                // A refusal is reported with an ordinary finish reason.
+                _ if c.message.refusal.is_some() => FinishReason::Refusal,
                 "stop" => FinishReason::Stop,
                 "length" => FinishReason::Length,
                 "content_filter" => FinishReason::ContentFilter,
@@ -415,6 +413,27 @@ pub trait OpenAIProviderConfig {
     fn extra_body(&self) -> Option<Map<String, Value>> {
         None
     }
+    /// End-user identifier sent as `user` for abuse monitoring.
+    fn user_id(&self) -> Option<&str> {
+        None
+    }
+    /// Per-token bias from -100 to 100, keyed by token id, sent as
+    /// `logit_bias`.
+    fn logit_bias(&self) -> Option<&HashMap<String, f32>> {
+        None
+    }
+    /// Number of choices to request, sent as `n`.
+    fn n(&self) -> Option<u32> {
+        None
+    }
+    /// Sampling seed for best-effort deterministic output, sent as `seed`.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+    /// Keep the response body for [`ChatResponse::raw`].
+    fn keep_raw(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -777,11 +796,33 @@ pub fn openai_parse_embed<C: OpenAIProviderConfig>(
     _cfg: &C,
     resp: Response<Vec<u8>>,
 ) -> Result<Vec<Vec<f32>>, LLMError> {
-    let json_resp: OpenAIEmbeddingResponse = serde_json::from_slice(resp.body())?;
+    let mut json_resp: OpenAIEmbeddingResponse =
+        decode_json_response(resp.body(), "embedding response")?;
+    // The API does not guarantee `data` is in input order; `index` is authoritative.
+    json_resp.data.sort_by_key(|d| d.index);
     let embeddings = json_resp.data.into_iter().map(|d| d.embedding).collect();
     Ok(embeddings)
 }
 
+/// Range of bias values accepted by `logit_bias`.
+const LOGIT_BIAS_RANGE: std::ops::RangeInclusive<f32> = -100.0..=100.0;
+
+fn validate_logit_bias(bias: &HashMap<String, f32>) -> Result<(), LLMError> {
+    for (token, value) in bias {
+        if token.parse::<u32>().is_err() {
+            return Err(LLMError::InvalidRequest(format!(
+                "logit_bias key '{token}' is not a token id"
+            )));
+        }
+        if !LOGIT_BIAS_RANGE.contains(value) {
+            return Err(LLMError::InvalidRequest(format!(
+                "logit_bias for token {token} is {value}, expected a value between -100 and 100"
+            )));
+        }
+    }
+    Ok(())
+}
+
 pub fn openai_chat_request<C: OpenAIProviderConfig>(
     cfg: &C,
     messages: &[ChatMessage],
@@ -789,35 +830,30 @@ pub fn openai_chat_request<C: OpenAIProviderConfig>(
 ) -> Result<Request<Vec<u8>>, LLMError> {
     let token = cfg.api_key();
     let auth = determine_effective_auth(token, cfg.auth_type(), cfg.base_url())?;
+    if let Some(bias) = cfg.logit_bias() {
+        validate_logit_bias(bias)?;
+    }
 
-    let mut openai_msgs: Vec<OpenAIChatMessage<'_>> = vec![];
-
-    for msg in messages {
-        convert_chat_message_to_openai(msg, &mut openai_msgs);
-    }
-
-    let system_parts = cfg.system();
-    if !system_parts.is_empty() {
-        // Insert system messages in reverse order at position 0
-        // so they end up in the correct order.
-        for part in system_parts.iter().rev() {
-            openai_msgs.insert(
-                0,
-                OpenAIChatMessage {
-                    role: Cow::Borrowed("system"),
-                    content: Some(Left(vec![MessageContent {
-                        message_type: Some(Cow::Borrowed("text")),
-                        text: Some(Cow::Borrowed(part)),
-                        image_url: None,
-                        tool_call_id: None,
-                        tool_output: None,
-                    }])),
-                    tool_calls: None,
-                    tool_call_id: None,
-                    reasoning_content: None,
-                },
-            );
-        }
+    let mut openai_msgs: Vec<Value> = cfg
+        .system()
+        .iter()
+        .map(|part| {
+            serde_json::json!({
+                "role": "system",
+                "content": [{ "type": "text", "text": part }],
+            })
+        })
+        .collect();
+
+    // System messages are hoisted ahead of the conversation, after the
+    // configured system prompt.
+    let (system_msgs, conversation): (Vec<_>, Vec<_>) =
+        messages.iter().partition(|m| m.is_system());
+    for msg in system_msgs.into_iter().chain(conversation) {
+        openai_msgs.extend(to_openai_messages(
+            std::slice::from_ref(msg),
+            OpenAIMapOpts::default(),
+        ));
     }
 
     // Build the response format object
@@ -855,6 +891,10 @@ pub fn openai_chat_request<C: OpenAIProviderConfig>(
             .reasoning_effort()
             .map(|e| openai_effort_str(e).to_owned()),
         response_format,
+        user: cfg.user_id(),
+        logit_bias: cfg.logit_bias(),
+        n: cfg.n(),
+        seed: cfg.seed(),
         extra_body,
     };
 
@@ -873,192 +913,28 @@ pub fn openai_chat_request<C: OpenAIProviderConfig>(
 }
 
 pub fn openai_parse_chat<C: OpenAIProviderConfig>(
-    _cfg: &C,
+    cfg: &C,
     response: Response<Vec<u8>>,
 ) -> Result<Box<dyn ChatResponse>, LLMError> {
     // If we got a non-200 response, let's get the error details
     handle_http_error!(response);
 
-    // Parse the successful response
-    let json_resp: Result<OpenAIChatResponse, serde_json::Error> =
-        serde_json::from_slice(response.body());
-
-    let resp_text: String = "".to_string();
-    match json_resp {
-        Ok(response) => Ok(Box::new(response)),
-        Err(e) => Err(LLMError::ResponseFormatError {
-            message: format!("Failed to decode API response: {}", e),
-            raw_response: resp_text,
-        }),
-    }
-}
-
-/// Extract the thinking/reasoning content from a ChatMessage, if any.
-fn extract_reasoning_content<'a>(msg: &'a ChatMessage) -> Option<Cow<'a, str>> {
-    msg.thinking().map(Cow::Borrowed)
-}
-
-/// Convert a ChatMessage with Vec<Content> blocks into one or more OpenAI API messages.
-///
-/// Most messages map 1:1, but ToolResult blocks each become a separate `role: "tool"` message.
-fn convert_chat_message_to_openai<'a>(
-    chat_msg: &'a ChatMessage,
-    out: &mut Vec<OpenAIChatMessage<'a>>,
-) {
-    let role: Cow<'a, str> = match chat_msg.role {
-        ChatRole::User => Cow::Borrowed("user"),
-        ChatRole::Assistant => Cow::Borrowed("assistant"),
-    };
-
-    // Check if this message contains any ToolResult blocks — those must be
-    // emitted as separate `role: "tool"` messages in the OpenAI API.
-    let has_tool_results = chat_msg.content.iter().any(|b| b.is_tool_result());
-    // Check if this message contains any ToolUse blocks — those map to tool_calls.
-    let has_tool_use = chat_msg.content.iter().any(|b| b.is_tool_use());
-
-    // Emit tool result blocks as separate messages
-    if has_tool_results {
-        for block in &chat_msg.content {
-            if let Content::ToolResult { id, content, .. } = block {
-                // Extract text from the tool result content blocks
-                let text: String = content
-                    .iter()
-                    .filter_map(|c| c.as_text().map(str::to_string))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                out.push(OpenAIChatMessage {
-                    role: Cow::Borrowed("tool"),
-                    tool_call_id: Some(Cow::Borrowed(id.as_str())),
-                    tool_calls: None,
-                    content: Some(Right(Cow::Owned(text))),
-                    reasoning_content: None,
-                });
-            }
-        }
-        // If there are also non-tool-result blocks (e.g. text), emit those too
-        let non_tool_content: Vec<&Content> = chat_msg
-            .content
-            .iter()
-            .filter(|b| !b.is_tool_result())
-            .collect();
-        if !non_tool_content.is_empty() {
-            let text: String = non_tool_content
-                .iter()
-                .filter_map(|b| b.as_text().map(str::to_string))
-                .collect::<Vec<_>>()
-                .join("\n");
-            if !text.is_empty() {
-                out.push(OpenAIChatMessage {
-                    role,
-                    tool_call_id: None,
-                    tool_calls: None,
-                    content: Some(Right(Cow::Owned(text))),
-                    reasoning_content: extract_reasoning_content(chat_msg),
-                });
-            }
-        }
-        return;
-    }
-
-    // Emit tool use blocks as tool_calls on an assistant message
-    if has_tool_use {
-        // Collect any text blocks as the message content
-        let text: String = chat_msg.text();
-        let content_val = if text.is_empty() {
-            None
-        } else {
-            Some(Right(Cow::Owned(text)))
-        };
-
-        let tool_calls: Vec<OpenAIFunctionCall<'a>> = chat_msg
-            .content
-            .iter()
-            .filter_map(|block| {
-                if let Content::ToolUse {
-                    id,
-                    name,
-                    arguments,
-                } = block
-                {
-                    let args_string = serde_json::to_string(arguments).unwrap_or_default();
-                    Some(OpenAIFunctionCall {
-                        id: Cow::Borrowed(id.as_str()),
-                        content_type: Cow::Borrowed("function"),
-                        function: OpenAIFunctionPayload {
-                            name: Cow::Borrowed(name.as_str()),
-                            arguments: Cow::Owned(args_string),
-                        },
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
-
-        out.push(OpenAIChatMessage {
-            role,
-            tool_call_id: None,
-            tool_calls: if tool_calls.is_empty() {
-                None
-            } else {
-                Some(tool_calls)
-            },
-            content: content_val,
-            reasoning_content: extract_reasoning_content(chat_msg),
-        });
-        return;
-    }
-
-    // Normal message: text, images, etc.
-    // Check for image URLs — use content array format
-    let has_images = chat_msg
-        .content
-        .iter()
-        .any(|b| matches!(b, Content::ImageUrl { .. } | Content::Image { .. }));
-
-    if has_images {
-        let content_blocks: Vec<MessageContent<'a>> = chat_msg
-            .content
-            .iter()
-            .filter_map(|block| match block {
-                Content::Text { text } => Some(MessageContent {
-                    message_type: Some(Cow::Borrowed("text")),
-                    text: Some(Cow::Borrowed(text.as_str())),
-                    image_url: None,
-                    tool_call_id: None,
-                    tool_output: None,
-                }),
-                Content::ImageUrl { url } => Some(MessageContent {
-                    message_type: Some(Cow::Borrowed("image_url")),
-                    text: None,
-                    image_url: Some(ImageUrlContent {
-                        url: Cow::Borrowed(url.as_str()),
-                    }),
-                    tool_call_id: None,
-                    tool_output: None,
-                }),
-                _ => None,
-            })
-            .collect();
-
-        out.push(OpenAIChatMessage {
-            role,
-            tool_call_id: None,
-            tool_calls: None,
-            content: Some(Left(content_blocks)),
-            reasoning_content: extract_reasoning_content(chat_msg),
-        });
+    let request_id = response
+        .headers()
+        .get("x-request-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let mut parsed: OpenAIChatResponse = if cfg.keep_raw() {
+        let raw: Value = decode_json_response(response.body(), "API response")?;
+        let mut parsed = OpenAIChatResponse::deserialize(&raw)
+            .map_err(|e| response_format_error("API response", e, response.body()))?;
+        parsed.raw = Some(raw);
+        parsed
     } else {
-        // Simple text-only message
-        let text = chat_msg.text();
-        out.push(OpenAIChatMessage {
-            role,
-            tool_call_id: None,
-            tool_calls: None,
-            content: Some(Right(Cow::Owned(text))),
-            reasoning_content: extract_reasoning_content(chat_msg),
-        });
-    }
+        decode_json_response(response.body(), "API response")?
+    };
+    parsed.request_id = request_id;
+    Ok(Box::new(parsed))
 }
 
 pub fn openai_list_models_request(
@@ -1100,7 +976,7 @@ pub fn openai_parse_list_models(response: &Response<Vec<u8>>) -> Result<Vec<Stri
     let error_response = response.clone();
     handle_http_error!(error_response);
 
-    let resp_json: Value = serde_json::from_slice(response.body())?;
+    let resp_json: Value = decode_json_response(response.body(), "models response")?;
     let arr = resp_json
         .get("data")
         .and_then(Value::as_array)
@@ -1149,6 +1025,8 @@ pub struct OpenAIStreamDelta {
         alias = "reasoning_content"
     )]
     pub thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<OpenAIStreamToolCall>>,
 }
@@ -1184,10 +1062,19 @@ pub struct OpenAIToolUseState {
     pub started: bool,
 }
 
+/// State carried across the SSE chunks of one stream.
+#[derive(Debug, Default)]
+pub struct OpenAIStreamState {
+    pub tool_states: HashMap<usize, OpenAIToolUseState>,
+    /// Whether a refusal was streamed. OpenAI still reports an ordinary
+    /// finish reason for it.
+    pub refused: bool,
+}
+
 /// Parse an OpenAI SSE chunk into StreamChunk events
 pub fn parse_openai_sse_chunk(
     chunk: &[u8],
-    tool_states: &mut HashMap<usize, OpenAIToolUseState>,
+    state: &mut OpenAIStreamState,
 ) -> Result<Vec<StreamChunk>, LLMError> {
     // Skip empty chunks
     if chunk.is_empty() {
@@ -1220,16 +1107,16 @@ pub fn parse_openai_sse_chunk(
         // Handle stream end
         if data == "[DONE]" {
             // Emit remaining tool completions
-            for (index, state) in tool_states.drain() {
-                if state.started {
+            for (index, tool) in state.tool_states.drain() {
+                if tool.started {
                     results.push(StreamChunk::ToolUseComplete {
                         index,
                         tool_call: ToolCall {
-                            id: state.id,
+                            id: tool.id,
                             call_type: "function".to_string(),
                             function: FunctionCall {
-                                name: state.name,
-                                arguments: state.arguments_buffer,
+                                name: tool.name,
+                                arguments: tool.arguments_buffer,
                             },
                         },
                     });
@@ -1256,8 +1143,9 @@ pub fn parse_openai_sse_chunk(
             results.push(StreamChunk::Usage(usage.into_usage()));
         }
 
-        // Process each choice
-        for choice in &stream_chunk.choices {
+        // With `n` > 1 the choices' deltas interleave; only the first
+        // choice is streamed.
+        for choice in stream_chunk.choices.iter().filter(|c| c.index == 0) {
             // Handle thinking/reasoning content deltas.
             if let Some(thinking) = &choice.delta.thinking
                 && !thinking.is_empty()
@@ -1272,33 +1160,40 @@ pub fn parse_openai_sse_chunk(
                 results.push(StreamChunk::Text(content.clone()));
             }
 
+            if let Some(refusal) = &choice.delta.refusal
+                && !refusal.is_empty()
+            {
+                state.refused = true;
+                results.push(StreamChunk::Refusal(refusal.clone()));
+            }
+
             // Handle tool calls
             if let Some(tool_calls) = &choice.delta.tool_calls {
                 for tc in tool_calls {
                     let index = tc.index.unwrap_or(0);
-                    let state = tool_states.entry(index).or_default();
+                    let tool_state = state.tool_states.entry(index).or_default();
 
                     // First chunk: has id and name
                     if let Some(id) = &tc.id {
-                        state.id = id.clone();
+                        tool_state.id = id.clone();
                     }
                     if let Some(name) = &tc.function.name {
-                        state.name = name.clone();
+                        tool_state.name = name.clone();
 
                         // Emit ToolUseStart on first occurrence
-                        if !state.started {
-                            state.started = true;
+                        if !tool_state.started {
+                            tool_state.started = true;
                             results.push(StreamChunk::ToolUseStart {
                                 index,
-                                id: state.id.clone(),
-                                name: state.name.clone(),
+                                id: tool_state.id.clone(),
+                                name: tool_state.name.clone(),
                             });
                         }
                     }
 
                     // Accumulate arguments
                     if !tc.function.arguments.is_empty() {
-                        state.arguments_buffer.push_str(&tc.function.arguments);
+                        tool_state.arguments_buffer.push_str(&tc.function.arguments);
                         results.push(StreamChunk::ToolUseInputDelta {
                             index,
                             partial_json: tc.function.arguments.clone(),
@@ -1310,16 +1205,16 @@ pub fn parse_openai_sse_chunk(
             // Handle finish_reason
             if let Some(finish_reason) = &choice.finish_reason {
                 // Emit tool completions before done
-                for (index, state) in tool_states.drain() {
-                    if state.started {
+                for (index, tool) in state.tool_states.drain() {
+                    if tool.started {
                         results.push(StreamChunk::ToolUseComplete {
                             index,
                             tool_call: ToolCall {
-                                id: state.id,
+                                id: tool.id,
                                 call_type: "function".to_string(),
                                 function: FunctionCall {
-                                    name: state.name,
-                                    arguments: state.arguments_buffer,
+                                    name: tool.name,
+                                    arguments: tool.arguments_buffer,
                                 },
                             },
                         });
@@ -1328,6 +1223,7 @@ pub fn parse_openai_sse_chunk(
 
                 // Map finish_reason to FinishReason
                 let finish_reason = match finish_reason.as_str() {
+                    _ if state.refused => FinishReason::Refusal,
                     "tool_calls" => FinishReason::ToolCalls,
                     "stop" => FinishReason::Stop,
                     "length" => FinishReason::Length,
@@ -1361,13 +1257,12 @@ pub(crate) fn openai_effort_str(e: ReasoningEffort) -> &'static str {
 mod tests {
     use http::Response;
     use querymt::{
-        chat::{ChatResponse, StreamChunk},
+        chat::{ChatMessage, ChatResponse, FinishReason, StreamChunk},
         error::LLMError,
     };
-    use std::collections::HashMap;
 
     use super::{
-        MultipartForm, OpenAIChatResponse, OpenAIToolUseState, openai_parse_list_models,
+        MultipartForm, OpenAIChatResponse, OpenAIStreamState, openai_parse_list_models,
         parse_openai_sse_chunk,
     };
 
@@ -1468,16 +1363,61 @@ mod tests {
         assert_eq!(response.thinking().as_deref(), Some("step two"));
     }
 
+    #[test]
+    fn empty_tool_calls_are_none() {
+        let body = br#"{
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "hi", "tool_calls": [] }
+            }]
+        }"#;
+        let response: OpenAIChatResponse = serde_json::from_slice(body).unwrap();
+        assert_eq!(response.tool_calls(), None);
+
+        let body = br#"{
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "hi" }
+            }]
+        }"#;
+        let response: OpenAIChatResponse = serde_json::from_slice(body).unwrap();
+        assert_eq!(response.tool_calls(), None);
+    }
+
+    #[test]
+    fn parse_chat_response_surfaces_refusal() {
+        let body = br#"{
+            "choices": [{
+                "finish_reason": "stop",
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "refusal": "I'm sorry, I can't help with that."
+                }
+            }]
+        }"#;
+        let response: OpenAIChatResponse = serde_json::from_slice(body).unwrap();
+        assert_eq!(response.finish_reason(), Some(FinishReason::Refusal));
+        assert_eq!(
+            response.refusal().as_deref(),
+            Some("I'm sorry, I can't help with that.")
+        );
+        assert_eq!(response.text(), None);
+
+        let message = ChatMessage::from(&response as &dyn ChatResponse);
+        assert_eq!(message.text(), "I'm sorry, I can't help with that.");
+    }
+
     #[test]
     fn parse_sse_chunk_emits_thinking_and_text_deltas() {
-        let mut tool_states: HashMap<usize, OpenAIToolUseState> = HashMap::new();
+        let mut state = OpenAIStreamState::default();
         let chunk = br#"data: {"choices":[{"index":0,"delta":{"reasoning":"thought ","content":"answer "}}]}
 
 data: {"choices":[{"index":0,"delta":{"reasoning_content":"continued"}}]}
 
 "#;
 
-        let events = parse_openai_sse_chunk(chunk, &mut tool_states).unwrap();
+        let events = parse_openai_sse_chunk(chunk, &mut state).unwrap();
         assert_eq!(events.len(), 3);
         match &events[0] {
             StreamChunk::Thinking(text) => assert_eq!(text, "thought "),
@@ -1493,6 +1433,61 @@ data: {"choices":[{"index":0,"delta":{"reasoning_content":"continued"}}]}
         }
     }
 
+    #[test]
+    fn parse_sse_chunk_streams_refusal() {
+        let mut state = OpenAIStreamState::default();
+        let events = parse_openai_sse_chunk(
+            br#"data: {"choices":[{"index":0,"delta":{"refusal":"I can't help with that."}}]}
+"#,
+            &mut state,
+        )
+        .unwrap();
+        assert!(
+            matches!(&events[..], [StreamChunk::Refusal(text)] if text == "I can't help with that.")
+        );
+
+        let events = parse_openai_sse_chunk(
+            br#"data: {"choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}
+"#,
+            &mut state,
+        )
+        .unwrap();
+        assert!(matches!(
+            &events[..],
+            [StreamChunk::Done {
+                finish_reason: FinishReason::Refusal
+            }]
+        ));
+    }
+
+    #[test]
+    fn parse_sse_chunk_streams_only_the_first_choice() {
+        let mut state = OpenAIStreamState::default();
+        let chunk = br#"data: {"choices":[{"index":0,"delta":{"content":"first"}},{"index":1,"delta":{"content":"second"}}]}
+
+data: {"choices":[{"index":1,"delta":{},"finish_reason":"length"}]}
+
+data: {"choices":[{"index":0,"delta":{"content":" choice"},"finish_reason":"stop"}]}
+
+"#;
+
+        let events = parse_openai_sse_chunk(chunk, &mut state).unwrap();
+        let text: String = events
+            .iter()
+            .filter_map(|e| match e {
+                StreamChunk::Text(t) => Some(t.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "first choice");
+        assert!(matches!(
+            events.last(),
+            Some(StreamChunk::Done {
+                finish_reason: FinishReason::Stop
+            })
+        ));
+    }
+
     #[test]
     fn openai_effort_str_maps_correctly() {
         use super::{ReasoningEffort, openai_effort_str};
@@ -12,7 +12,7 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
     stt, tts,
 };
 use schemars::{JsonSchema, schema_for};
@@ -22,22 +22,6 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use url::Url;
 
-fn normalize_base_url(mut url: Url) -> Url {
-    if !url.path().ends_with('/') {
-        let p = url.path().to_string();
-        url.set_path(&(p + "/"));
-    }
-    url
-}
-
-fn deserialize_base_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let url = Url::deserialize(deserializer)?;
-    Ok(normalize_base_url(url))
-}
-
 /// Authentication type for OpenAI API.
 #[derive(Debug, Clone, Deserialize, JsonSchema, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -70,7 +54,7 @@ pub struct OpenAI {
     #[schemars(schema_with = "api::url_schema")]
     #[serde(
         default = "OpenAI::default_base_url",
-        deserialize_with = "deserialize_base_url"
+        deserialize_with = "querymt::params::deserialize_base_url"
     )]
     pub base_url: Url,
     pub model: String,
@@ -94,6 +78,27 @@ pub struct OpenAI {
     /// These are passed through as-is via `#[serde(flatten)]` in the request body.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub extra_body: Option<serde_json::Map<String, Value>>,
+    /// End-user identifier sent as `user`, for abuse monitoring and per-user
+    /// attribution in multi-tenant deployments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Adjust the likelihood of specific tokens, keyed by token id, with
+    /// values from -100 to 100.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<String, f32>>,
+    /// Number of choices to generate. The first is the response and all of
+    /// them are available from `ChatResponse::candidates`; streams only
+    /// carry the first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub n: Option<u32>,
+    /// Sampling seed. Repeated requests with the same seed and parameters
+    /// should return the same result, on a best-effort basis.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Keep each chat response's JSON body, available from
+    /// `ChatResponse::raw`, for troubleshooting. Defaults to `false`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAI {
@@ -176,6 +181,26 @@ impl api::OpenAIProviderConfig for OpenAI {
     fn extra_body(&self) -> Option<serde_json::Map<String, Value>> {
         self.extra_body.clone()
     }
+
+    fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    fn logit_bias(&self) -> Option<&HashMap<String, f32>> {
+        self.logit_bias.as_ref()
+    }
+
+    fn n(&self) -> Option<u32> {
+        self.n
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for OpenAI {
@@ -212,12 +237,12 @@ impl HTTPChatProvider for OpenAI {
 
 #[derive(Default)]
 struct OpenAIStreamParser {
-    tool_states: HashMap<usize, api::OpenAIToolUseState>,
+    state: api::OpenAIStreamState,
 }
 
 impl ChatStreamParser for OpenAIStreamParser {
     fn parse_chunk(&mut self, chunk: &[u8]) -> Result<Vec<StreamChunk>, LLMError> {
-        api::parse_openai_sse_chunk(chunk, &mut self.tool_states)
+        api::parse_openai_sse_chunk(chunk, &mut self.state)
     }
 }
 
@@ -269,15 +294,32 @@ impl HTTPLLMProviderFactory for OpenAIFactory {
         "openai"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("OPENAI_API_KEY".into())
     }
 
+    fn default_model(&self) -> Option<&str> {
+        Some("gpt-4o-mini")
+    }
+
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => normalize_base_url(Url::parse(base_url_str)?),
-            None => normalize_base_url(OpenAI::default_base_url()),
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
+            None => querymt::params::normalize_base_url(OpenAI::default_base_url()),
         };
         api::openai_list_models_request(&base_url, &cfg)
     }
@@ -294,7 +336,7 @@ impl HTTPLLMProviderFactory for OpenAIFactory {
 
     fn from_config(&self, cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
         let mut provider: OpenAI = serde_json::from_str(cfg)?;
-        provider.base_url = normalize_base_url(provider.base_url);
+        provider.base_url = querymt::params::normalize_base_url(provider.base_url);
         Ok(Box::new(provider))
     }
 }
@@ -321,6 +363,196 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_chat_keeps_raw_body_when_configured() {
+        let body = serde_json::json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "hi" }
+            }],
+            "system_fingerprint": "fp_1"
+        });
+        let response = || {
+            http::Response::builder()
+                .status(200)
+                .body(serde_json::to_vec(&body).unwrap())
+                .unwrap()
+        };
+
+        let mut cfg = serde_json::json!({ "api_key": "test-key", "model": "gpt-4o-mini" });
+        let provider: OpenAI = serde_json::from_value(cfg.clone()).unwrap();
+        let resp = provider.parse_chat(response()).unwrap();
+        assert_eq!(resp.text().as_deref(), Some("hi"));
+        assert_eq!(resp.raw(), None);
+
+        cfg["keep_raw"] = true.into();
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let resp = provider.parse_chat(response()).unwrap();
+        assert_eq!(resp.text().as_deref(), Some("hi"));
+        assert_eq!(resp.raw(), Some(&body));
+    }
+
+    #[test]
+    fn parse_chat_exposes_response_id_and_system_fingerprint() {
+        let body = serde_json::json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "hi" }
+            }],
+            "system_fingerprint": "fp_1"
+        });
+        let cfg = serde_json::json!({ "api_key": "test-key", "model": "gpt-4o-mini" });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let response = http::Response::builder()
+            .status(200)
+            .header("x-request-id", "req_1")
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+        let resp = provider.parse_chat(response).unwrap();
+        assert_eq!(resp.response_id().as_deref(), Some("chatcmpl-1"));
+        assert_eq!(resp.system_fingerprint().as_deref(), Some("fp_1"));
+
+        // Without an `id` in the body, fall back to the request id header.
+        let response = http::Response::builder()
+            .status(200)
+            .header("x-request-id", "req_1")
+            .body(br#"{"choices": []}"#.to_vec())
+            .unwrap();
+        let resp = provider.parse_chat(response).unwrap();
+        assert_eq!(resp.response_id().as_deref(), Some("req_1"));
+        assert_eq!(resp.system_fingerprint(), None);
+    }
+
+    #[test]
+    fn chat_request_includes_user_id() {
+        let cfg = serde_json::json!({
+            "api_key": "test-key",
+            "model": "gpt-4o-mini",
+            "user_id": "tenant-42"
+        });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let messages = vec![querymt::chat::ChatMessage::user().text("hi").build()];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["user"], "tenant-42");
+    }
+
+    #[test]
+    fn chat_request_includes_participant_name() {
+        let cfg = serde_json::json!({ "api_key": "test-key", "model": "gpt-4o-mini" });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let messages = vec![
+            querymt::chat::ChatMessage::user()
+                .name("alice")
+                .text("hi")
+                .build(),
+            querymt::chat::ChatMessage::user().text("hello").build(),
+        ];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["messages"][0]["name"], "alice");
+        assert!(body["messages"][1].get("name").is_none());
+    }
+
+    #[test]
+    fn chat_request_includes_sampling_controls() {
+        let cfg = serde_json::json!({
+            "api_key": "test-key",
+            "model": "gpt-4o-mini",
+            "logit_bias": { "50256": -100, "1734": 2.5 },
+            "n": 2,
+            "seed": 1234
+        });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let messages = vec![querymt::chat::ChatMessage::user().text("hi").build()];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["logit_bias"]["50256"], -100.0);
+        assert_eq!(body["logit_bias"]["1734"], 2.5);
+        assert_eq!(body["n"], 2);
+        assert_eq!(body["seed"], 1234);
+
+        let provider: OpenAI = serde_json::from_value(serde_json::json!({
+            "api_key": "test-key",
+            "model": "gpt-4o-mini"
+        }))
+        .unwrap();
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert!(body.get("logit_bias").is_none());
+        assert!(body.get("n").is_none());
+        assert!(body.get("seed").is_none());
+    }
+
+    #[test]
+    fn chat_request_rejects_out_of_range_logit_bias() {
+        let messages = vec![querymt::chat::ChatMessage::user().text("hi").build()];
+        for bias in [
+            serde_json::json!({ "50256": 150 }),
+            serde_json::json!({ "not-a-token": 1 }),
+        ] {
+            let provider: OpenAI = serde_json::from_value(serde_json::json!({
+                "api_key": "test-key",
+                "model": "gpt-4o-mini",
+                "logit_bias": bias
+            }))
+            .unwrap();
+            let err = provider.chat_request(&messages, None).unwrap_err();
+            assert!(
+                matches!(err, querymt::error::LLMError::InvalidRequest(_)),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn chat_request_hoists_system_messages() {
+        let cfg = serde_json::json!({
+            "api_key": "test-key",
+            "model": "gpt-4o-mini",
+            "system": "Be brief."
+        });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let messages = vec![
+            querymt::chat::ChatMessage::user().text("hi").build(),
+            querymt::chat::ChatMessage::system()
+                .text("Answer in French.")
+                .build(),
+        ];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+
+        let msgs = &body["messages"];
+        assert_eq!(msgs[0]["role"], "system");
+        assert_eq!(msgs[0]["content"][0]["text"], "Be brief.");
+        assert_eq!(msgs[1]["role"], "system");
+        assert_eq!(msgs[1]["content"], "Answer in French.");
+        assert_eq!(msgs[2]["role"], "user");
+    }
+
+    #[test]
+    fn parse_embed_orders_by_index() {
+        use querymt::embedding::http::HTTPEmbeddingProvider;
+
+        let cfg = serde_json::json!({
+            "api_key": "test-key",
+            "model": "text-embedding-3-small"
+        });
+        let provider: OpenAI = serde_json::from_value(cfg).unwrap();
+        let resp = http::Response::builder()
+            .status(200)
+            .body(
+                br#"{"data":[{"index":1,"embedding":[2.0]},{"index":0,"embedding":[1.0]}]}"#
+                    .to_vec(),
+            )
+            .unwrap();
+
+        let embeddings = provider.parse_embed(resp).unwrap();
+        assert_eq!(embeddings, vec![vec![1.0], vec![2.0]]);
+    }
+
     #[test]
     fn chat_stream_request_forces_stream_true() {
         let cfg = serde_json::json!({
@@ -414,6 +646,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(OpenAIFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{OpenAI, OpenAIFactory};
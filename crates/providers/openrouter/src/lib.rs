@@ -10,12 +10,13 @@ use querymt::{
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    error::{LLMError, decode_json_response},
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
 use url::Url;
 
@@ -23,7 +24,10 @@ use url::Url;
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct OpenRouter {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "OpenRouter::default_base_url")]
+    #[serde(
+        default = "OpenRouter::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     pub api_key: String,
     pub model: String,
@@ -43,6 +47,12 @@ pub struct OpenRouter {
     pub reasoning_effort: Option<querymt::chat::ReasoningEffort>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Sampling seed for best-effort reproducible output
+    pub seed: Option<u64>,
+    /// Per-token bias from -100 to 100, keyed by token id
+    pub logit_bias: Option<HashMap<String, f32>>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAIProviderConfig for OpenRouter {
@@ -109,6 +119,18 @@ impl OpenAIProviderConfig for OpenRouter {
     fn json_schema(&self) -> Option<&StructuredOutputFormat> {
         self.json_schema.as_ref()
     }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn logit_bias(&self) -> Option<&HashMap<String, f32>> {
+        self.logit_bias.as_ref()
+    }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for OpenRouter {
@@ -164,6 +186,19 @@ impl HTTPLLMProviderFactory for OpenRouterFactory {
         "openrouter"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: false,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("OPENROUTER_API_KEY".into())
     }
@@ -171,7 +206,7 @@ impl HTTPLLMProviderFactory for OpenRouterFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => OpenRouter::default_base_url(),
         };
         let models_url = base_url.join("models")?;
@@ -183,7 +218,7 @@ impl HTTPLLMProviderFactory for OpenRouterFactory {
     }
 
     fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
-        let resp_json: Value = serde_json::from_slice(resp.body())?;
+        let resp_json: Value = decode_json_response(resp.body(), "OpenRouter models response")?;
         let arr = resp_json
             .get("data")
             .and_then(Value::as_array)
@@ -225,6 +260,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(OpenRouterFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{OpenRouter, OpenRouterFactory};
@@ -236,3 +280,22 @@ mod extism_exports {
         name   = "openrouter",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_request_includes_seed() {
+        let provider: OpenRouter = serde_json::from_value(serde_json::json!({
+            "api_key": "test-key",
+            "model": "openai/gpt-4o-mini",
+            "seed": 42
+        }))
+        .unwrap();
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let req = provider.chat_request(&messages, None).unwrap();
+        let body: Value = serde_json::from_slice(req.body()).unwrap();
+        assert_eq!(body["seed"], 42);
+    }
+}
@@ -21,12 +21,13 @@ use querymt::{
         ChatMessage, ChatResponse, ChatRole, Content, ReasoningEffort, StreamChunk,
         StructuredOutputFormat, Tool, ToolChoice,
         http::{ChatStreamParser, HTTPChatProvider},
+        system_prompts,
     },
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
-    error::LLMError,
+    error::{LLMError, decode_json_response},
     handle_http_error,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
@@ -42,7 +43,10 @@ const XAI_ADDITIONAL_LIST_MODELS: &[&str] = &["grok-composer-2.5-fast", "grok-4.
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Xai {
     #[schemars(schema_with = "url_schema")]
-    #[serde(default = "Xai::default_base_url")]
+    #[serde(
+        default = "Xai::default_base_url",
+        deserialize_with = "querymt::params::deserialize_base_url"
+    )]
     pub base_url: Url,
     #[serde(default)]
     pub api_key: String,
@@ -67,6 +71,8 @@ pub struct Xai {
     pub reasoning_effort: Option<querymt::chat::ReasoningEffort>,
     /// JSON schema for structured output
     pub json_schema: Option<StructuredOutputFormat>,
+    /// Sampling seed for best-effort reproducible output
+    pub seed: Option<u64>,
 
     /// Optional resolver for dynamic credential refresh (e.g., OAuth tokens).
     #[serde(skip)]
@@ -76,6 +82,8 @@ pub struct Xai {
     #[serde(skip)]
     #[schemars(skip)]
     pub conversation_id: Option<String>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -173,6 +181,14 @@ impl OpenAIProviderConfig for Xai {
     fn json_schema(&self) -> Option<&StructuredOutputFormat> {
         self.json_schema.as_ref()
     }
+
+    fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for Xai {
@@ -326,21 +342,19 @@ impl HTTPCompletionProvider for Xai {
     fn parse_complete(&self, resp: Response<Vec<u8>>) -> Result<CompletionResponse, LLMError> {
         handle_http_error!(resp);
 
-        let json_resp: Result<XaiCompletionResponse, serde_json::Error> =
-            serde_json::from_slice(resp.body());
-        match json_resp {
-            Ok(completion_response) => Ok(CompletionResponse {
-                text: completion_response.choices[0].message.content.clone(), // FIXME
-            }),
-            Err(e) => Err(LLMError::JsonError(e)),
-        }
+        let completion_response: XaiCompletionResponse =
+            decode_json_response(resp.body(), "completion response")?;
+        Ok(CompletionResponse {
+            text: completion_response.choices[0].message.content.clone(), // FIXME
+            usage: None,
+        })
     }
 }
 
 struct XaiStreamParser {
     use_responses_api: bool,
     codex_tool_state: Arc<Mutex<HashMap<usize, CodexToolUseState>>>,
-    openai_tool_state: HashMap<usize, qmt_openai::api::OpenAIToolUseState>,
+    openai_state: qmt_openai::api::OpenAIStreamState,
 }
 
 impl XaiStreamParser {
@@ -348,7 +362,7 @@ impl XaiStreamParser {
         Self {
             use_responses_api,
             codex_tool_state: Arc::new(Mutex::new(HashMap::new())),
-            openai_tool_state: HashMap::new(),
+            openai_state: Default::default(),
         }
     }
 }
@@ -358,7 +372,7 @@ impl ChatStreamParser for XaiStreamParser {
         if self.use_responses_api {
             codex_parse_stream_chunk_with_state(chunk, &self.codex_tool_state)
         } else {
-            parse_openai_sse_chunk(chunk, &mut self.openai_tool_state)
+            parse_openai_sse_chunk(chunk, &mut self.openai_state)
         }
     }
 }
@@ -556,7 +570,8 @@ struct XaiResponsesReasoning {
 fn to_xai_responses_input(messages: &[ChatMessage]) -> Vec<XaiResponsesInputItem<'_>> {
     let mut inputs = Vec::with_capacity(messages.len());
 
-    for msg in messages {
+    // System messages are sent as `instructions` instead.
+    for msg in messages.iter().filter(|m| !m.is_system()) {
         let is_user = matches!(msg.role, ChatRole::User);
         let mut content_blocks = Vec::new();
 
@@ -761,6 +776,9 @@ fn xai_responses_chat_request<C: qmt_openai::api::OpenAIProviderConfig>(
     } else {
         None
     };
+    if cfg.seed().is_some() {
+        log::warn!("xAI Responses API does not support `seed`; ignoring it");
+    }
     let text = cfg.json_schema().cloned().map(XaiResponsesText::from);
     let reasoning = cfg
         .reasoning_effort()
@@ -768,7 +786,13 @@ fn xai_responses_chat_request<C: qmt_openai::api::OpenAIProviderConfig>(
         .map(|effort| XaiResponsesReasoning {
             effort: xai_effort_str(effort),
         });
-    let instructions = cfg.system().join("\n");
+    let instructions = cfg
+        .system()
+        .iter()
+        .cloned()
+        .chain(system_prompts(messages))
+        .collect::<Vec<_>>()
+        .join("\n");
     let body = XaiResponsesRequest {
         model: cfg.model(),
         input: to_xai_responses_input(messages),
@@ -806,6 +830,19 @@ impl HTTPLLMProviderFactory for XaiFactory {
         "xai"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: true,
+            structured_output: true,
+            thinking: true,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("XAI_API_KEY".into())
     }
@@ -813,7 +850,7 @@ impl HTTPLLMProviderFactory for XaiFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => Url::parse(base_url_str)?,
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => Xai::default_base_url(),
         };
         openai_list_models_request(&base_url, &cfg)
@@ -857,6 +894,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(XaiFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Xai, XaiFactory};
@@ -893,6 +939,8 @@ mod tests {
             embedding_dimensions: None,
             reasoning_effort: None,
             json_schema: None,
+            seed: None,
+            keep_raw: None,
             key_resolver: None,
             conversation_id: None,
         }
@@ -904,6 +952,28 @@ mod tests {
             .and_then(|v| v.to_str().ok())
     }
 
+    #[test]
+    fn keep_raw_exposes_chat_completions_body() {
+        let body = serde_json::json!({
+            "id": "chatcmpl-1",
+            "choices": [{
+                "finish_reason": "stop",
+                "message": { "role": "assistant", "content": "hi" }
+            }]
+        });
+        let mut xai = test_xai("xai-api-key");
+        xai.base_url = Url::parse("http://localhost:8000/v1/").unwrap();
+        xai.keep_raw = Some(true);
+        let response = Response::builder()
+            .status(200)
+            .body(serde_json::to_vec(&body).unwrap())
+            .unwrap();
+
+        let resp = xai.parse_chat(response).unwrap();
+        assert_eq!(resp.text().as_deref(), Some("hi"));
+        assert_eq!(resp.raw(), Some(&body));
+    }
+
     #[test]
     fn deserialize_oauth_auth_type_without_api_key() {
         let cfg = serde_json::json!({
@@ -989,6 +1059,21 @@ mod tests {
         assert_eq!(auth_header(&req), Some("Bearer resolver-token"));
     }
 
+    #[test]
+    fn chat_request_includes_seed_on_openai_chat_completions_path() {
+        let mut xai = test_xai("xai-key");
+        xai.base_url = Url::parse("https://api.openai-compatible.test/v1/").unwrap();
+        xai.seed = Some(7);
+
+        let messages = vec![ChatMessage::user().text("hello").build()];
+        let req = xai
+            .chat_request(&messages, None)
+            .expect("chat request should build");
+        let body: Value = serde_json::from_slice(req.body()).expect("body should be JSON");
+
+        assert_eq!(body["seed"], 7);
+    }
+
     #[test]
     fn chat_stream_request_forces_stream_true_on_openai_chat_completions_path() {
         let mut xai = test_xai("xai-key");
@@ -1168,12 +1253,7 @@ mod tests {
     fn completion_request_uses_resolver_current_token() {
         let mut xai = test_xai("stale-token");
         xai.set_key_resolver(static_key("resolver-token"));
-        let completion = CompletionRequest {
-            prompt: "hello".to_string(),
-            suffix: None,
-            max_tokens: None,
-            temperature: None,
-        };
+        let completion = CompletionRequest::new("hello");
 
         let req = xai
             .complete_request(&completion)
@@ -1,6 +1,6 @@
 use http::{Request, Response};
 use qmt_openai::api::{
-    OpenAIProviderConfig, OpenAIToolUseState, openai_chat_request, openai_embed_request,
+    OpenAIProviderConfig, OpenAIStreamState, openai_chat_request, openai_embed_request,
     openai_list_models_request, openai_parse_chat, openai_parse_embed, openai_parse_list_models,
     parse_openai_sse_chunk, url_schema,
 };
@@ -13,38 +13,21 @@ use querymt::{
     completion::{CompletionRequest, CompletionResponse, http::HTTPCompletionProvider},
     embedding::http::HTTPEmbeddingProvider,
     error::LLMError,
-    plugin::HTTPLLMProviderFactory,
+    plugin::{HTTPLLMProviderFactory, ProviderCapabilities},
 };
 use schemars::{JsonSchema, schema_for};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
 use std::sync::Arc;
 use url::Url;
 
-fn normalize_base_url(mut url: Url) -> Url {
-    if !url.path().ends_with('/') {
-        let p = url.path().to_string();
-        url.set_path(&(p + "/"));
-    }
-    url
-}
-
-fn deserialize_base_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    let url = Url::deserialize(deserializer)?;
-    Ok(normalize_base_url(url))
-}
-
 #[derive(Debug, Clone, Deserialize, JsonSchema, Serialize)]
 #[serde(rename_all = "snake_case", deny_unknown_fields)]
 pub struct Zai {
     #[schemars(schema_with = "url_schema")]
     #[serde(
         default = "Zai::default_base_url",
-        deserialize_with = "deserialize_base_url"
+        deserialize_with = "querymt::params::deserialize_base_url"
     )]
     pub base_url: Url,
     pub api_key: String,
@@ -62,6 +45,8 @@ pub struct Zai {
     pub json_schema: Option<StructuredOutputFormat>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub extra_body: Option<serde_json::Map<String, Value>>,
+    /// Keep the raw JSON of chat responses. Defaults to `false`.
+    pub keep_raw: Option<bool>,
 }
 
 impl OpenAIProviderConfig for Zai {
@@ -132,6 +117,10 @@ impl OpenAIProviderConfig for Zai {
     fn extra_body(&self) -> Option<serde_json::Map<String, Value>> {
         self.extra_body.clone()
     }
+
+    fn keep_raw(&self) -> bool {
+        self.keep_raw.unwrap_or(false)
+    }
 }
 
 impl HTTPChatProvider for Zai {
@@ -168,12 +157,12 @@ impl HTTPChatProvider for Zai {
 
 #[derive(Default)]
 struct ZaiStreamParser {
-    tool_states: HashMap<usize, OpenAIToolUseState>,
+    state: OpenAIStreamState,
 }
 
 impl ChatStreamParser for ZaiStreamParser {
     fn parse_chunk(&mut self, chunk: &[u8]) -> Result<Vec<StreamChunk>, LLMError> {
-        parse_openai_sse_chunk(chunk, &mut self.tool_states)
+        parse_openai_sse_chunk(chunk, &mut self.state)
     }
 }
 
@@ -216,6 +205,19 @@ impl HTTPLLMProviderFactory for ZaiFactory {
         "zai"
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities {
+            streaming: true,
+            tools: true,
+            vision: true,
+            pdf: false,
+            embeddings: true,
+            completion: false,
+            structured_output: true,
+            thinking: false,
+        }
+    }
+
     fn api_key_name(&self) -> Option<String> {
         Some("ZAI_API_KEY".into())
     }
@@ -223,7 +225,7 @@ impl HTTPLLMProviderFactory for ZaiFactory {
     fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
         let cfg: Value = serde_json::from_str(cfg)?;
         let base_url = match cfg.get("base_url").and_then(Value::as_str) {
-            Some(base_url_str) => normalize_base_url(Url::parse(base_url_str)?),
+            Some(base_url_str) => querymt::params::normalize_base_url(Url::parse(base_url_str)?),
             None => Zai::default_base_url(),
         };
         openai_list_models_request(&base_url, &cfg)
@@ -240,7 +242,7 @@ impl HTTPLLMProviderFactory for ZaiFactory {
 
     fn from_config(&self, cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
         let mut provider: Zai = serde_json::from_str(cfg)?;
-        provider.base_url = normalize_base_url(provider.base_url);
+        provider.base_url = querymt::params::normalize_base_url(provider.base_url);
         Ok(Box::new(provider))
     }
 }
@@ -256,6 +258,15 @@ pub extern "C" fn plugin_http_factory() -> *mut dyn HTTPLLMProviderFactory {
     Box::into_raw(Box::new(ZaiFactory)) as *mut _
 }
 
+#[cfg(feature = "native")]
+#[unsafe(no_mangle)]
+pub extern "C" fn plugin_abi_version() -> u32 {
+    querymt::plugin::PLUGIN_ABI_VERSION
+}
+
+#[cfg(feature = "native")]
+querymt::export_plugin_init_logging!();
+
 #[cfg(feature = "extism")]
 mod extism_exports {
     use super::{Zai, ZaiFactory};
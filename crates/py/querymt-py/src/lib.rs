@@ -468,6 +468,7 @@ fn finish_reason_to_string(reason: FinishReason) -> String {
         FinishReason::ContentFilter => "content_filter",
         FinishReason::ToolCalls => "tool_calls",
         FinishReason::Error => "error",
+        FinishReason::Refusal => "refusal",
         FinishReason::Other => "other",
         FinishReason::Unknown => "unknown",
     }
@@ -543,6 +544,7 @@ fn stream_chunk_to_python(chunk: StreamChunk) -> PyStreamChunk {
             "thinking_signature",
             serde_json::json!({ "signature": signature }),
         ),
+        StreamChunk::Refusal(text) => ("refusal", serde_json::json!({ "text": text })),
         StreamChunk::ToolUseStart { index, id, name } => (
             "tool_use_start",
             serde_json::json!({ "index": index, "id": id, "name": name }),
@@ -578,6 +580,7 @@ fn stream_chunk_to_python(chunk: StreamChunk) -> PyStreamChunk {
                 "cache_write": usage.cache_write,
             }),
         ),
+        StreamChunk::Warning(message) => ("warning", serde_json::json!({ "message": message })),
         StreamChunk::Done { finish_reason } => (
             "done",
             serde_json::json!({ "finish_reason": finish_reason_to_string(finish_reason) }),
@@ -706,10 +709,17 @@ fn py_message_to_rust(message: &Bound<'_, PyDict>) -> Result<ChatMessage> {
     match role.as_str() {
         "user" => Ok(ChatMessage::from_user(blocks)),
         "assistant" => Ok(ChatMessage::from_assistant(blocks)),
+        "system" => Ok(ChatMessage {
+            role: ChatRole::System,
+            content: blocks,
+            cache: None,
+            name: None,
+        }),
         "tool" => Ok(ChatMessage {
             role: ChatRole::Assistant,
             content: blocks,
             cache: None,
+            name: None,
         }),
         other => Err(anyhow!("unsupported role '{}'", other)),
     }
@@ -1241,6 +1251,13 @@ mod tests {
         assert_eq!(chunk.data["name"], "lookup");
     }
 
+    #[test]
+    fn converts_warning_chunk_to_python() {
+        let chunk = stream_chunk_to_python(StreamChunk::Warning("dropped call".to_string()));
+        assert_eq!(chunk.kind, "warning");
+        assert_eq!(chunk.data["message"], "dropped call");
+    }
+
     #[test]
     fn converts_python_tools_to_rust() {
         with_python(|py| {
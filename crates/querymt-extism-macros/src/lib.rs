@@ -187,6 +187,18 @@ macro_rules! impl_extism_http_plugin {
             Ok(Json(cfg.supports_streaming()))
         }
 
+        #[plugin_fn]
+        pub fn capabilities() -> FnResult<Json<querymt::plugin::ProviderCapabilities>> {
+            Ok(Json(HTTPLLMProviderFactory::capabilities(&$Factory)))
+        }
+
+        #[plugin_fn]
+        pub fn default_model() -> FnResult<Json<Option<String>>> {
+            Ok(Json(
+                HTTPLLMProviderFactory::default_model(&$Factory).map(str::to_string),
+            ))
+        }
+
         // Export the API key env var name
         #[plugin_fn]
         pub fn api_key_name() -> FnResult<Option<String>> {
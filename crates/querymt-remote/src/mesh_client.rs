@@ -409,6 +409,16 @@ impl CompletionProvider for MeshChatProvider {
     async fn complete(&self, req: &CompletionRequest) -> Result<CompletionResponse, LLMError> {
         self.inner.complete(req).await
     }
+
+    async fn complete_stream(
+        &self,
+        req: &CompletionRequest,
+    ) -> Result<
+        Pin<Box<dyn futures_util::Stream<Item = Result<StreamChunk, LLMError>> + Send>>,
+        LLMError,
+    > {
+        self.inner.complete_stream(req).await
+    }
 }
 
 #[async_trait]
@@ -47,6 +47,7 @@ impl ChatResponse for ProviderChatResponse {
             "ContentFilter" => FinishReason::ContentFilter,
             "ToolCalls" => FinishReason::ToolCalls,
             "Error" => FinishReason::Error,
+            "Refusal" => FinishReason::Refusal,
             "Other" => FinishReason::Other,
             _ => FinishReason::Unknown,
         })
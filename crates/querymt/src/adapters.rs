@@ -1,26 +1,292 @@
 use crate::{
     HTTPLLMProvider, LLMProvider, Tool,
-    chat::{ChatMessage, ChatProvider, ChatResponse, StreamChunk},
+    chat::{
+        ChatMessage, ChatProvider, ChatResponse, StreamChunk, json_repair, validate_conversation,
+    },
     completion::{CompletionProvider, CompletionRequest, CompletionResponse},
     embedding::EmbeddingProvider,
-    error::LLMError,
-    outbound::{call_outbound, call_outbound_stream},
-    stt, tts,
+    error::{LLMError, classify_http_status},
+    stt,
+    transport::{ByteStream, HttpTransport, check_response_size, default_transport},
+    tts,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
+use http::{HeaderValue, Request, Response};
+use serde_json::{Value, json};
 use std::pin::Pin;
 use std::sync::Arc;
 #[cfg(feature = "tracing")]
 use tracing::instrument;
 
+/// Header carrying the per-request idempotency key.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+tokio::task_local! {
+    static REQUEST_IDEMPOTENCY_KEY: String;
+}
+
+/// A new random idempotency key.
+pub fn new_idempotency_key() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// Run `fut` with `key` as the idempotency key of every chat request it
+/// sends.
+///
+/// A retry layer that calls `chat` again wraps each attempt with the same
+/// key, so the provider sees one logical request. Outside such a scope each
+/// call gets a fresh key. Only applies to providers with idempotency keys
+/// enabled.
+pub async fn with_idempotency_key<F: Future>(key: String, fut: F) -> F::Output {
+    REQUEST_IDEMPOTENCY_KEY.scope(key, fut).await
+}
+
+/// Whether a provider config opts in to idempotency keys via
+/// `idempotency_keys`. Defaults to `false`.
+pub fn idempotency_keys_enabled(cfg: &Value) -> bool {
+    cfg.get("idempotency_keys")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+/// The `max_messages` set in a provider config, if any.
+pub fn config_max_messages(cfg: &Value) -> Option<usize> {
+    cfg.get("max_messages")
+        .and_then(Value::as_u64)
+        .and_then(|limit| usize::try_from(limit).ok())
+}
+
+/// Options the HTTP and Extism adapters read from any provider config, with
+/// the JSON schema of each.
+///
+/// Providers don't declare these themselves: [`with_adapter_options`] adds
+/// them to a provider's config schema, so config pruning keeps them, and
+/// [`strip_adapter_options`] removes them again before the provider parses
+/// its config.
+fn adapter_options() -> [(&'static str, Value); 6] {
+    [
+        (
+            "tls_ca_cert",
+            json!({
+                "type": "string",
+                "description": "PEM bundle of extra CA certificates to trust for https:// base URLs.",
+            }),
+        ),
+        (
+            "tls_client_cert",
+            json!({
+                "type": "string",
+                "description": "PEM client certificate (chain) presented for mTLS. Requires tls_client_key.",
+            }),
+        ),
+        (
+            "tls_client_key",
+            json!({
+                "type": "string",
+                "description": "PEM private key for tls_client_cert.",
+            }),
+        ),
+        (
+            "max_messages",
+            json!({
+                "type": "integer",
+                "minimum": 0,
+                "description": "Reject chat requests with more messages than this, before sending.",
+            }),
+        ),
+        (
+            "repair_tool_json",
+            json!({
+                "type": "boolean",
+                "description": "Repair slightly malformed JSON in tool-call arguments. Defaults to true; set to false to receive arguments exactly as the model produced them.",
+            }),
+        ),
+        (
+            "idempotency_keys",
+            json!({
+                "type": "boolean",
+                "description": "Send an Idempotency-Key header with chat requests, reused when a request is retried. Defaults to false.",
+            }),
+        ),
+    ]
+}
+
+/// `schema` with the adapter-level options added to its `properties`.
+///
+/// Properties the provider already declares are left as they are. A schema
+/// that isn't a JSON object is returned unchanged.
+pub fn with_adapter_options(schema: &str) -> String {
+    let Ok(mut schema_value) = serde_json::from_str::<Value>(schema) else {
+        return schema.to_string();
+    };
+    let Some(props) = schema_value
+        .as_object_mut()
+        .map(|obj| obj.entry("properties").or_insert_with(|| json!({})))
+        .and_then(Value::as_object_mut)
+    else {
+        return schema.to_string();
+    };
+    for (key, option) in adapter_options() {
+        props.entry(key).or_insert(option);
+    }
+    schema_value.to_string()
+}
+
+/// Remove the adapter-level options `provider_schema` doesn't declare from
+/// `cfg`, so providers that reject unknown fields still accept it.
+///
+/// Returns whether anything was removed.
+pub fn strip_adapter_options(cfg: &mut Value, provider_schema: &str) -> bool {
+    let Some(cfg) = cfg.as_object_mut() else {
+        return false;
+    };
+    let declared = serde_json::from_str::<Value>(provider_schema).ok();
+    let declared = declared
+        .as_ref()
+        .and_then(|schema| schema.get("properties"))
+        .and_then(Value::as_object);
+    let mut stripped = false;
+    for (key, _) in adapter_options() {
+        if !declared.is_some_and(|props| props.contains_key(key)) {
+            stripped |= cfg.remove(key).is_some();
+        }
+    }
+    stripped
+}
+
 pub struct LLMProviderFromHTTP {
     inner: Box<dyn HTTPLLMProvider>,
+    transport: Arc<dyn HttpTransport>,
+    max_response_bytes: Option<usize>,
+    repair_tool_json: bool,
+    idempotency_keys: bool,
+    max_messages: Option<usize>,
 }
 
 impl LLMProviderFromHTTP {
     pub fn new(inner: Box<dyn HTTPLLMProvider>) -> Self {
-        Self { inner }
+        Self::with_transport(inner, default_transport())
+    }
+
+    /// Wrap `inner`, executing its requests through a custom [`HttpTransport`].
+    pub fn with_transport(
+        inner: Box<dyn HTTPLLMProvider>,
+        transport: Arc<dyn HttpTransport>,
+    ) -> Self {
+        Self {
+            inner,
+            transport,
+            max_response_bytes: None,
+            repair_tool_json: true,
+            idempotency_keys: false,
+            max_messages: None,
+        }
+    }
+
+    /// Reject response bodies larger than `limit` bytes before parsing them.
+    ///
+    /// For streaming responses the limit applies to a single unterminated
+    /// line, which is what the stream parser has to buffer, rather than to
+    /// the whole stream.
+    pub fn with_max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = Some(limit);
+        self
+    }
+
+    /// Enable or disable best-effort repair of malformed arguments in
+    /// streamed `ToolUseComplete` chunks. Enabled by default; see
+    /// [`json_repair::repair_tool_arguments`].
+    pub fn with_tool_json_repair(mut self, enabled: bool) -> Self {
+        self.repair_tool_json = enabled;
+        self
+    }
+
+    /// Send an `Idempotency-Key` header with chat requests.
+    ///
+    /// A fresh key is generated for each logical request and reused when the
+    /// adapter retries it, so a request that succeeded server-side but whose
+    /// response was lost is not executed twice. Disabled by default since
+    /// not every API accepts the header.
+    pub fn with_idempotency_keys(mut self, enabled: bool) -> Self {
+        self.idempotency_keys = enabled;
+        self
+    }
+
+    /// Reject chat requests whose conversation has more than `limit`
+    /// messages, before the request body is built.
+    pub fn with_max_messages(mut self, limit: Option<usize>) -> Self {
+        self.max_messages = limit;
+        self
+    }
+
+    /// The idempotency key for this request, if keys are enabled: the one
+    /// set by [`with_idempotency_key`], otherwise a new one.
+    fn idempotency_key(&self) -> Option<HeaderValue> {
+        if !self.idempotency_keys {
+            return None;
+        }
+        let key = REQUEST_IDEMPOTENCY_KEY
+            .try_with(Clone::clone)
+            .unwrap_or_else(|_| new_idempotency_key());
+        HeaderValue::try_from(key).ok()
+    }
+
+    /// Build a chat request, tagging it with `key` unless the provider set
+    /// its own idempotency key.
+    fn chat_request_with_key(
+        &self,
+        messages: &[ChatMessage],
+        tools: Option<&[Tool]>,
+        stream: bool,
+        key: Option<&HeaderValue>,
+    ) -> Result<Request<Vec<u8>>, LLMError> {
+        if let Some(limit) = self.max_messages
+            && messages.len() > limit
+        {
+            return Err(LLMError::InvalidRequest(format!(
+                "conversation has {} messages, exceeding max_messages ({limit})",
+                messages.len()
+            )));
+        }
+        validate_conversation(messages)?;
+        let req = if stream {
+            self.inner.chat_stream_request(messages, tools)
+        } else {
+            self.inner.chat_request(messages, tools)
+        };
+        let mut req = req.map_err(|e| LLMError::ProviderError(format!("{:#}", e)))?;
+        log::debug!(
+            "chat request to {}{}: {} messages, {} byte body",
+            req.uri().host().unwrap_or_default(),
+            req.uri().path(),
+            messages.len(),
+            req.body().len()
+        );
+        if let Some(key) = key
+            && !req.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+        {
+            req.headers_mut()
+                .insert(IDEMPOTENCY_KEY_HEADER, key.clone());
+        }
+        Ok(req)
+    }
+
+    async fn send(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        let resp = self.transport.execute(req).await?;
+        check_response_size(resp.body().len(), self.max_response_bytes)?;
+        if !resp.status().is_success() {
+            return Err(classify_http_status(
+                resp.status().as_u16(),
+                resp.headers(),
+                resp.body(),
+            ));
+        }
+        Ok(resp)
+    }
+
+    async fn send_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        self.transport.execute_stream(req).await
     }
 
     /// Ensure the provider's credential is fresh before building a request.
@@ -35,6 +301,21 @@ impl LLMProviderFromHTTP {
         Ok(())
     }
 
+    /// Force a credential refresh after an authentication failure.
+    ///
+    /// Returns `Ok(())` when the request should be retried, i.e. the error is
+    /// an [`LLMError::AuthError`] and the provider has a resolver that
+    /// refreshed successfully. Otherwise the original error is returned.
+    async fn refresh_credential_after(&self, err: LLMError) -> Result<(), LLMError> {
+        match (&err, self.inner.key_resolver()) {
+            (LLMError::AuthError(_), Some(resolver)) => {
+                log::debug!("request rejected with auth error, refreshing credential and retrying");
+                resolver.refresh().await
+            }
+            _ => Err(err),
+        }
+    }
+
     async fn do_chat(
         &self,
         messages: &[ChatMessage],
@@ -42,12 +323,17 @@ impl LLMProviderFromHTTP {
     ) -> Result<Box<dyn ChatResponse>, LLMError> {
         self.ensure_credential_fresh().await?;
 
-        let req = self
-            .inner
-            .chat_request(messages, tools)
-            .map_err(|e| LLMError::ProviderError(format!("{:#}", e)))?;
+        let key = self.idempotency_key();
+        let req = self.chat_request_with_key(messages, tools, false, key.as_ref())?;
 
-        let resp = call_outbound(req).await?;
+        let resp = match self.send(req).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                self.refresh_credential_after(e).await?;
+                let req = self.chat_request_with_key(messages, tools, false, key.as_ref())?;
+                self.send(req).await?
+            }
+        };
 
         self.inner.parse_chat(resp)
     }
@@ -89,19 +375,25 @@ impl ChatProvider for LLMProviderFromHTTP {
 
         self.ensure_credential_fresh().await?;
 
-        let req = self
-            .inner
-            .chat_stream_request(messages, tools)
-            .map_err(|e| LLMError::ProviderError(format!("{:#}", e)))?;
+        let key = self.idempotency_key();
+        let req = self.chat_request_with_key(messages, tools, true, key.as_ref())?;
 
-        let stream = call_outbound_stream(req).await?;
+        let stream = match self.send_stream(req).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.refresh_credential_after(e).await?;
+                let req = self.chat_request_with_key(messages, tools, true, key.as_ref())?;
+                self.send_stream(req).await?
+            }
+        };
         let mut parser = self
             .inner
             .chat_stream_parser()
             .map_err(|e| LLMError::ProviderError(format!("{:#}", e)))?;
+        let max_line_bytes = self.max_response_bytes;
+        let repair_tool_json = self.repair_tool_json;
 
         let s = stream
-            .map(move |res: reqwest::Result<bytes::Bytes>| res.map_err(LLMError::from))
             .chain(futures::stream::iter([
                 Ok(bytes::Bytes::from_static(b"\n")),
                 Ok(bytes::Bytes::new()),
@@ -140,6 +432,10 @@ impl ChatProvider for LLMProviderFromHTTP {
                             }
                         }
                         *buffer = buffer[start..].to_vec();
+                        if let Err(e) = check_response_size(buffer.len(), max_line_bytes) {
+                            *done = true;
+                            return futures::future::ready(Some(Err(e)));
+                        }
 
                         if bytes.is_empty() {
                             *done = true;
@@ -158,9 +454,19 @@ impl ChatProvider for LLMProviderFromHTTP {
                 };
                 futures::future::ready(Some(res))
             })
-            .flat_map(|res: Result<Vec<StreamChunk>, LLMError>| {
+            .flat_map(move |res: Result<Vec<StreamChunk>, LLMError>| {
                 let v: Vec<Result<StreamChunk, LLMError>> = match res {
-                    Ok(chunks) => chunks.into_iter().map(Ok).collect(),
+                    Ok(chunks) => chunks
+                        .into_iter()
+                        .map(|mut chunk| {
+                            if let StreamChunk::ToolUseComplete { tool_call, .. } = &mut chunk
+                                && repair_tool_json
+                            {
+                                json_repair::repair_tool_arguments(tool_call);
+                            }
+                            Ok(chunk)
+                        })
+                        .collect(),
                     Err(e) => vec![Err(e)],
                 };
                 futures::stream::iter(v)
@@ -176,7 +482,8 @@ impl EmbeddingProvider for LLMProviderFromHTTP {
     async fn embed(&self, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError> {
         self.ensure_credential_fresh().await?;
         let req = self.inner.embed_request(&inputs)?;
-        let resp = call_outbound(req)
+        let resp = self
+            .send(req)
             .await
             .map_err(|e| LLMError::HttpError(format!("{:#}", e)))?;
         self.inner
@@ -194,7 +501,8 @@ impl CompletionProvider for LLMProviderFromHTTP {
     async fn complete(&self, req_obj: &CompletionRequest) -> Result<CompletionResponse, LLMError> {
         self.ensure_credential_fresh().await?;
         let req = self.inner.complete_request(req_obj)?;
-        let resp = call_outbound(req)
+        let resp = self
+            .send(req)
             .await
             .map_err(|e| LLMError::HttpError(format!("{:#}", e)))?;
         self.inner
@@ -224,7 +532,8 @@ impl LLMProvider for LLMProviderFromHTTP {
     async fn transcribe(&self, req_obj: &stt::SttRequest) -> Result<stt::SttResponse, LLMError> {
         self.ensure_credential_fresh().await?;
         let req = self.inner.stt_request(req_obj)?;
-        let resp = call_outbound(req)
+        let resp = self
+            .send(req)
             .await
             .map_err(|e| LLMError::HttpError(format!("{:#}", e)))?;
         self.inner
@@ -239,7 +548,8 @@ impl LLMProvider for LLMProviderFromHTTP {
     async fn speech(&self, req_obj: &tts::TtsRequest) -> Result<tts::TtsResponse, LLMError> {
         self.ensure_credential_fresh().await?;
         let req = self.inner.tts_request(req_obj)?;
-        let resp = call_outbound(req)
+        let resp = self
+            .send(req)
             .await
             .map_err(|e| LLMError::HttpError(format!("{:#}", e)))?;
         self.inner
@@ -296,8 +606,46 @@ mod tests {
         }
     }
 
+    /// Resolver whose token only becomes valid after an explicit `refresh()`.
+    #[derive(Debug)]
+    struct RefreshingResolver {
+        refreshes: AtomicUsize,
+    }
+
+    impl RefreshingResolver {
+        fn new() -> Self {
+            Self {
+                refreshes: AtomicUsize::new(0),
+            }
+        }
+
+        fn refresh_count(&self) -> usize {
+            self.refreshes.load(Ordering::SeqCst)
+        }
+    }
+
+    impl ApiKeyResolver for RefreshingResolver {
+        fn resolve(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn current(&self) -> String {
+            if self.refresh_count() > 0 {
+                "refreshed-token".to_string()
+            } else {
+                "stale-token".to_string()
+            }
+        }
+
+        fn refresh(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+            self.refreshes.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
     struct ResolveAwareHttpProvider {
         resolver: Arc<dyn ApiKeyResolver>,
+        uri: String,
     }
 
     impl HTTPChatProvider for DummyHttpProvider {
@@ -361,7 +709,7 @@ mod tests {
             let token = self.resolver.current();
             let req = Request::builder()
                 .method("POST")
-                .uri("https://example.invalid/chat")
+                .uri(&self.uri)
                 .header("authorization", format!("Bearer {token}"))
                 .body(Vec::new())
                 .map_err(|e| LLMError::InvalidRequest(format!("failed building request: {e}")))?;
@@ -426,6 +774,7 @@ mod tests {
         let resolver = Arc::new(CountingResolver::new());
         let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
             resolver: resolver.clone(),
+            uri: "https://example.invalid/chat".to_string(),
         });
         let adapter = LLMProviderFromHTTP::new(inner);
 
@@ -458,4 +807,254 @@ mod tests {
             "Bearer resolved-token"
         );
     }
+
+    #[tokio::test]
+    async fn chat_refreshes_credential_and_retries_once_on_auth_error() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Minimal HTTP server: rejects the stale token with 401, accepts anything else.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind test listener");
+        let addr = listener.local_addr().expect("listener address");
+        let server = tokio::spawn(async move {
+            let mut seen_tokens = Vec::new();
+            for _ in 0..2 {
+                let (mut socket, _) = listener.accept().await.expect("accept connection");
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 1024];
+                while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                    let n = socket.read(&mut chunk).await.expect("read request");
+                    if n == 0 {
+                        break;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                }
+                let request = String::from_utf8_lossy(&buf).to_ascii_lowercase();
+                let stale = request.contains("authorization: bearer stale-token");
+                seen_tokens.push(stale);
+                let status = if stale { "401 Unauthorized" } else { "200 OK" };
+                let response =
+                    format!("HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n");
+                socket
+                    .write_all(response.as_bytes())
+                    .await
+                    .expect("write response");
+            }
+            seen_tokens
+        });
+
+        let resolver = Arc::new(RefreshingResolver::new());
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
+            resolver: resolver.clone(),
+            uri: format!("http://{addr}/chat"),
+        });
+        let adapter = LLMProviderFromHTTP::new(inner);
+
+        let err = adapter
+            .do_chat(&[], None)
+            .await
+            .expect_err("test provider cannot parse responses");
+
+        // The retried request reached `parse_chat`, so the 401 was not surfaced.
+        assert!(matches!(err, LLMError::NotImplemented(_)), "got {err:?}");
+        assert_eq!(resolver.refresh_count(), 1);
+        assert_eq!(
+            server.await.expect("server task"),
+            vec![true, false],
+            "first request should use the stale token, the retry the refreshed one"
+        );
+    }
+
+    struct RecordingTransport {
+        uris: std::sync::Mutex<Vec<String>>,
+        status: u16,
+        body: Vec<u8>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for RecordingTransport {
+        async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            self.uris.lock().unwrap().push(req.uri().to_string());
+            Ok(Response::builder()
+                .status(self.status)
+                .body(self.body.clone())
+                .expect("response should build"))
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_uses_custom_transport() {
+        let transport = Arc::new(RecordingTransport {
+            uris: std::sync::Mutex::new(Vec::new()),
+            status: 200,
+            body: Vec::new(),
+        });
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
+            resolver: static_key("token"),
+            uri: "https://example.invalid/chat".to_string(),
+        });
+        let adapter = LLMProviderFromHTTP::with_transport(inner, transport.clone());
+
+        let err = adapter
+            .do_chat(&[], None)
+            .await
+            .expect_err("test provider cannot parse responses");
+
+        assert!(matches!(err, LLMError::NotImplemented(_)), "got {err:?}");
+        assert_eq!(
+            *transport.uris.lock().unwrap(),
+            vec!["https://example.invalid/chat".to_string()]
+        );
+    }
+
+    /// Rejects the first request with 401 and records every request's
+    /// idempotency key.
+    #[derive(Default)]
+    struct IdempotencyRecordingTransport {
+        keys: std::sync::Mutex<Vec<Option<String>>>,
+    }
+
+    #[async_trait]
+    impl HttpTransport for IdempotencyRecordingTransport {
+        async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            let mut keys = self.keys.lock().unwrap();
+            keys.push(
+                req.headers()
+                    .get(IDEMPOTENCY_KEY_HEADER)
+                    .map(|v| v.to_str().unwrap().to_string()),
+            );
+            let status = if keys.len() == 1 { 401 } else { 200 };
+            Ok(Response::builder()
+                .status(status)
+                .body(Vec::new())
+                .expect("response should build"))
+        }
+    }
+
+    async fn recorded_keys(enabled: bool) -> Vec<Option<String>> {
+        let transport = Arc::new(IdempotencyRecordingTransport::default());
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
+            resolver: Arc::new(RefreshingResolver::new()),
+            uri: "https://example.invalid/chat".to_string(),
+        });
+        let adapter = LLMProviderFromHTTP::with_transport(inner, transport.clone())
+            .with_idempotency_keys(enabled);
+
+        let err = adapter
+            .do_chat(&[], None)
+            .await
+            .expect_err("test provider cannot parse responses");
+        assert!(matches!(err, LLMError::NotImplemented(_)), "got {err:?}");
+
+        transport.keys.lock().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn retried_request_reuses_idempotency_key() {
+        let keys = recorded_keys(true).await;
+        assert_eq!(keys.len(), 2);
+        assert!(keys[0].is_some());
+        assert_eq!(keys[0], keys[1]);
+
+        let other = recorded_keys(true).await;
+        assert_ne!(other[0], keys[0], "each logical request gets its own key");
+    }
+
+    #[tokio::test]
+    async fn scoped_idempotency_key_is_reused_across_calls() {
+        let keys = with_idempotency_key("logical-request-1".to_string(), async {
+            let mut keys = recorded_keys(true).await;
+            keys.extend(recorded_keys(true).await);
+            keys
+        })
+        .await;
+        assert_eq!(keys, vec![Some("logical-request-1".to_string()); 4]);
+    }
+
+    #[tokio::test]
+    async fn idempotency_keys_are_opt_in() {
+        assert_eq!(recorded_keys(false).await, vec![None, None]);
+        assert!(!idempotency_keys_enabled(&serde_json::json!({})));
+        assert!(idempotency_keys_enabled(
+            &serde_json::json!({ "idempotency_keys": true })
+        ));
+    }
+
+    #[tokio::test]
+    async fn max_messages_rejects_long_conversations_before_building_request() {
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(DummyHttpProvider { resolver: None });
+        let adapter = LLMProviderFromHTTP::new(inner).with_max_messages(config_max_messages(
+            &serde_json::json!({ "max_messages": 2 }),
+        ));
+        let messages: Vec<ChatMessage> = (0..3)
+            .map(|i| ChatMessage::user().text(format!("message {i}")).build())
+            .collect();
+
+        let err = adapter.chat(&messages).await.unwrap_err();
+        assert!(
+            matches!(&err, LLMError::InvalidRequest(msg) if msg.contains("max_messages")),
+            "{err:?}"
+        );
+
+        // Within the limit the provider's request builder is reached.
+        let err = adapter.chat(&messages[..2]).await.unwrap_err();
+        assert!(matches!(err, LLMError::ProviderError(_)), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn custom_transport_error_status_is_classified() {
+        let transport = Arc::new(RecordingTransport {
+            uris: std::sync::Mutex::new(Vec::new()),
+            status: 401,
+            body: Vec::new(),
+        });
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
+            resolver: static_key("token"),
+            uri: "https://example.invalid/chat".to_string(),
+        });
+        let adapter = LLMProviderFromHTTP::with_transport(inner, transport);
+
+        let err = adapter
+            .do_chat(&[], None)
+            .await
+            .expect_err("401 should surface as an error");
+
+        assert!(matches!(err, LLMError::AuthError(_)), "got {err:?}");
+    }
+
+    fn sized_response_adapter(body_len: usize, limit: usize) -> LLMProviderFromHTTP {
+        let transport = Arc::new(RecordingTransport {
+            uris: std::sync::Mutex::new(Vec::new()),
+            status: 200,
+            body: vec![b' '; body_len],
+        });
+        let inner: Box<dyn HTTPLLMProvider> = Box::new(ResolveAwareHttpProvider {
+            resolver: static_key("token"),
+            uri: "https://example.invalid/chat".to_string(),
+        });
+        LLMProviderFromHTTP::with_transport(inner, transport).with_max_response_bytes(limit)
+    }
+
+    #[tokio::test]
+    async fn oversized_response_is_rejected_before_parse() {
+        let err = sized_response_adapter(1025, 1024)
+            .do_chat(&[], None)
+            .await
+            .expect_err("oversized body should be rejected");
+
+        // The test provider's parser returns NotImplemented, so a
+        // ProviderError means parsing was never attempted.
+        assert!(matches!(err, LLMError::ProviderError(_)), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn response_within_limit_reaches_parser() {
+        let err = sized_response_adapter(1024, 1024)
+            .do_chat(&[], None)
+            .await
+            .expect_err("test provider cannot parse responses");
+
+        assert!(matches!(err, LLMError::NotImplemented(_)), "got {err:?}");
+    }
 }
@@ -78,10 +78,12 @@ pub async fn handle_chat(
             role: match msg.role.as_str() {
                 "user" => ChatRole::User,
                 "assistant" => ChatRole::Assistant,
+                "system" => ChatRole::System,
                 _ => ChatRole::User,
             },
             content: vec![Content::text(msg.content)],
             cache: None,
+            name: None,
         })
         .collect();
 
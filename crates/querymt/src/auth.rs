@@ -9,11 +9,18 @@
 //! 2. The provider calls [`ApiKeyResolver::current()`] from sync context
 //!    (inside `chat_request()`, `embed_request()`, etc.) to read the
 //!    most recently resolved value.
+//! 3. If the server still rejects the credential (HTTP 401/403), the adapter
+//!    calls [`ApiKeyResolver::refresh()`] once and retries the request.
 //!
 //! # Implementations
 //!
 //! - [`StaticKeyResolver`]: Returns a fixed credential. Used for environment
 //!   variable API keys that don't expire.
+//! - [`EnvApiKeyResolver`]: Re-reads an environment variable on every
+//!   request, for keys rotated in place by a secrets operator.
+//! - `ExecApiKeyResolver`: Runs a credential helper command (e.g.
+//!   `op read ...`) and caches its output for a TTL. Requires the
+//!   `http-client` feature.
 //!
 //! For OAuth-based resolvers that refresh tokens, see the `oauth` feature
 //! in the agent crate.
@@ -55,6 +62,19 @@ pub trait ApiKeyResolver: Send + Sync + std::fmt::Debug {
     /// mutability (e.g., `RwLock`) to make the value set by `resolve()`
     /// available here.
     fn current(&self) -> String;
+
+    /// Force a credential refresh after the server rejected the current one.
+    ///
+    /// Called by the adapter layer when a request fails with an
+    /// authentication error (HTTP 401/403), before retrying the request once.
+    /// Unlike [`resolve()`](ApiKeyResolver::resolve), implementations should
+    /// not trust local expiry metadata here: the server has already said the
+    /// credential is no longer valid.
+    ///
+    /// The default implementation delegates to `resolve()`.
+    fn refresh(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+        self.resolve()
+    }
 }
 
 /// A resolver that always returns the same fixed credential.
@@ -94,3 +114,256 @@ impl ApiKeyResolver for StaticKeyResolver {
 pub fn static_key(key: impl Into<String>) -> Arc<dyn ApiKeyResolver> {
     Arc::new(StaticKeyResolver::new(key))
 }
+
+/// A resolver that reads an environment variable on every
+/// [`current()`](ApiKeyResolver::current) call.
+///
+/// Unlike a key read once at config time, this picks up credentials that a
+/// secrets operator rotates by updating the variable in place. See
+/// [`HTTPLLMProviderFactory::with_env_resolver`](crate::plugin::HTTPLLMProviderFactory::with_env_resolver)
+/// to attach one for a provider's `api_key_name()`.
+#[derive(Debug, Clone)]
+pub struct EnvApiKeyResolver {
+    var: String,
+}
+
+impl EnvApiKeyResolver {
+    /// Create a resolver for the environment variable `var`.
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+
+    /// Name of the environment variable this resolver reads.
+    pub fn var(&self) -> &str {
+        &self.var
+    }
+}
+
+impl ApiKeyResolver for EnvApiKeyResolver {
+    fn resolve(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+        Box::pin(async {
+            match std::env::var(&self.var) {
+                Ok(key) if !key.is_empty() => Ok(()),
+                _ => Err(LLMError::AuthError(format!(
+                    "Environment variable {} is not set",
+                    self.var
+                ))),
+            }
+        })
+    }
+
+    fn current(&self) -> String {
+        std::env::var(&self.var).unwrap_or_default()
+    }
+}
+
+/// Convenience function to create a resolver that re-reads `var`.
+pub fn env_key(var: impl Into<String>) -> Arc<dyn ApiKeyResolver> {
+    Arc::new(EnvApiKeyResolver::new(var))
+}
+
+/// How long [`ExecApiKeyResolver`] reuses a key before re-running the command.
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+pub const DEFAULT_EXEC_KEY_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// Upper bound on how long a credential helper may run.
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+const EXEC_KEY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A resolver that runs a credential helper command and uses its trimmed
+/// stdout as the key.
+///
+/// This integrates secret managers such as 1Password (`op read ...`) or
+/// Vault (`vault kv get -field=...`) without writing Rust. The output is
+/// cached for a TTL ([`DEFAULT_EXEC_KEY_TTL`] unless set with
+/// [`with_ttl`](Self::with_ttl)); [`refresh()`](ApiKeyResolver::refresh)
+/// always re-runs the command.
+///
+/// The command runs directly in the host process with its privileges and
+/// environment, not through a shell and not inside any agent tool sandbox.
+/// Only configure commands you trust.
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+pub struct ExecApiKeyResolver {
+    program: String,
+    args: Vec<String>,
+    ttl: std::time::Duration,
+    cached: std::sync::RwLock<Option<(String, std::time::Instant)>>,
+}
+
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+impl ExecApiKeyResolver {
+    /// Create a resolver that runs `program` with `args`.
+    pub fn new(
+        program: impl Into<String>,
+        args: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            ttl: DEFAULT_EXEC_KEY_TTL,
+            cached: std::sync::RwLock::new(None),
+        }
+    }
+
+    /// Set how long a key is reused before the command runs again.
+    pub fn with_ttl(mut self, ttl: std::time::Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn is_fresh(&self) -> bool {
+        self.cached
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .is_some_and(|(_, fetched)| fetched.elapsed() < self.ttl)
+    }
+
+    async fn run(&self) -> Result<(), LLMError> {
+        let fail = |reason: String| {
+            LLMError::AuthError(format!(
+                "Credential helper '{}' failed: {reason}",
+                self.program
+            ))
+        };
+        let output = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output();
+        let output = tokio::time::timeout(EXEC_KEY_TIMEOUT, output)
+            .await
+            .map_err(|_| fail(format!("timed out after {EXEC_KEY_TIMEOUT:?}")))?
+            .map_err(|e| fail(e.to_string()))?;
+        if !output.status.success() {
+            return Err(fail(format!(
+                "{}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let key = String::from_utf8(output.stdout)
+            .map_err(|_| fail("output is not valid UTF-8".to_string()))?
+            .trim()
+            .to_string();
+        if key.is_empty() {
+            return Err(fail("no output".to_string()));
+        }
+        *self.cached.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((key, std::time::Instant::now()));
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+impl std::fmt::Debug for ExecApiKeyResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecApiKeyResolver")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("ttl", &self.ttl)
+            .field("cached", &"<redacted>")
+            .finish()
+    }
+}
+
+#[cfg(all(feature = "http-client", not(target_arch = "wasm32")))]
+impl ApiKeyResolver for ExecApiKeyResolver {
+    fn resolve(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+        Box::pin(async {
+            if self.is_fresh() {
+                return Ok(());
+            }
+            self.run().await
+        })
+    }
+
+    fn current(&self) -> String {
+        self.cached
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|(key, _)| key.clone())
+            .unwrap_or_default()
+    }
+
+    fn refresh(&self) -> Pin<Box<dyn Future<Output = Result<(), LLMError>> + Send + '_>> {
+        Box::pin(self.run())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(all(feature = "http-client", unix))]
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn env_resolver_rereads_variable() {
+        const VAR: &str = "QMT_TEST_ENV_RESOLVER_KEY";
+        let resolver = EnvApiKeyResolver::new(VAR);
+
+        // SAFETY: no other test touches this variable.
+        unsafe { std::env::remove_var(VAR) };
+        assert!(resolver.resolve().await.is_err());
+        assert_eq!(resolver.current(), "");
+
+        unsafe { std::env::set_var(VAR, "sk-first") };
+        resolver.resolve().await.unwrap();
+        assert_eq!(resolver.current(), "sk-first");
+
+        unsafe { std::env::set_var(VAR, "sk-rotated") };
+        assert_eq!(resolver.current(), "sk-rotated");
+        unsafe { std::env::remove_var(VAR) };
+    }
+
+    /// A helper whose output changes on every run.
+    #[cfg(all(feature = "http-client", unix))]
+    fn counter_helper(name: &str) -> ExecApiKeyResolver {
+        let counter =
+            std::env::temp_dir().join(format!("qmt-exec-key-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_file(&counter);
+        let script = format!(
+            "echo x >> {0}; echo \"  sk-test-$(wc -l < {0} | tr -d ' ')  \"",
+            counter.display()
+        );
+        ExecApiKeyResolver::new("sh", ["-c", script.as_str()])
+    }
+
+    #[cfg(all(feature = "http-client", unix))]
+    #[tokio::test]
+    async fn exec_resolver_uses_trimmed_stdout_and_caches() {
+        let resolver = counter_helper("cache");
+        assert_eq!(resolver.current(), "");
+
+        resolver.resolve().await.unwrap();
+        assert_eq!(resolver.current(), "sk-test-1");
+
+        resolver.resolve().await.unwrap();
+        assert_eq!(resolver.current(), "sk-test-1", "cached within TTL");
+
+        resolver.refresh().await.unwrap();
+        assert_eq!(resolver.current(), "sk-test-2", "refresh re-runs");
+    }
+
+    #[cfg(all(feature = "http-client", unix))]
+    #[tokio::test]
+    async fn exec_resolver_reruns_after_ttl() {
+        let resolver = counter_helper("ttl").with_ttl(Duration::ZERO);
+        resolver.resolve().await.unwrap();
+        resolver.resolve().await.unwrap();
+        assert_eq!(resolver.current(), "sk-test-2");
+    }
+
+    #[cfg(all(feature = "http-client", unix))]
+    #[tokio::test]
+    async fn exec_resolver_reports_failing_command() {
+        let resolver = ExecApiKeyResolver::new("sh", ["-c", "echo denied >&2; exit 3"]);
+        let err = resolver.resolve().await.unwrap_err();
+        assert!(
+            matches!(err, LLMError::AuthError(ref m) if m.contains("denied")),
+            "{err:?}"
+        );
+        assert_eq!(resolver.current(), "");
+    }
+}
@@ -63,6 +63,9 @@ pub struct LLMBuilder<State = Unbound> {
     /// Maximum tokens to generate in responses
     #[serde(skip_serializing_if = "Option::is_none")]
     max_tokens: Option<u32>,
+    /// Clamp `max_tokens` to the model's known output limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clamp_max_tokens: Option<bool>,
     /// Temperature parameter for controlling response randomness (0.0-1.0)
     #[serde(skip_serializing_if = "Option::is_none")]
     temperature: Option<f32>,
@@ -129,6 +132,7 @@ impl LLMBuilder<Unbound> {
             base_url: None,
             model: None,
             max_tokens: None,
+            clamp_max_tokens: None,
             temperature: None,
             system: Vec::new(),
             timeout_seconds: None,
@@ -173,6 +177,7 @@ impl<State> LLMBuilder<State> {
             base_url: self.base_url,
             model: self.model,
             max_tokens: self.max_tokens,
+            clamp_max_tokens: self.clamp_max_tokens,
             temperature: self.temperature,
             system: self.system,
             timeout_seconds: self.timeout_seconds,
@@ -223,6 +228,14 @@ impl<State> LLMBuilder<State> {
         self
     }
 
+    /// Clamps `max_tokens` to the model's output limit from the providers
+    /// registry, with a warning, instead of letting the provider reject the
+    /// request.
+    pub fn clamp_max_tokens(mut self, clamp: bool) -> Self {
+        self.clamp_max_tokens = Some(clamp);
+        self
+    }
+
     /// Sets the temperature for controlling response randomness (0.0-1.0).
     pub fn temperature(mut self, temperature: f32) -> Self {
         self.temperature = Some(temperature);
@@ -394,7 +407,10 @@ impl<State> LLMBuilder<State> {
         }
 
         let schema: Value = serde_json::from_str(&factory.config_schema())?;
-        let full_cfg = Value::Object(full_cfg);
+        let mut full_cfg = Value::Object(full_cfg);
+        if let Some(models) = crate::providers::ProvidersRegistry::global() {
+            crate::provider_config::clamp_max_tokens(&mut full_cfg, &provider_name, models);
+        }
         let pruned_cfg = prune_config_by_schema(&full_cfg, &schema);
         let pruned_cfg_str = serde_json::to_string(&pruned_cfg)?;
         let base = factory.from_config(&pruned_cfg_str)?;
@@ -0,0 +1,319 @@
+//! Folding a chat stream back into a complete response.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::pin::Pin;
+
+use futures::{Stream, StreamExt};
+
+use super::{ChatResponse, FinishReason, StreamChunk};
+use crate::{FunctionCall, ToolCall, Usage, error::LLMError};
+
+/// Collects [`StreamChunk`]s into a complete [`ChatResponse`].
+///
+/// Text, thinking and refusal deltas are concatenated, the last thinking signature
+/// is kept, tool calls are ordered by
+/// their block index, `Usage` chunks are merged field-wise with
+/// [`Usage::merge_max`] and the last `Done` chunk wins. Tool calls
+/// whose stream never produced a `ToolUseComplete` are rebuilt from their
+/// `ToolUseStart` and input deltas.
+///
+/// ```rust,ignore
+/// let mut acc = StreamAccumulator::new();
+/// while let Some(chunk) = stream.next().await {
+///     acc.push(chunk?);
+/// }
+/// let response = acc.finish();
+/// ```
+#[derive(Debug, Default)]
+pub struct StreamAccumulator {
+    text: String,
+    thinking: String,
+    thinking_signature: Option<String>,
+    refusal: String,
+    tool_calls: BTreeMap<usize, ToolCall>,
+    pending_tools: BTreeMap<usize, ToolCall>,
+    usage: Option<Usage>,
+    finish_reason: Option<FinishReason>,
+}
+
+impl StreamAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one chunk into the response.
+    pub fn push(&mut self, chunk: StreamChunk) {
+        match chunk {
+            StreamChunk::Text(delta) => self.text.push_str(&delta),
+            StreamChunk::Thinking(delta) => self.thinking.push_str(&delta),
+            StreamChunk::ThinkingSignature(signature) => self.thinking_signature = Some(signature),
+            StreamChunk::Refusal(delta) => self.refusal.push_str(&delta),
+            StreamChunk::ToolUseStart { index, id, name } => {
+                self.pending_tools.insert(
+                    index,
+                    ToolCall {
+                        id,
+                        call_type: "function".to_string(),
+                        function: FunctionCall {
+                            name,
+                            arguments: String::new(),
+                        },
+                    },
+                );
+            }
+            StreamChunk::ToolUseInputDelta {
+                index,
+                partial_json,
+            } => {
+                if let Some(call) = self.pending_tools.get_mut(&index) {
+                    call.function.arguments.push_str(&partial_json);
+                }
+            }
+            StreamChunk::ToolUseComplete { index, tool_call } => {
+                self.pending_tools.remove(&index);
+                self.tool_calls.insert(index, tool_call);
+            }
+            StreamChunk::Usage(usage) => {
+                self.usage = Some(match self.usage.take() {
+                    Some(prev) => prev.merge_max(usage),
+                    None => usage,
+                });
+            }
+            StreamChunk::Warning(message) => log::warn!("Stream warning: {message}"),
+            StreamChunk::Done { finish_reason } => self.finish_reason = Some(finish_reason),
+        }
+    }
+
+    /// Drain `stream` and assemble the response, failing on the first
+    /// error chunk.
+    pub async fn collect(
+        mut stream: Pin<Box<dyn Stream<Item = Result<StreamChunk, LLMError>> + Send>>,
+    ) -> Result<Box<dyn ChatResponse>, LLMError> {
+        let mut acc = Self::new();
+        while let Some(chunk) = stream.next().await {
+            acc.push(chunk?);
+        }
+        Ok(acc.finish())
+    }
+
+    /// Assemble the collected chunks into a response.
+    pub fn finish(mut self) -> Box<dyn ChatResponse> {
+        for (index, mut call) in std::mem::take(&mut self.pending_tools) {
+            if call.function.arguments.is_empty() {
+                call.function.arguments = "{}".to_string();
+            }
+            self.tool_calls.entry(index).or_insert(call);
+        }
+
+        Box::new(AccumulatedResponse {
+            text: (!self.text.is_empty()).then_some(self.text),
+            thinking: (!self.thinking.is_empty()).then_some(self.thinking),
+            thinking_signature: self.thinking_signature,
+            refusal: (!self.refusal.is_empty()).then_some(self.refusal),
+            tool_calls: (!self.tool_calls.is_empty())
+                .then(|| self.tool_calls.into_values().collect()),
+            usage: self.usage,
+            finish_reason: self.finish_reason,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct AccumulatedResponse {
+    text: Option<String>,
+    thinking: Option<String>,
+    thinking_signature: Option<String>,
+    refusal: Option<String>,
+    tool_calls: Option<Vec<ToolCall>>,
+    usage: Option<Usage>,
+    finish_reason: Option<FinishReason>,
+}
+
+impl fmt::Display for AccumulatedResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text.as_deref().unwrap_or_default())
+    }
+}
+
+impl ChatResponse for AccumulatedResponse {
+    fn text(&self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn tool_calls(&self) -> Option<Vec<ToolCall>> {
+        self.tool_calls.clone()
+    }
+
+    fn finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason
+    }
+
+    fn thinking(&self) -> Option<String> {
+        self.thinking.clone()
+    }
+
+    fn thinking_signature(&self) -> Option<String> {
+        self.thinking_signature.clone()
+    }
+
+    fn usage(&self) -> Option<Usage> {
+        self.usage.clone()
+    }
+
+    fn refusal(&self) -> Option<String> {
+        self.refusal.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::{ChatMessage, Content};
+
+    fn call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn assembles_text_tool_call_usage_and_finish_reason() {
+        let usage = Usage {
+            input_tokens: 12,
+            output_tokens: 7,
+            ..Default::default()
+        };
+        let mut acc = StreamAccumulator::new();
+        for chunk in [
+            StreamChunk::Thinking("let me ".into()),
+            StreamChunk::Thinking("check".into()),
+            StreamChunk::Text("Checking ".into()),
+            StreamChunk::Text("the weather.".into()),
+            StreamChunk::ToolUseStart {
+                index: 1,
+                id: "call_1".into(),
+                name: "get_weather".into(),
+            },
+            StreamChunk::ToolUseInputDelta {
+                index: 1,
+                partial_json: "{\"city\":".into(),
+            },
+            StreamChunk::ToolUseComplete {
+                index: 1,
+                tool_call: call("call_1", "get_weather", "{\"city\":\"Paris\"}"),
+            },
+            StreamChunk::Usage(usage.clone()),
+            StreamChunk::Done {
+                finish_reason: FinishReason::ToolCalls,
+            },
+        ] {
+            acc.push(chunk);
+        }
+
+        let resp = acc.finish();
+        assert_eq!(resp.text().as_deref(), Some("Checking the weather."));
+        assert_eq!(resp.thinking().as_deref(), Some("let me check"));
+        assert_eq!(
+            resp.tool_calls(),
+            Some(vec![call("call_1", "get_weather", "{\"city\":\"Paris\"}")])
+        );
+        assert_eq!(resp.usage(), Some(usage));
+        assert_eq!(resp.finish_reason(), Some(FinishReason::ToolCalls));
+    }
+
+    #[test]
+    fn signed_thinking_survives_into_chat_message() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(StreamChunk::Thinking("hmm".into()));
+        acc.push(StreamChunk::ThinkingSignature("sig-1".into()));
+        acc.push(StreamChunk::Text("done".into()));
+
+        let resp = acc.finish();
+        assert_eq!(resp.thinking_signature().as_deref(), Some("sig-1"));
+        assert_eq!(
+            ChatMessage::from(resp).content[0],
+            Content::Thinking {
+                text: "hmm".into(),
+                signature: Some("sig-1".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn refusal_deltas_become_the_message_content() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(StreamChunk::Refusal("I can't ".into()));
+        acc.push(StreamChunk::Refusal("help with that.".into()));
+        acc.push(StreamChunk::Done {
+            finish_reason: FinishReason::Refusal,
+        });
+
+        let resp = acc.finish();
+        assert_eq!(resp.refusal().as_deref(), Some("I can't help with that."));
+        assert_eq!(resp.finish_reason(), Some(FinishReason::Refusal));
+        assert_eq!(
+            ChatMessage::from(resp).content,
+            vec![Content::text("I can't help with that.")]
+        );
+    }
+
+    #[test]
+    fn merges_split_usage_chunks() {
+        let mut acc = StreamAccumulator::new();
+        acc.push(StreamChunk::Usage(Usage {
+            input_tokens: 40,
+            ..Default::default()
+        }));
+        acc.push(StreamChunk::Usage(Usage {
+            output_tokens: 9,
+            ..Default::default()
+        }));
+
+        let usage = acc.finish().usage().unwrap();
+        assert_eq!((usage.input_tokens, usage.output_tokens), (40, 9));
+    }
+
+    #[test]
+    fn rebuilds_tool_calls_without_complete_chunk_in_index_order() {
+        let mut acc = StreamAccumulator::new();
+        for chunk in [
+            StreamChunk::ToolUseStart {
+                index: 2,
+                id: "b".into(),
+                name: "second".into(),
+            },
+            StreamChunk::ToolUseStart {
+                index: 0,
+                id: "a".into(),
+                name: "first".into(),
+            },
+            StreamChunk::ToolUseInputDelta {
+                index: 0,
+                partial_json: "{\"x\":".into(),
+            },
+            StreamChunk::ToolUseInputDelta {
+                index: 0,
+                partial_json: "1}".into(),
+            },
+        ] {
+            acc.push(chunk);
+        }
+
+        let resp = acc.finish();
+        assert_eq!(
+            resp.tool_calls(),
+            Some(vec![
+                call("a", "first", "{\"x\":1}"),
+                call("b", "second", "{}")
+            ])
+        );
+        assert_eq!(resp.text(), None);
+        assert_eq!(resp.finish_reason(), None);
+    }
+}
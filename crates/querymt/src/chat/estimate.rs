@@ -0,0 +1,192 @@
+//! Rough, tokenizer-free token estimates for chat messages.
+//!
+//! [`estimate_tokens`] is meant for cheap client-side budgeting, such as an
+//! approximate context-fill bar. It is not a substitute for a provider's own
+//! token counting: for English prose it is usually within ±25% of the real
+//! count, and it underestimates scripts that tokenize densely (e.g. CJK).
+
+use super::{ChatMessage, Content};
+
+/// Characters per token for text, the usual rule of thumb for English.
+const CHARS_PER_TOKEN: usize = 4;
+/// Fixed framing per message (role markers, separators).
+const MESSAGE_OVERHEAD: usize = 4;
+/// Framing per tool call or tool result block.
+const TOOL_BLOCK_OVERHEAD: usize = 8;
+/// Pixels per image token, as in Anthropic's published `w * h / 750`.
+const PIXELS_PER_IMAGE_TOKEN: usize = 750;
+/// Upper bound for a single image; providers downscale larger images.
+const MAX_IMAGE_TOKENS: usize = 1_600;
+/// Used for images whose dimensions can't be read (URLs, unknown formats).
+const DEFAULT_IMAGE_TOKENS: usize = 1_000;
+/// Per PDF page, assuming the page is sent as both text and an image.
+const PDF_PAGE_TOKENS: usize = 1_500;
+/// Audio bytes per token, assuming 16 kB/s of audio at 32 tokens/s.
+const AUDIO_BYTES_PER_TOKEN: usize = 500;
+
+/// Estimate how many input tokens `messages` will use.
+///
+/// This is a heuristic, not a count:
+///
+/// - text, thinking and tool arguments: one token per 4 characters;
+/// - every message: 4 tokens of framing, plus 8 per tool call or result;
+/// - images: `width * height / 750` tokens, capped at 1600, when the
+///   dimensions can be read from PNG, JPEG or GIF data, otherwise 1000;
+/// - PDFs: 1500 tokens per page;
+/// - audio: one token per 500 bytes.
+///
+/// See the [module docs](self) for the expected accuracy.
+pub fn estimate_tokens(messages: &[ChatMessage]) -> u32 {
+    let total: usize = messages
+        .iter()
+        .map(|m| MESSAGE_OVERHEAD + m.content.iter().map(content_tokens).sum::<usize>())
+        .sum();
+    u32::try_from(total).unwrap_or(u32::MAX)
+}
+
+fn content_tokens(content: &Content) -> usize {
+    match content {
+        Content::Text { text } | Content::Thinking { text, .. } => text_tokens(text),
+        Content::Image { data, .. } => image_dimensions(data)
+            .map(|(w, h)| {
+                (w as usize * h as usize)
+                    .div_ceil(PIXELS_PER_IMAGE_TOKEN)
+                    .min(MAX_IMAGE_TOKENS)
+            })
+            .unwrap_or(DEFAULT_IMAGE_TOKENS),
+        Content::ImageUrl { .. } => DEFAULT_IMAGE_TOKENS,
+        Content::Pdf { data } => PDF_PAGE_TOKENS * pdf_page_count(data).max(1),
+        Content::Audio { data, .. } => data.len().div_ceil(AUDIO_BYTES_PER_TOKEN),
+        Content::ToolUse {
+            name, arguments, ..
+        } => TOOL_BLOCK_OVERHEAD + text_tokens(name) + text_tokens(&arguments.to_string()),
+        Content::ToolResult { content, .. } => {
+            TOOL_BLOCK_OVERHEAD + content.iter().map(content_tokens).sum::<usize>()
+        }
+        Content::ResourceLink {
+            uri,
+            name,
+            description,
+            ..
+        } => {
+            text_tokens(uri)
+                + name.as_deref().map_or(0, text_tokens)
+                + description.as_deref().map_or(0, text_tokens)
+        }
+    }
+}
+
+fn text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// Read `(width, height)` from the header of a PNG, GIF or JPEG image.
+fn image_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]) as u32);
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        let be32 = |i: usize| Some(u32::from_be_bytes(data.get(i..i + 4)?.try_into().ok()?));
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF8") {
+        let le16 = |i: usize| Some(u16::from_le_bytes([*data.get(i)?, *data.get(i + 1)?]) as u32);
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        // Walk the marker segments up to the first start-of-frame.
+        let mut i = 2;
+        while *data.get(i)? == 0xFF {
+            let marker = *data.get(i + 1)?;
+            let is_sof = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_sof {
+                return Some((be16(i + 7)?, be16(i + 5)?));
+            }
+            i += 2 + be16(i + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// Count `/Type /Page` objects, skipping the `/Pages` tree nodes.
+fn pdf_page_count(data: &[u8]) -> usize {
+    let mut count = 0;
+    for needle in [&b"/Type /Page"[..], &b"/Type/Page"[..]] {
+        count += data
+            .windows(needle.len() + 1)
+            .filter(|w| w.starts_with(needle) && w[needle.len()] != b's')
+            .count();
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn within(estimate: u32, actual: u32, tolerance: f64) -> bool {
+        (estimate as f64 - actual as f64).abs() <= actual as f64 * tolerance
+    }
+
+    #[test]
+    fn english_text_is_within_tolerance() {
+        // Reference counts from the cl100k_base tokenizer.
+        let cases = [
+            ("The quick brown fox jumps over the lazy dog.", 10),
+            ("Hello, world!", 4),
+        ];
+        for (text, actual) in cases {
+            let estimate = estimate_tokens(&[ChatMessage::user().text(text).build()])
+                - MESSAGE_OVERHEAD as u32;
+            assert!(within(estimate, actual, 0.25), "{text}: {estimate}");
+        }
+    }
+
+    #[test]
+    fn counts_framing_per_message() {
+        let messages = vec![
+            ChatMessage::user().text("abcd").build(),
+            ChatMessage::assistant().text("abcdefgh").build(),
+        ];
+        assert_eq!(estimate_tokens(&messages), 4 + 1 + 4 + 2);
+        assert_eq!(estimate_tokens(&[]), 0);
+    }
+
+    #[test]
+    fn uses_png_dimensions_for_images() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&300u32.to_be_bytes());
+        png.extend_from_slice(&250u32.to_be_bytes());
+        let message = ChatMessage::user().image("image/png", png).build();
+        assert_eq!(estimate_tokens(&[message]), 4 + 100);
+
+        let unknown = ChatMessage::user().image("image/webp", vec![0; 16]).build();
+        assert_eq!(estimate_tokens(&[unknown]), 4 + DEFAULT_IMAGE_TOKENS as u32);
+    }
+
+    #[test]
+    fn reads_jpeg_and_gif_dimensions() {
+        let jpeg = [
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0, 2 bytes of payload
+            0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0xE0, 0x02, 0x80, // SOF0 480x640
+        ];
+        assert_eq!(image_dimensions(&jpeg), Some((640, 480)));
+
+        let gif = b"GIF89a\x40\x01\xc8\x00";
+        assert_eq!(image_dimensions(gif), Some((320, 200)));
+        assert_eq!(image_dimensions(b"\xFF\xD8"), None);
+    }
+
+    #[test]
+    fn counts_pdf_pages_and_tool_blocks() {
+        let pdf = b"<< /Type /Pages /Count 2 >> << /Type /Page >> << /Type/Page >>".to_vec();
+        assert_eq!(pdf_page_count(&pdf), 2);
+
+        let message = ChatMessage::assistant()
+            .tool_use("call_1", "echo", json!({ "text": "hi" }))
+            .build();
+        // "echo" = 1, {"text":"hi"} (13 chars) = 4, plus tool and message framing.
+        assert_eq!(estimate_tokens(&[message]), 4 + 8 + 1 + 4);
+    }
+}
@@ -0,0 +1,232 @@
+//! Best-effort repair of slightly malformed tool-call arguments.
+//!
+//! Models, local ones especially, sometimes stream tool arguments that are
+//! almost JSON: a trailing comma, single-quoted strings, bare object keys or
+//! a string cut off by the token limit. [`repair_json`] fixes those cases so
+//! that callers deserializing the arguments don't fail outright.
+
+use std::iter::Peekable;
+use std::str::Chars;
+
+use serde_json::Value;
+
+use crate::ToolCall;
+
+/// Whether tool-call argument repair is enabled by a provider config.
+///
+/// Reads the `repair_tool_json` flag, which defaults to `true`; set it to
+/// `false` to receive arguments exactly as the model produced them.
+pub fn repair_enabled(cfg: &Value) -> bool {
+    cfg.get("repair_tool_json")
+        .and_then(Value::as_bool)
+        .unwrap_or(true)
+}
+
+/// Repair `call`'s arguments in place if they are not valid JSON.
+///
+/// Returns `true` when the arguments were rewritten. Valid or empty
+/// arguments, and ones that cannot be repaired, are left untouched.
+pub fn repair_tool_arguments(call: &mut ToolCall) -> bool {
+    let args = &call.function.arguments;
+    if args.trim().is_empty() || serde_json::from_str::<Value>(args).is_ok() {
+        return false;
+    }
+    match repair_json(args) {
+        Some(repaired) => {
+            log::debug!(
+                "Repaired malformed arguments for tool call '{}'",
+                call.function.name
+            );
+            call.function.arguments = repaired;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Attempt to turn almost-JSON into valid JSON.
+///
+/// Handles trailing commas, single-quoted strings, unquoted object keys and
+/// unterminated strings, objects or arrays. Returns the repaired document in
+/// compact form, or `None` if it still does not parse.
+pub fn repair_json(input: &str) -> Option<String> {
+    let mut out = String::with_capacity(input.len() + 8);
+    // Open `{` / `[` brackets, innermost last.
+    let mut stack: Vec<char> = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => push_string(c, &mut chars, &mut out),
+            '{' | '[' => {
+                stack.push(c);
+                out.push(c);
+            }
+            '}' | ']' => {
+                trim_trailing_comma(&mut out);
+                if stack.last() == Some(&if c == '}' { '{' } else { '[' }) {
+                    stack.pop();
+                }
+                out.push(c);
+            }
+            c if is_key_start(c) && stack.last() == Some(&'{') => {
+                let mut word = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if !is_key_char(next) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                let is_key = chars.clone().find(|c| !c.is_whitespace()) == Some(':');
+                if is_key {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(&word);
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    if out.ends_with(':') {
+        out.push_str("null");
+    }
+    while let Some(open) = stack.pop() {
+        trim_trailing_comma(&mut out);
+        out.push(if open == '{' { '}' } else { ']' });
+    }
+
+    serde_json::from_str::<Value>(&out)
+        .ok()
+        .map(|value| value.to_string())
+}
+
+/// Copy a string opened by `quote` into `out` as a double-quoted JSON
+/// string, closing it if the input ends first.
+fn push_string(quote: char, chars: &mut Peekable<Chars<'_>>, out: &mut String) {
+    out.push('"');
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // `\'` is not a JSON escape.
+                Some('\'') => out.push('\''),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => {}
+            },
+            c if c == quote => {
+                out.push('"');
+                return;
+            }
+            // Only reachable inside a single-quoted string.
+            '"' => out.push_str("\\\""),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn trim_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end().len();
+    out.truncate(trimmed);
+    if out.ends_with(',') {
+        out.pop();
+    }
+}
+
+fn is_key_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '$'
+}
+
+fn is_key_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '$' | '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionCall;
+    use serde_json::json;
+
+    fn repaired(input: &str) -> Value {
+        let out = repair_json(input).unwrap_or_else(|| panic!("could not repair {input:?}"));
+        serde_json::from_str(&out).unwrap()
+    }
+
+    #[test]
+    fn removes_trailing_commas() {
+        assert_eq!(
+            repaired(r#"{"path": "src/main.rs", "lines": [1, 2,],}"#),
+            json!({"path": "src/main.rs", "lines": [1, 2]})
+        );
+    }
+
+    #[test]
+    fn converts_single_quotes_and_bare_keys() {
+        assert_eq!(
+            repaired(r#"{path: 'it\'s "here"', recursive: true}"#),
+            json!({"path": "it's \"here\"", "recursive": true})
+        );
+    }
+
+    #[test]
+    fn closes_unterminated_strings_and_containers() {
+        assert_eq!(
+            repaired(r#"{"cmd": "ls", "args": ["-la", "/tm"#),
+            json!({"cmd": "ls", "args": ["-la", "/tm"]})
+        );
+        assert_eq!(repaired(r#"{"a": 1, "b":"#), json!({"a": 1, "b": null}));
+    }
+
+    #[test]
+    fn leaves_commas_and_quotes_inside_strings_alone() {
+        assert_eq!(
+            repaired(r#"{"text": "a, b,}", 'q': "it's",}"#),
+            json!({"text": "a, b,}", "q": "it's"})
+        );
+    }
+
+    #[test]
+    fn gives_up_on_unrepairable_input() {
+        assert_eq!(repair_json("not json at all"), None);
+    }
+
+    fn call(arguments: &str) -> ToolCall {
+        ToolCall {
+            id: "call_1".into(),
+            call_type: "function".into(),
+            function: FunctionCall {
+                name: "read_file".into(),
+                arguments: arguments.into(),
+            },
+        }
+    }
+
+    #[test]
+    fn repairs_only_invalid_tool_arguments() {
+        let mut broken = call(r#"{"path": "a.txt",}"#);
+        assert!(repair_tool_arguments(&mut broken));
+        assert_eq!(broken.function.arguments, r#"{"path":"a.txt"}"#);
+
+        let valid_args = r#"{ "path": "a.txt" }"#;
+        let mut valid = call(valid_args);
+        assert!(!repair_tool_arguments(&mut valid));
+        assert_eq!(valid.function.arguments, valid_args);
+    }
+
+    #[test]
+    fn repair_is_enabled_unless_config_opts_out() {
+        assert!(repair_enabled(&json!({})));
+        assert!(repair_enabled(&json!({ "repair_tool_json": true })));
+        assert!(!repair_enabled(&json!({ "repair_tool_json": false })));
+    }
+}
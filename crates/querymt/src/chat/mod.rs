@@ -10,7 +10,15 @@ use crate::{ToolCall, Usage, error::LLMError};
 use futures::Stream;
 use std::pin::Pin;
 
+pub mod accumulator;
+pub mod estimate;
 pub mod http;
+pub mod json_repair;
+#[cfg(feature = "tool-validation")]
+pub mod tool_validation;
+
+pub use accumulator::StreamAccumulator;
+pub use estimate::estimate_tokens;
 
 // ---------------------------------------------------------------------------
 // Content — a single content block within a message
@@ -253,6 +261,18 @@ impl PartialEq for Content {
 
 impl Eq for Content {}
 
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Content::Text { text }
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Content::text(text)
+    }
+}
+
 impl fmt::Display for Content {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -373,6 +393,9 @@ pub enum ChatRole {
     User,
     /// The AI assistant participant in the conversation
     Assistant,
+    /// Instructions for the assistant. Providers hoist these to the top of
+    /// the request, after any configured `system` prompt.
+    System,
 }
 
 /// Cache hint for providers that support prompt caching.
@@ -427,6 +450,11 @@ pub struct ChatMessage {
     /// will translate this into provider-specific cache breakpoint markers.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub cache: Option<CacheHint>,
+    /// Optional participant name, distinguishing several users or
+    /// assistants in multi-agent transcripts. Sent by providers that
+    /// support named participants (OpenAI-compatible, Ollama).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
 }
 
 /// Represents a parameter in a function tool
@@ -733,14 +761,104 @@ impl JsonSchema for ToolChoice {
     }
 }
 
+/// `calls`, or `None` if it holds no calls.
+///
+/// For [`ChatResponse::tool_calls`] implementations whose wire format may
+/// carry an empty list.
+pub fn non_empty_tool_calls(calls: Option<Vec<ToolCall>>) -> Option<Vec<ToolCall>> {
+    calls.filter(|calls| !calls.is_empty())
+}
+
+/// A tool the provider ran on its own side (e.g. Anthropic's web search or
+/// code execution), reported for information only: unlike
+/// [`ChatResponse::tool_calls`], the caller must not execute it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerToolUse {
+    pub id: String,
+    pub name: String,
+    pub input: Value,
+    /// The result block the provider returned for this call, verbatim.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+}
+
+/// A source backing part of a response, from web search grounding or
+/// document citations.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Citation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Start of the supported span, as a byte offset into
+    /// [`ChatResponse::text`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<usize>,
+    /// End (exclusive) of the supported span, as a byte offset into
+    /// [`ChatResponse::text`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<usize>,
+}
+
 pub trait ChatResponse: std::fmt::Debug + std::fmt::Display + Send {
     fn text(&self) -> Option<String>;
+    /// Tool calls requested by the model. `None` when there are none, never
+    /// an empty list.
     fn tool_calls(&self) -> Option<Vec<ToolCall>>;
     fn finish_reason(&self) -> Option<FinishReason>;
+    /// The custom stop sequence that ended generation, for providers that
+    /// report which one matched.
+    fn stop_sequence(&self) -> Option<String> {
+        None
+    }
     fn thinking(&self) -> Option<String> {
         None
     }
+    /// Signature of the thinking block, for providers (Anthropic) that
+    /// require it when the block is sent back in a later turn.
+    fn thinking_signature(&self) -> Option<String> {
+        None
+    }
     fn usage(&self) -> Option<Usage>;
+    /// The model's explanation when it refused the request
+    /// ([`FinishReason::Refusal`]).
+    fn refusal(&self) -> Option<String> {
+        None
+    }
+    /// Text of every candidate completion, in the order returned by the
+    /// provider. `None` for providers that only ever return one candidate.
+    fn candidates(&self) -> Option<Vec<String>> {
+        None
+    }
+    /// Images generated by the model, as `(mime_type, bytes)` pairs.
+    fn images(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        None
+    }
+    /// The provider's response body as received, for troubleshooting
+    /// responses that map incorrectly. Only kept by providers configured
+    /// with `keep_raw`.
+    fn raw(&self) -> Option<&Value> {
+        None
+    }
+    /// Identifier the provider assigned to this response or request (e.g.
+    /// OpenAI's `chatcmpl-…` id, Anthropic's `msg_…` id), to quote when
+    /// contacting the provider's support.
+    fn response_id(&self) -> Option<String> {
+        None
+    }
+    /// Backend configuration fingerprint reported by OpenAI-compatible APIs,
+    /// which changes when the serving stack changes.
+    fn system_fingerprint(&self) -> Option<String> {
+        None
+    }
+    /// Server-side tools the provider ran while producing this response.
+    fn server_tool_uses(&self) -> Option<Vec<ServerToolUse>> {
+        None
+    }
+    /// Sources the provider attached to the response text.
+    fn citations(&self) -> Option<Vec<Citation>> {
+        None
+    }
 }
 
 impl From<&dyn ChatResponse> for ChatMessage {
@@ -750,12 +868,19 @@ impl From<&dyn ChatResponse> for ChatMessage {
         if let Some(t) = response.thinking()
             && !t.is_empty()
         {
-            content.push(Content::thinking(t));
+            content.push(Content::Thinking {
+                text: t,
+                signature: response.thinking_signature(),
+            });
         }
         if let Some(text) = response.text()
             && !text.is_empty()
         {
             content.push(Content::text(text));
+        } else if let Some(refusal) = response.refusal()
+            && !refusal.is_empty()
+        {
+            content.push(Content::text(refusal));
         }
         if let Some(calls) = response.tool_calls() {
             for call in calls {
@@ -772,6 +897,7 @@ impl From<&dyn ChatResponse> for ChatMessage {
             role: ChatRole::Assistant,
             content,
             cache: None,
+            name: None,
         }
     }
 }
@@ -789,6 +915,9 @@ pub enum FinishReason {
     ContentFilter,
     ToolCalls,
     Error,
+    /// The model declined the request, e.g. for safety reasons. See
+    /// [`ChatResponse::refusal`] for the explanation, when provided.
+    Refusal,
     Other,
     Unknown,
 }
@@ -808,6 +937,10 @@ pub enum StreamChunk {
     /// signed thinking replay emit this once the block is complete).
     ThinkingSignature(String),
 
+    /// Refusal text delta. A stream carrying one ends with
+    /// [`FinishReason::Refusal`].
+    Refusal(String),
+
     /// Tool use block started (contains tool id and name)
     ToolUseStart {
         /// The index of this content block in the response
@@ -837,6 +970,10 @@ pub enum StreamChunk {
     /// Usage metadata containing token counts
     Usage(Usage),
 
+    /// Non-fatal condition reported mid-stream, e.g. a malformed tool call
+    /// the provider had to drop. The stream continues after a warning.
+    Warning(String),
+
     /// Stream ended with finish reason
     Done {
         /// The typed finish reason from the provider, mapped at emission time
@@ -891,11 +1028,25 @@ pub trait ChatProvider: Send + Sync {
     /// * `messages` - The conversation history
     /// * `tools` - Optional list of tools available to the model. Pass `None` to disable tools
     ///   for this specific call, even if the provider has tools configured.
+    ///
+    /// # Default Implementation
+    ///
+    /// Providers that only implement streaming get a non-streaming API for free: when
+    /// `supports_streaming()` is true, the stream from `chat_stream_with_tools` is collected
+    /// with a [`StreamAccumulator`]. Otherwise this returns a `NotImplemented` error.
     async fn chat_with_tools(
         &self,
         messages: &[ChatMessage],
         tools: Option<&[Tool]>,
-    ) -> Result<Box<dyn ChatResponse>, LLMError>;
+    ) -> Result<Box<dyn ChatResponse>, LLMError> {
+        if !self.supports_streaming() {
+            return Err(LLMError::NotImplemented(
+                "Chat not supported by this provider".into(),
+            ));
+        }
+        let stream = self.chat_stream_with_tools(messages, tools).await?;
+        StreamAccumulator::collect(stream).await
+    }
 
     /// Basic streaming chat interaction.
     ///
@@ -972,12 +1123,23 @@ impl ChatMessage {
         ChatMessageBuilder::new(ChatRole::Assistant)
     }
 
+    /// Create a new builder for a system message.
+    pub fn system() -> ChatMessageBuilder {
+        ChatMessageBuilder::new(ChatRole::System)
+    }
+
+    /// Returns true if this is a system message.
+    pub fn is_system(&self) -> bool {
+        self.role == ChatRole::System
+    }
+
     /// Convenience: create a user message from content blocks.
     pub fn from_user(content: Vec<Content>) -> Self {
         ChatMessage {
             role: ChatRole::User,
             content,
             cache: None,
+            name: None,
         }
     }
 
@@ -987,6 +1149,7 @@ impl ChatMessage {
             role: ChatRole::Assistant,
             content,
             cache: None,
+            name: None,
         }
     }
 
@@ -1023,6 +1186,105 @@ impl ChatMessage {
     }
 }
 
+/// Text of the non-empty system messages in `messages`, in order.
+///
+/// Providers append these to their configured `system` prompt and skip
+/// system messages when converting the rest of the conversation.
+pub fn system_prompts(messages: &[ChatMessage]) -> Vec<String> {
+    messages
+        .iter()
+        .filter(|m| m.is_system())
+        .map(ChatMessage::text)
+        .filter(|text| !text.is_empty())
+        .collect()
+}
+
+/// Check that tool calls and tool results in `messages` pair up.
+///
+/// Every `ToolUse` in an assistant message needs a `ToolResult` with the
+/// same id before the next assistant message, and every `ToolResult` must
+/// answer a `ToolUse` of the latest assistant message. Providers reject
+/// conversations that break this with an opaque 400; this reports the
+/// offending id instead, as [`LLMError::InvalidRequest`].
+pub fn validate_conversation(messages: &[ChatMessage]) -> Result<(), LLMError> {
+    let mut pending: Vec<&str> = Vec::new();
+    let mut answerable: Vec<&str> = Vec::new();
+
+    for message in messages.iter().filter(|m| !m.is_system()) {
+        if message.role == ChatRole::Assistant {
+            if let Some(id) = pending.first() {
+                return Err(LLMError::InvalidRequest(format!(
+                    "tool call '{id}' has no matching tool result"
+                )));
+            }
+            answerable.clear();
+        }
+        for block in &message.content {
+            match block {
+                Content::ToolUse { id, .. } => {
+                    pending.push(id);
+                    answerable.push(id);
+                }
+                Content::ToolResult { id, .. } => {
+                    if !answerable.contains(&id.as_str()) {
+                        return Err(LLMError::InvalidRequest(format!(
+                            "tool result '{id}' does not answer a tool call of the preceding \
+                             assistant message"
+                        )));
+                    }
+                    pending.retain(|pending| *pending != id);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    match pending.first() {
+        Some(id) => Err(LLMError::InvalidRequest(format!(
+            "tool call '{id}' has no matching tool result"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// HEIC `ftyp` major brands.
+const HEIC_BRANDS: [&[u8; 4]; 6] = [b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx"];
+
+/// Detect an image MIME type from the file's magic bytes.
+///
+/// Recognizes PNG, JPEG, GIF, WebP and HEIC.
+pub fn detect_image_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.len() >= 12
+        && &data[4..8] == b"ftyp"
+        && HEIC_BRANDS.iter().any(|b| &data[8..12] == *b)
+    {
+        Some("image/heic")
+    } else {
+        None
+    }
+}
+
+/// Map an image file extension (without the dot, case-insensitive) to its
+/// MIME type.
+pub fn image_mime_from_extension(ext: &str) -> Option<&'static str> {
+    match ext.to_ascii_lowercase().as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "heic" | "heif" => Some("image/heic"),
+        _ => None,
+    }
+}
+
 /// Builder for ChatMessage.
 ///
 /// Accumulates `Content` blocks and produces a `ChatMessage`.
@@ -1031,6 +1293,7 @@ pub struct ChatMessageBuilder {
     role: ChatRole,
     content: Vec<Content>,
     cache: Option<CacheHint>,
+    name: Option<String>,
 }
 
 impl ChatMessageBuilder {
@@ -1040,6 +1303,7 @@ impl ChatMessageBuilder {
             role,
             content: Vec::new(),
             cache: None,
+            name: None,
         }
     }
 
@@ -1065,6 +1329,20 @@ impl ChatMessageBuilder {
         self
     }
 
+    /// Read an image file and append it as an image content block.
+    ///
+    /// The MIME type is sniffed from the file contents; anything other than
+    /// PNG, JPEG, GIF, WebP or HEIC is rejected with
+    /// [`LLMError::InvalidRequest`].
+    pub fn image_path(self, path: impl AsRef<std::path::Path>) -> Result<Self, LLMError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        let mime = detect_image_mime(&data).ok_or_else(|| {
+            LLMError::InvalidRequest(format!("unsupported image format: {}", path.display()))
+        })?;
+        Ok(self.image(mime, data))
+    }
+
     /// Append an image URL content block.
     pub fn image_url(mut self, url: impl Into<String>) -> Self {
         self.content.push(Content::image_url(url));
@@ -1077,6 +1355,22 @@ impl ChatMessageBuilder {
         self
     }
 
+    /// Read a PDF file and append it as a PDF content block.
+    ///
+    /// Files without a `%PDF-` header are rejected with
+    /// [`LLMError::InvalidRequest`].
+    pub fn pdf_path(self, path: impl AsRef<std::path::Path>) -> Result<Self, LLMError> {
+        let path = path.as_ref();
+        let data = std::fs::read(path)?;
+        if !data.starts_with(b"%PDF-") {
+            return Err(LLMError::InvalidRequest(format!(
+                "not a PDF file: {}",
+                path.display()
+            )));
+        }
+        Ok(self.pdf(data))
+    }
+
     /// Append a tool use content block.
     pub fn tool_use(mut self, id: impl Into<String>, name: impl Into<String>, args: Value) -> Self {
         self.content.push(Content::tool_use(id, name, args));
@@ -1112,12 +1406,19 @@ impl ChatMessageBuilder {
         self
     }
 
+    /// Set the participant name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
     /// Build the ChatMessage.
     pub fn build(self) -> ChatMessage {
         ChatMessage {
             role: self.role,
             content: self.content,
             cache: self.cache,
+            name: self.name,
         }
     }
 }
@@ -1126,6 +1427,89 @@ impl ChatMessageBuilder {
 mod tests {
     use super::*;
 
+    #[test]
+    fn non_empty_tool_calls_maps_empty_to_none() {
+        assert_eq!(non_empty_tool_calls(None), None);
+        assert_eq!(non_empty_tool_calls(Some(vec![])), None);
+
+        let call = ToolCall {
+            id: "call_1".into(),
+            call_type: "function".into(),
+            function: crate::FunctionCall {
+                name: "echo".into(),
+                arguments: "{}".into(),
+            },
+        };
+        assert_eq!(
+            non_empty_tool_calls(Some(vec![call.clone()])),
+            Some(vec![call])
+        );
+    }
+
+    #[test]
+    fn system_prompts_collects_system_messages_in_order() {
+        let messages = vec![
+            ChatMessage::system().text("Be brief.").build(),
+            ChatMessage::user().text("hi").build(),
+            ChatMessage::system().build(),
+            ChatMessage::system().text("Answer in French.").build(),
+        ];
+        assert_eq!(
+            system_prompts(&messages),
+            vec!["Be brief.".to_string(), "Answer in French.".to_string()]
+        );
+    }
+
+    #[test]
+    fn strings_convert_into_text_tool_result_content() {
+        let msg = ChatMessage::user()
+            .tool_result(
+                "call_1".to_string(),
+                None,
+                false,
+                vec!["done".to_string().into()],
+            )
+            .build();
+        assert_eq!(
+            msg.content,
+            vec![Content::tool_result("call_1", vec![Content::text("done")])]
+        );
+    }
+
+    /// Implements only the streaming path.
+    struct StreamingOnly;
+
+    #[async_trait]
+    impl ChatProvider for StreamingOnly {
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+
+        async fn chat_stream_with_tools(
+            &self,
+            _messages: &[ChatMessage],
+            _tools: Option<&[Tool]>,
+        ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LLMError>> + Send>>, LLMError>
+        {
+            let chunks = vec![
+                Ok(StreamChunk::Text("Hello, ".into())),
+                Ok(StreamChunk::Text("world".into())),
+                Ok(StreamChunk::Done {
+                    finish_reason: FinishReason::Stop,
+                }),
+            ];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    #[tokio::test]
+    async fn chat_collects_stream_for_streaming_only_providers() {
+        let messages = vec![ChatMessage::user().text("hi").build()];
+        let response = StreamingOnly.chat(&messages).await.unwrap();
+        assert_eq!(response.text().as_deref(), Some("Hello, world"));
+        assert_eq!(response.finish_reason(), Some(FinishReason::Stop));
+    }
+
     #[test]
     fn extract_thinking_handles_multiple_blocks() {
         let input = "start <think>reasoning 1</think> middle <think>reasoning 2</think> end";
@@ -1184,6 +1568,78 @@ mod tests {
         }
     }
 
+    const PNG: &[u8] = &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0, 0];
+
+    fn write_temp(name: &str, data: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("querymt-{}-{name}", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn detect_image_mime_sniffs_magic_bytes() {
+        assert_eq!(detect_image_mime(PNG), Some("image/png"));
+        assert_eq!(
+            detect_image_mime(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]),
+            Some("image/jpeg")
+        );
+        assert_eq!(
+            detect_image_mime(b"RIFF\x24\x00\x00\x00WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(detect_image_mime(b"GIF89a..."), Some("image/gif"));
+        assert_eq!(
+            detect_image_mime(b"\x00\x00\x00\x18ftypheic\x00\x00\x00\x00"),
+            Some("image/heic")
+        );
+        assert_eq!(
+            detect_image_mime(b"\x00\x00\x00\x18ftypisom\x00\x00\x00\x00"),
+            None
+        );
+        assert_eq!(detect_image_mime(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(detect_image_mime(b"%PDF-1.7"), None);
+    }
+
+    #[test]
+    fn image_mime_from_extension_is_case_insensitive() {
+        assert_eq!(image_mime_from_extension("WebP"), Some("image/webp"));
+        assert_eq!(image_mime_from_extension("gif"), Some("image/gif"));
+        assert_eq!(image_mime_from_extension("HEIC"), Some("image/heic"));
+        assert_eq!(image_mime_from_extension("jpg"), Some("image/jpeg"));
+        assert_eq!(image_mime_from_extension("bmp"), None);
+    }
+
+    #[test]
+    fn builder_image_path_infers_mime() {
+        let path = write_temp("image.bin", PNG);
+        let msg = ChatMessage::user().image_path(&path).unwrap().build();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(msg.content, vec![Content::image("image/png", PNG.to_vec())]);
+    }
+
+    #[test]
+    fn builder_image_path_rejects_unknown_format() {
+        let path = write_temp("notes.png", b"plain text");
+        let err = ChatMessage::user().image_path(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, LLMError::InvalidRequest(_)));
+    }
+
+    #[test]
+    fn builder_pdf_path_checks_header() {
+        let pdf = write_temp("doc.pdf", b"%PDF-1.7\n");
+        let msg = ChatMessage::user().pdf_path(&pdf).unwrap().build();
+        std::fs::remove_file(&pdf).unwrap();
+        assert_eq!(msg.content, vec![Content::pdf(b"%PDF-1.7\n".to_vec())]);
+
+        let fake = write_temp("fake.pdf", PNG);
+        let err = ChatMessage::user().pdf_path(&fake).unwrap_err();
+        std::fs::remove_file(&fake).unwrap();
+        assert!(matches!(err, LLMError::InvalidRequest(_)));
+    }
+
     #[test]
     fn builder_produces_correct_blocks() {
         let msg = ChatMessage::user()
@@ -1242,6 +1698,75 @@ mod tests {
         assert_eq!(blocks, roundtripped);
     }
 
+    fn tool_call_turn(ids: &[&str]) -> ChatMessage {
+        ids.iter()
+            .fold(ChatMessage::assistant(), |builder, id| {
+                builder.tool_use(*id, "read_file", serde_json::json!({}))
+            })
+            .build()
+    }
+
+    fn tool_result_turn(ids: &[&str]) -> ChatMessage {
+        ids.iter()
+            .fold(ChatMessage::user(), |builder, id| {
+                builder.tool_result(id.to_string(), None, false, vec!["ok".into()])
+            })
+            .build()
+    }
+
+    #[test]
+    fn validate_conversation_accepts_paired_tool_calls() {
+        let messages = vec![
+            ChatMessage::user().text("read both").build(),
+            tool_call_turn(&["call_1", "call_2"]),
+            tool_result_turn(&["call_2", "call_1"]),
+            ChatMessage::assistant().text("done").build(),
+        ];
+        validate_conversation(&messages).unwrap();
+    }
+
+    #[test]
+    fn validate_conversation_rejects_missing_result() {
+        let messages = vec![
+            ChatMessage::user().text("read both").build(),
+            tool_call_turn(&["call_1", "call_2"]),
+            tool_result_turn(&["call_1"]),
+            ChatMessage::assistant().text("done").build(),
+        ];
+        let err = validate_conversation(&messages).unwrap_err();
+        assert!(
+            matches!(&err, LLMError::InvalidRequest(msg) if msg.contains("'call_2'")),
+            "{err:?}"
+        );
+
+        let trailing = vec![tool_call_turn(&["call_3"])];
+        let err = validate_conversation(&trailing).unwrap_err();
+        assert!(err.to_string().contains("'call_3'"), "{err}");
+    }
+
+    #[test]
+    fn validate_conversation_rejects_orphan_result() {
+        let messages = vec![
+            ChatMessage::user().text("hi").build(),
+            tool_call_turn(&["call_1"]),
+            tool_result_turn(&["call_1", "call_9"]),
+        ];
+        let err = validate_conversation(&messages).unwrap_err();
+        assert!(
+            matches!(&err, LLMError::InvalidRequest(msg) if msg.contains("'call_9'")),
+            "{err:?}"
+        );
+
+        // A result for a call of an earlier assistant turn is an orphan too.
+        let stale = vec![
+            tool_call_turn(&["call_1"]),
+            tool_result_turn(&["call_1"]),
+            ChatMessage::assistant().text("done").build(),
+            tool_result_turn(&["call_1"]),
+        ];
+        assert!(validate_conversation(&stale).is_err());
+    }
+
     #[test]
     fn tool_choice_schema_has_any_of() {
         let schema = schemars::schema_for!(ToolChoice);
@@ -0,0 +1,97 @@
+//! Checking model-produced tool-call arguments against the tool's schema.
+
+use super::Tool;
+use crate::error::LLMError;
+use serde_json::Value;
+
+/// Validate `arguments`, the raw JSON of a tool call, against the parameter
+/// schema of `tool`.
+///
+/// Empty arguments are treated as `{}`, matching how tool calls are
+/// normalized elsewhere. Returns [`LLMError::InvalidRequest`] listing every
+/// violation when the arguments don't conform, and
+/// [`LLMError::ToolConfigError`] when the tool's own schema is invalid.
+pub fn validate_tool_arguments(tool: &Tool, arguments: &str) -> Result<(), LLMError> {
+    let name = &tool.function.name;
+    let arguments = if arguments.trim().is_empty() {
+        "{}"
+    } else {
+        arguments
+    };
+    let instance: Value = serde_json::from_str(arguments).map_err(|e| {
+        LLMError::InvalidRequest(format!(
+            "Arguments for tool '{name}' are not valid JSON: {e}"
+        ))
+    })?;
+
+    let validator = jsonschema::validator_for(&tool.function.parameters).map_err(|e| {
+        LLMError::ToolConfigError(format!("Invalid parameter schema for tool '{name}': {e}"))
+    })?;
+
+    let violations: Vec<String> = validator
+        .iter_errors(&instance)
+        .map(|err| match err.instance_path().to_string() {
+            path if path.is_empty() => err.to_string(),
+            path => format!("{path}: {err}"),
+        })
+        .collect();
+    if violations.is_empty() {
+        return Ok(());
+    }
+    Err(LLMError::InvalidRequest(format!(
+        "Invalid arguments for tool '{name}': {}",
+        violations.join("; ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat::FunctionTool;
+    use serde_json::json;
+
+    fn weather_tool() -> Tool {
+        Tool {
+            tool_type: "function".into(),
+            function: FunctionTool {
+                name: "get_weather".into(),
+                description: "Current weather for a city".into(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } },
+                    "required": ["city"]
+                }),
+            },
+        }
+    }
+
+    #[test]
+    fn accepts_conforming_arguments() {
+        validate_tool_arguments(&weather_tool(), r#"{"city": "Paris"}"#).unwrap();
+    }
+
+    #[test]
+    fn rejects_wrong_type_with_path() {
+        let err = validate_tool_arguments(&weather_tool(), r#"{"city": 42}"#).unwrap_err();
+        let LLMError::InvalidRequest(msg) = err else {
+            panic!("unexpected error: {err:?}");
+        };
+        assert!(msg.contains("get_weather"), "{msg}");
+        assert!(msg.contains("/city"), "{msg}");
+    }
+
+    #[test]
+    fn rejects_missing_required_field() {
+        let err = validate_tool_arguments(&weather_tool(), "").unwrap_err();
+        assert!(
+            matches!(err, LLMError::InvalidRequest(ref m) if m.contains("city")),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        let err = validate_tool_arguments(&weather_tool(), r#"{"city": "#).unwrap_err();
+        assert!(matches!(err, LLMError::InvalidRequest(_)), "{err:?}");
+    }
+}
@@ -1,8 +1,10 @@
 use async_trait::async_trait;
+use futures::Stream;
+use std::pin::Pin;
 
 use crate::{
     ToolCall, Usage,
-    chat::{ChatResponse, FinishReason},
+    chat::{ChatResponse, FinishReason, StreamChunk},
     error::LLMError,
 };
 use serde::{Deserialize, Serialize};
@@ -20,6 +22,19 @@ pub struct CompletionRequest {
     pub max_tokens: Option<u32>,
     /// Optional temperature parameter to control randomness (0.0-1.0)
     pub temperature: Option<f32>,
+    /// Sequences that end generation; the matched sequence is not included
+    /// in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
+    /// Optional top-p (nucleus) sampling parameter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Optional penalty for tokens that already appeared in the output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Optional penalty proportional to how often a token already appeared
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
 }
 
 /// A response containing generated text from a completion request.
@@ -27,6 +42,9 @@ pub struct CompletionRequest {
 pub struct CompletionResponse {
     /// The generated completion text
     pub text: String,
+    /// Token usage, when the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 impl ChatResponse for CompletionResponse {
@@ -37,7 +55,7 @@ impl ChatResponse for CompletionResponse {
         None
     }
     fn usage(&self) -> Option<Usage> {
-        None
+        self.usage.clone()
     }
     fn finish_reason(&self) -> Option<FinishReason> {
         None
@@ -56,6 +74,10 @@ impl CompletionRequest {
             suffix: None, // FIXME
             max_tokens: None,
             temperature: None,
+            stop: None,
+            top_p: None,
+            presence_penalty: None,
+            frequency_penalty: None,
         }
     }
 
@@ -70,6 +92,10 @@ impl CompletionRequest {
             suffix: None,
             max_tokens: None,
             temperature: None,
+            stop: None,
+            top_p: None,
+            presence_penalty: None,
+            frequency_penalty: None,
         }
     }
 }
@@ -84,6 +110,10 @@ pub struct CompletionRequestBuilder {
     pub max_tokens: Option<u32>,
     /// Optional temperature parameter to control randomness (0.0-1.0)
     pub temperature: Option<f32>,
+    pub stop: Option<Vec<String>>,
+    pub top_p: Option<f32>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
 }
 
 impl CompletionRequestBuilder {
@@ -104,6 +134,30 @@ impl CompletionRequestBuilder {
         self
     }
 
+    /// Adds a sequence that ends generation.
+    pub fn stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.get_or_insert_with(Vec::new).push(stop.into());
+        self
+    }
+
+    /// Sets the top-p (nucleus) sampling parameter.
+    pub fn top_p(mut self, val: f32) -> Self {
+        self.top_p = Some(val);
+        self
+    }
+
+    /// Sets the presence penalty.
+    pub fn presence_penalty(mut self, val: f32) -> Self {
+        self.presence_penalty = Some(val);
+        self
+    }
+
+    /// Sets the frequency penalty.
+    pub fn frequency_penalty(mut self, val: f32) -> Self {
+        self.frequency_penalty = Some(val);
+        self
+    }
+
     /// Builds the completion request with the configured parameters.
     pub fn build(self) -> CompletionRequest {
         CompletionRequest {
@@ -111,6 +165,10 @@ impl CompletionRequestBuilder {
             suffix: self.suffix,
             max_tokens: self.max_tokens,
             temperature: self.temperature,
+            stop: self.stop,
+            top_p: self.top_p,
+            presence_penalty: self.presence_penalty,
+            frequency_penalty: self.frequency_penalty,
         }
     }
 }
@@ -128,6 +186,26 @@ pub trait CompletionProvider {
     ///
     /// The generated completion text or an error
     async fn complete(&self, req: &CompletionRequest) -> Result<CompletionResponse, LLMError>;
+
+    /// Streaming text completion, for e.g. code-completion UIs that render
+    /// the completion as it is generated.
+    ///
+    /// Yields [`StreamChunk::Text`] deltas, then [`StreamChunk::Usage`] when
+    /// the provider reports it, and ends with [`StreamChunk::Done`].
+    ///
+    /// # Default Implementation
+    ///
+    /// By default, this returns a `NotImplemented` error. Providers that can
+    /// stream completions should override this method.
+    async fn complete_stream(
+        &self,
+        req: &CompletionRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LLMError>> + Send>>, LLMError> {
+        let _ = req;
+        Err(LLMError::NotImplemented(
+            "Streaming completion not supported by this provider".into(),
+        ))
+    }
 }
 
 impl std::fmt::Display for CompletionResponse {
@@ -1,9 +1,122 @@
 use crate::error::LLMError;
 use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
 
 pub mod http;
 
 #[async_trait]
 pub trait EmbeddingProvider {
     async fn embed(&self, input: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError>;
+
+    /// Embed `inputs` in chunks of `batch_size`, keeping up to `concurrency`
+    /// [`embed`](EmbeddingProvider::embed) calls in flight.
+    ///
+    /// Embeddings are returned in input order. A `batch_size` or
+    /// `concurrency` of zero is treated as one.
+    async fn embed_batched(
+        &self,
+        inputs: Vec<String>,
+        batch_size: usize,
+        concurrency: usize,
+    ) -> Result<Vec<Vec<f32>>, LLMError>
+    where
+        Self: Sync,
+    {
+        let batches: Vec<Vec<String>> = inputs
+            .chunks(batch_size.max(1))
+            .map(<[String]>::to_vec)
+            .collect();
+
+        let results: Vec<Vec<Vec<f32>>> = futures::stream::iter(batches)
+            .map(|batch| async move {
+                let expected = batch.len();
+                let embeddings = self.embed(batch).await?;
+                if embeddings.len() != expected {
+                    return Err(LLMError::ProviderError(format!(
+                        "embedding batch returned {} vectors for {} inputs",
+                        embeddings.len(),
+                        expected
+                    )));
+                }
+                Ok(embeddings)
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(results.into_iter().flatten().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Embeds each input as `[len]`, finishing earlier batches last so that
+    /// out-of-order completion would be visible.
+    #[derive(Default)]
+    struct MockEmbedder {
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for MockEmbedder {
+        async fn embed(&self, input: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+
+            tokio::time::sleep(Duration::from_millis(
+                30u64.saturating_sub(call as u64 * 10),
+            ))
+            .await;
+
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(input.iter().map(|s| vec![s.len() as f32]).collect())
+        }
+    }
+
+    fn inputs(n: usize) -> Vec<String> {
+        (1..=n).map(|i| "x".repeat(i)).collect()
+    }
+
+    #[tokio::test]
+    async fn embed_batched_preserves_order_across_batches() {
+        let embedder = MockEmbedder::default();
+        let out = embedder.embed_batched(inputs(7), 2, 3).await.unwrap();
+
+        let expected: Vec<Vec<f32>> = (1..=7).map(|i| vec![i as f32]).collect();
+        assert_eq!(out, expected);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 4);
+        assert!(embedder.max_in_flight.load(Ordering::SeqCst) <= 3);
+    }
+
+    #[tokio::test]
+    async fn embed_batched_treats_zero_as_one() {
+        let embedder = MockEmbedder::default();
+        let out = embedder.embed_batched(inputs(3), 0, 0).await.unwrap();
+
+        assert_eq!(out.len(), 3);
+        assert_eq!(embedder.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(embedder.max_in_flight.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn embed_batched_rejects_short_batches() {
+        struct Short;
+
+        #[async_trait]
+        impl EmbeddingProvider for Short {
+            async fn embed(&self, _input: Vec<String>) -> Result<Vec<Vec<f32>>, LLMError> {
+                Ok(vec![vec![0.0]])
+            }
+        }
+
+        let err = Short.embed_batched(inputs(4), 2, 2).await.unwrap_err();
+        assert!(matches!(err, LLMError::ProviderError(_)));
+    }
 }
@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::string::FromUtf8Error;
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
@@ -33,6 +34,18 @@ pub enum LLMErrorPayload {
     PluginError {
         message: String,
     },
+    PluginLoadError {
+        name: String,
+        message: String,
+    },
+    PluginAbiMismatch {
+        name: String,
+        message: String,
+    },
+    PluginSymbolMissing {
+        name: String,
+        symbol: String,
+    },
     InvalidRequest {
         message: String,
     },
@@ -100,6 +113,22 @@ pub enum LLMError {
     #[error("Plugin Error: {0}")]
     PluginError(String),
 
+    /// A provider plugin could not be opened or initialized.
+    #[error("Failed to load plugin '{name}': {source}")]
+    PluginLoadError {
+        name: String,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// A provider plugin was built against an incompatible host interface,
+    /// e.g. it imports host functions this host doesn't provide.
+    #[error("Plugin '{name}' is incompatible with this host: {message}")]
+    PluginAbiMismatch { name: String, message: String },
+
+    /// A provider plugin doesn't export a required entry point.
+    #[error("Plugin '{name}' does not export '{symbol}'")]
+    PluginSymbolMissing { name: String, symbol: String },
+
     /// Errors related to malformed requests.
     #[error("Invalid Request: {0}")]
     InvalidRequest(String),
@@ -182,6 +211,18 @@ impl LLMError {
             Self::PluginError(message) => LLMErrorPayload::PluginError {
                 message: message.clone(),
             },
+            Self::PluginLoadError { name, source } => LLMErrorPayload::PluginLoadError {
+                name: name.clone(),
+                message: source.to_string(),
+            },
+            Self::PluginAbiMismatch { name, message } => LLMErrorPayload::PluginAbiMismatch {
+                name: name.clone(),
+                message: message.clone(),
+            },
+            Self::PluginSymbolMissing { name, symbol } => LLMErrorPayload::PluginSymbolMissing {
+                name: name.clone(),
+                symbol: symbol.clone(),
+            },
             Self::InvalidRequest(message) => LLMErrorPayload::InvalidRequest {
                 message: message.clone(),
             },
@@ -246,6 +287,16 @@ impl LLMError {
             LLMErrorPayload::AuthError { message } => Self::AuthError(message),
             LLMErrorPayload::ToolConfigError { message } => Self::ToolConfigError(message),
             LLMErrorPayload::PluginError { message } => Self::PluginError(message),
+            LLMErrorPayload::PluginLoadError { name, message } => Self::PluginLoadError {
+                name,
+                source: message.into(),
+            },
+            LLMErrorPayload::PluginAbiMismatch { name, message } => {
+                Self::PluginAbiMismatch { name, message }
+            }
+            LLMErrorPayload::PluginSymbolMissing { name, symbol } => {
+                Self::PluginSymbolMissing { name, symbol }
+            }
             LLMErrorPayload::InvalidRequest { message } => Self::InvalidRequest(message),
             LLMErrorPayload::ResponseFormatError {
                 message,
@@ -328,6 +379,9 @@ impl LLMError {
             Self::InvalidRequest(_) => false,
             Self::ProviderError(_) => false,
             Self::ToolConfigError(_) => false,
+            Self::PluginLoadError { .. } => false,
+            Self::PluginAbiMismatch { .. } => false,
+            Self::PluginSymbolMissing { .. } => false,
             Self::ResponseFormatError { .. } => false,
             Self::GenericError(_) => false,
             Self::Cancelled => false,
@@ -454,6 +508,55 @@ fn extract_retry_after_from_json(json: &serde_json::Value) -> Option<u64> {
     .find_map(json_retry_after_value)
 }
 
+/// Longest response body, in bytes, kept in error messages.
+pub const MAX_ERROR_BODY_BYTES: usize = 4096;
+
+/// Lossily decode `body` for inclusion in an error message, truncated to
+/// [`MAX_ERROR_BODY_BYTES`].
+pub fn raw_body_preview(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    let text = text.trim();
+    if text.len() <= MAX_ERROR_BODY_BYTES {
+        return text.to_string();
+    }
+    let mut end = MAX_ERROR_BODY_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...(truncated, {} bytes)", &text[..end], body.len())
+}
+
+/// The message from a provider's structured error body.
+///
+/// Understands `{"error": {"message": ...}}` (OpenAI, Anthropic, Google),
+/// `{"error": "..."}` (Ollama) and a top-level `{"message": ...}`.
+fn structured_error_message(json: &serde_json::Value) -> Option<String> {
+    json.pointer("/error/message")
+        .or_else(|| json.get("error").filter(|v| v.is_string()))
+        .or_else(|| json.get("message"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Build a [`LLMError::ResponseFormatError`] for a successful response whose
+/// body could not be decoded, keeping a preview of the raw body.
+pub fn response_format_error(context: &str, err: impl fmt::Display, body: &[u8]) -> LLMError {
+    LLMError::ResponseFormatError {
+        message: format!("Failed to decode {context}: {err}"),
+        raw_response: raw_body_preview(body),
+    }
+}
+
+/// Decode a successful JSON response body, reporting failures with
+/// [`response_format_error`].
+pub fn decode_json_response<T: serde::de::DeserializeOwned>(
+    body: &[u8],
+    context: &str,
+) -> Result<T, LLMError> {
+    serde_json::from_slice(body).map_err(|e| response_format_error(context, e, body))
+}
+
 pub fn classify_http_status(status_code: u16, headers: &http::HeaderMap, body: &[u8]) -> LLMError {
     if status_code == 499 {
         return LLMError::Cancelled;
@@ -467,12 +570,8 @@ pub fn classify_http_status(status_code: u16, headers: &http::HeaderMap, body: &
 
     let clean_message = body_json
         .as_ref()
-        .and_then(|json| json.pointer("/error/message"))
-        .and_then(|v| v.as_str())
-        .map(str::to_string)
-        .unwrap_or_else(|| String::from_utf8_lossy(body).trim().to_string())
-        .trim()
-        .to_string();
+        .and_then(structured_error_message)
+        .unwrap_or_else(|| raw_body_preview(body));
     let message = if clean_message.is_empty() {
         format!("HTTP {}", status_code)
     } else {
@@ -749,6 +848,56 @@ mod tests {
         assert_eq!(err.retry_after_secs(), None);
     }
 
+    #[test]
+    fn classify_400_keeps_raw_body() {
+        let headers = http::HeaderMap::new();
+        let err = classify_http_status(400, &headers, b"<html>bad gateway config</html>");
+        assert!(
+            matches!(&err, LLMError::InvalidRequest(m) if m == "<html>bad gateway config</html>"),
+            "got {err:?}"
+        );
+    }
+
+    #[test]
+    fn classify_prefers_structured_error_message() {
+        let headers = http::HeaderMap::new();
+        let anthropic = br#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: too large"}}"#;
+        let ollama = br#"{"error":"model 'x' not found"}"#;
+        assert!(matches!(
+            classify_http_status(400, &headers, anthropic),
+            LLMError::InvalidRequest(m) if m == "max_tokens: too large"
+        ));
+        assert!(matches!(
+            classify_http_status(404, &headers, ollama),
+            LLMError::ProviderError(m) if m == "model 'x' not found"
+        ));
+    }
+
+    #[test]
+    fn raw_body_preview_truncates_on_char_boundary() {
+        let body = "é".repeat(MAX_ERROR_BODY_BYTES);
+        let preview = raw_body_preview(body.as_bytes());
+        assert!(preview.starts_with("éé"));
+        assert!(preview.ends_with(&format!("...(truncated, {} bytes)", body.len())));
+        assert!(preview.len() < body.len());
+    }
+
+    #[test]
+    fn decode_json_response_keeps_raw_body() {
+        let err = decode_json_response::<serde_json::Value>(b"not json", "test response")
+            .expect_err("invalid JSON");
+        match err {
+            LLMError::ResponseFormatError {
+                message,
+                raw_response,
+            } => {
+                assert!(message.starts_with("Failed to decode test response"));
+                assert_eq!(raw_response, "not json");
+            }
+            other => panic!("unexpected error: {other:?}"),
+        }
+    }
+
     #[test]
     fn classify_500_with_x_ratelimit_header() {
         let mut headers = http::HeaderMap::new();
@@ -49,9 +49,16 @@ pub mod error;
 /// Credential resolution for dynamic API keys (OAuth, token refresh)
 pub mod auth;
 
+/// Masking secrets and user content in debug logs
+pub mod redact;
+
 #[cfg(feature = "http-client")]
 pub mod outbound;
 
+/// Pluggable HTTP execution (proxies, mTLS, logging) for HTTP providers
+#[cfg(feature = "http-client")]
+pub mod transport;
+
 #[cfg(feature = "mcp")]
 pub mod mcp;
 
@@ -252,6 +259,25 @@ pub struct Usage {
 }
 
 impl Usage {
+    /// A `Usage` with every count at zero, the starting point for summing
+    /// usage across turns.
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    /// Every token counted by this usage.
+    ///
+    /// Providers normalize the fields to be disjoint (cached input is not
+    /// part of `input_tokens`, reasoning is not part of `output_tokens`), so
+    /// this is their plain sum.
+    pub fn total_tokens(&self) -> u64 {
+        u64::from(self.input_tokens)
+            + u64::from(self.output_tokens)
+            + u64::from(self.reasoning_tokens)
+            + u64::from(self.cache_read)
+            + u64::from(self.cache_write)
+    }
+
     /// Merge two `Usage` values by taking the field-wise maximum.
     ///
     /// This is the correct strategy when a provider splits usage across multiple
@@ -270,6 +296,27 @@ impl Usage {
     }
 }
 
+/// Field-wise sum, e.g. for totalling usage across the turns of an agent
+/// loop. Counts saturate instead of overflowing.
+impl std::ops::Add for Usage {
+    type Output = Usage;
+
+    fn add(mut self, other: Usage) -> Usage {
+        self += other;
+        self
+    }
+}
+
+impl std::ops::AddAssign for Usage {
+    fn add_assign(&mut self, other: Usage) {
+        self.input_tokens = self.input_tokens.saturating_add(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_add(other.output_tokens);
+        self.reasoning_tokens = self.reasoning_tokens.saturating_add(other.reasoning_tokens);
+        self.cache_read = self.cache_read.saturating_add(other.cache_read);
+        self.cache_write = self.cache_write.saturating_add(other.cache_write);
+    }
+}
+
 // NOTE: We need this part to be a macro instead of two separate functions for specific
 // implementations like native and wasm, because otherwise functions need to be in each provider
 // to get configuration from extism_pdk.
@@ -295,6 +342,50 @@ macro_rules! get_env_var {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_usage_add_sums_every_field() {
+        let a = Usage {
+            input_tokens: 10,
+            output_tokens: 20,
+            reasoning_tokens: 5,
+            cache_read: 100,
+            cache_write: 7,
+        };
+        let b = Usage {
+            input_tokens: 1,
+            output_tokens: 2,
+            reasoning_tokens: 3,
+            cache_read: 4,
+            cache_write: 5,
+        };
+        let expected = Usage {
+            input_tokens: 11,
+            output_tokens: 22,
+            reasoning_tokens: 8,
+            cache_read: 104,
+            cache_write: 12,
+        };
+
+        assert_eq!(a.clone() + b.clone(), expected);
+
+        let mut total = Usage::zero();
+        total += a;
+        total += b;
+        assert_eq!(total, expected);
+        assert_eq!(total.total_tokens(), 157);
+    }
+
+    #[test]
+    fn test_usage_add_saturates() {
+        let big = Usage {
+            output_tokens: u32::MAX,
+            ..Usage::zero()
+        };
+        let sum = big.clone() + big;
+        assert_eq!(sum.output_tokens, u32::MAX);
+        assert_eq!(sum.total_tokens(), u64::from(u32::MAX));
+    }
+
     #[test]
     fn test_merge_max_combines_split_anthropic_usage() {
         // Simulates Anthropic's two-event streaming usage:
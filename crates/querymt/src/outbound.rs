@@ -2,6 +2,8 @@ mod http_client {
     #[cfg(not(target_arch = "wasm32"))]
     pub mod imp {
         use crate::error::{LLMError, classify_http_status};
+        #[cfg(debug_assertions)]
+        use crate::redact;
         use http::{Request, Response};
         use once_cell::sync::Lazy;
         use reqwest::Client;
@@ -11,23 +13,6 @@ mod http_client {
         /// A single, global client, built once
         pub static CLIENT: Lazy<Client> = Lazy::new(Client::new);
 
-        #[cfg(debug_assertions)]
-        fn header_token_hint(value: Option<&http::HeaderValue>) -> String {
-            let Some(value) = value else {
-                return "<missing>".to_string();
-            };
-            let Ok(value_str) = value.to_str() else {
-                return "<non-utf8>".to_string();
-            };
-            let mut parts = value_str.splitn(2, ' ');
-            let scheme = parts.next().unwrap_or("<unknown>");
-            let token = parts.next().unwrap_or("");
-            if token.is_empty() {
-                return format!("{scheme} <empty>");
-            }
-            format!("{scheme} <redacted>")
-        }
-
         #[cfg(debug_assertions)]
         fn request_json_summary(req: &Request<Vec<u8>>) -> String {
             let Ok(value) = serde_json::from_slice::<Value>(req.body()) else {
@@ -51,39 +36,6 @@ mod http_client {
             format!("model={model} stream={stream} messages_len={messages_len}")
         }
 
-        #[cfg(debug_assertions)]
-        fn is_sensitive_key(key: &str) -> bool {
-            let key = key.to_ascii_lowercase();
-            matches!(
-                key.as_str(),
-                "api_key" | "apikey" | "authorization" | "bearer" | "token" | "access_token"
-            ) || key.ends_with("_token")
-                || key.ends_with("_key")
-                || key.ends_with("-token")
-                || key.ends_with("-key")
-        }
-
-        #[cfg(debug_assertions)]
-        fn redact_json_value(value: &mut Value) {
-            match value {
-                Value::Object(obj) => {
-                    for (key, value) in obj.iter_mut() {
-                        if is_sensitive_key(key) {
-                            *value = Value::String("[redacted]".to_string());
-                        } else {
-                            redact_json_value(value);
-                        }
-                    }
-                }
-                Value::Array(values) => {
-                    for value in values {
-                        redact_json_value(value);
-                    }
-                }
-                _ => {}
-            }
-        }
-
         #[cfg(debug_assertions)]
         fn truncate_preview(mut out: String, max_len: usize) -> String {
             if out.len() > max_len {
@@ -98,13 +50,19 @@ mod http_client {
             let Ok(mut value) = serde_json::from_slice::<Value>(bytes) else {
                 return format!("<non-json body omitted: {} bytes>", bytes.len());
             };
-            redact_json_value(&mut value);
+            redact::redact_json(&mut value);
             truncate_preview(value.to_string(), max_len)
         }
 
         pub async fn call_outbound(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
-            let client = &*CLIENT;
+            call_outbound_with(&CLIENT, req).await
+        }
 
+        /// [`call_outbound`] through `client` instead of the shared client.
+        pub async fn call_outbound_with(
+            client: &Client,
+            req: Request<Vec<u8>>,
+        ) -> Result<Response<Vec<u8>>, LLMError> {
             let method = req
                 .method()
                 .as_str()
@@ -113,17 +71,11 @@ mod http_client {
 
             #[cfg(debug_assertions)]
             {
-                let auth_hint = header_token_hint(req.headers().get(http::header::AUTHORIZATION));
                 log::debug!(
-                    "outbound.call method={} uri={} content_type={} has_authorization={} auth_hint={} body_len={} body_summary={}",
+                    "outbound.call method={} uri={} headers=[{}] body_len={} body_summary={}",
                     req.method(),
                     req.uri(),
-                    req.headers()
-                        .get(http::header::CONTENT_TYPE)
-                        .and_then(|v| v.to_str().ok())
-                        .unwrap_or("<missing>"),
-                    req.headers().contains_key(http::header::AUTHORIZATION),
-                    auth_hint,
+                    redact::redact_headers(req.headers()),
                     req.body().len(),
                     request_json_summary(&req)
                 );
@@ -184,8 +136,16 @@ mod http_client {
         pub async fn call_outbound_stream(
             req: Request<Vec<u8>>,
         ) -> Result<impl futures::Stream<Item = reqwest::Result<bytes::Bytes>>, LLMError> {
-            let client = &*CLIENT;
+            call_outbound_stream_with(&CLIENT, req).await
+        }
 
+        /// [`call_outbound_stream`] through `client` instead of the shared
+        /// client.
+        pub async fn call_outbound_stream_with(
+            client: &Client,
+            req: Request<Vec<u8>>,
+        ) -> Result<impl futures::Stream<Item = reqwest::Result<bytes::Bytes>> + use<>, LLMError>
+        {
             let method = req
                 .method()
                 .as_str()
@@ -194,17 +154,11 @@ mod http_client {
 
             #[cfg(debug_assertions)]
             {
-                let auth_hint = header_token_hint(req.headers().get(http::header::AUTHORIZATION));
                 log::debug!(
-                    "outbound.call_stream method={} uri={} content_type={} has_authorization={} auth_hint={} body_len={} body_summary={}",
+                    "outbound.call_stream method={} uri={} headers=[{}] body_len={} body_summary={}",
                     req.method(),
                     req.uri(),
-                    req.headers()
-                        .get(http::header::CONTENT_TYPE)
-                        .and_then(|v| v.to_str().ok())
-                        .unwrap_or("<missing>"),
-                    req.headers().contains_key(http::header::AUTHORIZATION),
-                    auth_hint,
+                    redact::redact_headers(req.headers()),
                     req.body().len(),
                     request_json_summary(&req)
                 );
@@ -275,3 +229,5 @@ mod http_client {
 }
 
 pub use http_client::imp::{call_outbound, call_outbound_stream};
+#[cfg(not(target_arch = "wasm32"))]
+pub use http_client::imp::{call_outbound_stream_with, call_outbound_with};
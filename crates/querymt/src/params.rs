@@ -7,6 +7,7 @@ use crate::chat::ReasoningEffort;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use url::Url;
 
 /// Parses a system prompt value (null, string, or array of strings) into `Vec<String>`.
 fn parse_system_parts<E: serde::de::Error>(value: Option<Value>) -> Result<Vec<String>, E> {
@@ -53,6 +54,48 @@ where
     parse_system_parts::<D::Error>(Option::deserialize(deserializer)?)
 }
 
+/// Ensures a base URL ends with a `/` so that relative joins (e.g.
+/// `messages`) append to the configured path instead of replacing its last
+/// segment: `https://gw.internal/v1` and `https://gw.internal/v1/` then
+/// resolve to the same endpoints.
+pub fn normalize_base_url(mut url: Url) -> Url {
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    url
+}
+
+/// Deserializes a provider's `base_url`, applying [`normalize_base_url`].
+pub fn deserialize_base_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(normalize_base_url(Url::deserialize(deserializer)?))
+}
+
+/// Merges a provider's `extra_body` config into a serialized request body.
+///
+/// User keys take precedence: objects present on both sides are merged
+/// recursively, so `{"generationConfig": {"seed": 1}}` adds one field to an
+/// existing `generationConfig`, and any other value replaces the computed
+/// one.
+pub fn merge_extra_body(body: &mut Value, extra: &serde_json::Map<String, Value>) {
+    let Value::Object(target) = body else {
+        return;
+    };
+    for (key, value) in extra {
+        match (target.get_mut(key), value) {
+            (Some(existing @ Value::Object(_)), Value::Object(nested)) => {
+                merge_extra_body(existing, nested)
+            }
+            _ => {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
 /// Pure configuration parameters for LLM providers.
 ///
 /// This struct contains only serializable configuration data without
@@ -1,17 +1,135 @@
-use super::{Fut, LLMProviderFactory, http::HTTPLLMProviderFactory};
-use crate::{LLMProvider, adapters::LLMProviderFromHTTP, error::LLMError, outbound::call_outbound};
+use super::{
+    Fut, LLMProviderFactory, ProviderCapabilities, config_with_default_model,
+    http::HTTPLLMProviderFactory, model_cache::ModelListCache,
+};
+use crate::{
+    LLMProvider,
+    adapters::{
+        LLMProviderFromHTTP, config_max_messages, idempotency_keys_enabled, strip_adapter_options,
+        with_adapter_options,
+    },
+    chat::json_repair::repair_enabled,
+    error::{LLMError, classify_http_status},
+    transport::{HttpTransport, check_response_size, config_transport, transport_for_config},
+};
 use futures::future::FutureExt;
 use http::{Request, Response};
-use std::{ops::Deref, sync::Arc};
+use serde_json::Value;
+use std::{borrow::Cow, ops::Deref, sync::Arc, time::Duration};
 
 pub struct HTTPFactoryAdapter {
     inner: Arc<dyn HTTPLLMProviderFactory>,
+    /// `None` builds the transport from each config, see [`config_transport`].
+    transport: Option<Arc<dyn HttpTransport>>,
+    max_response_bytes: Option<usize>,
+    models_cache: Option<Arc<ModelListCache>>,
 }
 
 impl HTTPFactoryAdapter {
     pub fn new(inner: Arc<dyn HTTPLLMProviderFactory>) -> Self {
-        Self { inner }
+        Self {
+            inner,
+            transport: None,
+            max_response_bytes: None,
+            models_cache: None,
+        }
+    }
+
+    /// Execute model listing and all requests of the providers built by this
+    /// factory through `transport`.
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+
+    /// The configured transport, or one honouring the config's TLS settings,
+    /// with the deadline from the config's `timeout_seconds` applied.
+    fn transport_for(&self, cfg: &Value) -> Result<Arc<dyn HttpTransport>, LLMError> {
+        let transport = match &self.transport {
+            Some(transport) => Arc::clone(transport),
+            None => config_transport(cfg)?,
+        };
+        Ok(transport_for_config(transport, cfg))
+    }
+
+    /// Reject response bodies larger than `limit` bytes, both for model
+    /// listing and for every provider built by this factory. See
+    /// [`LLMProviderFromHTTP::with_max_response_bytes`].
+    pub fn with_max_response_bytes(mut self, limit: usize) -> Self {
+        self.max_response_bytes = Some(limit);
+        self
+    }
+
+    /// Cache `list_models` results per base URL for `ttl`.
+    /// [`refresh_models`](LLMProviderFactory::refresh_models) bypasses the
+    /// cache and stores the fresh list.
+    pub fn with_list_models_cache(mut self, ttl: Duration) -> Self {
+        self.models_cache = Some(Arc::new(ModelListCache::new(ttl)));
+        self
+    }
+
+    /// `cfg` without the adapter-level options the inner factory doesn't
+    /// declare, see [`strip_adapter_options`].
+    fn provider_config<'a>(&self, cfg: &'a str, cfg_value: &Value) -> Cow<'a, str> {
+        let mut provider_cfg = cfg_value.clone();
+        if strip_adapter_options(&mut provider_cfg, &self.inner.config_schema()) {
+            Cow::Owned(provider_cfg.to_string())
+        } else {
+            Cow::Borrowed(cfg)
+        }
+    }
+
+    fn fetch_models(&self, cfg: &str) -> Fut<'static, Result<Vec<String>, LLMError>> {
+        // clone the Arc so we can move it into the async block
+        let inner = Arc::clone(&self.inner);
+        let cfg_value = parse_config(cfg);
+        let transport = self.transport_for(&cfg_value);
+        let max_response_bytes = self.max_response_bytes;
+        let cloned_cfg = self.provider_config(cfg, &cfg_value).into_owned();
+
+        async move {
+            if let Some(result) = inner.list_models_static(&cloned_cfg) {
+                return result;
+            }
+
+            let req: Request<Vec<u8>> = inner.list_models_request(&cloned_cfg)?;
+            let resp: Response<Vec<u8>> = transport?.execute(req).await?;
+            check_response_size(resp.body().len(), max_response_bytes)?;
+            if !resp.status().is_success() {
+                return Err(classify_http_status(
+                    resp.status().as_u16(),
+                    resp.headers(),
+                    resp.body(),
+                ));
+            }
+
+            inner
+                .parse_list_models(resp)
+                .map_err(|e| LLMError::PluginError(format!("{:#}", e)))
+        }
+        .boxed()
     }
+
+    fn cached_models<'a>(
+        &'a self,
+        cfg: &str,
+        force_refresh: bool,
+    ) -> Fut<'a, Result<Vec<String>, LLMError>> {
+        let fetch = self.fetch_models(cfg);
+        match &self.models_cache {
+            Some(cache) => {
+                let key = ModelListCache::key(self.inner.name(), &parse_config(cfg));
+                cache.get_or_fetch(key, force_refresh, fetch).boxed()
+            }
+            None => fetch,
+        }
+    }
+}
+
+/// Parse a provider config for the adapter-level options it carries. Invalid
+/// JSON is left for the provider itself to reject.
+fn parse_config(cfg: &str) -> Value {
+    serde_json::from_str(cfg).unwrap_or(Value::Null)
 }
 
 impl LLMProviderFactory for HTTPFactoryAdapter {
@@ -27,37 +145,389 @@ impl LLMProviderFactory for HTTPFactoryAdapter {
         self.inner.supports_custom_models()
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.inner.capabilities()
+    }
+
     fn config_schema(&self) -> String {
-        self.inner.config_schema()
+        with_adapter_options(&self.inner.config_schema())
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.inner.default_model()
     }
 
     fn from_config(&self, cfg: &str) -> Result<Box<dyn LLMProvider>, LLMError> {
+        let cfg = config_with_default_model(cfg, self.inner.default_model());
+        let cfg = cfg.as_ref();
+        let cfg_value = parse_config(cfg);
         let sync_provider = self
             .inner
-            .from_config(cfg)
+            .from_config(&self.provider_config(cfg, &cfg_value))
             .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?;
 
-        let adapter = LLMProviderFromHTTP::new(sync_provider);
+        let transport = self.transport_for(&cfg_value)?;
+        let mut adapter = LLMProviderFromHTTP::with_transport(sync_provider, transport)
+            .with_tool_json_repair(repair_enabled(&cfg_value))
+            .with_idempotency_keys(idempotency_keys_enabled(&cfg_value))
+            .with_max_messages(config_max_messages(&cfg_value));
+        if let Some(limit) = self.max_response_bytes {
+            adapter = adapter.with_max_response_bytes(limit);
+        }
         Ok(Box::new(adapter))
     }
 
     fn list_models<'a>(&'a self, cfg: &str) -> Fut<'a, Result<Vec<String>, LLMError>> {
-        // clone the Arc so we can move it into the async block
-        let inner = Arc::clone(&self.inner);
-        let cloned_cfg = cfg.to_string();
+        self.cached_models(cfg, false)
+    }
 
-        async move {
-            if let Some(result) = inner.list_models_static(&cloned_cfg) {
-                return result;
-            }
+    fn refresh_models<'a>(&'a self, cfg: &str) -> Fut<'a, Result<Vec<String>, LLMError>> {
+        self.cached_models(cfg, true)
+    }
 
-            let req: Request<Vec<u8>> = inner.list_models_request(&cloned_cfg)?;
-            let resp: Response<Vec<u8>> = call_outbound(req).await?;
+    /// Lists models, which parses the config and exercises both connectivity
+    /// and credentials with a single cheap request. Always goes to the
+    /// network, even when model lists are cached.
+    fn health_check<'a>(&'a self, cfg: &str) -> Fut<'a, Result<(), LLMError>> {
+        self.fetch_models(cfg).map(|res| res.map(|_| ())).boxed()
+    }
+}
 
-            inner
-                .parse_list_models(resp)
-                .map_err(|e| LLMError::PluginError(format!("{:#}", e)))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HTTPLLMProvider;
+    use async_trait::async_trait;
+
+    struct ModelsFactory;
+
+    impl HTTPLLMProviderFactory for ModelsFactory {
+        fn name(&self) -> &str {
+            "models"
         }
-        .boxed()
+
+        fn config_schema(&self) -> String {
+            "{}".to_string()
+        }
+
+        fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
+            serde_json::from_str::<serde_json::Value>(cfg)?;
+            Ok(Request::get("https://example.invalid/models").body(Vec::new())?)
+        }
+
+        fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
+            Ok(serde_json::from_slice(resp.body())?)
+        }
+
+        fn from_config(&self, _cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    struct StatusTransport(u16);
+
+    #[async_trait]
+    impl HttpTransport for StatusTransport {
+        async fn execute(&self, _req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            Ok(Response::builder()
+                .status(self.0)
+                .body(b"[\"m1\"]".to_vec())
+                .expect("response should build"))
+        }
+    }
+
+    fn adapter(status: u16) -> HTTPFactoryAdapter {
+        HTTPFactoryAdapter::new(Arc::new(ModelsFactory))
+            .with_transport(Arc::new(StatusTransport(status)))
+    }
+
+    #[tokio::test]
+    async fn list_models_uses_configured_transport() {
+        let models = adapter(200).list_models("{}").await.unwrap();
+        assert_eq!(models, vec!["m1".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn health_check_reports_auth_failure() {
+        let err = adapter(401)
+            .health_check("{}")
+            .await
+            .expect_err("401 should fail the health check");
+        assert!(matches!(err, LLMError::AuthError(_)), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn health_check_passes_when_models_list() {
+        adapter(200).health_check("{}").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn health_check_rejects_invalid_config() {
+        let err = adapter(200)
+            .health_check("not json")
+            .await
+            .expect_err("invalid config should fail the health check");
+        assert!(matches!(err, LLMError::JsonError(_)), "got {err:?}");
+    }
+
+    #[derive(Default)]
+    struct CountingTransport(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl HttpTransport for CountingTransport {
+        async fn execute(&self, _req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(Response::new(b"[\"m1\"]".to_vec()))
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_list_models_hits_network_once_within_ttl() {
+        let transport = Arc::new(CountingTransport::default());
+        let adapter = HTTPFactoryAdapter::new(Arc::new(ModelsFactory))
+            .with_transport(transport.clone())
+            .with_list_models_cache(Duration::from_secs(60));
+        let requests = || transport.0.load(std::sync::atomic::Ordering::SeqCst);
+
+        assert_eq!(adapter.list_models("{}").await.unwrap(), vec!["m1"]);
+        assert_eq!(adapter.list_models("{}").await.unwrap(), vec!["m1"]);
+        assert_eq!(requests(), 1);
+
+        adapter.refresh_models("{}").await.unwrap();
+        assert_eq!(requests(), 2);
+
+        adapter
+            .list_models(r#"{"base_url": "http://localhost:8000/v1"}"#)
+            .await
+            .unwrap();
+        assert_eq!(requests(), 3, "a different base URL is cached separately");
+    }
+
+    /// Records the config it is built from and defaults to `m1`.
+    #[derive(Default)]
+    struct DefaultModelFactory(std::sync::Mutex<Option<Value>>);
+
+    impl HTTPLLMProviderFactory for DefaultModelFactory {
+        fn name(&self) -> &str {
+            "defaults"
+        }
+
+        fn default_model(&self) -> Option<&str> {
+            Some("m1")
+        }
+
+        fn config_schema(&self) -> String {
+            "{}".to_string()
+        }
+
+        fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
+            ModelsFactory.list_models_request(cfg)
+        }
+
+        fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
+            ModelsFactory.parse_list_models(resp)
+        }
+
+        fn from_config(&self, cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
+            *self.0.lock().unwrap() = Some(serde_json::from_str(cfg)?);
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    fn model_seen_by(factory: &Arc<DefaultModelFactory>, cfg: &str) -> Value {
+        let adapter = HTTPFactoryAdapter::new(factory.clone());
+        let _ = adapter.from_config(cfg);
+        factory.0.lock().unwrap().take().unwrap()["model"].clone()
+    }
+
+    #[test]
+    fn missing_or_empty_model_resolves_to_factory_default() {
+        let factory = Arc::new(DefaultModelFactory::default());
+        assert_eq!(model_seen_by(&factory, "{}"), "m1");
+        assert_eq!(model_seen_by(&factory, r#"{"model": ""}"#), "m1");
+        assert_eq!(model_seen_by(&factory, r#"{"model": "m2"}"#), "m2");
+    }
+
+    #[tokio::test]
+    async fn validate_model_checks_listed_models() {
+        adapter(200).validate_model("{}", "m1").await.unwrap();
+        let err = adapter(200)
+            .validate_model("{}", "m2")
+            .await
+            .expect_err("unlisted model should be rejected");
+        assert!(matches!(err, LLMError::InvalidRequest(_)), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn validate_model_accepts_anything_when_listing_fails() {
+        adapter(503).validate_model("{}", "m2").await.unwrap();
+    }
+
+    /// A provider config that, like most, rejects unknown fields and
+    /// declares none of the adapter-level options.
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct StrictConfig {
+        api_key: String,
+        model: String,
+    }
+
+    struct StrictFactory;
+
+    impl HTTPLLMProviderFactory for StrictFactory {
+        fn name(&self) -> &str {
+            "strict"
+        }
+
+        fn api_key_name(&self) -> Option<String> {
+            Some("STRICT_API_KEY".to_string())
+        }
+
+        fn config_schema(&self) -> String {
+            serde_json::to_string(&schemars::schema_for!(StrictConfig)).unwrap()
+        }
+
+        fn list_models_request(&self, cfg: &str) -> Result<Request<Vec<u8>>, LLMError> {
+            ModelsFactory.list_models_request(cfg)
+        }
+
+        fn parse_list_models(&self, resp: Response<Vec<u8>>) -> Result<Vec<String>, LLMError> {
+            ModelsFactory.parse_list_models(resp)
+        }
+
+        fn from_config(&self, cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
+            serde_json::from_str::<StrictConfig>(cfg)?;
+            Ok(Box::new(StrictProvider))
+        }
+    }
+
+    struct StrictProvider;
+
+    impl crate::chat::http::HTTPChatProvider for StrictProvider {
+        fn chat_request(
+            &self,
+            _messages: &[crate::chat::ChatMessage],
+            _tools: Option<&[crate::chat::Tool]>,
+        ) -> Result<Request<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+
+        fn parse_chat(
+            &self,
+            _resp: Response<Vec<u8>>,
+        ) -> Result<Box<dyn crate::chat::ChatResponse>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    impl crate::completion::http::HTTPCompletionProvider for StrictProvider {
+        fn complete_request(
+            &self,
+            _req: &crate::completion::CompletionRequest,
+        ) -> Result<Request<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+
+        fn parse_complete(
+            &self,
+            _resp: Response<Vec<u8>>,
+        ) -> Result<crate::completion::CompletionResponse, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    impl crate::embedding::http::HTTPEmbeddingProvider for StrictProvider {
+        fn embed_request(&self, _inputs: &[String]) -> Result<Request<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+
+        fn parse_embed(&self, _resp: Response<Vec<u8>>) -> Result<Vec<Vec<f32>>, LLMError> {
+            Err(LLMError::NotImplemented("unused in test".into()))
+        }
+    }
+
+    impl HTTPLLMProvider for StrictProvider {}
+
+    /// Build the strict provider through [`crate::builder::LLMBuilder`],
+    /// which prunes the config to the factory's schema.
+    async fn build_strict(params: Value) -> Result<Box<dyn LLMProvider>, LLMError> {
+        let registry = crate::plugin::host::PluginRegistry::empty();
+        registry.register_static_http(Arc::new(StrictFactory));
+        crate::builder::LLMBuilder::new()
+            .provider("strict")
+            .api_key("key")
+            .model("m1")
+            .parameters_from_value(&params)
+            .build_with(&registry)
+            .await
+    }
+
+    fn tls_fixture(name: &str) -> String {
+        format!("{}/testdata/tls/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[tokio::test]
+    async fn builder_keeps_tls_settings_for_any_provider() {
+        let err = build_strict(serde_json::json!({ "tls_ca_cert": "/nonexistent/ca.pem" }))
+            .await
+            .err()
+            .expect("the CA file is read when building the client");
+        assert!(err.to_string().contains("/nonexistent/ca.pem"), "got {err}");
+
+        build_strict(serde_json::json!({
+            "tls_ca_cert": tls_fixture("ca.pem"),
+            "tls_client_cert": tls_fixture("client.pem"),
+            "tls_client_key": tls_fixture("client.key"),
+        }))
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn config_schema_declares_adapter_options() {
+        let adapter = HTTPFactoryAdapter::new(Arc::new(StrictFactory));
+        let schema: Value = serde_json::from_str(&adapter.config_schema()).unwrap();
+        for key in [
+            "api_key",
+            "model",
+            "tls_ca_cert",
+            "tls_client_cert",
+            "tls_client_key",
+        ] {
+            assert!(schema["properties"].get(key).is_some(), "missing {key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn builder_keeps_max_messages_for_any_provider() {
+        let provider = build_strict(serde_json::json!({ "max_messages": 1 }))
+            .await
+            .unwrap();
+        let messages = vec![crate::chat::ChatMessage::user().text("hi").build(); 2];
+        let err = provider
+            .chat(&messages)
+            .await
+            .expect_err("two messages exceed the limit");
+        assert!(
+            matches!(&err, LLMError::InvalidRequest(msg) if msg.contains("max_messages")),
+            "got {err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn adapter_options_are_stripped_before_the_provider_parses_its_config() {
+        build_strict(serde_json::json!({ "repair_tool_json": false }))
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn env_resolver_requires_key_resolver_support() {
+        let err = StrictFactory
+            .with_env_resolver(Box::new(StrictProvider))
+            .err()
+            .expect("the provider ignores key resolvers");
+        assert!(matches!(err, LLMError::NotImplemented(_)), "got {err:?}");
     }
 }
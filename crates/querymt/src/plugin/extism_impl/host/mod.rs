@@ -2,11 +2,15 @@
 use crate::providers::ProvidersRegistry;
 use crate::{
     HTTPLLMProvider, LLMProvider,
-    adapters::LLMProviderFromHTTP,
+    adapters::{
+        LLMProviderFromHTTP, config_max_messages, idempotency_keys_enabled, strip_adapter_options,
+        with_adapter_options,
+    },
     auth::ApiKeyResolver,
     chat::{
         ChatMessage, ChatProvider, ChatResponse, StreamChunk, Tool,
         http::{ChatStreamParser, HTTPChatProvider},
+        json_repair,
     },
     completion::{
         CompletionProvider, CompletionRequest, CompletionResponse, http::HTTPCompletionProvider,
@@ -14,7 +18,7 @@ use crate::{
     embedding::{EmbeddingProvider, http::HTTPEmbeddingProvider},
     error::LLMError,
     plugin::{
-        Fut, HTTPLLMProviderFactory, LLMProviderFactory,
+        Fut, HTTPLLMProviderFactory, LLMProviderFactory, ProviderCapabilities, apply_default_model,
         extism_impl::{
             ExtismChatChunk, ExtismChatChunkParseRequest, ExtismChatParseRequest,
             ExtismChatRequest, ExtismChatResponse, ExtismCompleteParseRequest,
@@ -27,6 +31,7 @@ use crate::{
     stt, tts,
 };
 
+use crate::transport::{config_transport, transport_for_config};
 use async_trait::async_trait;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use extism::{Manifest, Plugin, PluginBuilder, Wasm, convert::Json};
@@ -144,6 +149,7 @@ pub struct ExtismFactory {
     name: String,
     user_data: Option<extism::UserData<functions::HostState>>,
     allowed_hosts: Vec<String>,
+    default_model: Option<String>,
 }
 
 fn call_plugin_str(plugin: Arc<Mutex<Plugin>>, func: &str, arg: &Value) -> anyhow::Result<String> {
@@ -155,6 +161,23 @@ fn call_plugin_str(plugin: Arc<Mutex<Plugin>>, func: &str, arg: &Value) -> anyho
     Ok(std::str::from_utf8(&output_bytes)?.to_string())
 }
 
+/// Classify a failure to instantiate plugin `name`: a plugin importing host
+/// functions this host lacks was built for a different host version.
+fn plugin_build_error(name: &str, err: extism::Error) -> LLMError {
+    let message = format!("{:#}", err);
+    if message.contains("unknown import") || message.contains("incompatible import type") {
+        LLMError::PluginAbiMismatch {
+            name: name.to_string(),
+            message,
+        }
+    } else {
+        LLMError::PluginLoadError {
+            name: name.to_string(),
+            source: err.into(),
+        }
+    }
+}
+
 fn add_allowed_host(allowed_hosts: &mut Vec<String>, host: String) {
     if !host.is_empty() && !allowed_hosts.iter().any(|h| h == &host) {
         allowed_hosts.push(host);
@@ -241,7 +264,7 @@ impl ExtismFactory {
             providers: HashMap::new(),
         };
         env_map.insert(
-            "PROVIDERS_REGISTRY_DATA".to_string(),
+            crate::providers::REGISTRY_DATA_ENV.to_string(),
             serde_json::to_string(&providers_registry)?,
         );
 
@@ -257,11 +280,18 @@ impl ExtismFactory {
         let init_builder =
             with_host_functions!(PluginBuilder::new(initial_manifest), init_user_data);
 
+        let load_name = config_name.unwrap_or("<unnamed>");
         let init_plugin = Arc::new(Mutex::new(
             init_builder
                 .build()
-                .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?,
+                .map_err(|e| plugin_build_error(load_name, e))?,
         ));
+        if !init_plugin.lock().unwrap().function_exists("name") {
+            return Err(LLMError::PluginSymbolMissing {
+                name: load_name.to_string(),
+                symbol: "name".into(),
+            });
+        }
 
         let plugin_name = call_plugin_str(init_plugin.clone(), "name", &Value::Null)
             .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?;
@@ -300,9 +330,7 @@ impl ExtismFactory {
         let builder = with_host_functions!(PluginBuilder::new(manifest), user_data);
 
         let plugin = Arc::new(Mutex::new(
-            builder
-                .build()
-                .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?,
+            builder.build().map_err(|e| plugin_build_error(&name, e))?,
         ));
 
         {
@@ -322,11 +350,24 @@ impl ExtismFactory {
                 })?;
         }
 
+        // Plugins built before the `default_model` export existed have none.
+        let default_model = if plugin.lock().unwrap().function_exists("default_model") {
+            call_plugin_str(plugin.clone(), "default_model", &Value::Null)
+                .and_then(|s| Ok(serde_json::from_str::<Option<String>>(&s)?))
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to read default model of plugin '{}': {:#}", name, e);
+                    None
+                })
+        } else {
+            None
+        };
+
         Ok(Self {
             plugin,
             name,
             user_data: Some(user_data),
             allowed_hosts,
+            default_model,
         })
     }
 
@@ -334,6 +375,17 @@ impl ExtismFactory {
         call_plugin_str(self.plugin.clone(), func, arg)
     }
 
+    /// `cfg` without the adapter-level options the plugin's own schema
+    /// doesn't declare, see [`strip_adapter_options`].
+    fn plugin_config(&self, cfg: &Value) -> Result<Value, LLMError> {
+        let schema = self
+            .call("config_schema", &Value::Null)
+            .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?;
+        let mut cfg = cfg.clone();
+        strip_adapter_options(&mut cfg, &schema);
+        Ok(cfg)
+    }
+
     fn validate_runtime_base_url(&self, cfg: &Value) -> Result<(), LLMError> {
         validate_runtime_base_url(&self.name, &self.allowed_hosts, cfg)
     }
@@ -379,30 +431,65 @@ impl LLMProviderFactory for ExtismFactory {
         &self.name
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        // Plugins built before the `capabilities` export existed report nothing.
+        if !self.plugin.lock().unwrap().function_exists("capabilities") {
+            return ProviderCapabilities::default();
+        }
+        self.call("capabilities", &Value::Null)
+            .and_then(|s| Ok(serde_json::from_str(&s)?))
+            .unwrap_or_else(|e| {
+                log::warn!(
+                    "Failed to read capabilities of plugin '{}': {:#}",
+                    self.name,
+                    e
+                );
+                ProviderCapabilities::default()
+            })
+    }
+
     fn config_schema(&self) -> String {
-        self.call("config_schema", &Value::Null)
-            .expect("config_schema() must return valid JSON string")
+        with_adapter_options(
+            &self
+                .call("config_schema", &Value::Null)
+                .expect("config_schema() must return valid JSON string"),
+        )
+    }
+
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
     }
 
     fn from_config(&self, cfg: &str) -> Result<Box<dyn LLMProvider>, LLMError> {
-        let cfg_value: Value = serde_json::from_str(cfg)
+        let mut cfg_value: Value = serde_json::from_str(cfg)
             .map_err(|e| LLMError::PluginError(format!("Invalid JSON config: {:#}", e)))?;
+        apply_default_model(&mut cfg_value, self.default_model.as_deref());
         self.validate_runtime_base_url(&cfg_value)?;
+        let plugin_cfg = self.plugin_config(&cfg_value)?;
 
         let _from_cfg = self
-            .call("from_config", &cfg_value)
+            .call("from_config", &plugin_cfg)
             .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?;
 
+        let transport = transport_for_config(config_transport(&cfg_value)?, &cfg_value);
+        let repair_enabled = json_repair::repair_enabled(&cfg_value);
+        let idempotency_keys = idempotency_keys_enabled(&cfg_value);
+        let max_messages = config_max_messages(&cfg_value);
         let provider = ExtismProvider {
             plugin: self.plugin.clone(),
-            config: cfg_value,
+            config: plugin_cfg,
             user_data: self.user_data.clone(),
             key_resolver: None,
         };
 
         if self.supports_http_adapter_abi() {
             let http_provider: Box<dyn HTTPLLMProvider> = Box::new(provider);
-            return Ok(Box::new(LLMProviderFromHTTP::new(http_provider)));
+            return Ok(Box::new(
+                LLMProviderFromHTTP::with_transport(http_provider, transport)
+                    .with_tool_json_repair(repair_enabled)
+                    .with_idempotency_keys(idempotency_keys)
+                    .with_max_messages(max_messages),
+            ));
         }
 
         Ok(Box::new(provider))
@@ -432,8 +519,12 @@ impl LLMProviderFactory for ExtismFactory {
                 Ok(req) => req,
                 Err(e) => return Box::pin(async move { Err(e) }),
             };
+            let transport = match config_transport(&cfg_value) {
+                Ok(transport) => transport_for_config(transport, &cfg_value),
+                Err(e) => return Box::pin(async move { Err(e) }),
+            };
             return async move {
-                let resp = crate::outbound::call_outbound(req).await?;
+                let resp = transport.execute(req).await?;
                 <Self as HTTPLLMProviderFactory>::parse_list_models(self, resp)
             }
             .boxed();
@@ -470,6 +561,10 @@ impl LLMProviderFactory for ExtismFactory {
         .boxed()
     }
 
+    fn health_check<'a>(&'a self, cfg: &str) -> Fut<'a, Result<(), LLMError>> {
+        self.list_models(cfg).map(|res| res.map(|_| ())).boxed()
+    }
+
     fn as_http(&self) -> Option<&dyn crate::plugin::http::HTTPLLMProviderFactory> {
         // Only return Some if the plugin is HTTP-based
         // Check if plugin exports the api_key_name function (exported by impl_extism_http_plugin!)
@@ -496,22 +591,32 @@ impl HTTPLLMProviderFactory for ExtismFactory {
         (self as &dyn LLMProviderFactory).name()
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        (self as &dyn LLMProviderFactory).capabilities()
+    }
+
     fn config_schema(&self) -> String {
         (self as &dyn LLMProviderFactory).config_schema()
     }
 
+    fn default_model(&self) -> Option<&str> {
+        self.default_model.as_deref()
+    }
+
     fn from_config(&self, cfg: &str) -> Result<Box<dyn crate::HTTPLLMProvider>, LLMError> {
-        let cfg_value: Value = serde_json::from_str(cfg)
+        let mut cfg_value: Value = serde_json::from_str(cfg)
             .map_err(|e| LLMError::PluginError(format!("Invalid JSON config: {:#}", e)))?;
+        apply_default_model(&mut cfg_value, self.default_model.as_deref());
         self.validate_runtime_base_url(&cfg_value)?;
+        let plugin_cfg = self.plugin_config(&cfg_value)?;
 
         let _from_cfg = self
-            .call("from_config", &cfg_value)
+            .call("from_config", &plugin_cfg)
             .map_err(|e| LLMError::PluginError(format!("{:#}", e)))?;
 
         let provider = ExtismProvider {
             plugin: self.plugin.clone(),
-            config: cfg_value,
+            config: plugin_cfg,
             user_data: self.user_data.clone(),
             key_resolver: None,
         };
@@ -609,6 +714,28 @@ impl ExtismProvider {
         Ok(cfg)
     }
 
+    async fn call_chat(
+        &self,
+        messages: &[ChatMessage],
+        tools: Option<&[Tool]>,
+    ) -> Result<ExtismChatResponse, LLMError> {
+        let arg = ExtismChatRequest {
+            cfg: self.effective_config()?,
+            messages: messages.to_vec(),
+            tools: tools.map(|v| v.to_vec()),
+        };
+        // chat can do host HTTP calls, so run the Extism VM call off the Tokio runtime thread to
+        // avoid deadlocks on current-thread runtimes. Also wire cancellation so dropping the
+        // future can interrupt host HTTP and release the plugin mutex.
+        self.call_blocking_with_cancel("chat", move |plug| {
+            let out: Json<ExtismChatResponse> = plug
+                .call_get_error_code("chat", Json(arg))
+                .map_err(|(e, code)| decode_plugin_error(e, code))?;
+            Ok(out.0)
+        })
+        .await
+    }
+
     fn call_short_blocking<T, F>(&self, op: &'static str, f: F) -> Result<T, LLMError>
     where
         F: FnOnce(&mut Plugin) -> Result<T, LLMError>,
@@ -728,35 +855,22 @@ impl ChatProvider for ExtismProvider {
         messages: &[ChatMessage],
         tools: Option<&[Tool]>,
     ) -> Result<Box<dyn ChatResponse>, LLMError> {
-        let mut cfg = self.config.clone();
-
         // Refresh OAuth token if resolver is present
         if let Some(ref resolver) = self.key_resolver {
             resolver.resolve().await?;
-            if let Some(obj) = cfg.as_object_mut() {
-                obj.insert(
-                    "api_key".to_string(),
-                    serde_json::Value::String(resolver.current()),
-                );
-            }
         }
 
-        let arg = ExtismChatRequest {
-            cfg,
-            messages: messages.to_vec(),
-            tools: tools.map(|v| v.to_vec()),
+        let out = match self.call_chat(messages, tools).await {
+            Ok(out) => out,
+            // The server rejected the credential: force a refresh and retry once.
+            Err(LLMError::AuthError(_)) if self.key_resolver.is_some() => {
+                if let Some(ref resolver) = self.key_resolver {
+                    resolver.refresh().await?;
+                }
+                self.call_chat(messages, tools).await?
+            }
+            Err(e) => return Err(e),
         };
-        // chat can do host HTTP calls, so run the Extism VM call off the Tokio runtime thread to
-        // avoid deadlocks on current-thread runtimes. Also wire cancellation so dropping the
-        // future can interrupt host HTTP and release the plugin mutex.
-        let out = self
-            .call_blocking_with_cancel("chat", move |plug| {
-                let out: Json<ExtismChatResponse> = plug
-                    .call_get_error_code("chat", Json(arg))
-                    .map_err(|(e, code)| decode_plugin_error(e, code))?;
-                Ok(out.0)
-            })
-            .await?;
 
         Ok(Box::new(out) as Box<dyn ChatResponse>)
     }
@@ -1381,7 +1495,7 @@ impl HTTPLLMProvider for ExtismProvider {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::chat::StreamChunk;
+    use crate::chat::{FinishReason, StreamChunk};
 
     #[test]
     fn build_allowed_hosts_prefers_configured_base_url_over_plugin_default() {
@@ -1448,6 +1562,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn plugin_response_with_empty_tool_calls_has_none() {
+        let resp: ExtismChatResponse = serde_json::from_value(serde_json::json!({
+            "text": "hi",
+            "tool_calls": [],
+            "thinking": null,
+            "usage": null,
+            "finish_reason": "Stop"
+        }))
+        .expect("response");
+        assert_eq!(resp.tool_calls(), None);
+    }
+
+    #[test]
+    fn plugin_response_round_trips_thinking_signature() {
+        let resp: ExtismChatResponse = serde_json::from_value(serde_json::json!({
+            "text": "hi",
+            "tool_calls": null,
+            "thinking": "let me think",
+            "thinking_signature": "sig-1",
+            "usage": null,
+            "finish_reason": "Stop"
+        }))
+        .expect("response");
+        let boxed: Box<dyn ChatResponse> = Box::new(resp);
+
+        let wire = serde_json::to_value(ExtismChatResponse::from(boxed)).expect("serialize");
+        let resp: ExtismChatResponse = serde_json::from_value(wire).expect("deserialize");
+        assert_eq!(resp.thinking().as_deref(), Some("let me think"));
+        assert_eq!(resp.thinking_signature().as_deref(), Some("sig-1"));
+    }
+
+    #[test]
+    fn plugin_response_round_trips_refusal() {
+        let resp: ExtismChatResponse = serde_json::from_value(serde_json::json!({
+            "text": null,
+            "tool_calls": null,
+            "thinking": null,
+            "usage": null,
+            "finish_reason": "Refusal",
+            "refusal": "I can't help with that."
+        }))
+        .expect("response");
+        let boxed: Box<dyn ChatResponse> = Box::new(resp);
+
+        let wire = serde_json::to_value(ExtismChatResponse::from(boxed)).expect("serialize");
+        let resp: ExtismChatResponse = serde_json::from_value(wire).expect("deserialize");
+        assert_eq!(resp.finish_reason(), Some(FinishReason::Refusal));
+        assert_eq!(resp.refusal().as_deref(), Some("I can't help with that."));
+    }
+
     #[test]
     fn decode_stream_item_returns_chunk_for_valid_payload() {
         let bytes = serde_json::to_vec(&crate::plugin::extism_impl::ExtismChatChunk {
@@ -1,6 +1,6 @@
 use crate::{
     ToolCall, Usage,
-    chat::{ChatMessage, ChatResponse, FinishReason, Tool},
+    chat::{ChatMessage, ChatResponse, Citation, FinishReason, ServerToolUse, Tool},
     completion::CompletionRequest,
     error::{LLMError, LLMErrorPayload},
     plugin::extism_impl::SerializableHttpResponse,
@@ -323,8 +323,28 @@ pub struct ExtismChatResponse {
     pub text: Option<String>,
     pub tool_calls: Option<Vec<ToolCall>>,
     pub thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thinking_signature: Option<String>,
     pub usage: Option<Usage>,
     pub finish_reason: Option<FinishReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequence: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub candidates: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<(String, Vec<u8>)>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_fingerprint: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server_tool_uses: Option<Vec<ServerToolUse>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<Citation>>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -350,17 +370,47 @@ impl ChatResponse for ExtismChatResponse {
         self.text.clone()
     }
     fn tool_calls(&self) -> Option<Vec<ToolCall>> {
-        self.tool_calls.clone()
+        crate::chat::non_empty_tool_calls(self.tool_calls.clone())
     }
     fn thinking(&self) -> Option<String> {
         self.thinking.clone()
     }
+    fn thinking_signature(&self) -> Option<String> {
+        self.thinking_signature.clone()
+    }
     fn usage(&self) -> Option<Usage> {
         self.usage.clone()
     }
     fn finish_reason(&self) -> Option<FinishReason> {
         self.finish_reason
     }
+    fn stop_sequence(&self) -> Option<String> {
+        self.stop_sequence.clone()
+    }
+    fn refusal(&self) -> Option<String> {
+        self.refusal.clone()
+    }
+    fn candidates(&self) -> Option<Vec<String>> {
+        self.candidates.clone()
+    }
+    fn images(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        self.images.clone()
+    }
+    fn raw(&self) -> Option<&serde_json::Value> {
+        self.raw.as_ref()
+    }
+    fn response_id(&self) -> Option<String> {
+        self.response_id.clone()
+    }
+    fn system_fingerprint(&self) -> Option<String> {
+        self.system_fingerprint.clone()
+    }
+    fn server_tool_uses(&self) -> Option<Vec<ServerToolUse>> {
+        self.server_tool_uses.clone()
+    }
+    fn citations(&self) -> Option<Vec<Citation>> {
+        self.citations.clone()
+    }
 }
 
 impl From<Box<dyn ChatResponse>> for ExtismChatResponse {
@@ -369,8 +419,18 @@ impl From<Box<dyn ChatResponse>> for ExtismChatResponse {
             text: r.text(),
             tool_calls: r.tool_calls(),
             thinking: r.thinking(),
+            thinking_signature: r.thinking_signature(),
             usage: r.usage(),
             finish_reason: r.finish_reason(),
+            stop_sequence: r.stop_sequence(),
+            refusal: r.refusal(),
+            candidates: r.candidates(),
+            images: r.images(),
+            raw: r.raw().cloned(),
+            response_id: r.response_id(),
+            system_fingerprint: r.system_fingerprint(),
+            server_tool_uses: r.server_tool_uses(),
+            citations: r.citations(),
         }
     }
 }
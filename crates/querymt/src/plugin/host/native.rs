@@ -1,9 +1,10 @@
 use crate::{
     error::LLMError,
     plugin::{
-        FactoryCtor, HTTPFactoryCtor, HTTPLLMProviderFactory, LLMProviderFactory,
-        PluginInitLoggingFn,
+        FactoryCtor, HTTPFactoryCtor, HTTPLLMProviderFactory, LLMProviderFactory, ModelMetadata,
+        PLUGIN_ABI_VERSION, PluginAbiVersionFn, PluginInitLoggingFn, ProviderCapabilities,
         adapters::HTTPFactoryAdapter,
+        config_with_default_model,
         host::{PluginLoader, PluginType, ProviderConfig, ProviderPlugin},
     },
 };
@@ -30,11 +31,28 @@ impl LLMProviderFactory for NativeFactoryWrapper {
         self.factory_impl.supports_custom_models()
     }
 
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.factory_impl.capabilities()
+    }
+
     fn config_schema(&self) -> String {
         self.factory_impl.config_schema()
     }
+    fn default_model(&self) -> Option<&str> {
+        self.factory_impl.default_model()
+    }
+
     fn from_config(&self, cfg: &str) -> Result<Box<dyn crate::LLMProvider>, LLMError> {
-        self.factory_impl.from_config(cfg)
+        let cfg = config_with_default_model(cfg, self.factory_impl.default_model());
+        self.factory_impl.from_config(&cfg)
+    }
+
+    fn validate_model<'a>(
+        &'a self,
+        cfg: &str,
+        model: &str,
+    ) -> crate::plugin::Fut<'a, Result<(), LLMError>> {
+        self.factory_impl.validate_model(cfg, model)
     }
 
     fn list_models<'a>(
@@ -43,6 +61,29 @@ impl LLMProviderFactory for NativeFactoryWrapper {
     ) -> crate::plugin::Fut<'a, Result<Vec<String>, LLMError>> {
         self.factory_impl.list_models(cfg)
     }
+
+    fn refresh_models<'a>(
+        &'a self,
+        cfg: &str,
+    ) -> crate::plugin::Fut<'a, Result<Vec<String>, LLMError>> {
+        self.factory_impl.refresh_models(cfg)
+    }
+
+    fn health_check<'a>(&'a self, cfg: &str) -> crate::plugin::Fut<'a, Result<(), LLMError>> {
+        self.factory_impl.health_check(cfg)
+    }
+
+    fn evict(&self, model: &str) -> bool {
+        self.factory_impl.evict(model)
+    }
+
+    /// Falls back to the host's registry, which the plugin's own copy of
+    /// the global registry may not have loaded.
+    fn model_metadata(&self, model: &str) -> Option<ModelMetadata> {
+        self.factory_impl
+            .model_metadata(model)
+            .or_else(|| ModelMetadata::from_registry(self.name(), model))
+    }
 }
 
 /// Host-side logging callback that forwards plugin log calls to the host's logger.
@@ -121,35 +162,52 @@ impl NativeLoader {
         path: &Path,
     ) -> Result<Arc<dyn LLMProviderFactory>, LLMError> {
         let lib = unsafe {
-            Arc::new(Library::new(path).map_err(|e| LLMError::PluginError(format!("{:#}", e)))?)
+            Library::new(path).map_err(|e| LLMError::PluginLoadError {
+                name: name.to_string(),
+                source: Box::new(e),
+            })?
+        };
+        let version = unsafe {
+            lib.get::<PluginAbiVersionFn>(b"plugin_abi_version")
+                .ok()
+                .map(|abi_version| abi_version())
+        };
+        check_abi_version(name, version)?;
+        Self::factory_from_library(name, path, Arc::new(lib))
+    }
+
+    fn factory_from_library(
+        name: &str,
+        path: &Path,
+        lib: Arc<Library>,
+    ) -> Result<Arc<dyn LLMProviderFactory>, LLMError> {
+        let null_factory = |symbol: &str| LLMError::PluginLoadError {
+            name: name.to_string(),
+            source: format!("{symbol} returned null in {}", path.display()).into(),
         };
 
         let factory: Box<dyn LLMProviderFactory> = unsafe {
             if let Ok(async_ctor) = lib.get::<FactoryCtor>(b"plugin_factory") {
                 let raw = async_ctor();
                 if raw.is_null() {
-                    return Err(LLMError::PluginError(format!(
-                        "plugin_factory returned null in {}",
-                        path.display()
-                    )));
+                    return Err(null_factory("plugin_factory"));
                 }
                 Box::from_raw(raw)
             } else if let Ok(sync_ctor) = lib.get::<HTTPFactoryCtor>(b"plugin_http_factory") {
                 let raw: *mut dyn HTTPLLMProviderFactory = sync_ctor();
                 if raw.is_null() {
-                    return Err(LLMError::PluginError(format!(
-                        "plugin_http_factory returned null in {}",
-                        path.display()
-                    )));
+                    return Err(null_factory("plugin_http_factory"));
                 }
                 let sync_fact: Box<dyn HTTPLLMProviderFactory> = Box::from_raw(raw);
                 let async_fact = HTTPFactoryAdapter::new(Arc::from(sync_fact));
                 Box::new(async_fact)
             } else {
-                return Err(LLMError::PluginError(format!(
-                    "no plugin_factory or plugin_http_factory in {}",
-                    path.display()
-                )));
+                // `plugin_factory` is only exported by native-only providers;
+                // HTTP providers export `plugin_http_factory`.
+                return Err(LLMError::PluginSymbolMissing {
+                    name: name.to_string(),
+                    symbol: "plugin_http_factory".into(),
+                });
             }
         };
 
@@ -183,3 +241,70 @@ impl NativeLoader {
         }))
     }
 }
+
+/// Reject a plugin whose `plugin_abi_version` export (`None` if missing)
+/// differs from the host's, before its factory pointer is trusted.
+fn check_abi_version(name: &str, version: Option<u32>) -> Result<(), LLMError> {
+    match version {
+        Some(PLUGIN_ABI_VERSION) => Ok(()),
+        Some(version) => Err(LLMError::PluginAbiMismatch {
+            name: name.to_string(),
+            message: format!(
+                "plugin ABI version {version}, host expects {PLUGIN_ABI_VERSION}; rebuild the plugin"
+            ),
+        }),
+        None => Err(LLMError::PluginAbiMismatch {
+            name: name.to_string(),
+            message: format!(
+                "plugin does not export plugin_abi_version, host expects {PLUGIN_ABI_VERSION}; rebuild the plugin"
+            ),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abi_version_must_match_host() {
+        assert!(check_abi_version("fake", Some(PLUGIN_ABI_VERSION)).is_ok());
+        for version in [Some(PLUGIN_ABI_VERSION + 1), Some(0), None] {
+            let err = check_abi_version("fake", version).expect_err("mismatch should fail");
+            assert!(
+                matches!(err, LLMError::PluginAbiMismatch { ref name, .. } if name == "fake"),
+                "{err:?}"
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn library_without_factory_reports_missing_symbol() {
+        // The test binary itself exports neither factory symbol.
+        let lib = Library::from(libloading::os::unix::Library::this());
+        let err = NativeLoader::factory_from_library("fake", Path::new("fake.so"), Arc::new(lib))
+            .err()
+            .expect("loading should fail");
+        assert!(
+            matches!(
+                err,
+                LLMError::PluginSymbolMissing { ref name, ref symbol }
+                    if name == "fake" && symbol == "plugin_http_factory"
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn unopenable_library_reports_load_error() {
+        let err = NativeLoader
+            .load_library("fake", Path::new("/nonexistent/libfake.so"))
+            .err()
+            .expect("loading should fail");
+        assert!(
+            matches!(err, LLMError::PluginLoadError { ref name, .. } if name == "fake"),
+            "{err:?}"
+        );
+    }
+}
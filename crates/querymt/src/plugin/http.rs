@@ -1,3 +1,4 @@
+use super::ProviderCapabilities;
 use crate::{HTTPLLMProvider, error::LLMError};
 use http::{Request, Response};
 
@@ -9,10 +10,20 @@ pub trait HTTPLLMProviderFactory: Send + Sync {
         false
     }
 
+    /// Features supported by providers built from this factory.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
     fn api_key_name(&self) -> Option<String> {
         None
     }
 
+    /// Model used when a config leaves `model` unset or empty.
+    fn default_model(&self) -> Option<&str> {
+        None
+    }
+
     /// Schema for plugin config
     fn config_schema(&self) -> String;
 
@@ -33,6 +44,31 @@ pub trait HTTPLLMProviderFactory: Send + Sync {
     // FIXME: refactor to follow rust standards
     #[allow(clippy::wrong_self_convention)]
     fn from_config(&self, cfg: &str) -> Result<Box<dyn HTTPLLMProvider>, LLMError>;
+
+    /// Attach an [`EnvApiKeyResolver`](crate::auth::EnvApiKeyResolver) for
+    /// [`api_key_name`](Self::api_key_name) to `provider`, so the key is
+    /// re-read from the environment on every request instead of once at
+    /// config time. Returns `provider` unchanged when the factory names no
+    /// variable.
+    ///
+    /// Fails with [`LLMError::NotImplemented`] when `provider` doesn't
+    /// support key resolvers, rather than silently keeping the key it was
+    /// configured with.
+    fn with_env_resolver(
+        &self,
+        mut provider: Box<dyn HTTPLLMProvider>,
+    ) -> Result<Box<dyn HTTPLLMProvider>, LLMError> {
+        if let Some(var) = self.api_key_name() {
+            provider.set_key_resolver(crate::auth::env_key(var));
+            if provider.key_resolver().is_none() {
+                return Err(LLMError::NotImplemented(format!(
+                    "{} providers don't support key resolvers",
+                    self.name()
+                )));
+            }
+        }
+        Ok(provider)
+    }
 }
 
 #[allow(improper_ctypes_definitions)]
@@ -1,8 +1,19 @@
-use crate::{LLMProvider, error::LLMError};
+use crate::{
+    LLMProvider,
+    error::LLMError,
+    providers::{ModelInfo, ProvidersRegistry},
+};
 use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
 
 #[cfg(feature = "http-client")]
 pub mod adapters;
+#[cfg(feature = "http-client")]
+pub mod model_cache;
+pub mod model_filter;
+pub use model_filter::ModelFilter;
 
 pub mod http;
 pub use http::HTTPFactoryCtor;
@@ -40,6 +51,69 @@ pub type LogCallbackFn = unsafe extern "C" fn(
 #[allow(improper_ctypes_definitions)]
 pub type PluginInitLoggingFn = unsafe extern "C" fn(callback: LogCallbackFn, max_level: usize);
 
+/// Features a provider supports, queryable before making any request.
+///
+/// Lets callers (e.g. a UI) hide or disable features up front instead of
+/// discovering them through `LLMError::NotImplemented` at runtime.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderCapabilities {
+    /// Incremental chat responses via `chat_stream`.
+    pub streaming: bool,
+    /// Tool / function calling.
+    pub tools: bool,
+    /// Image input in chat messages.
+    pub vision: bool,
+    /// PDF document input in chat messages.
+    pub pdf: bool,
+    /// Text embeddings via `embed`.
+    pub embeddings: bool,
+    /// Raw text completion via `complete`.
+    pub completion: bool,
+    /// JSON-schema constrained output.
+    pub structured_output: bool,
+    /// Reasoning / thinking output.
+    pub thinking: bool,
+}
+
+/// Limits and capabilities of a single model, e.g. for deciding how much
+/// conversation history to send.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelMetadata {
+    /// Context window in tokens.
+    pub max_context: Option<u64>,
+    /// Maximum tokens generated per response.
+    pub max_output: Option<u64>,
+    /// Image input in chat messages.
+    pub supports_vision: bool,
+    /// Tool / function calling.
+    pub supports_tools: bool,
+}
+
+impl ModelMetadata {
+    /// Metadata for `model` of `provider` from the global [`ProvidersRegistry`].
+    pub fn from_registry(provider: &str, model: &str) -> Option<Self> {
+        ProvidersRegistry::global()?
+            .get_model(provider, model)
+            .map(Self::from)
+    }
+}
+
+impl From<&ModelInfo> for ModelMetadata {
+    fn from(info: &ModelInfo) -> Self {
+        Self {
+            max_context: info.context_limit(),
+            max_output: info.output_limit(),
+            supports_vision: info
+                .capabilities
+                .modalities
+                .input
+                .iter()
+                .any(|m| m == "image"),
+            supports_tools: info.capabilities.supports_tools(),
+        }
+    }
+}
+
 pub trait LLMProviderFactory: Send + Sync {
     fn name(&self) -> &str;
     fn config_schema(&self) -> String;
@@ -49,6 +123,63 @@ pub trait LLMProviderFactory: Send + Sync {
 
     fn list_models<'a>(&'a self, cfg: &str) -> Fut<'a, Result<Vec<String>, LLMError>>;
 
+    /// Model used when a config leaves `model` unset or empty.
+    fn default_model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Check that `model` is offered by the provider configured by `cfg`.
+    ///
+    /// Only a model missing from a non-empty `list_models` result is
+    /// rejected; when the list can't be fetched (e.g. offline) the model is
+    /// accepted as-is.
+    fn validate_model<'a>(&'a self, cfg: &str, model: &str) -> Fut<'a, Result<(), LLMError>> {
+        let models = self.list_models(cfg);
+        let model = model.to_string();
+        Box::pin(async move {
+            match models.await {
+                Ok(models) if !models.is_empty() && !models.contains(&model) => {
+                    Err(LLMError::InvalidRequest(format!(
+                        "Unknown model '{}' for provider '{}'",
+                        model,
+                        self.name()
+                    )))
+                }
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    log::debug!("Skipping model validation for '{}': {:#}", self.name(), e);
+                    Ok(())
+                }
+            }
+        })
+    }
+
+    /// [`list_models`](LLMProviderFactory::list_models), keeping only the
+    /// models that pass `filter`. Chat support is taken from the global
+    /// [`ProvidersRegistry`] where it knows the model.
+    fn list_models_filtered<'a>(
+        &'a self,
+        cfg: &str,
+        filter: &ModelFilter,
+    ) -> Fut<'a, Result<Vec<String>, LLMError>> {
+        let models = self.list_models(cfg);
+        let filter = filter.clone();
+        Box::pin(async move {
+            let registry = if filter.chat_only {
+                ProvidersRegistry::global()
+            } else {
+                None
+            };
+            Ok(filter.apply_for(self.name(), registry, models.await?))
+        })
+    }
+
+    /// Like [`list_models`](LLMProviderFactory::list_models), but bypasses
+    /// any cached result. Defaults to `list_models` for uncached factories.
+    fn refresh_models<'a>(&'a self, cfg: &str) -> Fut<'a, Result<Vec<String>, LLMError>> {
+        self.list_models(cfg)
+    }
+
     fn as_http(&self) -> Option<&dyn http::HTTPLLMProviderFactory> {
         None
     }
@@ -58,7 +189,84 @@ pub trait LLMProviderFactory: Send + Sync {
     fn supports_custom_models(&self) -> bool {
         false
     }
+
+    /// Features supported by providers built from this factory.
+    fn capabilities(&self) -> ProviderCapabilities {
+        ProviderCapabilities::default()
+    }
+
+    /// Limits and capabilities of `model`, if known.
+    ///
+    /// Defaults to the entry for this factory's name in the global
+    /// [`ProvidersRegistry`]. Local providers override it with what the model
+    /// file itself reports.
+    fn model_metadata(&self, model: &str) -> Option<ModelMetadata> {
+        ModelMetadata::from_registry(self.name(), model)
+    }
+
+    /// Cheaply verify that a provider built from `cfg` is usable: the backend
+    /// is reachable and credentials are valid (or, for local providers, the
+    /// model loads). Intended as a readiness signal before routing traffic.
+    ///
+    /// Defaults to `Ok(())` so providers can opt in incrementally.
+    fn health_check<'a>(&'a self, _cfg: &str) -> Fut<'a, Result<(), LLMError>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Drop `model` from any cache of loaded models the factory keeps, so
+    /// its memory can be reclaimed. Returns whether it was cached.
+    ///
+    /// Defaults to `false` for factories that don't cache models.
+    fn evict(&self, _model: &str) -> bool {
+        false
+    }
+}
+
+/// Set `model` to `default_model` when `cfg` leaves it unset or empty.
+pub fn apply_default_model(cfg: &mut Value, default_model: Option<&str>) {
+    let (Some(default_model), Some(obj)) = (default_model, cfg.as_object_mut()) else {
+        return;
+    };
+    let has_model = match obj.get("model") {
+        None | Some(Value::Null) => false,
+        Some(Value::String(model)) => !model.is_empty(),
+        Some(_) => true,
+    };
+    if !has_model {
+        obj.insert("model".into(), Value::String(default_model.to_string()));
+    }
+}
+
+/// String form of [`apply_default_model`] for `from_config` inputs.
+///
+/// Configs that don't parse are returned unchanged so `from_config` can
+/// report the error itself.
+pub fn config_with_default_model<'a>(cfg: &'a str, default_model: Option<&str>) -> Cow<'a, str> {
+    if default_model.is_none() {
+        return Cow::Borrowed(cfg);
+    }
+    let Ok(mut value) = serde_json::from_str::<Value>(cfg) else {
+        return Cow::Borrowed(cfg);
+    };
+    let before = value.get("model").cloned();
+    apply_default_model(&mut value, default_model);
+    if value.get("model") == before.as_ref() {
+        return Cow::Borrowed(cfg);
+    }
+    serde_json::to_string(&value).map_or(Cow::Borrowed(cfg), Cow::Owned)
 }
 
 #[allow(improper_ctypes_definitions)]
 pub type FactoryCtor = unsafe extern "C" fn() -> *mut dyn LLMProviderFactory;
+
+/// Version of the native plugin ABI: the layout of the factory trait objects
+/// handed across `plugin_factory` / `plugin_http_factory`.
+///
+/// Native plugins export it from `plugin_abi_version`, and the host refuses
+/// to load a plugin whose version differs. Bump it whenever
+/// [`LLMProviderFactory`], [`HTTPLLMProviderFactory`] or any type they pass by
+/// value changes.
+pub const PLUGIN_ABI_VERSION: u32 = 3;
+
+/// Type for the `plugin_abi_version` symbol in native plugins.
+pub type PluginAbiVersionFn = unsafe extern "C" fn() -> u32;
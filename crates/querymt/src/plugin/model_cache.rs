@@ -0,0 +1,121 @@
+//! In-memory caching of `list_models` results.
+//!
+//! Model pickers tend to refresh the list every time they are opened, while
+//! the set of models a provider offers rarely changes. [`ModelListCache`]
+//! keeps each provider's list for a fixed TTL so repeated calls within that
+//! window don't go back to the network.
+
+use crate::error::LLMError;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// Model lists keyed by provider name and base URL, each kept for `ttl`.
+#[derive(Debug)]
+pub struct ModelListCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, Vec<String>)>>,
+}
+
+impl ModelListCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Cache key for `provider` with config `cfg`: the provider name plus the
+    /// config's `base_url`, so self-hosted endpoints are cached separately.
+    pub fn key(provider: &str, cfg: &Value) -> String {
+        let base_url = cfg.get("base_url").and_then(Value::as_str).unwrap_or("");
+        format!("{provider}\n{base_url}")
+    }
+
+    /// The cached list for `key`, if it is younger than the TTL.
+    pub fn get(&self, key: &str) -> Option<Vec<String>> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        entries
+            .get(key)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < self.ttl)
+            .map(|(_, models)| models.clone())
+    }
+
+    pub fn insert(&self, key: String, models: Vec<String>) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, (Instant::now(), models));
+    }
+
+    /// Drop every cached list.
+    pub fn clear(&self) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Return the cached list for `key`, or run `fetch` and cache its result.
+    ///
+    /// `force_refresh` skips the lookup but still caches the fresh list.
+    /// Errors are never cached.
+    pub async fn get_or_fetch<F>(
+        &self,
+        key: String,
+        force_refresh: bool,
+        fetch: F,
+    ) -> Result<Vec<String>, LLMError>
+    where
+        F: Future<Output = Result<Vec<String>, LLMError>>,
+    {
+        if !force_refresh && let Some(models) = self.get(&key) {
+            return Ok(models);
+        }
+        let models = fetch.await?;
+        self.insert(key, models.clone());
+        Ok(models)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn key_separates_base_urls() {
+        assert_ne!(
+            ModelListCache::key("openai", &json!({})),
+            ModelListCache::key("openai", &json!({ "base_url": "http://localhost:8000/v1" }))
+        );
+        assert_eq!(
+            ModelListCache::key("openai", &json!({ "api_key": "a" })),
+            ModelListCache::key("openai", &json!({ "api_key": "b" }))
+        );
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_ttl() {
+        let cache = ModelListCache::new(Duration::from_millis(20));
+        cache.insert("k".into(), vec!["m1".into()]);
+        assert_eq!(cache.get("k"), Some(vec!["m1".to_string()]));
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(cache.get("k"), None);
+    }
+
+    #[tokio::test]
+    async fn errors_are_not_cached() {
+        let cache = ModelListCache::new(Duration::from_secs(60));
+        let err = cache
+            .get_or_fetch("k".into(), false, async {
+                Err(LLMError::ProviderError("down".into()))
+            })
+            .await;
+        assert!(err.is_err());
+        assert_eq!(cache.get("k"), None);
+    }
+}
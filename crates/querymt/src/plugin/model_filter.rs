@@ -0,0 +1,259 @@
+//! Narrowing `list_models` results for model pickers.
+//!
+//! OpenAI-compatible endpoints can list hundreds of models, including
+//! embedding, speech and image models. [`ModelFilter`] keeps the ones whose
+//! id matches a glob pattern and, optionally, drops non-chat models.
+
+use crate::providers::{ModelCapabilities, ProvidersRegistry};
+use serde::{Deserialize, Serialize};
+
+/// Id fragments of models that can't be used for chat.
+///
+/// Matched against the alphanumeric words of an id when the registry
+/// doesn't know the model, since model lists carry only ids.
+const NON_CHAT_MARKERS: &[&str] = &[
+    "embed",
+    "embedding",
+    "whisper",
+    "tts",
+    "transcribe",
+    "dall",
+    "imagen",
+    "image",
+    "moderation",
+    "rerank",
+    "davinci",
+    "babbage",
+];
+
+/// Criteria for [`LLMProviderFactory::list_models_filtered`](super::LLMProviderFactory::list_models_filtered).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelFilter {
+    /// Glob matched against the whole model id: `*` matches any run of
+    /// characters and `?` a single one, e.g. `claude-3*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Drop embedding, speech, image and moderation models.
+    #[serde(default)]
+    pub chat_only: bool,
+    /// Id words marking a model as non-chat for `chat_only`, replacing the
+    /// built-in list. Only used for models missing from the registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_chat_markers: Option<Vec<String>>,
+}
+
+impl ModelFilter {
+    /// Filter matching ids against the glob `pattern`.
+    pub fn pattern(pattern: impl Into<String>) -> Self {
+        Self {
+            pattern: Some(pattern.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Also drop non-chat models.
+    pub fn chat_only(mut self) -> Self {
+        self.chat_only = true;
+        self
+    }
+
+    /// Replace the id words that mark a model as non-chat.
+    pub fn non_chat_markers<S: Into<String>>(
+        mut self,
+        markers: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.non_chat_markers = Some(markers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Whether `model` passes the filter.
+    pub fn matches(&self, model: &str) -> bool {
+        self.matches_with(model, None)
+    }
+
+    /// Whether `model` passes the filter, judging chat support by its
+    /// registry `capabilities` when known.
+    pub fn matches_with(&self, model: &str, capabilities: Option<&ModelCapabilities>) -> bool {
+        if let Some(pattern) = &self.pattern
+            && !glob_match(pattern, model)
+        {
+            return false;
+        }
+        !self.chat_only || self.is_chat_model(model, capabilities)
+    }
+
+    /// Keep the models in `models` that pass the filter, in order.
+    pub fn apply(&self, models: Vec<String>) -> Vec<String> {
+        models.into_iter().filter(|m| self.matches(m)).collect()
+    }
+
+    /// Like [`apply`](Self::apply), but looks up `provider`'s models in
+    /// `registry` to tell chat models apart.
+    pub fn apply_for(
+        &self,
+        provider: &str,
+        registry: Option<&ProvidersRegistry>,
+        models: Vec<String>,
+    ) -> Vec<String> {
+        models
+            .into_iter()
+            .filter(|m| {
+                let capabilities = registry.and_then(|r| r.get_capabilities(provider, m));
+                self.matches_with(m, capabilities)
+            })
+            .collect()
+    }
+
+    fn is_chat_model(&self, model: &str, capabilities: Option<&ModelCapabilities>) -> bool {
+        if let Some(capabilities) = capabilities
+            && !capabilities.modalities.output.is_empty()
+        {
+            return capabilities.modalities.output.iter().any(|m| m == "text");
+        }
+        let lower = model.to_ascii_lowercase();
+        let mut words = lower.split(|c: char| !c.is_ascii_alphanumeric());
+        let non_chat = match &self.non_chat_markers {
+            Some(markers) => words.any(|word| markers.iter().any(|m| m.eq_ignore_ascii_case(word))),
+            None => words.any(|word| NON_CHAT_MARKERS.contains(&word)),
+        };
+        !non_chat
+    }
+}
+
+/// Match `text` against a glob with `*` and `?` wildcards.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it currently covers up to.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ModelInfo, ProviderInfo};
+    use std::collections::HashMap;
+
+    fn models() -> Vec<String> {
+        [
+            "claude-3-5-sonnet-20241022",
+            "claude-3-haiku-20240307",
+            "claude-sonnet-4-20250514",
+            "gpt-4o",
+            "text-embedding-3-small",
+            "whisper-1",
+            "dall-e-3",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    #[test]
+    fn pattern_filters_by_glob() {
+        assert_eq!(
+            ModelFilter::pattern("claude-3*").apply(models()),
+            vec!["claude-3-5-sonnet-20241022", "claude-3-haiku-20240307"]
+        );
+        assert_eq!(
+            ModelFilter::pattern("gpt-4?").apply(models()),
+            vec!["gpt-4o"]
+        );
+        assert_eq!(ModelFilter::pattern("*sonnet*").apply(models()).len(), 2);
+        assert!(ModelFilter::pattern("claude").apply(models()).is_empty());
+    }
+
+    #[test]
+    fn chat_only_drops_non_chat_models() {
+        let filtered = ModelFilter::default().chat_only().apply(models());
+        assert_eq!(
+            filtered,
+            vec![
+                "claude-3-5-sonnet-20241022",
+                "claude-3-haiku-20240307",
+                "claude-sonnet-4-20250514",
+                "gpt-4o",
+            ]
+        );
+        assert_eq!(ModelFilter::default().apply(models()), models());
+    }
+
+    #[test]
+    fn non_chat_markers_override_the_built_in_list() {
+        let filter = ModelFilter::default()
+            .chat_only()
+            .non_chat_markers(["haiku", "DALL"]);
+        assert_eq!(
+            filter.apply(models()),
+            vec![
+                "claude-3-5-sonnet-20241022",
+                "claude-sonnet-4-20250514",
+                "gpt-4o",
+                "text-embedding-3-small",
+                "whisper-1",
+            ]
+        );
+
+        let filter: ModelFilter = serde_json::from_value(serde_json::json!({
+            "chat_only": true,
+            "non_chat_markers": ["sonnet"]
+        }))
+        .unwrap();
+        assert_eq!(filter.apply(models()).len(), 5);
+    }
+
+    #[test]
+    fn chat_only_prefers_registry_modalities() {
+        let model = |id: &str, output: &str| {
+            let mut info = ModelInfo {
+                id: id.to_string(),
+                ..Default::default()
+            };
+            info.capabilities.modalities.output = vec![output.to_string()];
+            (id.to_string(), info)
+        };
+        let provider = ProviderInfo {
+            id: "openai".to_string(),
+            models: HashMap::from([
+                // Registry knowledge wins over the id heuristic either way.
+                model("gpt-4o", "embedding"),
+                model("text-embedding-3-small", "text"),
+            ]),
+            ..Default::default()
+        };
+        let registry = ProvidersRegistry::from(HashMap::from([("openai".to_string(), provider)]));
+
+        assert_eq!(
+            ModelFilter::default()
+                .chat_only()
+                .apply_for("openai", Some(&registry), models()),
+            vec![
+                "claude-3-5-sonnet-20241022",
+                "claude-3-haiku-20240307",
+                "claude-sonnet-4-20250514",
+                "text-embedding-3-small",
+            ]
+        );
+    }
+}
@@ -111,3 +111,72 @@ pub unsafe fn init_from_host(callback: LogCallbackFn, max_level: usize) {
 
     log::set_max_level(level);
 }
+
+/// Export `plugin_init_logging` from a native plugin, forwarding its `log`
+/// output to the host through [`init_from_host`].
+///
+/// Plugins that only export `plugin_http_factory` would otherwise log into
+/// their own, uninitialized copy of the `log` crate.
+///
+/// # Example
+///
+/// ```ignore
+/// #[cfg(feature = "native")]
+/// querymt::export_plugin_init_logging!();
+/// ```
+#[macro_export]
+macro_rules! export_plugin_init_logging {
+    () => {
+        /// Initialize logging from the host process.
+        ///
+        /// # Safety
+        ///
+        /// The `callback` function pointer must remain valid and thread-safe for
+        /// the lifetime of the plugin. The host calls this once per plugin load.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn plugin_init_logging(
+            callback: $crate::plugin::LogCallbackFn,
+            max_level: usize,
+        ) {
+            unsafe {
+                $crate::plugin::plugin_log::init_from_host(callback, max_level);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::plugin::PluginInitLoggingFn;
+    use std::ffi::{CStr, c_char};
+    use std::sync::Mutex;
+
+    mod exported {
+        crate::export_plugin_init_logging!();
+    }
+
+    static RECEIVED: Mutex<Vec<(usize, String)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn record(level: usize, target: *const c_char, message: *const c_char) {
+        let target = unsafe { CStr::from_ptr(target) }.to_string_lossy();
+        if target == "plugin_log_test" {
+            let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+            RECEIVED.lock().unwrap().push((level, message.into_owned()));
+        }
+    }
+
+    #[test]
+    fn exported_init_logging_forwards_to_callback() {
+        // The host resolves the symbol as a `PluginInitLoggingFn`.
+        let init: PluginInitLoggingFn = exported::plugin_init_logging;
+        unsafe { init(record, log::LevelFilter::Debug as usize) };
+
+        log::debug!(target: "plugin_log_test", "hello from plugin");
+        log::trace!(target: "plugin_log_test", "filtered out");
+
+        assert_eq!(
+            *RECEIVED.lock().unwrap(),
+            vec![(log::Level::Debug as usize, "hello from plugin".to_string())]
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::LLMError;
 use crate::plugin::{LLMProviderFactory, host::PluginRegistry};
+use crate::providers::ProvidersRegistry;
 use serde_json::{Map, Value};
 use std::env;
 
@@ -184,6 +185,34 @@ pub fn pruned_top_level_keys(before: &Value, after: &Value) -> Vec<String> {
     removed
 }
 
+/// When `cfg` sets `clamp_max_tokens = true`, lower its `max_tokens` to the
+/// output limit `models` knows for its model, logging a warning.
+///
+/// Without the flag, or without a known limit, `cfg` is left as-is and an
+/// over-limit `max_tokens` is left for the provider to reject.
+pub fn clamp_max_tokens(cfg: &mut Value, provider_name: &str, models: &ProvidersRegistry) {
+    if cfg.get("clamp_max_tokens").and_then(Value::as_bool) != Some(true) {
+        return;
+    }
+    let (Some(model), Some(max_tokens)) = (
+        cfg.get("model").and_then(Value::as_str),
+        cfg.get("max_tokens").and_then(Value::as_u64),
+    ) else {
+        return;
+    };
+    let Some(limit) = models
+        .get_limits(provider_name, model)
+        .and_then(|limits| limits.output)
+        .filter(|&limit| limit > 0 && limit < max_tokens)
+    else {
+        return;
+    };
+    log::warn!(
+        "max_tokens {max_tokens} exceeds the {limit}-token output limit of {provider_name}/{model}; clamping to {limit}"
+    );
+    cfg["max_tokens"] = limit.into();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -284,4 +313,47 @@ mod tests {
         );
         assert_eq!(resolved.pruned_keys, vec!["extra".to_string()]);
     }
+
+    #[test]
+    fn clamp_max_tokens_lowers_over_limit_requests_when_enabled() {
+        let models: ProvidersRegistry = serde_json::from_value(serde_json::json!({
+            "providers": {
+                "anthropic": {
+                    "id": "anthropic",
+                    "name": "Anthropic",
+                    "models": {
+                        "claude-3-haiku-20240307": {
+                            "id": "claude-3-haiku-20240307",
+                            "limit": { "context": 200000, "output": 4096 }
+                        }
+                    }
+                }
+            }
+        }))
+        .expect("registry");
+        let cfg = serde_json::json!({
+            "model": "claude-3-haiku-20240307",
+            "max_tokens": 100000
+        });
+
+        let mut unclamped = cfg.clone();
+        clamp_max_tokens(&mut unclamped, "anthropic", &models);
+        assert_eq!(unclamped["max_tokens"], 100000);
+
+        let mut clamped = cfg.clone();
+        clamped["clamp_max_tokens"] = true.into();
+        clamp_max_tokens(&mut clamped, "anthropic", &models);
+        assert_eq!(clamped["max_tokens"], 4096);
+
+        let mut within = clamped.clone();
+        within["max_tokens"] = 1024.into();
+        clamp_max_tokens(&mut within, "anthropic", &models);
+        assert_eq!(within["max_tokens"], 1024);
+
+        let mut unknown = clamped;
+        unknown["model"] = "claude-unknown".into();
+        unknown["max_tokens"] = 100000.into();
+        clamp_max_tokens(&mut unknown, "anthropic", &models);
+        assert_eq!(unknown["max_tokens"], 100000);
+    }
 }
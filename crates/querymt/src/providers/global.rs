@@ -0,0 +1,124 @@
+//! Loading the providers registry once per process.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use super::types::ProvidersRegistry;
+use crate::error::LLMError;
+
+/// Environment variable pointing at a registry JSON file.
+pub const REGISTRY_PATH_ENV: &str = "QMT_PROVIDERS_REGISTRY_PATH";
+
+/// Environment (or Extism plugin config) variable carrying the registry JSON
+/// inline. The Extism host sets it for every plugin it loads.
+pub const REGISTRY_DATA_ENV: &str = "PROVIDERS_REGISTRY_DATA";
+
+static GLOBAL_REGISTRY: OnceLock<Option<ProvidersRegistry>> = OnceLock::new();
+
+impl ProvidersRegistry {
+    /// Read a registry from a JSON file in the models.dev format.
+    pub fn load_from_path(path: impl AsRef<Path>) -> Result<Self, LLMError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// The process-wide registry, parsed on first use.
+    ///
+    /// Sources are tried in order: the file named by
+    /// [`REGISTRY_PATH_ENV`], the inline JSON in [`REGISTRY_DATA_ENV`], and
+    /// with the `model-registry` feature the local models.dev cache. Returns
+    /// `None` if none of them yields a registry.
+    pub fn global() -> Option<&'static ProvidersRegistry> {
+        GLOBAL_REGISTRY
+            .get_or_init(|| {
+                load_registry(
+                    std::env::var(REGISTRY_PATH_ENV).ok(),
+                    std::env::var(REGISTRY_DATA_ENV).ok(),
+                )
+            })
+            .as_ref()
+    }
+}
+
+fn load_registry(path: Option<String>, data: Option<String>) -> Option<ProvidersRegistry> {
+    if let Some(path) = path.filter(|p| !p.trim().is_empty()) {
+        match ProvidersRegistry::load_from_path(&path) {
+            Ok(registry) => return Some(registry),
+            Err(e) => log::warn!("Failed to load providers registry from {path}: {e}"),
+        }
+    }
+
+    if let Some(data) = data {
+        match serde_json::from_str(&data) {
+            Ok(registry) => return Some(registry),
+            Err(e) => log::warn!("Failed to parse {REGISTRY_DATA_ENV}: {e}"),
+        }
+    }
+
+    #[cfg(feature = "model-registry")]
+    match super::read_providers_from_cache() {
+        Ok(registry) => return Some(registry),
+        Err(e) => log::debug!("No cached providers registry: {e}"),
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTRY: &str = r#"{
+        "providers": {
+            "anthropic": {
+                "id": "anthropic",
+                "name": "Anthropic",
+                "models": {
+                    "claude-test": {
+                        "id": "claude-test",
+                        "name": "Test",
+                        "cost": { "input": 3.0, "output": 15.0, "cache_read": 0.3 }
+                    }
+                }
+            }
+        }
+    }"#;
+
+    fn registry_file(name: &str) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("qmt-registry-{}-{name}.json", std::process::id()));
+        std::fs::write(&path, REGISTRY).unwrap();
+        path
+    }
+
+    #[test]
+    fn load_from_path_reads_pricing() {
+        let path = registry_file("load");
+        let registry = ProvidersRegistry::load_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let pricing = registry.get_pricing("anthropic", "claude-test").unwrap();
+        assert_eq!(pricing.input, Some(3.0));
+        assert_eq!(pricing.output, Some(15.0));
+        assert_eq!(pricing.cache_read, Some(0.3));
+    }
+
+    #[test]
+    fn load_registry_prefers_path_and_falls_back_to_data() {
+        let path = registry_file("prefer");
+        let from_path = load_registry(
+            Some(path.display().to_string()),
+            Some(r#"{"providers": {}}"#.to_string()),
+        )
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(from_path.get_model("anthropic", "claude-test").is_some());
+
+        let from_data = load_registry(
+            Some("/nonexistent/registry.json".to_string()),
+            Some(REGISTRY.to_string()),
+        )
+        .unwrap();
+        assert!(from_data.get_model("anthropic", "claude-test").is_some());
+    }
+}
@@ -1,8 +1,10 @@
+mod global;
 mod queries;
 #[cfg(feature = "model-registry")]
 mod registry;
 mod types;
 
+pub use global::{REGISTRY_DATA_ENV, REGISTRY_PATH_ENV};
 #[cfg(feature = "model-registry")]
 pub use registry::{read_providers_from_cache, update_providers_if_stale};
 pub use types::{
@@ -1,4 +1,5 @@
 use super::types::{ModelInfo, ProviderInfo, ProvidersRegistry};
+use crate::{Usage, chat::ChatResponse};
 
 impl ProvidersRegistry {
     pub fn get_provider(&self, id: &str) -> Option<&ProviderInfo> {
@@ -63,6 +64,22 @@ impl ProvidersRegistry {
         self.get_model(provider, model).map(|m| &m.pricing)
     }
 
+    /// Estimate the USD cost of `usage` on `model`. See
+    /// [`ModelPricing::estimate_cost`].
+    pub fn estimate_cost(&self, provider: &str, model: &str, usage: &Usage) -> Option<f64> {
+        self.get_pricing(provider, model)?.estimate_cost(usage)
+    }
+
+    /// Estimate the USD cost of a chat response from its reported usage.
+    pub fn estimate_response_cost(
+        &self,
+        provider: &str,
+        model: &str,
+        response: &dyn ChatResponse,
+    ) -> Option<f64> {
+        self.estimate_cost(provider, model, &response.usage()?)
+    }
+
     pub fn get_limits(&self, provider: &str, model: &str) -> Option<&super::types::ModelLimits> {
         self.get_model(provider, model).map(|m| &m.limits)
     }
@@ -337,6 +354,35 @@ mod tests {
         assert_eq!(model.unwrap().name, "GLM-5.1 Coding");
     }
 
+    #[test]
+    fn test_estimate_cost_uses_model_pricing() {
+        let mut registry = create_test_registry();
+        registry
+            .providers
+            .get_mut("openai")
+            .unwrap()
+            .models
+            .get_mut("gpt-4")
+            .unwrap()
+            .pricing = crate::providers::ModelPricing {
+            input: Some(10.0),
+            output: Some(30.0),
+            cache_read: Some(1.0),
+            cache_write: None,
+        };
+        let usage = Usage {
+            input_tokens: 100_000,
+            output_tokens: 10_000,
+            cache_read: 500_000,
+            ..Usage::zero()
+        };
+
+        // 1.0 input + 0.3 output + 0.5 cache read; also reachable via codex fallback
+        assert_eq!(registry.estimate_cost("openai", "gpt-4", &usage), Some(1.8));
+        assert_eq!(registry.estimate_cost("codex", "gpt-4", &usage), Some(1.8));
+        assert_eq!(registry.estimate_cost("openai", "missing", &usage), None);
+    }
+
     #[test]
     fn test_fallback_propagates_through_helper_methods() {
         let registry = create_test_registry();
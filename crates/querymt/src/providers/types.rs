@@ -1,3 +1,4 @@
+use crate::Usage;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -118,6 +119,27 @@ impl ModelPricing {
 
         (read_cost, write_cost)
     }
+
+    /// Estimate the cost of a request from its [`Usage`].
+    ///
+    /// Reasoning tokens are billed at the output rate. Cache reads and
+    /// writes use their own rates, falling back to the input rate for
+    /// models that don't price them separately.
+    ///
+    /// Returns cost in USD, or None if input or output pricing is missing
+    pub fn estimate_cost(&self, usage: &Usage) -> Option<f64> {
+        let input_rate = self.input?;
+        let output_rate = self.output?;
+        let per_token = |tokens: u32, rate: f64| (f64::from(tokens) / 1_000_000.0) * rate;
+
+        Some(
+            per_token(usage.input_tokens, input_rate)
+                + per_token(usage.output_tokens, output_rate)
+                + per_token(usage.reasoning_tokens, output_rate)
+                + per_token(usage.cache_read, self.cache_read.unwrap_or(input_rate))
+                + per_token(usage.cache_write, self.cache_write.unwrap_or(input_rate)),
+        )
+    }
 }
 
 impl ModelCapabilities {
@@ -227,6 +249,46 @@ mod tests {
         assert_eq!(write, Some(1.875)); // 500k * 3.75 / 1M
     }
 
+    #[test]
+    fn test_pricing_estimate_cost_from_usage() {
+        let pricing = ModelPricing {
+            input: Some(3.0),
+            output: Some(15.0),
+            cache_read: Some(0.3),
+            cache_write: Some(3.75),
+        };
+        let usage = Usage {
+            input_tokens: 200_000,
+            output_tokens: 50_000,
+            reasoning_tokens: 10_000,
+            cache_read: 1_000_000,
+            cache_write: 100_000,
+        };
+
+        // 0.6 input + 0.9 output incl. reasoning + 0.3 cache read + 0.375 cache write
+        let cost = pricing.estimate_cost(&usage).unwrap();
+        assert!((cost - 2.175).abs() < 1e-9, "cost was {cost}");
+    }
+
+    #[test]
+    fn test_pricing_estimate_cost_without_cache_rates() {
+        let pricing = ModelPricing {
+            input: Some(2.0),
+            output: Some(8.0),
+            cache_read: None,
+            cache_write: None,
+        };
+        let usage = Usage {
+            input_tokens: 500_000,
+            cache_read: 500_000,
+            ..Usage::zero()
+        };
+
+        // Cached tokens fall back to the input rate.
+        assert_eq!(pricing.estimate_cost(&usage), Some(2.0));
+        assert_eq!(ModelPricing::default().estimate_cost(&usage), None);
+    }
+
     #[test]
     fn test_capabilities_supports() {
         let caps = ModelCapabilities {
@@ -0,0 +1,231 @@
+//! Masking secrets, and optionally user content, before logging requests.
+//!
+//! Credentials are always masked: `authorization`-style headers and JSON
+//! keys such as `api_key` or `access_token`. Setting `QMT_LOG_REDACT=1`
+//! additionally replaces message content (`content`, `text`, `arguments`,
+//! ...) with its length, for running with `RUST_LOG=debug` in production.
+
+use http::HeaderMap;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+pub(crate) const REDACTED: &str = "[redacted]";
+
+/// Environment variable that enables content redaction.
+pub const REDACT_ENV: &str = "QMT_LOG_REDACT";
+
+/// Whether `QMT_LOG_REDACT` asks for user content to be redacted. Read once.
+pub fn content_redaction_enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        std::env::var(REDACT_ENV).is_ok_and(|v| {
+            matches!(
+                v.trim().to_ascii_lowercase().as_str(),
+                "1" | "true" | "yes" | "on"
+            )
+        })
+    })
+}
+
+/// Whether a header name or JSON key holds a credential.
+pub fn is_sensitive_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    matches!(
+        key.as_str(),
+        "api_key"
+            | "apikey"
+            | "api-key"
+            | "authorization"
+            | "proxy-authorization"
+            | "bearer"
+            | "token"
+            | "access_token"
+            | "cookie"
+            | "set-cookie"
+    ) || key.ends_with("_token")
+        || key.ends_with("_key")
+        || key.ends_with("-token")
+        || key.ends_with("-key")
+}
+
+/// JSON keys whose string values are user or model content.
+fn is_content_key(key: &str) -> bool {
+    matches!(
+        key,
+        "content" | "text" | "input" | "prompt" | "arguments" | "thinking" | "data" | "delta"
+    )
+}
+
+/// Render `headers` for logging, masking credential values. For
+/// `Authorization`, the scheme (e.g. `Bearer`) is kept.
+pub fn redact_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive_key(name.as_str()) {
+                match value.to_str().ok().and_then(|v| v.split_once(' ')) {
+                    Some((scheme, _)) => format!("{scheme} {REDACTED}"),
+                    None => REDACTED.to_string(),
+                }
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            format!("{}: {value}", name.as_str())
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Mask credentials in `value`, and user content when
+/// [`content_redaction_enabled`].
+pub fn redact_json(value: &mut Value) {
+    redact_json_with(value, content_redaction_enabled());
+}
+
+fn redact_json_with(value: &mut Value, content: bool) {
+    match value {
+        Value::Object(obj) => {
+            for (key, value) in obj.iter_mut() {
+                if is_sensitive_key(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else if content && is_content_key(key) {
+                    redact_content(value);
+                } else {
+                    redact_json_with(value, content);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                redact_json_with(value, content);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replace every string under a content key with its length, keeping the
+/// structure (content blocks, tool calls) visible.
+fn redact_content(value: &mut Value) {
+    match value {
+        Value::String(s) => *value = Value::String(format!("[{} chars]", s.chars().count())),
+        Value::Object(obj) => {
+            for (key, value) in obj.iter_mut() {
+                if is_sensitive_key(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else if key != "type" {
+                    redact_content(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_content),
+        _ => {}
+    }
+}
+
+/// Redact a request/response body or SSE chunk for logging.
+///
+/// JSON, and the `data:` lines of server-sent events, are redacted with
+/// [`redact_json`]. Other text is returned unchanged, or replaced by its
+/// size when content redaction is enabled.
+pub fn redact_text(text: &str) -> String {
+    redact_text_with(text, content_redaction_enabled())
+}
+
+fn redact_text_with(text: &str, content: bool) -> String {
+    let redact_json_str = |s: &str| {
+        serde_json::from_str::<Value>(s).ok().map(|mut v| {
+            redact_json_with(&mut v, content);
+            v.to_string()
+        })
+    };
+    if let Some(redacted) = redact_json_str(text) {
+        return redacted;
+    }
+
+    let mut saw_event = false;
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| match line.strip_prefix("data:") {
+            Some(data) => {
+                saw_event = true;
+                match redact_json_str(data.trim()) {
+                    Some(redacted) => format!("data: {redacted}"),
+                    None => line.to_string(),
+                }
+            }
+            None => line.to_string(),
+        })
+        .collect();
+    if saw_event {
+        lines.join("\n")
+    } else if content {
+        format!("<{} bytes omitted>", text.len())
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::HeaderValue;
+    use serde_json::json;
+
+    const KEY: &str = "sk-live-1234567890";
+
+    #[test]
+    fn headers_mask_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_str(&format!("Bearer {KEY}")).unwrap(),
+        );
+        headers.insert("x-api-key", HeaderValue::from_static(KEY));
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+
+        let line = redact_headers(&headers);
+        assert!(!line.contains(KEY), "{line}");
+        assert!(line.contains("authorization: Bearer [redacted]"), "{line}");
+        assert!(line.contains("x-api-key: [redacted]"), "{line}");
+        assert!(line.contains("content-type: application/json"), "{line}");
+    }
+
+    #[test]
+    fn json_masks_keys_and_keeps_content_by_default() {
+        let mut body = json!({
+            "api_key": KEY,
+            "metadata": { "refresh_token": KEY },
+            "messages": [{ "role": "user", "content": "hello" }]
+        });
+        redact_json_with(&mut body, false);
+        let line = body.to_string();
+        assert!(!line.contains(KEY), "{line}");
+        assert_eq!(body["messages"][0]["content"], "hello");
+    }
+
+    #[test]
+    fn json_masks_content_when_enabled() {
+        let mut body = json!({
+            "messages": [{
+                "role": "user",
+                "content": [{ "type": "text", "text": "my password is hunter2" }]
+            }]
+        });
+        redact_json_with(&mut body, true);
+        assert_eq!(body["messages"][0]["role"], "user");
+        assert_eq!(body["messages"][0]["content"][0]["type"], "text");
+        assert_eq!(body["messages"][0]["content"][0]["text"], "[22 chars]");
+    }
+
+    #[test]
+    fn sse_chunks_are_redacted_per_event() {
+        let chunk = "data: {\"choices\":[{\"delta\":{\"content\":\"secret\"}}]}\n\ndata: [DONE]\n";
+        let line = redact_text_with(chunk, true);
+        assert!(!line.contains("secret"), "{line}");
+        assert!(line.contains("data: [DONE]"), "{line}");
+
+        assert_eq!(redact_text_with("plain", false), "plain");
+        assert_eq!(redact_text_with("plain", true), "<5 bytes omitted>");
+    }
+}
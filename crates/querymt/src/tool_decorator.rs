@@ -106,6 +106,13 @@ impl CompletionProvider for ToolEnabledProvider {
     async fn complete(&self, req: &CompletionRequest) -> Result<CompletionResponse, LLMError> {
         self.inner.complete(req).await
     }
+
+    async fn complete_stream(
+        &self,
+        req: &CompletionRequest,
+    ) -> Result<Pin<Box<dyn Stream<Item = Result<StreamChunk, LLMError>> + Send>>, LLMError> {
+        self.inner.complete_stream(req).await
+    }
 }
 
 #[async_trait]
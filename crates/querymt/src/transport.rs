@@ -0,0 +1,437 @@
+//! Pluggable HTTP execution for HTTP-based providers.
+//!
+//! HTTP providers only build [`http::Request`]s and parse [`http::Response`]s;
+//! the [`HttpTransport`] decides how those requests actually reach the
+//! network. Supplying a custom transport to
+//! [`LLMProviderFromHTTP::with_transport`](crate::adapters::LLMProviderFromHTTP::with_transport)
+//! or [`HTTPFactoryAdapter::with_transport`](crate::plugin::adapters::HTTPFactoryAdapter::with_transport)
+//! is the hook for corporate proxies, mTLS, request signing or
+//! request/response logging.
+//!
+//! Self-hosted endpoints behind a private CA or requiring client
+//! certificates don't need a custom transport: set the [`TlsConfig`] keys in
+//! the provider config and [`config_transport`] builds a dedicated client.
+//!
+//! [`RecordingTransport`] and [`ReplayTransport`] capture real traffic to a
+//! cassette file and play it back, for deterministic offline tests.
+
+pub mod cassette;
+
+pub use cassette::{MatchRules, RecordingTransport, ReplayTransport};
+
+use crate::{
+    error::{LLMError, TransportErrorKind, transport_error},
+    outbound::{call_outbound, call_outbound_stream},
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use http::{Request, Response};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A stream of raw response body chunks.
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, LLMError>> + Send>>;
+
+/// Executes HTTP requests on behalf of HTTP-based providers.
+///
+/// Implementations may return non-2xx responses as `Ok`; the adapter maps
+/// them to the appropriate [`LLMError`] variant.
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    /// Send `req` and return the fully buffered response.
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError>;
+
+    /// Send `req` and return the response body as a stream of chunks.
+    ///
+    /// The default implementation buffers the whole response via
+    /// [`execute`](HttpTransport::execute) and yields it as a single chunk,
+    /// which is correct but gives up incremental delivery. Transports that can
+    /// stream should override it.
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let resp = self.execute(req).await?;
+        if !resp.status().is_success() {
+            return Err(crate::error::classify_http_status(
+                resp.status().as_u16(),
+                resp.headers(),
+                resp.body(),
+            ));
+        }
+        let body = Bytes::from(resp.into_body());
+        Ok(Box::pin(futures::stream::once(async move { Ok(body) })))
+    }
+}
+
+/// The default transport, backed by the shared `reqwest` client.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReqwestTransport;
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        call_outbound(req).await
+    }
+
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let stream = call_outbound_stream(req).await?;
+        Ok(Box::pin(stream.map(|res| res.map_err(LLMError::from))))
+    }
+}
+
+/// Applies a client-side deadline to another transport.
+///
+/// Buffered requests must complete within the timeout. Streaming requests
+/// must deliver their response headers within the timeout and then each
+/// chunk within the timeout of the previous one, so long generations are not
+/// cut off while they keep making progress. Expiry is reported as a
+/// [`TransportErrorKind::Timeout`] transport error.
+pub struct TimeoutTransport {
+    inner: Arc<dyn HttpTransport>,
+    timeout: Duration,
+}
+
+impl TimeoutTransport {
+    pub fn new(inner: Arc<dyn HttpTransport>, timeout: Duration) -> Self {
+        Self { inner, timeout }
+    }
+}
+
+fn timeout_error(timeout: Duration) -> LLMError {
+    transport_error(
+        TransportErrorKind::Timeout,
+        format!("request timed out after {}s", timeout.as_secs_f64()),
+    )
+}
+
+#[async_trait]
+impl HttpTransport for TimeoutTransport {
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        tokio::time::timeout(self.timeout, self.inner.execute(req))
+            .await
+            .map_err(|_| timeout_error(self.timeout))?
+    }
+
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let timeout = self.timeout;
+        let stream = tokio::time::timeout(timeout, self.inner.execute_stream(req))
+            .await
+            .map_err(|_| timeout_error(timeout))??;
+
+        let idle_limited = futures::stream::unfold(Some(stream), move |stream| async move {
+            let mut stream = stream?;
+            match tokio::time::timeout(timeout, stream.next()).await {
+                Ok(Some(item)) => Some((item, Some(stream))),
+                Ok(None) => None,
+                Err(_) => Some((Err(timeout_error(timeout)), None)),
+            }
+        });
+        Ok(Box::pin(idle_limited))
+    }
+}
+
+/// The `timeout_seconds` set in a provider config, if any.
+pub fn config_timeout(cfg: &Value) -> Option<Duration> {
+    cfg.get("timeout_seconds")
+        .and_then(Value::as_u64)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Wrap `transport` in a [`TimeoutTransport`] when the provider config sets
+/// `timeout_seconds`.
+pub fn transport_for_config(
+    transport: Arc<dyn HttpTransport>,
+    cfg: &Value,
+) -> Arc<dyn HttpTransport> {
+    match config_timeout(cfg) {
+        Some(timeout) => Arc::new(TimeoutTransport::new(transport, timeout)),
+        None => transport,
+    }
+}
+
+/// TLS material for self-hosted endpoints, read from the provider config.
+///
+/// | key               | value                                            |
+/// |-------------------|--------------------------------------------------|
+/// | `tls_ca_cert`     | PEM bundle of extra CAs trusted for the endpoint |
+/// | `tls_client_cert` | PEM client certificate (chain) for mTLS          |
+/// | `tls_client_key`  | PEM private key for `tls_client_cert`            |
+///
+/// Extra CAs are trusted in addition to the built-in roots. These settings
+/// only apply to `https://` base URLs; plain `http://` requests never
+/// perform a TLS handshake, so they are ignored there.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    pub ca_cert: Option<PathBuf>,
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// The TLS settings of a provider config, or `None` when it sets none.
+    pub fn from_config(cfg: &Value) -> Option<Self> {
+        let path = |key: &str| {
+            cfg.get(key)
+                .and_then(Value::as_str)
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+        };
+        let tls = Self {
+            ca_cert: path("tls_ca_cert"),
+            client_cert: path("tls_client_cert"),
+            client_key: path("tls_client_key"),
+        };
+        (tls != Self::default()).then_some(tls)
+    }
+
+    /// A `reqwest` client builder trusting `ca_cert` and presenting the
+    /// client identity, if set.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn client_builder(&self) -> Result<reqwest::ClientBuilder, LLMError> {
+        let mut builder = reqwest::Client::builder();
+
+        if let Some(path) = &self.ca_cert {
+            let certs = reqwest::Certificate::from_pem_bundle(&read_pem(path)?)
+                .map_err(|e| invalid_pem(path, e))?;
+            builder = builder.tls_certs_merge(certs);
+        }
+
+        match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                let mut pem = read_pem(cert)?;
+                pem.push(b'\n');
+                pem.extend(read_pem(key)?);
+                let identity =
+                    reqwest::Identity::from_pem(&pem).map_err(|e| invalid_pem(cert, e))?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(LLMError::InvalidRequest(
+                    "tls_client_cert and tls_client_key must be set together".to_string(),
+                ));
+            }
+        }
+
+        Ok(builder)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_pem(path: &std::path::Path) -> Result<Vec<u8>, LLMError> {
+    std::fs::read(path).map_err(|e| {
+        LLMError::InvalidRequest(format!("cannot read TLS file {}: {e}", path.display()))
+    })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn invalid_pem(path: &std::path::Path, err: reqwest::Error) -> LLMError {
+    LLMError::InvalidRequest(format!("invalid PEM in {}: {err}", path.display()))
+}
+
+/// A transport backed by its own `reqwest` client rather than the shared one.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ClientTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ClientTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait]
+impl HttpTransport for ClientTransport {
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        crate::outbound::call_outbound_with(&self.client, req).await
+    }
+
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let stream = crate::outbound::call_outbound_stream_with(&self.client, req).await?;
+        Ok(Box::pin(stream.map(|res| res.map_err(LLMError::from))))
+    }
+}
+
+/// The base transport for a provider config: a dedicated client when the
+/// config carries [`TlsConfig`] settings, otherwise [`default_transport`].
+pub fn config_transport(cfg: &Value) -> Result<Arc<dyn HttpTransport>, LLMError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(tls) = TlsConfig::from_config(cfg) {
+        let client = tls
+            .client_builder()?
+            .build()
+            .map_err(|e| LLMError::InvalidRequest(format!("failed to build TLS client: {e}")))?;
+        return Ok(Arc::new(ClientTransport::new(client)));
+    }
+    #[cfg(target_arch = "wasm32")]
+    let _ = cfg;
+    Ok(default_transport())
+}
+
+/// Fails with [`LLMError::ProviderError`] when `len` exceeds `limit`.
+///
+/// Used by the HTTP adapters to reject oversized responses before they are
+/// handed to a provider's parser.
+pub(crate) fn check_response_size(len: usize, limit: Option<usize>) -> Result<(), LLMError> {
+    match limit {
+        Some(limit) if len > limit => Err(LLMError::ProviderError(format!(
+            "response body of {len} bytes exceeds the {limit} byte limit"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Returns the transport used when none is configured.
+pub fn default_transport() -> Arc<dyn HttpTransport> {
+    Arc::new(ReqwestTransport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers after `delay`, streaming `chunks` with `delay` between them.
+    struct SlowTransport {
+        delay: Duration,
+        chunks: usize,
+    }
+
+    #[async_trait]
+    impl HttpTransport for SlowTransport {
+        async fn execute(&self, _req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            tokio::time::sleep(self.delay).await;
+            Ok(Response::new(b"ok".to_vec()))
+        }
+
+        async fn execute_stream(&self, _req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+            let delay = self.delay;
+            let chunks = futures::stream::iter(0..self.chunks).then(move |_| async move {
+                tokio::time::sleep(delay).await;
+                Ok(Bytes::from_static(b"data"))
+            });
+            Ok(Box::pin(chunks))
+        }
+    }
+
+    fn request() -> Request<Vec<u8>> {
+        Request::get("https://example.invalid/")
+            .body(Vec::new())
+            .unwrap()
+    }
+
+    fn with_timeout(delay_ms: u64, chunks: usize, timeout_ms: u64) -> TimeoutTransport {
+        TimeoutTransport::new(
+            Arc::new(SlowTransport {
+                delay: Duration::from_millis(delay_ms),
+                chunks,
+            }),
+            Duration::from_millis(timeout_ms),
+        )
+    }
+
+    fn is_timeout(err: &LLMError) -> bool {
+        matches!(
+            err,
+            LLMError::Transport {
+                kind: TransportErrorKind::Timeout,
+                ..
+            }
+        )
+    }
+
+    #[tokio::test]
+    async fn slow_response_hits_deadline() {
+        let err = with_timeout(5_000, 0, 20)
+            .execute(request())
+            .await
+            .expect_err("slow server should time out");
+        assert!(is_timeout(&err), "got {err:?}");
+    }
+
+    #[tokio::test]
+    async fn fast_response_passes_through() {
+        let resp = with_timeout(0, 0, 1_000).execute(request()).await.unwrap();
+        assert_eq!(resp.body(), b"ok");
+    }
+
+    #[tokio::test]
+    async fn stalled_stream_hits_idle_deadline() {
+        let mut stream = with_timeout(5_000, 1, 20)
+            .execute_stream(request())
+            .await
+            .unwrap();
+        let err = stream.next().await.unwrap().expect_err("stalled chunk");
+        assert!(is_timeout(&err), "got {err:?}");
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn steady_stream_outlives_timeout() {
+        // Three chunks 15ms apart take longer than the 25ms timeout in total,
+        // but each arrives within it.
+        let stream = with_timeout(15, 3, 25)
+            .execute_stream(request())
+            .await
+            .unwrap();
+        let chunks: Vec<_> = stream.collect().await;
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(Result::is_ok));
+    }
+
+    fn fixture(name: &str) -> String {
+        format!("{}/testdata/tls/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn tls_config_reads_paths() {
+        let cfg = serde_json::json!({
+            "tls_ca_cert": "/etc/ca.pem",
+            "tls_client_cert": "/etc/client.pem",
+            "tls_client_key": "/etc/client.key",
+        });
+        let tls = TlsConfig::from_config(&cfg).unwrap();
+        assert_eq!(tls.ca_cert, Some(PathBuf::from("/etc/ca.pem")));
+        assert_eq!(tls.client_cert, Some(PathBuf::from("/etc/client.pem")));
+        assert_eq!(tls.client_key, Some(PathBuf::from("/etc/client.key")));
+        assert_eq!(TlsConfig::from_config(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn ca_path_is_plumbed_into_client_builder() {
+        let missing = serde_json::json!({ "tls_ca_cert": "/nonexistent/ca.pem" });
+        let err = config_transport(&missing).err().expect("missing CA file");
+        assert!(err.to_string().contains("/nonexistent/ca.pem"), "got {err}");
+
+        let cfg = serde_json::json!({
+            "tls_ca_cert": fixture("ca.pem"),
+            "tls_client_cert": fixture("client.pem"),
+            "tls_client_key": fixture("client.key"),
+        });
+        config_transport(&cfg).unwrap();
+    }
+
+    #[test]
+    fn client_cert_requires_key() {
+        let cfg = serde_json::json!({ "tls_client_cert": fixture("client.pem") });
+        let err = config_transport(&cfg).err().expect("cert without key");
+        assert!(err.to_string().contains("tls_client_key"), "got {err}");
+    }
+
+    #[test]
+    fn config_timeout_reads_timeout_seconds() {
+        let cfg = serde_json::json!({ "timeout_seconds": 30 });
+        assert_eq!(config_timeout(&cfg), Some(Duration::from_secs(30)));
+        assert_eq!(
+            config_timeout(&serde_json::json!({ "timeout_seconds": 0 })),
+            None
+        );
+        assert_eq!(config_timeout(&serde_json::json!({})), None);
+    }
+}
@@ -0,0 +1,619 @@
+//! Record provider traffic to a cassette file and replay it offline.
+//!
+//! Wrap the real transport in a [`RecordingTransport`] once to capture the
+//! request/response pairs of a test run, then run the same test against a
+//! [`ReplayTransport`] reading that cassette: no network access or live API
+//! keys are needed.
+//!
+//! Credential headers and query parameters (see
+//! [`redact::is_sensitive_key`]) are never written to the cassette.
+
+use super::{ByteStream, HttpTransport};
+use crate::{
+    error::{LLMError, TransportErrorKind, classify_http_status, transport_error},
+    redact,
+};
+use async_trait::async_trait;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use bytes::Bytes;
+use futures::StreamExt;
+use http::{HeaderMap, Request, Response, Uri};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// A request or response body. Text bodies are stored verbatim so cassettes
+/// stay readable and diffable; anything else is base64 encoded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RecordedBody {
+    Text(String),
+    Binary { base64: String },
+}
+
+impl RecordedBody {
+    fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Binary {
+                base64: BASE64.encode(bytes),
+            },
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, LLMError> {
+        match self {
+            Self::Text(text) => Ok(text.clone().into_bytes()),
+            Self::Binary { base64 } => BASE64
+                .decode(base64)
+                .map_err(|e| LLMError::GenericError(format!("invalid cassette body: {e}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub uri: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: RecordedBody,
+}
+
+impl RecordedRequest {
+    fn new(req: &Request<Vec<u8>>) -> Self {
+        Self {
+            method: req.method().to_string(),
+            uri: redact_uri(req.uri()),
+            headers: recorded_headers(req.headers()),
+            body: RecordedBody::new(req.body()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: RecordedBody,
+}
+
+impl RecordedResponse {
+    fn to_response(&self) -> Result<Response<Vec<u8>>, LLMError> {
+        let mut builder = Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+        builder
+            .body(self.body.to_bytes()?)
+            .map_err(|e| LLMError::GenericError(format!("invalid cassette response: {e}")))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// The recorded interactions of a test run, stored as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> Result<Self, LLMError> {
+        let data = std::fs::read(path).map_err(|e| {
+            LLMError::GenericError(format!("cannot read cassette {}: {e}", path.display()))
+        })?;
+        serde_json::from_slice(&data).map_err(|e| {
+            LLMError::GenericError(format!("invalid cassette {}: {e}", path.display()))
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), LLMError> {
+        let data = serde_json::to_vec_pretty(self)
+            .map_err(|e| LLMError::GenericError(format!("cannot encode cassette: {e}")))?;
+        std::fs::write(path, data).map_err(|e| {
+            LLMError::GenericError(format!("cannot write cassette {}: {e}", path.display()))
+        })
+    }
+}
+
+fn recorded_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter(|(name, _)| !redact::is_sensitive_key(name.as_str()))
+        .map(|(name, value)| {
+            (
+                name.as_str().to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// `uri` with the values of credential query parameters (e.g. `?key=`)
+/// masked.
+fn redact_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if is_sensitive_param(key) => format!("{key}={}", redact::REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let base = uri.to_string();
+    let base = base.split_once('?').map_or(base.as_str(), |(base, _)| base);
+    format!("{base}?{query}")
+}
+
+fn is_sensitive_param(key: &str) -> bool {
+    key.eq_ignore_ascii_case("key") || redact::is_sensitive_key(key)
+}
+
+/// Records every answered request of the wrapped transport and rewrites
+/// the cassette file after each one.
+///
+/// Streamed responses are recorded as a single body once the stream ends,
+/// fails or is dropped, with whatever arrived up to that point.
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+}
+
+impl RecordingTransport {
+    /// Record into a new cassette at `path`, replacing any existing file.
+    pub fn new(inner: Arc<dyn HttpTransport>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            path: path.into(),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+        }
+    }
+
+    /// The interactions recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+fn record(
+    cassette: &Mutex<Cassette>,
+    path: &Path,
+    interaction: Interaction,
+) -> Result<(), LLMError> {
+    let mut cassette = cassette.lock().unwrap_or_else(PoisonError::into_inner);
+    cassette.interactions.push(interaction);
+    cassette.save(path)
+}
+
+/// A streamed response being recorded. The interaction is written once, by
+/// [`finish`](Self::finish) or, for streams abandoned midway, on drop.
+struct StreamRecording {
+    cassette: Arc<Mutex<Cassette>>,
+    path: PathBuf,
+    request: Option<RecordedRequest>,
+    body: Vec<u8>,
+}
+
+impl StreamRecording {
+    fn finish(&mut self) -> Result<(), LLMError> {
+        let Some(request) = self.request.take() else {
+            return Ok(());
+        };
+        let interaction = Interaction {
+            request,
+            response: RecordedResponse {
+                status: 200,
+                headers: BTreeMap::new(),
+                body: RecordedBody::new(&self.body),
+            },
+        };
+        record(&self.cassette, &self.path, interaction)
+    }
+}
+
+impl Drop for StreamRecording {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish() {
+            log::warn!("Failed to record streamed response: {e}");
+        }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for RecordingTransport {
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        let request = RecordedRequest::new(&req);
+        let resp = self.inner.execute(req).await?;
+        let response = RecordedResponse {
+            status: resp.status().as_u16(),
+            headers: recorded_headers(resp.headers()),
+            body: RecordedBody::new(resp.body()),
+        };
+        record(
+            &self.cassette,
+            &self.path,
+            Interaction { request, response },
+        )?;
+        Ok(resp)
+    }
+
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let request = RecordedRequest::new(&req);
+        let stream = self.inner.execute_stream(req).await?;
+        let recording = StreamRecording {
+            cassette: Arc::clone(&self.cassette),
+            path: self.path.clone(),
+            request: Some(request),
+            body: Vec::new(),
+        };
+
+        // Pass chunks through as they arrive. Once the stream is exhausted,
+        // record the concatenated body and surface a failed write as a final
+        // error item; a failed stream is recorded up to the error.
+        let recorded = futures::stream::unfold(
+            (Some(stream), recording),
+            |(stream, mut recording)| async move {
+                let mut stream = stream?;
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        recording.body.extend_from_slice(&chunk);
+                        Some((Ok(chunk), (Some(stream), recording)))
+                    }
+                    Some(Err(e)) => {
+                        if let Err(record_err) = recording.finish() {
+                            log::warn!("Failed to record streamed response: {record_err}");
+                        }
+                        Some((Err(e), (None, recording)))
+                    }
+                    None => match recording.finish() {
+                        Ok(()) => None,
+                        Err(e) => Some((Err(e), (None, recording))),
+                    },
+                }
+            },
+        );
+        Ok(Box::pin(recorded))
+    }
+}
+
+/// Which parts of a request [`ReplayTransport`] compares against the
+/// recorded ones.
+///
+/// Credential headers are never recorded, so authentication never takes
+/// part in matching and replays work with any (or no) API key.
+#[derive(Debug, Clone)]
+pub struct MatchRules {
+    pub method: bool,
+    /// Scheme, host and path.
+    pub path: bool,
+    /// Query string, with credential parameters masked on both sides.
+    pub query: bool,
+    /// Request body. JSON bodies are compared structurally, so key order and
+    /// whitespace do not matter.
+    pub body: bool,
+    /// Additional headers that must match, by name.
+    pub headers: Vec<String>,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        Self {
+            method: true,
+            path: true,
+            query: true,
+            body: true,
+            headers: Vec::new(),
+        }
+    }
+}
+
+impl MatchRules {
+    fn matches(&self, recorded: &RecordedRequest, incoming: &RecordedRequest) -> bool {
+        let (recorded_path, recorded_query) = split_query(&recorded.uri);
+        let (incoming_path, incoming_query) = split_query(&incoming.uri);
+        (!self.method || recorded.method == incoming.method)
+            && (!self.path || recorded_path == incoming_path)
+            && (!self.query || recorded_query == incoming_query)
+            && (!self.body || bodies_match(&recorded.body, &incoming.body))
+            && self.headers.iter().all(|name| {
+                let name = name.to_ascii_lowercase();
+                recorded.headers.get(&name) == incoming.headers.get(&name)
+            })
+    }
+}
+
+fn split_query(uri: &str) -> (&str, &str) {
+    uri.split_once('?').unwrap_or((uri, ""))
+}
+
+fn bodies_match(recorded: &RecordedBody, incoming: &RecordedBody) -> bool {
+    if let (RecordedBody::Text(a), RecordedBody::Text(b)) = (recorded, incoming)
+        && let (Ok(a), Ok(b)) = (
+            serde_json::from_str::<Value>(a),
+            serde_json::from_str::<Value>(b),
+        )
+    {
+        return a == b;
+    }
+    recorded == incoming
+}
+
+/// Answers requests from a cassette instead of the network.
+///
+/// Each request is answered by the first matching interaction that has not
+/// been replayed yet, so repeated identical requests get their responses in
+/// recording order. A request without a match fails with a transport error.
+pub struct ReplayTransport {
+    interactions: Mutex<Vec<(Interaction, bool)>>,
+    rules: MatchRules,
+}
+
+impl ReplayTransport {
+    pub fn new(cassette: Cassette) -> Self {
+        Self {
+            interactions: Mutex::new(
+                cassette
+                    .interactions
+                    .into_iter()
+                    .map(|interaction| (interaction, false))
+                    .collect(),
+            ),
+            rules: MatchRules::default(),
+        }
+    }
+
+    /// Replay the cassette stored at `path`.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, LLMError> {
+        Ok(Self::new(Cassette::load(path.as_ref())?))
+    }
+
+    pub fn with_match_rules(mut self, rules: MatchRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    fn replay(&self, req: &Request<Vec<u8>>) -> Result<RecordedResponse, LLMError> {
+        let incoming = RecordedRequest::new(req);
+        let mut interactions = self
+            .interactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(interaction, used)| {
+                !*used && self.rules.matches(&interaction.request, &incoming)
+            })
+            .ok_or_else(|| {
+                transport_error(
+                    TransportErrorKind::Other,
+                    format!(
+                        "no recorded interaction matches {} {}",
+                        incoming.method, incoming.uri
+                    ),
+                )
+            })?;
+        *used = true;
+        Ok(interaction.response.clone())
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn execute(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+        self.replay(&req)?.to_response()
+    }
+
+    async fn execute_stream(&self, req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+        let resp = self.replay(&req)?.to_response()?;
+        if !resp.status().is_success() {
+            return Err(classify_http_status(
+                resp.status().as_u16(),
+                resp.headers(),
+                resp.body(),
+            ));
+        }
+        let body = Bytes::from(resp.into_body());
+        Ok(Box::pin(futures::stream::once(async move { Ok(body) })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request with an OpenAI-style chat completion.
+    struct FakeChatServer;
+
+    const CHAT_REPLY: &str = r#"{"choices":[{"message":{"role":"assistant","content":"Hello!"}}]}"#;
+
+    #[async_trait]
+    impl HttpTransport for FakeChatServer {
+        async fn execute(&self, _req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            Ok(Response::builder()
+                .status(200)
+                .header("content-type", "application/json")
+                .body(CHAT_REPLY.as_bytes().to_vec())
+                .unwrap())
+        }
+
+        async fn execute_stream(&self, _req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+            let chunks = [
+                "data: {\"delta\":\"Hel\"}\n\n",
+                "data: {\"delta\":\"lo\"}\n\n",
+            ]
+            .map(|chunk| Ok(Bytes::from_static(chunk.as_bytes())));
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    fn chat_request(api_key: &str, body: &str) -> Request<Vec<u8>> {
+        Request::post("https://api.example.com/v1/chat/completions?key=secret")
+            .header("authorization", format!("Bearer {api_key}"))
+            .header("content-type", "application/json")
+            .body(body.as_bytes().to_vec())
+            .unwrap()
+    }
+
+    fn cassette_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("qmt-cassette-{}-{name}.json", std::process::id()))
+    }
+
+    const CHAT_BODY: &str = r#"{"model":"gpt-4o","messages":[{"role":"user","content":"Hi"}]}"#;
+
+    #[tokio::test]
+    async fn records_and_replays_a_chat() {
+        let path = cassette_path("chat");
+        let recorder = RecordingTransport::new(Arc::new(FakeChatServer), &path);
+        let live = recorder
+            .execute(chat_request("sk-live", CHAT_BODY))
+            .await
+            .unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("sk-live"), "{saved}");
+        assert!(!saved.contains("secret"), "{saved}");
+
+        // Same request with another key and reordered JSON keys.
+        let replay = ReplayTransport::from_file(&path).unwrap();
+        let reordered = r#"{"messages":[{"content":"Hi","role":"user"}],"model":"gpt-4o"}"#;
+        let replayed = replay
+            .execute(chat_request("sk-other", reordered))
+            .await
+            .unwrap();
+        assert_eq!(replayed.status(), live.status());
+        assert_eq!(replayed.body(), live.body());
+        assert_eq!(replayed.headers()["content-type"], "application/json");
+
+        // Each interaction is replayed once.
+        let err = replay
+            .execute(chat_request("sk-other", CHAT_BODY))
+            .await
+            .expect_err("cassette exhausted");
+        assert!(err.to_string().contains("no recorded interaction"), "{err}");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn replay_rejects_a_different_body_unless_ignored() {
+        let recorder = RecordingTransport::new(Arc::new(FakeChatServer), cassette_path("body"));
+        recorder
+            .execute(chat_request("sk-live", CHAT_BODY))
+            .await
+            .unwrap();
+        let other = r#"{"model":"gpt-4o","messages":[]}"#;
+
+        let strict = ReplayTransport::new(recorder.cassette());
+        assert!(strict.execute(chat_request("k", other)).await.is_err());
+
+        let lenient = ReplayTransport::new(recorder.cassette()).with_match_rules(MatchRules {
+            body: false,
+            ..MatchRules::default()
+        });
+        assert!(lenient.execute(chat_request("k", other)).await.is_ok());
+        let _ = std::fs::remove_file(cassette_path("body"));
+    }
+
+    #[tokio::test]
+    async fn records_and_replays_a_stream() {
+        let path = cassette_path("stream");
+        let recorder = RecordingTransport::new(Arc::new(FakeChatServer), &path);
+        let live: Vec<Bytes> = recorder
+            .execute_stream(chat_request("sk-live", CHAT_BODY))
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(live.len(), 2);
+
+        let replay = ReplayTransport::from_file(&path).unwrap();
+        let replayed: Vec<Bytes> = replay
+            .execute_stream(chat_request("sk-other", CHAT_BODY))
+            .await
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+            .await;
+        assert_eq!(replayed.concat(), live.concat());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Streams one chunk, then fails.
+    struct FailingStreamServer;
+
+    #[async_trait]
+    impl HttpTransport for FailingStreamServer {
+        async fn execute(&self, _req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, LLMError> {
+            Err(LLMError::NotImplemented("non-streaming".into()))
+        }
+
+        async fn execute_stream(&self, _req: Request<Vec<u8>>) -> Result<ByteStream, LLMError> {
+            let chunks = [
+                Ok(Bytes::from_static(b"data: {\"delta\":\"Hel\"}\n\n")),
+                Err(transport_error(
+                    TransportErrorKind::Other,
+                    "connection reset",
+                )),
+            ];
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+    }
+
+    #[tokio::test]
+    async fn records_a_stream_that_fails_midway() {
+        let path = cassette_path("stream-error");
+        let recorder = RecordingTransport::new(Arc::new(FailingStreamServer), &path);
+        let items: Vec<_> = recorder
+            .execute_stream(chat_request("sk-live", CHAT_BODY))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(items.len(), 2);
+        assert!(items[1].is_err());
+
+        let cassette = Cassette::load(&path).unwrap();
+        assert_eq!(cassette.interactions.len(), 1);
+        assert_eq!(
+            cassette.interactions[0].response.body,
+            RecordedBody::Text("data: {\"delta\":\"Hel\"}\n\n".into())
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn records_a_stream_dropped_before_the_end() {
+        let path = cassette_path("stream-drop");
+        let recorder = RecordingTransport::new(Arc::new(FakeChatServer), &path);
+        let mut stream = recorder
+            .execute_stream(chat_request("sk-live", CHAT_BODY))
+            .await
+            .unwrap();
+        stream.next().await.unwrap().unwrap();
+        assert!(recorder.cassette().interactions.is_empty());
+        drop(stream);
+
+        let cassette = Cassette::load(&path).unwrap();
+        assert_eq!(cassette.interactions.len(), 1);
+        assert_eq!(
+            cassette.interactions[0].response.body,
+            RecordedBody::Text("data: {\"delta\":\"Hel\"}\n\n".into())
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -155,6 +155,7 @@ impl ChatProvider for ValidatedLLM {
                             err
                         ))],
                         cache: None,
+                        name: None,
                     });
                 }
             }
@@ -213,6 +214,18 @@ impl CompletionProvider for ValidatedLLM {
             }
         }
     }
+
+    /// Passes streaming completions through without validation, as for
+    /// streaming chat.
+    async fn complete_stream(
+        &self,
+        req: &CompletionRequest,
+    ) -> Result<
+        std::pin::Pin<Box<dyn futures::Stream<Item = Result<StreamChunk, LLMError>> + Send>>,
+        LLMError,
+    > {
+        self.inner.complete_stream(req).await
+    }
 }
 
 #[async_trait]